@@ -7,6 +7,7 @@
 //! - Command history with persistence
 //! - Syntax highlighting for Nostos code
 //! - Autocomplete via server requests
+//! - Live process tracing (`:trace <pid>`)
 
 use std::borrow::Cow;
 use std::io::{BufRead, BufReader, Write};
@@ -92,6 +93,8 @@ fn print_help() {
     eprintln!("COMMANDS (after connecting):");
     eprintln!("    :load <file>         Load a .nos file or directory");
     eprintln!("    :reload              Reload all loaded files");
+    eprintln!("    :upgrade <module>    Upgrade a module in place, keeping processes running");
+    eprintln!("    :trace <pid> [flags] Print a process's trace events live until a key is pressed");
     eprintln!("    :status              Show compilation status");
     eprintln!("    :eval <expr>         Evaluate an expression");
     eprintln!("    :compile <file>      Compile a file (check for errors)");
@@ -229,7 +232,7 @@ impl Completer for ServerCompleter {
     fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
         // For commands starting with :, provide command completions
         if line.starts_with(':') {
-            let commands = [":load", ":reload", ":status", ":eval", ":compile", ":quit", ":help"];
+            let commands = [":load", ":reload", ":upgrade", ":trace", ":status", ":eval", ":compile", ":quit", ":help"];
             return commands.iter()
                 .filter(|c| c.starts_with(line))
                 .map(|c| Suggestion {
@@ -627,6 +630,14 @@ fn connect_to_server(port: u16) -> ExitCode {
 
                 // Parse and send command
                 let (cmd, args) = parse_input(line);
+
+                if cmd == "trace" {
+                    if let Err(e) = live_trace(&mut reader, &mut writer, &args) {
+                        eprintln!("Error: {}", e);
+                        break;
+                    }
+                    continue;
+                }
                 let json = format_command(&cmd, &args);
 
                 // Send to server
@@ -666,10 +677,89 @@ fn connect_to_server(port: u16) -> ExitCode {
     ExitCode::SUCCESS
 }
 
+/// Send one command and read its response line.
+fn request(reader: &mut BufReader<TcpStream>, writer: &mut TcpStream, cmd: &str, args: &str) -> std::io::Result<String> {
+    writeln!(writer, "{}", format_command(cmd, args))?;
+    writer.flush()?;
+    let mut response = String::new();
+    if reader.read_line(&mut response)? == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "server disconnected"));
+    }
+    Ok(response)
+}
+
+/// `:trace <pid> [flags]`: start tracing, poll the server for trace events and
+/// print them until a key is pressed, then stop tracing.
+fn live_trace(reader: &mut BufReader<TcpStream>, writer: &mut TcpStream, args: &str) -> std::io::Result<()> {
+    use crossterm::event::{self, Event, KeyEventKind};
+    use crossterm::terminal;
+
+    let pid = args.split_whitespace().next().unwrap_or("").to_string();
+    if pid.is_empty() {
+        eprintln!("Usage: :trace <pid> [send|receive|call|return|spawn|exit|all ...]");
+        return Ok(());
+    }
+
+    // Each `traceEvents` response starts with the sequence number to ask from
+    // next; asking without one only returns the current position.
+    let next_seq = |response: &str| -> Option<u64> {
+        unescape_json_string(&extract_json_field(response, "output")).lines().next()?.parse().ok()
+    };
+    let mut since = next_seq(&request(reader, writer, "traceEvents", "")?).unwrap_or(0);
+
+    let response = request(reader, writer, "trace", args)?;
+    if extract_json_field(&response, "status") != "ok" {
+        print_response(&response);
+        return Ok(());
+    }
+    eprintln!("Tracing {} (press any key to stop)", pid);
+
+    terminal::enable_raw_mode()?;
+    let result = (|| -> std::io::Result<()> {
+        loop {
+            if event::poll(std::time::Duration::from_millis(200))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        return Ok(());
+                    }
+                }
+                continue;
+            }
+            let response = request(reader, writer, "traceEvents", &since.to_string())?;
+            let output = unescape_json_string(&extract_json_field(&response, "output"));
+            let mut lines = output.lines();
+            if let Some(next) = lines.next().and_then(|line| line.parse().ok()) {
+                since = next;
+            }
+            // Raw mode: lines need an explicit carriage return
+            let mut stdout = std::io::stdout();
+            for line in lines {
+                write!(stdout, "{}\r\n", line)?;
+            }
+            stdout.flush()?;
+        }
+    })();
+    terminal::disable_raw_mode()?;
+    result?;
+
+    // The process may have exited meanwhile, which ends its trace anyway
+    let response = request(reader, writer, "untrace", &pid)?;
+    if extract_json_field(&response, "status") == "ok" {
+        print_response(&response);
+    } else {
+        eprintln!("Trace of {} ended", pid);
+    }
+    Ok(())
+}
+
 fn print_client_help() {
     eprintln!("Commands:");
     eprintln!("  :load <path>    Load a .nos file or directory");
     eprintln!("  :reload         Reload all loaded files");
+    eprintln!("  :upgrade <mod>  Upgrade a module in place, keeping processes running");
+    eprintln!("  :trace <pid> [flags]");
+    eprintln!("                  Print a process's trace events live until a key is pressed");
+    eprintln!("                  (flags: send receive call return spawn exit all; default all)");
     eprintln!("  :status         Show compilation status");
     eprintln!("  :eval <expr>    Evaluate an expression");
     eprintln!("  :compile <file> Compile a file and show errors");
@@ -707,6 +797,7 @@ fn format_command(cmd: &str, args: &str) -> String {
     let arg_key = match cmd {
         "load" | "compile" => "file",
         "eval" => "code",
+        "upgrade" => "module",
         _ => "args",
     };
 
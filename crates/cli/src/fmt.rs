@@ -0,0 +1,112 @@
+//! `nostos fmt`: format Nostos source files in place, or check that they are formatted.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use nostos_syntax::{eprint_errors, format_source, FormatError};
+
+fn print_help() {
+    println!("Format Nostos source files");
+    println!();
+    println!("USAGE:");
+    println!("    nostos fmt [options] [paths...]");
+    println!();
+    println!("    Formats the given files, or all .nos files under the given");
+    println!("    directories (default: current directory), in place.");
+    println!();
+    println!("OPTIONS:");
+    println!("    --check           Don't write files; list unformatted files and fail if there are any");
+    println!("    --help            Show this help");
+}
+
+/// Run the fmt subcommand
+pub fn run_fmt_command(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                print_help();
+                return ExitCode::SUCCESS;
+            }
+            "--check" => check = true,
+            _ if arg.starts_with('-') => {
+                eprintln!("Error: Unknown option '{}'", arg);
+                return ExitCode::FAILURE;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if path.is_dir() {
+            if let Err(e) = crate::visit_dirs(path, &mut files) {
+                eprintln!("Error: Failed to read {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        } else if path.exists() {
+            files.push(path.clone());
+        } else {
+            eprintln!("Error: {} does not exist", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    files.sort();
+
+    let mut failed = false;
+    let mut unformatted = 0;
+    for file in &files {
+        match format_file(file, check) {
+            Ok(true) => {
+                unformatted += 1;
+                if check {
+                    println!("{}", file.display());
+                }
+            }
+            Ok(false) => {}
+            Err(()) => failed = true,
+        }
+    }
+
+    if check && unformatted > 0 {
+        eprintln!("{} of {} files need formatting", unformatted, files.len());
+        failed = true;
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+/// Format one file. Returns whether it was (or, with `check`, would be) changed.
+/// Errors are reported to stderr.
+fn format_file(path: &Path, check: bool) -> Result<bool, ()> {
+    let source = fs::read_to_string(path).map_err(|e| {
+        eprintln!("Error: Failed to read {}: {}", path.display(), e);
+    })?;
+
+    let formatted = match format_source(&source) {
+        Ok(formatted) => formatted,
+        Err(FormatError::Parse(errors)) => {
+            eprint_errors(&errors, &path.display().to_string(), &source);
+            return Err(());
+        }
+        Err(e) => {
+            eprintln!("Error: {}: {}", path.display(), e);
+            return Err(());
+        }
+    };
+
+    if formatted == source {
+        return Ok(false);
+    }
+    if !check {
+        fs::write(path, &formatted).map_err(|e| {
+            eprintln!("Error: Failed to write {}: {}", path.display(), e);
+        })?;
+    }
+    Ok(true)
+}
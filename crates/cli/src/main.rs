@@ -38,24 +38,32 @@ mod autocomplete;
 mod inspector_panel;
 mod nostos_panel;
 mod debug_panel;
+mod trace_panel;
 mod git_panel;
 mod tutorial;
 mod packages;
 mod server;
 mod connect;
+mod test_runner;
+mod standalone;
+mod fmt;
+mod heap;
 
 // Include the embedded stdlib generated by build.rs
 include!(concat!(env!("OUT_DIR"), "/embedded_stdlib.rs"));
 
 use nostos_compiler::compile::{Compiler, MvarInitValue};
-use nostos_jit::{JitCompiler, JitConfig};
+use nostos_jit::{spawn_jit_compiler, JitCompiler, JitConfig, JIT_THRESHOLD};
 use nostos_syntax::{parse, parse_errors_to_source_errors, eprint_errors};
 use nostos_vm::async_vm::{AsyncVM, AsyncConfig};
-use nostos_vm::cache::{BytecodeCache, CachedModule, CachedMvar, CachedMvarValue, function_to_cached_with_fn_list, compute_file_hash};
+use nostos_vm::code::{CodeLoader, ReloadedCode};
+use nostos_vm::cache::{BytecodeCache, JitCache, CachedModule, CachedMvar, CachedMvarValue, function_to_cached_with_fn_list, compute_file_hash};
+use nostos_vm::node::NodeConfig;
 use nostos_vm::process::ThreadSafeValue;
+use nostos_vm::sampler::{SamplerConfig, StackSamples};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 /// Recursively visit directories and find .nos files
@@ -93,6 +101,11 @@ fn get_cache_dir() -> PathBuf {
     }
 }
 
+/// Get the path of the JIT cache, which remembers hot functions across runs
+fn get_jit_cache_path() -> PathBuf {
+    get_cache_dir().join("jit.bin")
+}
+
 /// Get the cache directory for extension modules
 fn get_extension_cache_dir(ext_name: &str) -> PathBuf {
     if let Some(home) = dirs::home_dir() {
@@ -1616,20 +1629,259 @@ fn cached_to_mvar_init(cached: &CachedMvarValue) -> MvarInitValue {
     }
 }
 
+/// Resolve the function to run: the `--bin` entry or default `[[bin]]` of a
+/// project, falling back to `main.main`/`main`, or `main/` for a single file.
+/// Function names include their signature, so `main` with no params is "main/".
+fn resolve_entry_point(
+    compiler: &Compiler,
+    input_path: &std::path::Path,
+    project_config: Option<&nostos_source::ProjectConfig>,
+    bin_name: Option<&str>,
+) -> Result<String, ExitCode> {
+    let entry_point_name = if input_path.is_dir() {
+        let funcs = compiler.get_all_functions();
+
+        // Helper to find function with signature suffix
+        let find_func = |base: &str| -> Option<String> {
+            let with_slash = format!("{}/", base);
+            if funcs.contains_key(&with_slash) {
+                Some(with_slash)
+            } else {
+                funcs.keys()
+                    .find(|k| k.starts_with(&with_slash))
+                    .cloned()
+            }
+        };
+
+        // Check if --bin was specified
+        if let Some(name) = bin_name {
+            if let Some(cfg) = project_config {
+                if let Some(bin_entry) = cfg.get_bin(name) {
+                    // Convert "module.func" to "module.func/"
+                    match find_func(&bin_entry.entry) {
+                        Some(f) => f,
+                        None => {
+                            eprintln!("Error: Entry point '{}' for bin '{}' not found", bin_entry.entry, name);
+                            return Err(ExitCode::FAILURE);
+                        }
+                    }
+                } else {
+                    eprintln!("Error: No [[bin]] entry named '{}' in nostos.toml", name);
+                    if cfg.has_bins() {
+                        eprintln!("Available bins: {}", cfg.bin_names().join(", "));
+                    }
+                    return Err(ExitCode::FAILURE);
+                }
+            } else {
+                eprintln!("Error: --bin requires a nostos.toml with [[bin]] entries");
+                return Err(ExitCode::FAILURE);
+            }
+        }
+        // Check for default bin in project config
+        else if let Some(cfg) = project_config {
+            if let Some(default_bin) = cfg.get_default_bin() {
+                match find_func(&default_bin.entry) {
+                    Some(f) => f,
+                    None => {
+                        eprintln!("Error: Default entry point '{}' not found", default_bin.entry);
+                        return Err(ExitCode::FAILURE);
+                    }
+                }
+            } else if cfg.has_bins() {
+                // Has bins but none is default - require --bin
+                eprintln!("Error: Project has [[bin]] entries but none is marked as default.");
+                eprintln!("Use --bin NAME to specify which to run. Available: {}", cfg.bin_names().join(", "));
+                return Err(ExitCode::FAILURE);
+            } else {
+                // No bins defined, fall back to main.main or main
+                find_func("main.main")
+                    .or_else(|| find_func("main"))
+                    .unwrap_or_else(|| {
+                        eprintln!("Error: No 'main.main' or 'main' function found in project.");
+                        std::process::exit(1);
+                    })
+            }
+        }
+        // No project config, fall back to main.main or main
+        else {
+            find_func("main.main")
+                .or_else(|| find_func("main"))
+                .unwrap_or_else(|| {
+                    eprintln!("Error: No 'main.main' or 'main' function found in project.");
+                    std::process::exit(1);
+                })
+        }
+    } else {
+        "main/".to_string()
+    };
+
+    // Verify entry point exists
+    if compiler.get_function(&entry_point_name).is_none() {
+        eprintln!("Error: Entry point '{}' not found", entry_point_name);
+        return Err(ExitCode::FAILURE);
+    }
+
+    Ok(entry_point_name)
+}
+
 /// Run program using the tokio-based AsyncVM.
+#[allow(clippy::too_many_arguments)]
 fn run_with_async_vm(
-    compiler: &Compiler,
+    compiler: Compiler,
+    input_path: &Path,
     entry_point_name: &str,
     profiling_enabled: bool,
+    sampling: Option<(PathBuf, SamplerConfig)>,
+    node: Option<NodeConfig>,
     enable_jit: bool,
     ext_mgr: Option<std::sync::Arc<nostos_vm::ExtensionManager>>,
 ) -> ExitCode {
+    let (sample_path, sampler) = sampling.unzip();
     let config = AsyncConfig {
         profiling_enabled,
+        sampler,
         ..AsyncConfig::default()
     };
     let mut vm = AsyncVM::new(config);
+    prepare_vm(&mut vm, &compiler, enable_jit, ext_mgr);
+    vm.set_code_loader(code_loader(compiler, input_path));
+    if let Some(node) = node {
+        if let Err(e) = vm.start_node(node) {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    let code = run_entry_point(&mut vm, entry_point_name);
+    if enable_jit {
+        save_jit_decisions(&vm);
+    }
+    if let (Some(path), Some(samples)) = (sample_path, vm.stack_samples()) {
+        write_stack_samples(&samples, &path, entry_point_name);
+    }
+    code
+}
+
+/// Recompile modules of the running program for `Code.reload`. Reusing the
+/// program's compiler keeps the function indices of the running code valid.
+fn code_loader(compiler: Compiler, input_path: &Path) -> CodeLoader {
+    let compiler = std::sync::Mutex::new(compiler);
+    let input_path = input_path.to_path_buf();
+    std::sync::Arc::new(move |module: &str| {
+        let (path, module_path) = module_source_path(&input_path, module)?;
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (parsed, errors) = parse(&source);
+        if let Some(error) = parse_errors_to_source_errors(&errors).first() {
+            let (line, _) = nostos_syntax::offset_to_line_col(&source, error.span.start);
+            return Err(format!("{}:{}: {}", path.display(), line, error.message));
+        }
+        let parsed = parsed.ok_or_else(|| format!("{}: failed to parse", path.display()))?;
+
+        let mut compiler = compiler.lock().map_err(|_| "compiler is unavailable".to_string())?;
+        // Drop the old definitions so they don't constrain the new ones; their
+        // function indices are kept and reused
+        let prefix = if module_path.is_empty() { String::new() } else { format!("{}.", module_path.join(".")) };
+        for item in &parsed.items {
+            if let nostos_syntax::ast::Item::FnDef(fn_def) = item {
+                compiler.remove_function(&format!("{}{}", prefix, fn_def.name.node));
+            }
+        }
+        let source_name = path.to_string_lossy().to_string();
+        compiler.add_module(&parsed, module_path, std::sync::Arc::new(source), source_name)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        compiler.compile_all()
+            .map_err(|(e, filename, _)| format!("{}: {}", filename, e))?;
+        Ok(ReloadedCode {
+            function_names: compiler.get_function_list_names().to_vec(),
+            functions: compiler.get_all_functions().clone(),
+            types: compiler.get_vm_types(),
+        })
+    })
+}
+
+/// Source file and module path of a module of the program at `input_path`:
+/// `"services.cache"` is `services/cache.nos` in a project directory, and a
+/// single-file program is a module named after its file.
+fn module_source_path(input_path: &Path, module: &str) -> Result<(PathBuf, Vec<String>), String> {
+    if input_path.is_dir() {
+        let module_path: Vec<String> = module.split('.').map(str::to_string).collect();
+        let mut path = input_path.join(module_path.join("/"));
+        path.set_extension("nos");
+        if module_path.iter().any(|part| part.is_empty()) || !path.is_file() {
+            return Err(format!("no module named {}", module));
+        }
+        Ok((path, module_path))
+    } else if input_path.file_stem().is_some_and(|stem| stem == module) {
+        Ok((input_path.to_path_buf(), vec![]))
+    } else {
+        Err(format!("no module named {}", module))
+    }
+}
+
+/// Write the samples of a `--sample` run: speedscope JSON for `.json` files,
+/// folded stacks otherwise.
+fn write_stack_samples(samples: &StackSamples, path: &Path, name: &str) {
+    let contents = if path.extension().is_some_and(|ext| ext == "json") {
+        samples.to_speedscope(name)
+    } else {
+        samples.to_folded()
+    };
+    match std::fs::write(path, contents) {
+        Ok(()) => eprintln!("Wrote {} stack samples to {}", samples.total(), path.display()),
+        Err(e) => eprintln!("Warning: Failed to write stack samples to {}: {}", path.display(), e),
+    }
+}
+
+/// Remember the functions JIT-compiled in this run, so the next run compiles
+/// them at startup. Entries of other programs are kept.
+fn save_jit_decisions(vm: &AsyncVM) {
+    let path = get_jit_cache_path();
+    let mut cache = JitCache::load(&path, env!("CARGO_PKG_VERSION"));
+    if vm.record_jit_decisions(&mut cache) {
+        if let Err(e) = cache.save(&path) {
+            eprintln!("Warning: Failed to save JIT cache: {}", e);
+        }
+    }
+}
+
+/// Run the entry point of a prepared VM, printing a non-unit result.
+fn run_entry_point(vm: &mut AsyncVM, entry_point_name: &str) -> ExitCode {
+    // Set up Ctrl+C handler - exit immediately since IO operations may block
+    // and not check the interrupt flag
+    if let Err(e) = ctrlc::set_handler(move || {
+        eprintln!("\nInterrupted");
+        std::process::exit(130); // 128 + SIGINT(2)
+    }) {
+        eprintln!("Warning: Could not set Ctrl+C handler: {}", e);
+    }
+
+    // Run the program
+    match vm.run(entry_point_name) {
+        Ok(result) => {
+            if !result.is_unit() {
+                println!("{}", result.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) if e.contains("Interrupted") => {
+            eprintln!("Interrupted");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Runtime error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
+/// Load a compiled program into the VM: natives, functions, types and mvars,
+/// run module initializers and JIT-compile suitable functions.
+fn prepare_vm(
+    vm: &mut AsyncVM,
+    compiler: &Compiler,
+    enable_jit: bool,
+    ext_mgr: Option<std::sync::Arc<nostos_vm::ExtensionManager>>,
+) {
     // Register default native functions
     vm.register_default_natives();
 
@@ -1664,13 +1916,24 @@ fn run_with_async_vm(
     }
 
     // JIT compile suitable functions (unless --no-jit was specified)
-    let function_list = compiler.get_function_list();
     if enable_jit {
+        jit_compile_functions(vm, &compiler.get_function_list());
+        // Everything else is compiled by the baseline and optimizing tiers once hot
+        if let Ok(sender) = spawn_jit_compiler(JitConfig::default()) {
+            vm.enable_tiered_jit(sender, JIT_THRESHOLD);
+            // Compile what was hot in earlier runs without waiting for it to get hot again
+            vm.jit_warm_start(&JitCache::load(&get_jit_cache_path(), env!("CARGO_PKG_VERSION")));
+        }
+    }
+}
+
+/// JIT-compile suitable functions of the VM's function list and register them.
+fn jit_compile_functions(vm: &mut AsyncVM, function_list: &[std::sync::Arc<nostos_vm::value::FunctionValue>]) {
     if let Ok(mut jit) = JitCompiler::new(JitConfig::default()) {
         for idx in 0..function_list.len() {
             jit.queue_compilation(idx as u16);
         }
-        if let Ok(compiled) = jit.process_queue(&function_list) {
+        if let Ok(compiled) = jit.process_queue(function_list) {
             if compiled > 0 {
                 for (idx, _func) in function_list.iter().enumerate() {
                     if let Some(jit_fn) = jit.get_int_function_0(idx as u16) {
@@ -1723,34 +1986,6 @@ fn run_with_async_vm(
             }
         }
     }
-    }
-
-    // Set up Ctrl+C handler - exit immediately since IO operations may block
-    // and not check the interrupt flag
-    if let Err(e) = ctrlc::set_handler(move || {
-        eprintln!("\nInterrupted");
-        std::process::exit(130); // 128 + SIGINT(2)
-    }) {
-        eprintln!("Warning: Could not set Ctrl+C handler: {}", e);
-    }
-
-    // Run the program
-    match vm.run(entry_point_name) {
-        Ok(result) => {
-            if !result.is_unit() {
-                println!("{}", result.display());
-            }
-            ExitCode::SUCCESS
-        }
-        Err(e) if e.contains("Interrupted") => {
-            eprintln!("Interrupted");
-            ExitCode::FAILURE
-        }
-        Err(e) => {
-            eprintln!("Runtime error: {}", e);
-            ExitCode::FAILURE
-        }
-    }
 }
 
 const REGISTRY_URL: &str = "https://raw.githubusercontent.com/pegesund/nostos/master/nostlets-registry.json";
@@ -2561,213 +2796,43 @@ fn nostlet_installed() -> ExitCode {
     ExitCode::SUCCESS
 }
 
-fn main() -> ExitCode {
-    let args: Vec<String> = env::args().collect();
+/// Options controlling how a program or project is loaded and compiled.
+struct LoadOptions {
+    /// Extension libraries to load (.so/.dylib paths)
+    extension_paths: Vec<String>,
+    /// Installed extensions to load by name from ~/.nostos/extensions/
+    use_extensions: Vec<String>,
+    /// Lower `test` blocks in project modules into runnable functions (for `nostos test`)
+    include_tests: bool,
+}
 
-    if args.len() < 2 {
-        eprintln!("Usage: nostos [options] <command|file.nos> [args...]");
-        eprintln!();
-        eprintln!("Commands:");
-        eprintln!("  init        Create a new Nostos project");
-        eprintln!("  repl        Start the interactive REPL");
-        eprintln!("  tui         Start the TUI editor");
-        eprintln!("  connect     Connect to a running REPL server");
-        eprintln!("  extension   Manage native Rust extensions");
-        eprintln!("  nostlet     Manage nostlets (pure Nostos plugins)");
-        eprintln!();
-        eprintln!("Run a Nostos program file or start the REPL.");
-        eprintln!();
-        eprintln!("Options:");
-        eprintln!("  --help      Show detailed help message");
-        eprintln!("  --version   Show version information");
-        return ExitCode::FAILURE;
-    }
+/// A compiled program, ready to be loaded into the VM.
+struct LoadedProgram {
+    compiler: Compiler,
+    project_config: Option<nostos_source::ProjectConfig>,
+    ext_mgr: Option<std::sync::Arc<nostos_vm::ExtensionManager>>,
+    /// Runtime backing the extension manager - must outlive program execution
+    _ext_runtime: Option<tokio::runtime::Runtime>,
+    /// Tests discovered in project modules (only populated with `include_tests`)
+    tests: Vec<test_runner::DiscoveredTest>,
+}
 
-    // Check for subcommands
-    if args.len() >= 2 {
-        if args[1] == "repl" {
-            return run_repl(&args[2..]);
-        }
-        if args[1] == "tui" {
-            return tui::run_tui(&args[2..]);
-        }
-        if args[1] == "init" {
-            return run_init_command(&args[2..]);
-        }
-        if args[1] == "nostlet" {
-            return run_nostlet_command(&args[2..]);
-        }
-        if args[1] == "extension" {
-            return run_extension_command(&args[2..]);
-        }
-        if args[1] == "cache" {
-            return run_cache_command(&args[2..]);
-        }
-        if args[1] == "connect" {
-            return connect::run_connect(&args[2..]);
-        }
-    }
+/// Load and compile a single file or a project directory, together with the
+/// stdlib, extensions and package dependencies it needs.
+///
+/// Errors are reported to stderr; the returned exit code is what the caller should exit with.
+fn load_program(input_path: &std::path::Path, options: LoadOptions) -> Result<LoadedProgram, ExitCode> {
+    let LoadOptions { mut extension_paths, use_extensions, include_tests } = options;
+    let file_path_arg = input_path.display();
+    let mut tests: Vec<test_runner::DiscoveredTest> = Vec::new();
 
-    // Parse options
-    let mut enable_jit = true;
-    let mut profiling_enabled = false; // Enable function call profiling
-    let mut extension_paths: Vec<String> = Vec::new(); // Extension library paths
-    let mut use_extensions: Vec<String> = Vec::new(); // Extensions to load by name from ~/.nostos/extensions/
-    let mut bin_name: Option<String> = None; // Binary entry point name from [[bin]] in nostos.toml
-
-    let mut i = 1;
-    let mut file_idx: Option<usize> = None;
-    while i < args.len() {
-        let arg = &args[i];
-        if arg.starts_with("--") || arg.starts_with("-") {
-            if arg == "--help" || arg == "-h" {
-                println!("Nostos - A functional programming language with native extensions");
-                println!();
-                println!("USAGE:");
-                println!("    nostos <file.nos>              Run a single file");
-                println!("    nostos <directory/>            Run a project (needs main.nos)");
-                println!("    nostos <dir/> --bin NAME       Run specific entry point from project");
-                println!("    nostos --use <ext> <file.nos>  Run with an extension");
-                println!("    nostos repl                    Start interactive TUI/REPL");
-                println!();
-                println!("EXAMPLES:");
-                println!("    nostos hello.nos                       # Run a program");
-                println!("    nostos myproject/                      # Run a project");
-                println!("    nostos myproject/ --bin server         # Run 'server' entry point");
-                println!("    nostos --use nalgebra script.nos       # Use nalgebra extension");
-                println!("    nostos --profile slow_program.nos      # Profile for performance");
-                println!();
-                println!("EXTENSIONS:");
-                println!("    --use NAME        Load installed extension from ~/.nostos/extensions/");
-                println!("                      Example: --use nalgebra, --use redis");
-                println!("    --extension PATH  Load extension directly from .so/.dylib file");
-                println!();
-                println!("    Projects can also declare extensions in nostos.toml:");
-                println!("        [extensions]");
-                println!("        nalgebra = {{ git = \"https://github.com/user/nostos-nalgebra\" }}");
-                println!();
-                println!("ENTRY POINTS:");
-                println!("    --bin NAME, -b    Run specific entry point from [[bin]] in nostos.toml");
-                println!("                      Example: --bin server, -b cli");
-                println!();
-                println!("    Define entry points in nostos.toml:");
-                println!("        [[bin]]");
-                println!("        name = \"server\"");
-                println!("        entry = \"server.main\"");
-                println!("        default = true");
-                println!();
-                println!("PERFORMANCE:");
-                println!("    --threads N       Use N worker threads (default: all CPUs)");
-                println!("    --profile         Show function call timing after execution");
-                println!("    --no-jit          Disable JIT compilation");
-                println!();
-                println!("DEBUGGING:");
-                println!("    --debug           Show local variables in stack traces");
-                println!("    --json-errors     Output errors as JSON (for IDE integration)");
-                println!();
-                println!("COMMANDS:");
-                println!("    init [name]       Create a new project (in current dir or new dir)");
-                println!("    repl              Start the interactive TUI with editor and REPL");
-                println!("    tui               Same as repl");
-                println!("    extension install Install a native extension from GitHub");
-                println!("    extension list    List installed extensions");
-                println!("    nostlet list      List available nostlets from registry");
-                println!("    nostlet install   Install a nostlet plugin");
-                println!();
-                println!("MORE INFO:");
-                println!("    --help            Show this help");
-                println!("    --build-cache     Build stdlib bytecode cache");
-                println!("    --clear-cache     Clear the bytecode cache");
-                println!("    --version         Show version");
-                println!();
-                println!("Documentation: https://pegesund.github.io/nostos/tutorial/24_command_line.html");
-                return ExitCode::SUCCESS;
-            }
-            if arg == "--version" || arg == "-v" {
-                println!("nostos {}", env!("CARGO_PKG_VERSION"));
-                return ExitCode::SUCCESS;
-            }
-            if arg == "--no-jit" {
-                enable_jit = false;
-                i += 1;
-                continue;
-            }
-            if arg == "--build-cache" {
-                // Build and save stdlib bytecode cache
-                return build_stdlib_cache();
-            }
-            if arg == "--clear-cache" {
-                // Clear the bytecode cache
-                return clear_bytecode_cache();
-            }
-            if arg == "--profile" {
-                profiling_enabled = true;
-                // JIT profiling is now supported - JIT functions show as "[JIT] function_name"
-                i += 1;
-                continue;
-            }
-            if arg == "--extension" || arg == "-e" {
-                // Load extension from shared library
-                if i + 1 < args.len() {
-                    extension_paths.push(args[i + 1].clone());
-                    i += 2;
-                    continue;
-                } else {
-                    eprintln!("Error: --extension requires a path argument");
-                    return ExitCode::FAILURE;
-                }
-            }
-            if arg == "--use" || arg == "-u" {
-                // Load installed extension by name from ~/.nostos/extensions/
-                if i + 1 < args.len() {
-                    use_extensions.push(args[i + 1].clone());
-                    i += 2;
-                    continue;
-                } else {
-                    eprintln!("Error: --use requires an extension name");
-                    return ExitCode::FAILURE;
-                }
-            }
-            if arg == "--bin" || arg == "-b" {
-                // Specify which binary entry point to run (from [[bin]] in nostos.toml)
-                if i + 1 < args.len() {
-                    bin_name = Some(args[i + 1].clone());
-                    i += 2;
-                    continue;
-                } else {
-                    eprintln!("Error: --bin requires a binary name");
-                    return ExitCode::FAILURE;
-                }
-            }
-            i += 1;
-        } else {
-            // First non-flag argument is the file
-            if file_idx.is_none() {
-                file_idx = Some(i);
-            }
-            i += 1;
-        }
-    }
-
-    let file_idx = match file_idx {
-        Some(idx) => idx,
-        None => {
-            eprintln!("Error: No input file specified");
-            eprintln!("Use 'nostos repl' to start the interactive REPL");
-            return ExitCode::FAILURE;
-        }
-    };
-
-    let file_path_arg = &args[file_idx];
-    let input_path = std::path::Path::new(file_path_arg);
-
-    // Look for nostos.toml and auto-load extensions
-    let search_dir = if input_path.is_dir() {
-        std::fs::canonicalize(input_path).unwrap_or_else(|_| input_path.to_path_buf())
-    } else {
-        let parent = input_path.parent().unwrap_or_else(|| std::path::Path::new("."));
-        std::fs::canonicalize(parent).unwrap_or_else(|_| parent.to_path_buf())
-    };
+    // Look for nostos.toml and auto-load extensions
+    let search_dir = if input_path.is_dir() {
+        std::fs::canonicalize(input_path).unwrap_or_else(|_| input_path.to_path_buf())
+    } else {
+        let parent = input_path.parent().unwrap_or_else(|| std::path::Path::new("."));
+        std::fs::canonicalize(parent).unwrap_or_else(|_| parent.to_path_buf())
+    };
 
     // Store extension module directories for loading later
     let mut extension_module_dirs: Vec<(String, std::path::PathBuf)> = Vec::new();
@@ -2833,7 +2898,7 @@ fn main() -> ExitCode {
             Some(h) => h,
             None => {
                 eprintln!("Error: Could not determine home directory");
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         };
 
@@ -2855,7 +2920,7 @@ fn main() -> ExitCode {
                 eprintln!();
                 eprintln!("Install extensions with: nostos nostlet install <name>");
                 eprintln!("Or use --extension <path> to load a .so file directly");
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         };
 
@@ -2873,7 +2938,7 @@ fn main() -> ExitCode {
             None => {
                 eprintln!("Error: Extension '{}' library not found. Build it first:", ext_name);
                 eprintln!("  cd {} && cargo build --release", ext_dir.display());
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         };
 
@@ -2890,26 +2955,27 @@ fn main() -> ExitCode {
 
         // Check for main.nos in the directory (unless project has [[bin]] entries)
         let main_file = input_path.join("main.nos");
+        // Test runs discover tests in every module and don't need an entry point.
         let has_bin_entries = project_config.as_ref().map(|c| c.has_bins()).unwrap_or(false);
-        if !main_file.exists() && !has_bin_entries {
+        if !main_file.exists() && !has_bin_entries && !include_tests {
             eprintln!("Error: No 'main.nos' found in directory '{}'", file_path_arg);
             eprintln!("Projects must have a main.nos file with a main() function,");
             eprintln!("or define entry points with [[bin]] in nostos.toml.");
-            return ExitCode::FAILURE;
+            return Err(ExitCode::FAILURE);
         }
 
         match visit_dirs(input_path, &mut source_files) {
             Ok(_) => {},
             Err(e) => {
                 eprintln!("Error scanning directory '{}': {}", file_path_arg, e);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         }
         // Sort files for deterministic compilation order across filesystems
         source_files.sort();
         if source_files.is_empty() {
             eprintln!("No .nos files found in '{}'", file_path_arg);
-            return ExitCode::FAILURE;
+            return Err(ExitCode::FAILURE);
         }
     } else {
         project_root = input_path.parent().unwrap_or_else(|| std::path::Path::new("."));
@@ -2934,7 +3000,7 @@ fn main() -> ExitCode {
                 Ok(msg) => eprintln!("{}", msg),
                 Err(e) => {
                     eprintln!("Error loading extension '{}': {}", path, e);
-                    return ExitCode::FAILURE;
+                    return Err(ExitCode::FAILURE);
                 }
             }
         }
@@ -3045,12 +3111,12 @@ fn main() -> ExitCode {
             if let Err((e, filename, source)) = compiler.compile_all() {
                 let source_error = e.to_source_error();
                 source_error.eprint(&filename, &source);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         } // end of else (no cache)
     } else {
         eprintln!("Error: Could not find or extract stdlib. Methods like .map(), .filter() won't be available.");
-        return ExitCode::FAILURE;
+        return Err(ExitCode::FAILURE);
     }
 
     // Load extension modules (.nos wrapper files from extension repos)
@@ -3192,7 +3258,8 @@ fn main() -> ExitCode {
 
     // Try to load project modules from cache (only for directory projects with multiple files)
     let mut project_modules_to_cache: Vec<(String, PathBuf, String)> = Vec::new();
-    let project_cache_used = if input_path.is_dir() && source_files.len() > 1 {
+    // The project cache never contains lowered tests, so test runs always compile from source.
+    let project_cache_used = if input_path.is_dir() && source_files.len() > 1 && !include_tests {
         if let Some(cache_result) = try_load_project_from_cache(&mut compiler, project_root, &source_files) {
             eprintln!("Loaded project from cache ({} modules, {} functions)",
                 cache_result.modules_loaded, cache_result.functions_loaded);
@@ -3225,7 +3292,7 @@ fn main() -> ExitCode {
                 Ok(s) => s,
                 Err(e) => {
                     eprintln!("Error reading file '{}': {}", path.display(), e);
-                    return ExitCode::FAILURE;
+                    return Err(ExitCode::FAILURE);
                 }
             };
 
@@ -3236,14 +3303,14 @@ fn main() -> ExitCode {
             if !errors.is_empty() {
                 let source_errors = parse_errors_to_source_errors(&errors);
                 eprint_errors(&source_errors, path.to_str().unwrap_or("unknown"), &source);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
 
             let mut module = match module_opt {
                 Some(m) => m,
                 None => {
                     eprintln!("Failed to parse '{}'", path.display());
-                    return ExitCode::FAILURE;
+                    return Err(ExitCode::FAILURE);
                 }
             };
 
@@ -3274,11 +3341,19 @@ fn main() -> ExitCode {
                 vec![]
             };
 
+            // Turn `test` blocks into ordinary functions before the compiler sees the module
+            if include_tests {
+                for case in nostos_compiler::compile::lower_test_defs(&mut module, &module_path) {
+                    let (line, _) = nostos_syntax::offset_to_line_col(&source, case.span.start);
+                    tests.push(test_runner::DiscoveredTest { case, file: path.clone(), line });
+                }
+            }
+
             // Register forward declarations before any compilation
             // This ensures all exports are known when use statements are processed
             if let Err(e) = compiler.register_module_forward_declarations(&module, module_path.clone()) {
                 eprintln!("Error registering forward declarations for '{}': {}", path.display(), e);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
 
             parsed_modules.push(ParsedProjectModule {
@@ -3310,7 +3385,7 @@ fn main() -> ExitCode {
             ) {
                 let source_error = e.to_source_error();
                 source_error.eprint(parsed.path.to_str().unwrap_or("unknown"), &parsed.source);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         }
 
@@ -3324,7 +3399,7 @@ fn main() -> ExitCode {
             if let Err(e) = compiler.forward_declare_module_functions(&parsed.module, parsed.module_path.clone()) {
                 let source_error = e.to_source_error();
                 source_error.eprint(parsed.path.to_str().unwrap_or("unknown"), &parsed.source);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         }
 
@@ -3340,7 +3415,7 @@ fn main() -> ExitCode {
             if let Err(e) = compiler.add_module(&module, module_path, std::sync::Arc::new(source.clone()), path.to_str().unwrap_or("unknown").to_string()) {
                 let source_error = e.to_source_error();
                 source_error.eprint(path.to_str().unwrap_or("unknown"), &source);
-                return ExitCode::FAILURE;
+                return Err(ExitCode::FAILURE);
             }
         }
     }
@@ -3350,7 +3425,7 @@ fn main() -> ExitCode {
         if let Err((e, filename, source)) = compiler.compile_all() {
             let source_error = e.to_source_error();
             source_error.eprint(&filename, &source);
-            return ExitCode::FAILURE;
+            return Err(ExitCode::FAILURE);
         }
     }
 
@@ -3368,97 +3443,348 @@ fn main() -> ExitCode {
         }
     }
 
-    // Save project modules to cache (after successful compile_all)
-    if !project_cache_used && !project_modules_to_cache.is_empty() {
+    // Save project modules to cache (after successful compile_all).
+    // Test builds contain extra functions and must not overwrite the regular cache.
+    if !project_cache_used && !include_tests && !project_modules_to_cache.is_empty() {
         if let Err(e) = save_project_to_cache(&compiler, project_root, &project_modules_to_cache) {
             eprintln!("Warning: Failed to cache project: {}", e);
         }
     }
 
-    // Resolve entry point (function names now include signature, main has no params so it's "main/")
-    let entry_point_name = if input_path.is_dir() {
-        let funcs = compiler.get_all_functions();
+    Ok(LoadedProgram {
+        compiler,
+        project_config,
+        ext_mgr,
+        _ext_runtime,
+        tests,
+    })
+}
 
-        // Helper to find function with signature suffix
-        let find_func = |base: &str| -> Option<String> {
-            let with_slash = format!("{}/", base);
-            if funcs.contains_key(&with_slash) {
-                Some(with_slash)
-            } else {
-                funcs.keys()
-                    .find(|k| k.starts_with(&with_slash))
-                    .cloned()
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+
+    // A standalone executable built by `nostos build` runs its embedded program
+    if let Some(image) = standalone::embedded_image() {
+        return match image {
+            Ok(image) => standalone::run_image(image),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
             }
         };
+    }
 
-        // Check if --bin was specified
-        if let Some(ref name) = bin_name {
-            if let Some(ref cfg) = project_config {
-                if let Some(bin_entry) = cfg.get_bin(name) {
-                    // Convert "module.func" to "module.func/"
-                    match find_func(&bin_entry.entry) {
-                        Some(f) => f,
-                        None => {
-                            eprintln!("Error: Entry point '{}' for bin '{}' not found", bin_entry.entry, name);
-                            return ExitCode::FAILURE;
-                        }
+    if args.len() < 2 {
+        eprintln!("Usage: nostos [options] <command|file.nos> [args...]");
+        eprintln!();
+        eprintln!("Commands:");
+        eprintln!("  init        Create a new Nostos project");
+        eprintln!("  repl        Start the interactive REPL");
+        eprintln!("  tui         Start the TUI editor");
+        eprintln!("  connect     Connect to a running REPL server");
+        eprintln!("  test        Run `test` blocks in a file or project");
+        eprintln!("  build       Build a standalone executable");
+        eprintln!("  fmt         Format source files");
+        eprintln!("  heap        Analyze a heap snapshot");
+        eprintln!("  extension   Manage native Rust extensions");
+        eprintln!("  nostlet     Manage nostlets (pure Nostos plugins)");
+        eprintln!();
+        eprintln!("Run a Nostos program file or start the REPL.");
+        eprintln!();
+        eprintln!("Options:");
+        eprintln!("  --help      Show detailed help message");
+        eprintln!("  --version   Show version information");
+        return ExitCode::FAILURE;
+    }
+
+    // Check for subcommands
+    if args.len() >= 2 {
+        if args[1] == "repl" {
+            return run_repl(&args[2..]);
+        }
+        if args[1] == "tui" {
+            return tui::run_tui(&args[2..]);
+        }
+        if args[1] == "init" {
+            return run_init_command(&args[2..]);
+        }
+        if args[1] == "nostlet" {
+            return run_nostlet_command(&args[2..]);
+        }
+        if args[1] == "extension" {
+            return run_extension_command(&args[2..]);
+        }
+        if args[1] == "cache" {
+            return run_cache_command(&args[2..]);
+        }
+        if args[1] == "connect" {
+            return connect::run_connect(&args[2..]);
+        }
+        if args[1] == "test" {
+            return test_runner::run_test_command(&args[2..]);
+        }
+        if args[1] == "build" {
+            return standalone::run_build_command(&args[2..]);
+        }
+        if args[1] == "fmt" {
+            return fmt::run_fmt_command(&args[2..]);
+        }
+        if args[1] == "heap" {
+            return heap::run_heap_command(&args[2..]);
+        }
+    }
+
+    // Parse options
+    let mut enable_jit = true;
+    let mut profiling_enabled = false; // Enable function call profiling
+    let mut sample_path: Option<PathBuf> = None; // Write sampled call stacks here
+    let mut sampler_config = SamplerConfig::default();
+    let mut extension_paths: Vec<String> = Vec::new(); // Extension library paths
+    let mut use_extensions: Vec<String> = Vec::new(); // Extensions to load by name from ~/.nostos/extensions/
+    let mut bin_name: Option<String> = None; // Binary entry point name from [[bin]] in nostos.toml
+    let mut node_name: Option<String> = None; // Run as distributed node name@host:port
+    let mut cookie: Option<String> = None; // Shared secret of connecting nodes
+
+    let mut i = 1;
+    let mut file_idx: Option<usize> = None;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg.starts_with("--") || arg.starts_with("-") {
+            if arg == "--help" || arg == "-h" {
+                println!("Nostos - A functional programming language with native extensions");
+                println!();
+                println!("USAGE:");
+                println!("    nostos <file.nos>              Run a single file");
+                println!("    nostos <directory/>            Run a project (needs main.nos)");
+                println!("    nostos <dir/> --bin NAME       Run specific entry point from project");
+                println!("    nostos --use <ext> <file.nos>  Run with an extension");
+                println!("    nostos repl                    Start interactive TUI/REPL");
+                println!();
+                println!("EXAMPLES:");
+                println!("    nostos hello.nos                       # Run a program");
+                println!("    nostos myproject/                      # Run a project");
+                println!("    nostos myproject/ --bin server         # Run 'server' entry point");
+                println!("    nostos --use nalgebra script.nos       # Use nalgebra extension");
+                println!("    nostos --profile slow_program.nos      # Profile for performance");
+                println!("    nostos --sample out.folded server.nos  # Sample stacks for a flamegraph");
+                println!("    nostos --node a@127.0.0.1:9000 --cookie s app.nos  # Run as a node");
+                println!();
+                println!("EXTENSIONS:");
+                println!("    --use NAME        Load installed extension from ~/.nostos/extensions/");
+                println!("                      Example: --use nalgebra, --use redis");
+                println!("    --extension PATH  Load extension directly from .so/.dylib file");
+                println!();
+                println!("    Projects can also declare extensions in nostos.toml:");
+                println!("        [extensions]");
+                println!("        nalgebra = {{ git = \"https://github.com/user/nostos-nalgebra\" }}");
+                println!();
+                println!("ENTRY POINTS:");
+                println!("    --bin NAME, -b    Run specific entry point from [[bin]] in nostos.toml");
+                println!("                      Example: --bin server, -b cli");
+                println!();
+                println!("    Define entry points in nostos.toml:");
+                println!("        [[bin]]");
+                println!("        name = \"server\"");
+                println!("        entry = \"server.main\"");
+                println!("        default = true");
+                println!();
+                println!("PERFORMANCE:");
+                println!("    --threads N       Use N worker threads (default: all CPUs)");
+                println!("    --profile         Show function call timing after execution");
+                println!("    --sample FILE     Sample the call stacks of running processes into FILE:");
+                println!("                      speedscope JSON if FILE ends in .json, else folded stacks");
+                println!("    --sample-interval MS  Time between samples of a process (default: 1)");
+                println!("    --sample-pid PID  Only sample this process (repeatable)");
+                println!("    --no-jit          Disable JIT compilation");
+                println!();
+                println!("DISTRIBUTION:");
+                println!("    --node NAME@HOST:PORT  Run as a node listening on HOST:PORT");
+                println!("    --cookie SECRET   Secret shared by nodes that may connect (default: $NOSTOS_COOKIE)");
+                println!();
+                println!("DEBUGGING:");
+                println!("    --debug           Show local variables in stack traces");
+                println!("    --json-errors     Output errors as JSON (for IDE integration)");
+                println!();
+                println!("COMMANDS:");
+                println!("    init [name]       Create a new project (in current dir or new dir)");
+                println!("    repl              Start the interactive TUI with editor and REPL");
+                println!("    tui               Same as repl");
+                println!("    test [path]       Run `test` blocks (see 'nostos test --help')");
+                println!("    build [path]      Build a standalone executable (see 'nostos build --help')");
+                println!("    fmt [paths]       Format source files (--check to only verify)");
+                println!("    heap <snapshot>   Analyze a heap snapshot written by Gc.snapshot");
+                println!("    extension install Install a native extension from GitHub");
+                println!("    extension list    List installed extensions");
+                println!("    nostlet list      List available nostlets from registry");
+                println!("    nostlet install   Install a nostlet plugin");
+                println!();
+                println!("MORE INFO:");
+                println!("    --help            Show this help");
+                println!("    --build-cache     Build stdlib bytecode cache");
+                println!("    --clear-cache     Clear the bytecode cache");
+                println!("    --version         Show version");
+                println!();
+                println!("Documentation: https://pegesund.github.io/nostos/tutorial/24_command_line.html");
+                return ExitCode::SUCCESS;
+            }
+            if arg == "--version" || arg == "-v" {
+                println!("nostos {}", env!("CARGO_PKG_VERSION"));
+                return ExitCode::SUCCESS;
+            }
+            if arg == "--no-jit" {
+                enable_jit = false;
+                i += 1;
+                continue;
+            }
+            if arg == "--build-cache" {
+                // Build and save stdlib bytecode cache
+                return build_stdlib_cache();
+            }
+            if arg == "--clear-cache" {
+                // Clear the bytecode cache
+                return clear_bytecode_cache();
+            }
+            if arg == "--profile" {
+                profiling_enabled = true;
+                // JIT profiling is now supported - JIT functions show as "[JIT] function_name"
+                i += 1;
+                continue;
+            }
+            if arg == "--sample" {
+                match args.get(i + 1) {
+                    Some(path) => sample_path = Some(PathBuf::from(path)),
+                    None => {
+                        eprintln!("Error: --sample requires an output file");
+                        return ExitCode::FAILURE;
                     }
-                } else {
-                    eprintln!("Error: No [[bin]] entry named '{}' in nostos.toml", name);
-                    if cfg.has_bins() {
-                        eprintln!("Available bins: {}", cfg.bin_names().join(", "));
+                }
+                i += 2;
+                continue;
+            }
+            if arg == "--sample-interval" {
+                match args.get(i + 1).and_then(|v| v.parse::<f64>().ok()) {
+                    Some(ms) if ms > 0.0 => sampler_config.interval = std::time::Duration::from_secs_f64(ms / 1000.0),
+                    _ => {
+                        eprintln!("Error: --sample-interval requires a positive number of milliseconds");
+                        return ExitCode::FAILURE;
                     }
+                }
+                i += 2;
+                continue;
+            }
+            if arg == "--sample-pid" {
+                match args.get(i + 1).and_then(|v| v.parse::<u64>().ok()) {
+                    Some(pid) => {
+                        sampler_config.pids.get_or_insert_with(Default::default).insert(pid);
+                    }
+                    None => {
+                        eprintln!("Error: --sample-pid requires a process id");
+                        return ExitCode::FAILURE;
+                    }
+                }
+                i += 2;
+                continue;
+            }
+            if arg == "--extension" || arg == "-e" {
+                // Load extension from shared library
+                if i + 1 < args.len() {
+                    extension_paths.push(args[i + 1].clone());
+                    i += 2;
+                    continue;
+                } else {
+                    eprintln!("Error: --extension requires a path argument");
                     return ExitCode::FAILURE;
                 }
-            } else {
-                eprintln!("Error: --bin requires a nostos.toml with [[bin]] entries");
-                return ExitCode::FAILURE;
             }
-        }
-        // Check for default bin in project config
-        else if let Some(ref cfg) = project_config {
-            if let Some(default_bin) = cfg.get_default_bin() {
-                match find_func(&default_bin.entry) {
-                    Some(f) => f,
+            if arg == "--use" || arg == "-u" {
+                // Load installed extension by name from ~/.nostos/extensions/
+                if i + 1 < args.len() {
+                    use_extensions.push(args[i + 1].clone());
+                    i += 2;
+                    continue;
+                } else {
+                    eprintln!("Error: --use requires an extension name");
+                    return ExitCode::FAILURE;
+                }
+            }
+            if arg == "--node" || arg == "--cookie" {
+                match args.get(i + 1) {
+                    Some(value) if arg == "--node" => node_name = Some(value.clone()),
+                    Some(value) => cookie = Some(value.clone()),
                     None => {
-                        eprintln!("Error: Default entry point '{}' not found", default_bin.entry);
+                        eprintln!("Error: {} requires an argument", arg);
                         return ExitCode::FAILURE;
                     }
                 }
-            } else if cfg.has_bins() {
-                // Has bins but none is default - require --bin
-                eprintln!("Error: Project has [[bin]] entries but none is marked as default.");
-                eprintln!("Use --bin NAME to specify which to run. Available: {}", cfg.bin_names().join(", "));
-                return ExitCode::FAILURE;
-            } else {
-                // No bins defined, fall back to main.main or main
-                find_func("main.main")
-                    .or_else(|| find_func("main"))
-                    .unwrap_or_else(|| {
-                        eprintln!("Error: No 'main.main' or 'main' function found in project.");
-                        std::process::exit(1);
-                    })
+                i += 2;
+                continue;
             }
+            if arg == "--bin" || arg == "-b" {
+                // Specify which binary entry point to run (from [[bin]] in nostos.toml)
+                if i + 1 < args.len() {
+                    bin_name = Some(args[i + 1].clone());
+                    i += 2;
+                    continue;
+                } else {
+                    eprintln!("Error: --bin requires a binary name");
+                    return ExitCode::FAILURE;
+                }
+            }
+            i += 1;
+        } else {
+            // First non-flag argument is the file
+            if file_idx.is_none() {
+                file_idx = Some(i);
+            }
+            i += 1;
         }
-        // No project config, fall back to main.main or main
-        else {
-            find_func("main.main")
-                .or_else(|| find_func("main"))
-                .unwrap_or_else(|| {
-                    eprintln!("Error: No 'main.main' or 'main' function found in project.");
-                    std::process::exit(1);
-                })
+    }
+
+    let file_idx = match file_idx {
+        Some(idx) => idx,
+        None => {
+            eprintln!("Error: No input file specified");
+            eprintln!("Use 'nostos repl' to start the interactive REPL");
+            return ExitCode::FAILURE;
         }
-    } else {
-        "main/".to_string()
     };
 
-    // Verify entry point exists
-    if compiler.get_function(&entry_point_name).is_none() {
-        eprintln!("Error: Entry point '{}' not found", entry_point_name);
-        return ExitCode::FAILURE;
-    }
+    let node = match node_name {
+        Some(name) => {
+            let Some(cookie) = cookie.or_else(|| std::env::var("NOSTOS_COOKIE").ok()) else {
+                eprintln!("Error: --node requires a cookie (--cookie SECRET or NOSTOS_COOKIE)");
+                return ExitCode::FAILURE;
+            };
+            match NodeConfig::new(&name, &cookie) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let file_path_arg = &args[file_idx];
+    let input_path = std::path::Path::new(file_path_arg);
+
+    let options = LoadOptions {
+        extension_paths,
+        use_extensions,
+        include_tests: false,
+    };
+    let LoadedProgram { compiler, project_config, ext_mgr, _ext_runtime, .. } = match load_program(input_path, options) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+
+    let entry_point_name = match resolve_entry_point(&compiler, input_path, project_config.as_ref(), bin_name.as_deref()) {
+        Ok(name) => name,
+        Err(code) => return code,
+    };
 
     // Run with AsyncVM
-    run_with_async_vm(&compiler, &entry_point_name, profiling_enabled, enable_jit, ext_mgr)
+    let sampling = sample_path.map(|path| (path, sampler_config));
+    run_with_async_vm(compiler, input_path, &entry_point_name, profiling_enabled, sampling, node, enable_jit, ext_mgr)
 }
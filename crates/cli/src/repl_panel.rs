@@ -38,6 +38,8 @@ use crate::autocomplete::{Autocomplete, CompletionContext, CompletionItem, Compl
 pub enum ReplPanelCommand {
     /// Open the tutorial panel
     OpenTutorial,
+    /// Open the trace panel (after `:trace`)
+    OpenTrace,
 }
 
 /// Wrapper to implement CompletionSource for ReplEngine
@@ -463,6 +465,9 @@ impl ReplPanel {
                     // Don't show the internal command in output
                     ReplOutput::Definition(String::new())
                 } else {
+                    if self.current.input.first().is_some_and(|line| line.trim_start().starts_with(":trace ")) {
+                        self.pending_tui_command = Some(ReplPanelCommand::OpenTrace);
+                    }
                     // Prepend spawned output BEFORE the result (println happens during execution)
                     let mut output = String::new();
                     for line in spawned_output {
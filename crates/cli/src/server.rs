@@ -57,6 +57,8 @@ fn parse_command(line: &str, response_tx: Sender<ServerResponse>) -> Option<Serv
     // Expected format: {"id": 1, "cmd": "load", "file": "main.nos"}
     // or: {"id": 2, "cmd": "eval", "code": "1 + 2"}
     // or: {"id": 3, "cmd": "reload"}
+    // or: {"id": 4, "cmd": "upgrade", "module": "counter"}
+    // or: {"id": 5, "cmd": "trace", "args": "4 send receive"}
 
     let line = line.trim();
     if !line.starts_with('{') || !line.ends_with('}') {
@@ -78,7 +80,7 @@ fn parse_command(line: &str, response_tx: Sender<ServerResponse>) -> Option<Serv
             "cmd" => {
                 cmd = value.clone();
             }
-            "file" | "code" | "expr" => {
+            "file" | "code" | "expr" | "module" | "args" => {
                 args = value.clone();
             }
             "pos" => {
@@ -0,0 +1,375 @@
+//! Standalone executables (`nostos build`).
+//!
+//! A standalone executable is a copy of the `nostos` binary with a compiled
+//! program image appended to it:
+//!
+//! ```text
+//! [nostos binary][program image][image length: u64 LE][IMAGE_MAGIC]
+//! ```
+//!
+//! On startup `main` checks its own executable for the trailer and, if found,
+//! loads the image into a VM and runs its entry point without reading source,
+//! the stdlib directory or the bytecode cache.
+
+use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use nostos_compiler::compile::Compiler;
+use nostos_vm::async_vm::{AsyncConfig, AsyncVM};
+use nostos_vm::cache::{
+    cached_to_function, cached_to_function_with_resolver, decode_program_image,
+    encode_program_image, function_to_cached, CachedFunction, CachedModule, CachedMvar,
+    ProgramImage, PROGRAM_IMAGE_FORMAT_VERSION,
+};
+use nostos_vm::value::{FunctionValue, Value};
+
+use crate::{LoadOptions, LoadedProgram};
+
+/// Marks an executable that carries a program image
+const IMAGE_MAGIC: &[u8; 8] = b"NOSIMG01";
+
+/// Length of the trailer after the image: length + magic
+const TRAILER_LEN: u64 = 16;
+
+fn print_help() {
+    println!("Build a standalone executable from a Nostos project or file");
+    println!();
+    println!("USAGE:");
+    println!("    nostos build [path] [options]");
+    println!();
+    println!("    The executable contains the compiled project, its packages and the");
+    println!("    stdlib, and runs the entry point without needing source files.");
+    println!();
+    println!("OPTIONS:");
+    println!("    --bin NAME, -b NAME   Entry point from [[bin]] in nostos.toml");
+    println!("    -o, --output PATH     Output file (default: bin name or 'main')");
+    println!("    --help                Show this help");
+    println!();
+    println!("EXAMPLES:");
+    println!("    nostos build myproject/ --bin server -o server");
+    println!("    nostos build hello.nos -o hello");
+}
+
+/// Run the build subcommand
+pub fn run_build_command(args: &[String]) -> ExitCode {
+    let mut path: Option<String> = None;
+    let mut bin_name: Option<String> = None;
+    let mut output: Option<String> = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        let needs_value = matches!(arg, "--bin" | "-b" | "--output" | "-o");
+        if needs_value && i + 1 >= args.len() {
+            eprintln!("Error: {} requires an argument", arg);
+            return ExitCode::FAILURE;
+        }
+        match arg {
+            "--help" | "-h" => {
+                print_help();
+                return ExitCode::SUCCESS;
+            }
+            "--bin" | "-b" => bin_name = Some(args[i + 1].clone()),
+            "--output" | "-o" => output = Some(args[i + 1].clone()),
+            _ if arg.starts_with('-') => {
+                eprintln!("Error: Unknown option '{}'", arg);
+                return ExitCode::FAILURE;
+            }
+            _ => {
+                if path.is_some() {
+                    eprintln!("Error: Only one path can be given");
+                    return ExitCode::FAILURE;
+                }
+                path = Some(arg.to_string());
+            }
+        }
+        i += if needs_value { 2 } else { 1 };
+    }
+
+    let path = path.unwrap_or_else(|| ".".to_string());
+    let input_path = Path::new(&path);
+    let output = PathBuf::from(output.unwrap_or_else(|| {
+        bin_name.clone().unwrap_or_else(|| "main".to_string())
+    }));
+
+    let options = LoadOptions {
+        extension_paths: Vec::new(),
+        use_extensions: Vec::new(),
+        include_tests: false,
+    };
+    let LoadedProgram { compiler, project_config, ext_mgr, .. } = match crate::load_program(input_path, options) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+    if ext_mgr.is_some() {
+        eprintln!("Error: Projects using native extensions can't be built into a standalone executable");
+        return ExitCode::FAILURE;
+    }
+
+    let entry_point = match crate::resolve_entry_point(&compiler, input_path, project_config.as_ref(), bin_name.as_deref()) {
+        Ok(name) => name,
+        Err(code) => return code,
+    };
+
+    let result = build_image(&compiler, &entry_point)
+        .and_then(|image| encode_program_image(&image))
+        .and_then(|payload| write_executable(&output, &payload));
+    match result {
+        Ok(()) => {
+            println!("Built {} (entry point {})", output.display(), entry_point);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Module a qualified name belongs to ("" for top-level names).
+fn module_of(name: &str) -> &str {
+    let base = name.split('/').next().unwrap_or(name);
+    base.rsplit_once('.').map(|(module, _)| module).unwrap_or("")
+}
+
+/// The module named `path` in `modules`, created empty if needed.
+fn module_entry<'m>(modules: &'m mut HashMap<String, CachedModule>, path: &str) -> &'m mut CachedModule {
+    modules.entry(path.to_string()).or_insert_with(|| CachedModule {
+        module_path: if path.is_empty() {
+            vec![]
+        } else {
+            path.split('.').map(String::from).collect()
+        },
+        source_hash: String::new(),
+        functions: Vec::new(),
+        function_signatures: HashMap::new(),
+        exports: Vec::new(),
+        prelude_imports: Vec::new(),
+        types: Vec::new(),
+        mvars: Vec::new(),
+        dependency_signatures: HashMap::new(),
+    })
+}
+
+/// Collect everything the VM needs to run `entry_point` into an image,
+/// with one `CachedModule` per module.
+fn build_image(compiler: &Compiler, entry_point: &str) -> Result<ProgramImage, String> {
+    let mut modules: HashMap<String, CachedModule> = HashMap::new();
+    let mut functions: Vec<_> = compiler.get_all_functions().iter().collect();
+    functions.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, func) in functions {
+        // Function indices are kept (the function list is stored as is), so
+        // CallDirect instructions don't need rewriting.
+        let mut cached = function_to_cached(func)
+            .ok_or_else(|| format!("Function '{}' has constants that can't be serialized", name))?;
+        cached.name = name.clone();
+        let path = func.module.clone().unwrap_or_else(|| module_of(name).to_string());
+        module_entry(&mut modules, &path).functions.push(cached);
+    }
+
+    for type_val in compiler.get_vm_types().values() {
+        module_entry(&mut modules, module_of(&type_val.name)).types.push((**type_val).clone());
+    }
+
+    for (name, info) in compiler.get_mvars() {
+        module_entry(&mut modules, module_of(name)).mvars.push(CachedMvar {
+            name: name.clone(),
+            type_name: info.type_name.clone(),
+            initial_value: crate::mvar_init_to_cached(&info.initial_value),
+        });
+    }
+
+    let mut modules: Vec<CachedModule> = modules.into_values().collect();
+    modules.sort_by(|a, b| a.module_path.cmp(&b.module_path));
+
+    Ok(ProgramImage {
+        format_version: PROGRAM_IMAGE_FORMAT_VERSION,
+        compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+        entry_point: entry_point.to_string(),
+        function_list: compiler.get_function_list_names().to_vec(),
+        module_init_functions: compiler.get_module_init_functions().to_vec(),
+        modules,
+    })
+}
+
+/// Write a copy of the running executable with `payload` appended as its image.
+fn write_executable(output: &Path, payload: &[u8]) -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Cannot locate the nostos executable: {}", e))?;
+    let mut bytes = fs::read(&exe)
+        .map_err(|e| format!("Failed to read {}: {}", exe.display(), e))?;
+    // A standalone executable can build again: drop its own image first
+    if let Some(len) = image_len(&bytes, bytes.len() as u64) {
+        bytes.truncate(bytes.len() - (len + TRAILER_LEN) as usize);
+    }
+
+    bytes.extend_from_slice(payload);
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(IMAGE_MAGIC);
+    fs::write(output, &bytes)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(output, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", output.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Image length recorded in a trailer (the last `TRAILER_LEN` bytes of `bytes`).
+fn trailer_image_len(bytes: &[u8]) -> Option<u64> {
+    let start = bytes.len().checked_sub(TRAILER_LEN as usize)?;
+    let (len, magic) = bytes[start..].split_at(8);
+    if magic != IMAGE_MAGIC {
+        return None;
+    }
+    Some(u64::from_le_bytes(len.try_into().ok()?))
+}
+
+/// Length of the image embedded in an executable of `file_len` bytes, if the
+/// trailer is valid.
+fn image_len(trailer: &[u8], file_len: u64) -> Option<u64> {
+    let len = trailer_image_len(trailer)?;
+    (len.checked_add(TRAILER_LEN)? <= file_len).then_some(len)
+}
+
+/// The program image embedded in the running executable, if any.
+/// Only the trailer is read unless an image is present.
+pub fn embedded_image() -> Option<Result<ProgramImage, String>> {
+    let exe = std::env::current_exe().ok()?;
+    let mut file = fs::File::open(exe).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    if file_len < TRAILER_LEN {
+        return None;
+    }
+    let mut trailer = [0u8; TRAILER_LEN as usize];
+    file.seek(SeekFrom::End(-(TRAILER_LEN as i64))).ok()?;
+    file.read_exact(&mut trailer).ok()?;
+    let len = image_len(&trailer, file_len)?;
+
+    let mut payload = vec![0u8; len as usize];
+    let read = file.seek(SeekFrom::End(-((len + TRAILER_LEN) as i64)))
+        .and_then(|_| file.read_exact(&mut payload))
+        .map_err(|e| format!("Failed to read the embedded program: {}", e));
+    Some(read.and_then(|_| decode_program_image(&payload)))
+}
+
+/// Converts cached functions back to function values, resolving function
+/// references in constants to the converted functions.
+struct FunctionLoader<'a> {
+    cached: HashMap<&'a str, &'a CachedFunction>,
+    loaded: RefCell<HashMap<String, Arc<FunctionValue>>>,
+    /// Functions being converted, to cut reference cycles
+    in_progress: RefCell<HashSet<String>>,
+}
+
+impl<'a> FunctionLoader<'a> {
+    fn new(image: &'a ProgramImage) -> Self {
+        let cached = image.modules.iter()
+            .flat_map(|module| module.functions.iter())
+            .map(|func| (func.name.as_str(), func))
+            .collect();
+        Self { cached, loaded: RefCell::new(HashMap::new()), in_progress: RefCell::new(HashSet::new()) }
+    }
+
+    fn load(&self, name: &str) -> Option<Arc<FunctionValue>> {
+        if let Some(func) = self.loaded.borrow().get(name) {
+            return Some(func.clone());
+        }
+        let cached = self.cached.get(name)?;
+        let func = if self.in_progress.borrow_mut().insert(name.to_string()) {
+            let func = cached_to_function_with_resolver(cached, |name| self.load(name).map(Value::Function));
+            self.in_progress.borrow_mut().remove(name);
+            func
+        } else {
+            // Cycle: same fallback as the bytecode cache
+            cached_to_function(cached)
+        };
+        let func = Arc::new(func);
+        self.loaded.borrow_mut().insert(name.to_string(), func.clone());
+        Some(func)
+    }
+}
+
+/// Load an embedded program image into a VM and run its entry point.
+pub fn run_image(image: ProgramImage) -> ExitCode {
+    if image.compiler_version != env!("CARGO_PKG_VERSION") {
+        eprintln!(
+            "Error: Program was built by nostos {} but this runtime is {}",
+            image.compiler_version,
+            env!("CARGO_PKG_VERSION")
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let mut vm = AsyncVM::new(AsyncConfig::default());
+    vm.register_default_natives();
+
+    let loader = FunctionLoader::new(&image);
+    let mut names: Vec<&str> = loader.cached.keys().copied().collect();
+    names.sort_unstable();
+    for name in names {
+        if let Some(func) = loader.load(name) {
+            vm.register_function(name, func);
+        }
+    }
+    let function_list: Vec<Arc<FunctionValue>> = image.function_list.iter()
+        .filter_map(|name| loader.load(name))
+        .collect();
+    vm.set_function_list(function_list.clone());
+
+    for module in &image.modules {
+        for type_val in &module.types {
+            vm.register_type(&type_val.name, Arc::new(type_val.clone()));
+        }
+        for mvar in &module.mvars {
+            let initial_value = crate::cached_to_mvar_init(&mvar.initial_value);
+            vm.register_mvar(&mvar.name, crate::mvar_init_to_thread_safe(&initial_value));
+        }
+    }
+
+    for init_fn_name in &image.module_init_functions {
+        let _ = vm.run(init_fn_name);
+    }
+    crate::jit_compile_functions(&mut vm, &function_list);
+
+    crate::run_entry_point(&mut vm, &image.entry_point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_trailer() {
+        let mut bytes = b"executable".to_vec();
+        assert_eq!(image_len(&bytes, bytes.len() as u64), None);
+
+        let payload = b"image";
+        bytes.extend_from_slice(payload);
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(IMAGE_MAGIC);
+        assert_eq!(image_len(&bytes, bytes.len() as u64), Some(payload.len() as u64));
+
+        // A length pointing before the start of the file is not an image
+        let trailer = &bytes[bytes.len() - TRAILER_LEN as usize..];
+        assert_eq!(image_len(trailer, 20), None);
+        let mut huge = u64::MAX.to_le_bytes().to_vec();
+        huge.extend_from_slice(IMAGE_MAGIC);
+        assert_eq!(image_len(&huge, 100), None);
+    }
+
+    #[test]
+    fn test_module_of() {
+        assert_eq!(module_of("server.handlers.index/String"), "server.handlers");
+        assert_eq!(module_of("main/"), "");
+        assert_eq!(module_of("Point"), "");
+    }
+}
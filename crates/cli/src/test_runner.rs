@@ -0,0 +1,423 @@
+//! Test runner - discovers and runs `test "name" = expr` blocks
+//!
+//! Usage: `nostos test [path] [--filter pat] [--format text|json|junit]`
+//!
+//! Every test block in the project is lowered to a zero-argument function by the
+//! compiler (see `lower_test_defs`). Each test then runs in its own process on the
+//! async VM. A test passes when it completes without a runtime error and does not
+//! evaluate to `false`. Output printed by a test is captured and only shown for
+//! failing tests (or included in the machine-readable report).
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use nostos_compiler::compile::TestCase;
+use nostos_vm::async_vm::{AsyncConfig, AsyncVM};
+use nostos_vm::shared_types::SendableValue;
+
+use crate::{LoadOptions, LoadedProgram, load_program, prepare_vm};
+
+/// Default per-test timeout in milliseconds
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// Separator the VM puts between a runtime error and its stack trace
+const STACK_TRACE_SEPARATOR: &str = "\n\nStack trace:\n";
+
+/// A test found while loading the project, with its source location.
+pub struct DiscoveredTest {
+    pub case: TestCase,
+    pub file: PathBuf,
+    /// 1-based line of the `test` keyword
+    pub line: usize,
+}
+
+impl DiscoveredTest {
+    /// Display name: `module: name`, or just `name` for top-level tests.
+    fn display_name(&self) -> String {
+        if self.case.module.is_empty() {
+            self.case.name.clone()
+        } else {
+            format!("{}: {}", self.case.module, self.case.name)
+        }
+    }
+}
+
+/// Report format for test results
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    Text,
+    Json,
+    Junit,
+}
+
+/// Outcome of running a single test
+enum TestOutcome {
+    Passed,
+    Failed { message: String, stack_trace: Option<String> },
+    TimedOut,
+}
+
+struct TestResult<'a> {
+    test: &'a DiscoveredTest,
+    outcome: TestOutcome,
+    duration: Duration,
+    /// Lines printed by the test
+    output: Vec<String>,
+}
+
+/// Parse command-line arguments for `nostos test` and run the tests
+pub fn run_test_command(args: &[String]) -> ExitCode {
+    let mut path: Option<String> = None;
+    let mut filter: Option<String> = None;
+    let mut format = ReportFormat::Text;
+    let mut timeout_ms = DEFAULT_TIMEOUT_MS;
+    let mut enable_jit = true;
+    let mut extension_paths: Vec<String> = Vec::new();
+    let mut use_extensions: Vec<String> = Vec::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        let needs_value = matches!(arg, "--filter" | "-f" | "--format" | "--timeout" | "--extension" | "-e" | "--use" | "-u");
+        if needs_value && i + 1 >= args.len() {
+            eprintln!("Error: {} requires an argument", arg);
+            return ExitCode::FAILURE;
+        }
+        match arg {
+            "--help" | "-h" => {
+                print_help();
+                return ExitCode::SUCCESS;
+            }
+            "--filter" | "-f" => filter = Some(args[i + 1].clone()),
+            "--format" => {
+                format = match args[i + 1].as_str() {
+                    "text" => ReportFormat::Text,
+                    "json" => ReportFormat::Json,
+                    "junit" => ReportFormat::Junit,
+                    other => {
+                        eprintln!("Error: Unknown format '{}' (expected text, json or junit)", other);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--timeout" => match args[i + 1].parse::<u64>() {
+                Ok(ms) if ms > 0 => timeout_ms = ms,
+                _ => {
+                    eprintln!("Error: Invalid timeout '{}' (expected milliseconds)", args[i + 1]);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--extension" | "-e" => extension_paths.push(args[i + 1].clone()),
+            "--use" | "-u" => use_extensions.push(args[i + 1].clone()),
+            "--no-jit" => enable_jit = false,
+            _ if arg.starts_with('-') => {
+                eprintln!("Error: Unknown option '{}'", arg);
+                return ExitCode::FAILURE;
+            }
+            _ => {
+                if path.is_some() {
+                    eprintln!("Error: Only one path can be given");
+                    return ExitCode::FAILURE;
+                }
+                path = Some(arg.to_string());
+            }
+        }
+        i += if needs_value { 2 } else { 1 };
+    }
+
+    let path = path.unwrap_or_else(|| ".".to_string());
+    let input_path = std::path::Path::new(&path);
+    if !input_path.exists() {
+        eprintln!("Error: '{}' does not exist", path);
+        return ExitCode::FAILURE;
+    }
+
+    let options = LoadOptions {
+        extension_paths,
+        use_extensions,
+        include_tests: true,
+    };
+    let LoadedProgram { compiler, ext_mgr, _ext_runtime, tests, .. } = match load_program(input_path, options) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+
+    let selected: Vec<&DiscoveredTest> = tests
+        .iter()
+        .filter(|t| matches_filter(t, filter.as_deref()))
+        .collect();
+
+    let mut vm = AsyncVM::new(AsyncConfig::default());
+    let output = vm.setup_output();
+    prepare_vm(&mut vm, &compiler, enable_jit, ext_mgr);
+
+    if format == ReportFormat::Text {
+        let filtered_out = tests.len() - selected.len();
+        if filtered_out > 0 {
+            println!("running {} tests ({} filtered out)", selected.len(), filtered_out);
+        } else {
+            println!("running {} tests", selected.len());
+        }
+    }
+
+    let timeout = Duration::from_millis(timeout_ms);
+    let started = Instant::now();
+    let mut results = Vec::with_capacity(selected.len());
+
+    for test in selected {
+        let start = Instant::now();
+        let handle = vm.run_threaded(&test.case.function_name);
+        let outcome = match handle.result_rx.recv_timeout(timeout) {
+            Ok(Ok(SendableValue::Bool(false))) => TestOutcome::Failed {
+                message: "test evaluated to false".to_string(),
+                stack_trace: None,
+            },
+            Ok(Ok(_)) => TestOutcome::Passed,
+            Ok(Err(e)) => match e.split_once(STACK_TRACE_SEPARATOR) {
+                Some((message, trace)) => TestOutcome::Failed {
+                    message: message.to_string(),
+                    // Show the test name instead of the generated function name
+                    stack_trace: Some(
+                        trace
+                            .trim_end()
+                            .replace(&test.case.function_name, &format!("test \"{}\"", test.case.name)),
+                    ),
+                },
+                None => TestOutcome::Failed { message: e, stack_trace: None },
+            },
+            Err(_) => {
+                // Interrupt the test process; it is abandoned either way
+                handle.cancel();
+                TestOutcome::TimedOut
+            }
+        };
+        let duration = start.elapsed();
+
+        if format == ReportFormat::Text {
+            let status = match outcome {
+                TestOutcome::Passed => "ok",
+                TestOutcome::Failed { .. } => "FAILED",
+                TestOutcome::TimedOut => "TIMEOUT",
+            };
+            println!("test {} ... {}", test.display_name(), status);
+        }
+
+        results.push(TestResult {
+            test,
+            outcome,
+            duration,
+            output: output.try_iter().collect(),
+        });
+    }
+
+    let elapsed = started.elapsed();
+    let report = match format {
+        ReportFormat::Text => format_text_summary(&results, elapsed, timeout),
+        ReportFormat::Json => format_json(&results, elapsed),
+        ReportFormat::Junit => format_junit(&results, elapsed),
+    };
+    print!("{}", report);
+
+    if results.iter().all(|r| matches!(r.outcome, TestOutcome::Passed)) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// A test matches if the pattern is a substring of its name or module.
+fn matches_filter(test: &DiscoveredTest, filter: Option<&str>) -> bool {
+    match filter {
+        None => true,
+        Some(pat) => test.case.name.contains(pat) || test.case.module.contains(pat),
+    }
+}
+
+fn status_str(outcome: &TestOutcome) -> &'static str {
+    match outcome {
+        TestOutcome::Passed => "passed",
+        TestOutcome::Failed { .. } => "failed",
+        TestOutcome::TimedOut => "timeout",
+    }
+}
+
+/// Failure details and the final summary line for the text report
+fn format_text_summary(results: &[TestResult], elapsed: Duration, timeout: Duration) -> String {
+    let mut out = String::new();
+    let failures: Vec<&TestResult> = results
+        .iter()
+        .filter(|r| !matches!(r.outcome, TestOutcome::Passed))
+        .collect();
+
+    if !failures.is_empty() {
+        out.push_str("\nfailures:\n");
+        for r in &failures {
+            out.push_str(&format!(
+                "\n---- {} ({}:{}) ----\n",
+                r.test.display_name(),
+                r.test.file.display(),
+                r.test.line
+            ));
+            match &r.outcome {
+                TestOutcome::Failed { message, stack_trace } => {
+                    out.push_str(message);
+                    out.push('\n');
+                    if let Some(trace) = stack_trace {
+                        out.push_str("\nStack trace:\n");
+                        out.push_str(trace);
+                        out.push('\n');
+                    }
+                }
+                TestOutcome::TimedOut => {
+                    out.push_str(&format!("timed out after {} ms\n", timeout.as_millis()));
+                }
+                TestOutcome::Passed => {}
+            }
+            if !r.output.is_empty() {
+                out.push_str("\noutput:\n");
+                for line in &r.output {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+
+    let passed = results.len() - failures.len();
+    let timed_out = failures.iter().filter(|r| matches!(r.outcome, TestOutcome::TimedOut)).count();
+    let failed = failures.len() - timed_out;
+    out.push_str(&format!(
+        "\ntest result: {}. {} passed; {} failed; {} timed out; finished in {:.2}s\n",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failed,
+        timed_out,
+        elapsed.as_secs_f64()
+    ));
+    out
+}
+
+fn format_json(results: &[TestResult], elapsed: Duration) -> String {
+    let tests: Vec<serde_json::Value> = results
+        .iter()
+        .map(|r| {
+            let (message, stack_trace) = match &r.outcome {
+                TestOutcome::Failed { message, stack_trace } => (Some(message.clone()), stack_trace.clone()),
+                _ => (None, None),
+            };
+            serde_json::json!({
+                "name": r.test.case.name,
+                "module": r.test.case.module,
+                "file": r.test.file.display().to_string(),
+                "line": r.test.line,
+                "status": status_str(&r.outcome),
+                "duration_ms": r.duration.as_secs_f64() * 1000.0,
+                "message": message,
+                "stack_trace": stack_trace,
+                "output": r.output,
+            })
+        })
+        .collect();
+
+    let count = |status: &str| results.iter().filter(|r| status_str(&r.outcome) == status).count();
+    let report = serde_json::json!({
+        "summary": {
+            "total": results.len(),
+            "passed": count("passed"),
+            "failed": count("failed"),
+            "timeout": count("timeout"),
+            "duration_ms": elapsed.as_secs_f64() * 1000.0,
+        },
+        "tests": tests,
+    });
+    format!("{}\n", serde_json::to_string_pretty(&report).unwrap_or_default())
+}
+
+fn format_junit(results: &[TestResult], elapsed: Duration) -> String {
+    let failures = results.iter().filter(|r| matches!(r.outcome, TestOutcome::Failed { .. })).count();
+    let errors = results.iter().filter(|r| matches!(r.outcome, TestOutcome::TimedOut)).count();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        results.len(), failures, errors, elapsed.as_secs_f64()
+    ));
+    out.push_str(&format!(
+        "  <testsuite name=\"nostos\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        results.len(), failures, errors, elapsed.as_secs_f64()
+    ));
+    for r in results {
+        let classname = if r.test.case.module.is_empty() { "main" } else { r.test.case.module.as_str() };
+        out.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\" file=\"{}\" line=\"{}\" time=\"{:.3}\"",
+            xml_escape(classname),
+            xml_escape(&r.test.case.name),
+            xml_escape(&r.test.file.display().to_string()),
+            r.test.line,
+            r.duration.as_secs_f64()
+        ));
+        if matches!(r.outcome, TestOutcome::Passed) && r.output.is_empty() {
+            out.push_str("/>\n");
+            continue;
+        }
+        out.push_str(">\n");
+        match &r.outcome {
+            TestOutcome::Failed { message, stack_trace } => {
+                out.push_str(&format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    xml_escape(message),
+                    xml_escape(stack_trace.as_deref().unwrap_or(""))
+                ));
+            }
+            TestOutcome::TimedOut => {
+                out.push_str("      <error type=\"timeout\" message=\"test timed out\"/>\n");
+            }
+            TestOutcome::Passed => {}
+        }
+        if !r.output.is_empty() {
+            out.push_str(&format!("      <system-out>{}</system-out>\n", xml_escape(&r.output.join("\n"))));
+        }
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn print_help() {
+    println!("Run `test` blocks in a Nostos file or project");
+    println!();
+    println!("USAGE:");
+    println!("    nostos test [path] [options]");
+    println!();
+    println!("    path defaults to the current directory. Every .nos file in a project");
+    println!("    directory is searched for tests:");
+    println!();
+    println!("        test \"addition works\" = assert_eq(1 + 1, 2)");
+    println!();
+    println!("    A test fails if it raises a runtime error or evaluates to false.");
+    println!();
+    println!("OPTIONS:");
+    println!("    --filter, -f PAT   Only run tests whose name or module contains PAT");
+    println!("    --format FORMAT    Report format: text (default), json or junit");
+    println!("    --timeout MS       Per-test timeout in milliseconds (default: {})", DEFAULT_TIMEOUT_MS);
+    println!("    --use, -u NAME     Load an installed extension");
+    println!("    --extension, -e P  Load an extension from a .so/.dylib file");
+    println!("    --no-jit           Disable JIT compilation");
+    println!("    --help, -h         Show this help");
+}
@@ -0,0 +1,186 @@
+//! Trace panel: live events of processes traced with `:trace` in the TUI.
+
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key};
+use cursive::theme::{Color, ColorStyle};
+use cursive::view::{CannotFocus, View};
+use cursive::{Printer, Vec2};
+use nostos_vm::trace::{TraceEvent, TraceKind};
+use std::collections::VecDeque;
+
+/// Maximum number of events kept in the panel
+const MAX_EVENTS: usize = 1000;
+
+/// The trace panel
+pub struct TracePanel {
+    /// Events, oldest first
+    events: VecDeque<TraceEvent>,
+    /// Sequence number of the next event to fetch from the engine
+    next_seq: u64,
+    /// First visible event
+    scroll: usize,
+    /// Keep the newest event in view
+    follow: bool,
+    /// Visible event rows
+    visible_rows: usize,
+}
+
+impl TracePanel {
+    /// A panel showing the engine's trace events from sequence number `since` on.
+    pub fn new(since: u64) -> Self {
+        Self {
+            events: VecDeque::new(),
+            next_seq: since,
+            scroll: 0,
+            follow: true,
+            visible_rows: 10,
+        }
+    }
+
+    /// Sequence number to pass to `ReplEngine::trace_events_since`.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Add fetched events.
+    pub fn push_events(&mut self, events: Vec<TraceEvent>, next_seq: u64) {
+        self.next_seq = next_seq;
+        for event in events {
+            if self.events.len() >= MAX_EVENTS {
+                self.events.pop_front();
+                self.scroll = self.scroll.saturating_sub(1);
+            }
+            self.events.push_back(event);
+        }
+        if self.follow {
+            self.scroll_to_end();
+        }
+    }
+
+    /// Drop the shown events. Returns the sequence number the panel continues from.
+    pub fn clear(&mut self) -> u64 {
+        self.events.clear();
+        self.scroll = 0;
+        self.follow = true;
+        self.next_seq
+    }
+
+    /// All events as text for clipboard copy
+    pub fn get_content(&self) -> String {
+        self.events.iter().map(|e| e.describe()).collect::<Vec<_>>().join("\n")
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.events.len().saturating_sub(self.visible_rows)
+    }
+
+    fn scroll_to_end(&mut self) {
+        self.scroll = self.max_scroll();
+    }
+
+    fn scroll_up(&mut self, rows: usize) {
+        self.scroll = self.scroll.saturating_sub(rows);
+        self.follow = false;
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        self.scroll = (self.scroll + rows).min(self.max_scroll());
+        self.follow = self.scroll == self.max_scroll();
+    }
+
+    fn kind_color(kind: TraceKind) -> ColorStyle {
+        let color = match kind {
+            TraceKind::Send => Color::Rgb(100, 255, 120),
+            TraceKind::Receive => Color::Rgb(80, 200, 255),
+            TraceKind::Call => Color::Rgb(255, 255, 0),
+            TraceKind::Return => Color::Rgb(200, 200, 120),
+            TraceKind::Spawn => Color::Rgb(255, 150, 255),
+            TraceKind::Exit => Color::Rgb(255, 100, 100),
+        };
+        ColorStyle::new(color, Color::TerminalDefault)
+    }
+}
+
+impl View for TracePanel {
+    fn draw(&self, printer: &Printer) {
+        if self.events.is_empty() {
+            printer.print((1, 1), "No trace events");
+            printer.print((1, 2), "Use :trace <pid> [flags] in a REPL");
+            return;
+        }
+
+        let width = printer.size.x;
+
+        // Status line
+        let status = format!("{} events{}", self.events.len(), if self.follow { " (following)" } else { "" });
+        printer.with_color(ColorStyle::new(Color::Rgb(100, 200, 255), Color::TerminalDefault), |p| {
+            p.print((0, 0), &status);
+        });
+        if width > 30 {
+            printer.print((width.saturating_sub(20), 0), "[f:follow c:clear]");
+        }
+
+        for i in 0..width {
+            printer.print((i, 1), "─");
+        }
+
+        for (row, event) in self.events.iter().skip(self.scroll).take(self.visible_rows).enumerate() {
+            let line: String = event.describe().chars().take(width).collect();
+            printer.with_color(Self::kind_color(event.kind), |p| {
+                p.print((0, row + 2), &line);
+            });
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
+        Ok(EventResult::Consumed(None))
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            // Let Tab propagate for window cycling
+            Event::Key(Key::Tab) | Event::Shift(Key::Tab) => EventResult::Ignored,
+            Event::Key(Key::Up) => {
+                self.scroll_up(1);
+                EventResult::Consumed(None)
+            }
+            Event::Key(Key::Down) => {
+                self.scroll_down(1);
+                EventResult::Consumed(None)
+            }
+            Event::Key(Key::PageUp) => {
+                self.scroll_up(self.visible_rows);
+                EventResult::Consumed(None)
+            }
+            Event::Key(Key::PageDown) => {
+                self.scroll_down(self.visible_rows);
+                EventResult::Consumed(None)
+            }
+            Event::Key(Key::Home) => {
+                self.scroll = 0;
+                self.follow = false;
+                EventResult::Consumed(None)
+            }
+            Event::Key(Key::End) | Event::Char('f') | Event::Char('F') => {
+                self.follow = true;
+                self.scroll_to_end();
+                EventResult::Consumed(None)
+            }
+            // 'c' (clear), Ctrl+Y and closing are handled at the tui.rs wrapper level
+            _ => EventResult::Ignored,
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.visible_rows = size.y.saturating_sub(2).max(1);
+        if self.follow {
+            self.scroll_to_end();
+        } else {
+            self.scroll = self.scroll.min(self.max_scroll());
+        }
+    }
+}
@@ -20,6 +20,7 @@ use std::process::ExitCode;
 
 use crate::repl_panel::{ReplPanel, ReplPanelCommand};
 use crate::inspector_panel::InspectorPanel;
+use crate::trace_panel::TracePanel;
 use crate::nostos_panel::NostosPanel;
 use crate::debug_panel::{DebugPanel, DebugPanelCommand};
 use crate::git_panel::{GitHistoryPanel, GitPanelCommand, HistoryTarget};
@@ -258,6 +259,9 @@ struct TuiState {
     active_window_idx: usize,
     engine: Rc<RefCell<ReplEngine>>,
     inspector_open: bool,
+    /// Trace panel state, and the trace event sequence number it shows events from
+    trace_open: bool,
+    trace_since: u64,
     console_open: bool,
     nostos_panel_open: bool,
     debug_panel_open: bool,
@@ -574,6 +578,8 @@ pub fn run_tui(args: &[String]) -> ExitCode {
         active_window_idx: 0,
         engine: engine.clone(),
         inspector_open: false,
+        trace_open: false,
+        trace_since: 0,
         console_open: true,
         nostos_panel_open: false,
         debug_panel_open: false,
@@ -772,6 +778,8 @@ pub fn run_tui(args: &[String]) -> ExitCode {
         poll_repl_panel_commands(s);
         // Poll for inspect() entries and update inspector panel
         poll_inspect_entries(s);
+        // Poll for events of traced processes and update the trace panel
+        poll_trace_events(s);
         // Poll for server commands from remote clients
         poll_server_commands(s);
     });
@@ -986,6 +994,79 @@ fn execute_server_command(cmd: &ServerCommand, engine: &Rc<RefCell<ReplEngine>>)
                 },
             }
         }
+        "upgrade" => {
+            let module = &cmd.args;
+            match engine.borrow_mut().upgrade_module(module) {
+                Ok(version) => ServerResponse {
+                    id: cmd.id,
+                    status: "ok".to_string(),
+                    output: format!("Upgraded {} to version {}", module, version),
+                    errors: vec![],
+                    completions: vec![],
+                    completion_items: vec![],
+                },
+                Err(e) => ServerResponse {
+                    id: cmd.id,
+                    status: "error".to_string(),
+                    output: e.clone(),
+                    errors: vec![ServerError {
+                        file: module.to_string(),
+                        line: 0,
+                        message: e,
+                    }],
+                    completions: vec![],
+                    completion_items: vec![],
+                },
+            }
+        }
+        "trace" | "untrace" => {
+            let mut words = cmd.args.split_whitespace();
+            let pid = words.next().unwrap_or("");
+            let flags: Vec<&str> = words.collect();
+            let result = if cmd.cmd == "trace" {
+                engine.borrow().trace_process(pid, &flags).map(|_| format!("Tracing {}", pid))
+            } else {
+                engine.borrow().stop_trace(pid).map(|_| format!("Stopped tracing {}", pid))
+            };
+            match result {
+                Ok(output) => ServerResponse {
+                    id: cmd.id,
+                    status: "ok".to_string(),
+                    output,
+                    errors: vec![],
+                    completions: vec![],
+                    completion_items: vec![],
+                },
+                Err(e) => ServerResponse {
+                    id: cmd.id,
+                    status: "error".to_string(),
+                    output: e,
+                    errors: vec![],
+                    completions: vec![],
+                    completion_items: vec![],
+                },
+            }
+        }
+        "traceEvents" => {
+            // Trace events from sequence number `args` on (only the current
+            // position when missing). The first output line is the sequence
+            // number to ask from next time, then one line per event.
+            let since = cmd.args.trim().parse().unwrap_or(u64::MAX);
+            let (events, next) = engine.borrow_mut().trace_events_since(since);
+            let mut output = next.to_string();
+            for event in events {
+                output.push('\n');
+                output.push_str(&event.describe());
+            }
+            ServerResponse {
+                id: cmd.id,
+                status: "ok".to_string(),
+                output,
+                errors: vec![],
+                completions: vec![],
+                completion_items: vec![],
+            }
+        }
         "eval" => {
             let code = &cmd.args;
             match engine.borrow_mut().eval_with_capture(code) {
@@ -1408,6 +1489,29 @@ fn poll_inspect_entries(s: &mut Cursive) {
     });
 }
 
+/// Poll for trace events and update the trace panel if open.
+fn poll_trace_events(s: &mut Cursive) {
+    let engine = match s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
+        let state = state.borrow();
+        if state.trace_open { Some(state.engine.clone()) } else { None }
+    }).flatten() {
+        Some(e) => e,
+        None => return,
+    };
+
+    let Some(since) = s.call_on_name("trace_panel", |panel: &mut TracePanel| panel.next_seq()) else {
+        return;
+    };
+    let (events, next) = engine.borrow_mut().trace_events_since(since);
+    if events.is_empty() {
+        return;
+    }
+
+    s.call_on_name("trace_panel", |panel: &mut TracePanel| {
+        panel.push_events(events, next);
+    });
+}
+
 /// Sync breakpoints from engine to debug panel.
 fn sync_debug_panel_breakpoints(s: &mut Cursive) {
     // Get engine breakpoints
@@ -1625,9 +1729,9 @@ fn poll_debug_events(s: &mut Cursive) {
 /// Uses LinearLayout for equal window distribution
 /// Navigation: Ctrl+Left/Right to move between windows
 fn rebuild_workspace(s: &mut Cursive) {
-    let (editor_names, nostlet_names, repl_ids, engine, inspector_open, console_open, nostos_panel_open, debug_panel_open, git_panel_open, git_panel_target, current_panel, current_panel_id, tutorial_open, tutorial_chapter_idx, fullscreen_window, saved_window_widths) = s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
+    let (editor_names, nostlet_names, repl_ids, engine, inspector_open, trace_open, trace_since, console_open, nostos_panel_open, debug_panel_open, git_panel_open, git_panel_target, current_panel, current_panel_id, tutorial_open, tutorial_chapter_idx, fullscreen_window, saved_window_widths) = s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
         let state = state.borrow();
-        (state.open_editors.clone(), state.open_nostlets.clone(), state.open_repls.clone(), state.engine.clone(), state.inspector_open, state.console_open, state.nostos_panel_open, state.debug_panel_open, state.git_panel_open, state.git_panel_target.clone(), state.current_panel.clone(), state.current_panel_id, state.tutorial_open, state.tutorial_chapter_idx, state.fullscreen_window.clone(), state.saved_window_widths.clone())
+        (state.open_editors.clone(), state.open_nostlets.clone(), state.open_repls.clone(), state.engine.clone(), state.inspector_open, state.trace_open, state.trace_since, state.console_open, state.nostos_panel_open, state.debug_panel_open, state.git_panel_open, state.git_panel_target.clone(), state.current_panel.clone(), state.current_panel_id, state.tutorial_open, state.tutorial_chapter_idx, state.fullscreen_window.clone(), state.saved_window_widths.clone())
     }).unwrap();
 
     // Get console content BEFORE clearing workspace
@@ -1701,6 +1805,10 @@ fn rebuild_workspace(s: &mut Cursive) {
             let view = create_inspector_view(&engine);
             s.add_fullscreen_layer(view);
             return;
+        } else if fs_window == "trace_panel" && trace_open {
+            let view = create_trace_view(&engine, trace_since);
+            s.add_fullscreen_layer(view);
+            return;
         } else if fs_window == "tutorial_content" && tutorial_open {
             let view = create_tutorial_view(tutorial_chapter_idx);
             s.add_fullscreen_layer(view);
@@ -1770,6 +1878,16 @@ fn rebuild_workspace(s: &mut Cursive) {
         }
     }
 
+    if trace_open {
+        let name = "aw_trace".to_string();
+        let view = create_trace_view(&engine, trace_since);
+        if let Some(&width) = saved_window_widths.get(&name) {
+            windows.push((name, Box::new(BoxedView::boxed(view).fixed_width(width))));
+        } else {
+            windows.push((name, Box::new(view)));
+        }
+    }
+
     if debug_panel_open {
         let name = "aw_debug".to_string();
         let view = create_debug_view(&engine);
@@ -2727,6 +2845,27 @@ fn open_tutorial_panel(s: &mut Cursive) {
     focus_window(s, "tutorial_content");
 }
 
+/// Open the trace panel (after `:trace` in a REPL)
+fn open_trace_panel(s: &mut Cursive) {
+    let already_open = s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
+        std::mem::replace(&mut state.borrow_mut().trace_open, true)
+    }).unwrap_or(true);
+    if !already_open {
+        rebuild_workspace(s);
+    }
+}
+
+/// Close the trace panel. Traced processes keep reporting until `:untrace`;
+/// the panel shows their events again when reopened.
+fn close_trace_panel(s: &mut Cursive) {
+    s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
+        state.borrow_mut().trace_open = false;
+    });
+    rebuild_workspace(s);
+    s.focus_name("repl_log").ok();
+    log_to_repl(s, "Trace panel closed");
+}
+
 /// Navigate to a different tutorial chapter (relative)
 fn navigate_tutorial_chapter(s: &mut Cursive, delta: i32) {
     let (current_idx, should_rebuild) = s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
@@ -2787,6 +2926,10 @@ fn poll_repl_panel_commands(s: &mut Cursive) {
                     open_tutorial_panel(s);
                     return; // Only handle one command per poll
                 }
+                ReplPanelCommand::OpenTrace => {
+                    open_trace_panel(s);
+                    return;
+                }
             }
         }
     }
@@ -3186,6 +3329,43 @@ fn create_inspector_view(engine: &Rc<RefCell<ReplEngine>>) -> impl View {
         .full_width()
 }
 
+/// Create the trace panel view
+fn create_trace_view(engine: &Rc<RefCell<ReplEngine>>, since: u64) -> impl View {
+    let mut panel = TracePanel::new(since);
+
+    // Show the events received so far
+    let (events, next) = engine.borrow_mut().trace_events_since(since);
+    panel.push_events(events, next);
+
+    let panel_with_events = OnEventView::new(panel.with_name("trace_panel"))
+        .on_event(Event::CtrlChar('y'), |s| {
+            if let Some(text) = s.call_on_name("trace_panel", |view: &mut TracePanel| {
+                view.get_content()
+            }) {
+                if !text.is_empty() {
+                    match copy_to_system_clipboard(&text) {
+                        Ok(_) => log_to_repl(s, &format!("Copied {} chars", text.len())),
+                        Err(e) => log_to_repl(s, &format!("Copy failed: {}", e)),
+                    }
+                }
+            }
+        })
+        .on_event('c', |s| {
+            // Clear, and keep the events cleared when the workspace is rebuilt
+            if let Some(since) = s.call_on_name("trace_panel", |view: &mut TracePanel| view.clear()) {
+                s.with_user_data(|state: &mut Rc<RefCell<TuiState>>| {
+                    state.borrow_mut().trace_since = since;
+                });
+            }
+        })
+        .on_event(Event::CtrlChar('w'), close_trace_panel)
+        .on_event(Key::Esc, close_trace_panel);
+
+    ActiveWindow::new(panel_with_events, "Trace")
+        .with_track_name("aw_trace")
+        .full_width()
+}
+
 /// Create a fullscreen console view (without max_width limitation)
 fn create_fullscreen_console_view(content: &str) -> impl View {
     let repl_log = FocusableConsole::new(
@@ -5590,6 +5770,11 @@ fn cycle_window(s: &mut Cursive) {
             windows.push("inspector_panel".to_string());
         }
 
+        // Trace panel
+        if state.trace_open {
+            windows.push("trace_panel".to_string());
+        }
+
         // Nostos panel
         if state.nostos_panel_open {
             windows.push("nostos_mvar_panel".to_string());
@@ -5646,6 +5831,11 @@ fn cycle_window_backward(s: &mut Cursive) {
             windows.push("inspector_panel".to_string());
         }
 
+        // Trace panel
+        if state.trace_open {
+            windows.push("trace_panel".to_string());
+        }
+
         // Nostos panel
         if state.nostos_panel_open {
             windows.push("nostos_mvar_panel".to_string());
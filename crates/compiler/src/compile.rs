@@ -50,6 +50,7 @@ pub const BUILTINS: &[BuiltinInfo] = &[
     BuiltinInfo { name: "self", signature: "() -> Pid", doc: "Get the current process ID" },
     BuiltinInfo { name: "spawn", signature: "(() -> a) -> Pid", doc: "Spawn a new lightweight process" },
     BuiltinInfo { name: "send", signature: "Pid -> a -> ()", doc: "Send a message to a process (also: pid <- msg)" },
+    BuiltinInfo { name: "trySend", signature: "Pid -> a -> Result[(), String]", doc: "Send a message unless the process's bounded mailbox is full: Err(\"mailbox full\") or Err(\"process not running\")" },
     BuiltinInfo { name: "receive", signature: "Pattern -> a", doc: "Receive a message matching a pattern" },
 
     // === Math ===
@@ -224,6 +225,7 @@ pub const BUILTINS: &[BuiltinInfo] = &[
     // === HTTP Server ===
     // Server functions throw exceptions on error
     BuiltinInfo { name: "Server.bind", signature: "Int -> Server", doc: "Start HTTP server on port, returns server handle, throws on error" },
+    BuiltinInfo { name: "Server.bindTls", signature: "Int -> String -> String -> Server", doc: "Start HTTPS server on port with PEM certificate and key files (port, certPath, keyPath), also serves wss://, throws on error" },
     BuiltinInfo { name: "Server.accept", signature: "Server -> HttpRequest", doc: "Accept next request on server handle, throws on error" },
     BuiltinInfo { name: "Server.respond", signature: "Int -> Int -> [(String, String)] -> String -> ()", doc: "Send response: respond(reqId, status, headers, body), throws on error" },
     BuiltinInfo { name: "Server.close", signature: "Server -> ()", doc: "Close server handle" },
@@ -242,6 +244,8 @@ pub const BUILTINS: &[BuiltinInfo] = &[
     // === TCP Sockets ===
     BuiltinInfo { name: "Tcp.connect", signature: "String -> Int -> Int", doc: "Connect to TCP server (host, port), returns socket handle, throws on error" },
     BuiltinInfo { name: "Tcp.listen", signature: "Int -> Int", doc: "Listen on TCP port, returns listener handle, throws on error" },
+    BuiltinInfo { name: "Tcp.connectTls", signature: "String -> Int -> [(String, String)] -> Int", doc: "Connect to TLS server (host, port, opts), opts: ca (PEM file of trusted certificates), serverName; returns socket handle, throws on error" },
+    BuiltinInfo { name: "Tcp.listenTls", signature: "Int -> String -> String -> Int", doc: "Listen on TCP port with TLS (port, certPath, keyPath), accepted sockets are TLS, returns listener handle, throws on error" },
     BuiltinInfo { name: "Tcp.accept", signature: "Int -> Int", doc: "Accept connection on listener, returns socket handle, blocks until connection" },
     BuiltinInfo { name: "Tcp.read", signature: "Int -> Int -> String", doc: "Read up to N bytes from socket, returns data as string, throws on error" },
     BuiltinInfo { name: "Tcp.write", signature: "Int -> String -> Int", doc: "Write string to socket, returns bytes written, throws on error" },
@@ -251,12 +255,62 @@ pub const BUILTINS: &[BuiltinInfo] = &[
     BuiltinInfo { name: "Process.all", signature: "() -> [Pid]", doc: "Get list of all process IDs on this thread" },
     BuiltinInfo { name: "Process.time", signature: "Pid -> Int", doc: "Get process uptime in milliseconds (-1 if not found)" },
     BuiltinInfo { name: "Process.alive", signature: "Pid -> Bool", doc: "Check if a process is still alive" },
-    BuiltinInfo { name: "Process.info", signature: "Pid -> ProcessInfo", doc: "Get process info: { status, mailbox, uptime }" },
+    BuiltinInfo { name: "Process.info", signature: "Pid -> ProcessInfo", doc: "Get process info: { status, mailbox, uptime, priority, mailboxCapacity } (status is \"alive\" and uptime -1 for other processes, () if not alive)" },
     BuiltinInfo { name: "Process.kill", signature: "Pid -> Bool", doc: "Kill a process (returns true if successful)" },
+    BuiltinInfo { name: "Process.spawnLimited", signature: "(() -> a) -> Int -> Int -> String -> Pid", doc: "Spawn a process with limits: spawnLimited(fn, maxHeapBytes, maxMailbox, linkage), a limit <= 0 keeps the default and linkage is \"none\", \"link\" or \"monitor\". A process over a limit exits with reason \"heap_limit\" or \"mailbox_limit\"" },
+    BuiltinInfo { name: "Process.spawnPriority", signature: "(() -> a) -> String -> Pid", doc: "Spawn a process with a scheduling priority: \"low\", \"normal\" or \"high\"" },
+    BuiltinInfo { name: "Process.spawnBounded", signature: "(() -> a) -> Int -> String -> Pid", doc: "Spawn a process with a bounded mailbox: spawnBounded(fn, capacity, policy), policy \"block\" (senders wait), \"dropNewest\" or \"dropOldest\" decides what send does when the mailbox is full" },
+    BuiltinInfo { name: "Process.setPriority", signature: "Pid -> String -> Bool", doc: "Change the scheduling priority of a process (false if it isn't alive)" },
+    BuiltinInfo { name: "Process.register", signature: "String -> Pid -> Bool", doc: "Register a name for a process (false if the name is taken or the process already has one)" },
+    BuiltinInfo { name: "Process.whereis", signature: "String -> Option[Pid]", doc: "Look up the process registered under a name" },
+    BuiltinInfo { name: "Process.unregister", signature: "String -> Bool", doc: "Remove a registered name (names are also removed when their process exits)" },
+    BuiltinInfo { name: "Process.registered", signature: "() -> [String]", doc: "List all registered process names" },
+    BuiltinInfo { name: "Process.send", signature: "String -> a -> Bool", doc: "Send a message to the process registered under a name (false if no such name)" },
+
+    // === Distributed Nodes ===
+    BuiltinInfo { name: "Node.self", signature: "() -> String", doc: "Name of this node (\"nonode@nohost\" unless nostos runs with --node)" },
+    BuiltinInfo { name: "Node.connect", signature: "String -> Result[(), String]", doc: "Connect to the node \"name@host:port\" (Ok if already connected); both nodes need the same cookie" },
+    BuiltinInfo { name: "Node.disconnect", signature: "String -> Bool", doc: "Close the connection to a node (false if not connected); links and monitors through it fire with reason \"noconnection\"" },
+    BuiltinInfo { name: "Node.list", signature: "() -> [String]", doc: "Names of the connected nodes" },
+    BuiltinInfo { name: "Node.of", signature: "Pid -> String", doc: "Name of the node a process runs on" },
+    BuiltinInfo { name: "Node.spawnOn", signature: "String -> (() -> a) -> String -> Pid", doc: "Spawn a process on a connected node: spawn(node, fn, linkage), linkage is \"none\", \"link\" or \"monitor\"; both nodes must run the same program" },
+    BuiltinInfo { name: "Node.send", signature: "String -> String -> a -> Bool", doc: "Send a message to the process registered under a name on a node (false if the node isn't connected)" },
+
+    // === Shared Tables ===
+    BuiltinInfo { name: "Table.new", signature: "String -> [String] -> Bool", doc: "Create a table owned by the calling process (false if the name is taken); options \"set\" (default) or \"bag\", and \"public\" to let any process write. The table is deleted when its owner exits" },
+    BuiltinInfo { name: "Table.insert", signature: "String -> k -> v -> ()", doc: "Store a value under a key: replaces the value in a set, adds it to the values of a bag" },
+    BuiltinInfo { name: "Table.lookup", signature: "String -> k -> [v]", doc: "Values stored under a key ([] if none, at most one for a set)" },
+    BuiltinInfo { name: "Table.delete", signature: "String -> k -> Bool", doc: "Remove a key and its values (false if it wasn't there)" },
+    BuiltinInfo { name: "Table.update", signature: "String -> k -> (v -> v) -> v -> v", doc: "Atomically replace the value of a key in a set with f(value), starting from the default if the key is missing; returns the new value" },
+    BuiltinInfo { name: "Table.matchKeys", signature: "String -> p -> [(k, v)]", doc: "Entries whose key matches a pattern, where () matches anything: Table.matchKeys(t, (\"user\", ()))" },
+    BuiltinInfo { name: "Table.select", signature: "String -> (k -> Bool) -> [(k, v)]", doc: "Entries whose key satisfies a predicate" },
+    BuiltinInfo { name: "Table.size", signature: "String -> Int", doc: "Number of keys in a table" },
+    BuiltinInfo { name: "Table.drop", signature: "String -> Bool", doc: "Delete a table (false if there is no such table)" },
+
+    // === Hot code upgrade ===
+    BuiltinInfo { name: "Code.reload", signature: "String -> Result[Int, String]", doc: "Recompile a module from its source file and swap it into the running program; returns the module's new version. Running functions finish on the old code, calls made after the reload use the new code" },
+    BuiltinInfo { name: "Code.version", signature: "String -> Int", doc: "How many times a module has been reloaded (0 if never)" },
+
+    // === Process tracing ===
+    BuiltinInfo { name: "Trace.start", signature: "Pid -> [String] -> Bool", doc: "Trace a process to the caller, which receives (\"TRACE\", pid, kind, peer, detail) messages; flags \"send\", \"receive\", \"call\", \"return\", \"spawn\", \"exit\" or \"all\" (false if it isn't alive)" },
+    BuiltinInfo { name: "Trace.stop", signature: "Pid -> Bool", doc: "Stop tracing a process (false if it isn't alive)" },
+
+    // === Timers ===
+    BuiltinInfo { name: "Timer.sendAfter", signature: "Int -> Pid -> a -> TimerRef", doc: "Send a message to a process after the given number of milliseconds" },
+    BuiltinInfo { name: "Timer.interval", signature: "Int -> Pid -> a -> TimerRef", doc: "Send a message to a process every given number of milliseconds until cancelled" },
+    BuiltinInfo { name: "Timer.cancel", signature: "TimerRef -> Bool", doc: "Cancel a timer (false if it already fired or was cancelled); timers are also cancelled when their creator exits" },
+
+    // === Supervision ===
+    BuiltinInfo { name: "Supervisor.start", signature: "String -> Int -> Int -> Pid", doc: "Start a supervisor linked to the caller: start(strategy, maxRestarts, maxSeconds), strategy is \"oneForOne\", \"oneForAll\" or \"restForOne\"" },
+    BuiltinInfo { name: "Supervisor.startChild", signature: "Pid -> String -> (() -> a) -> String -> Pid", doc: "Start a supervised child: startChild(sup, id, fn, restart), restart is \"permanent\", \"transient\" or \"temporary\"" },
+    BuiltinInfo { name: "Supervisor.terminateChild", signature: "Pid -> String -> Bool", doc: "Stop a child and remove it from the supervisor (false if no such child)" },
+    BuiltinInfo { name: "Supervisor.whichChildren", signature: "Pid -> [(String, Pid)]", doc: "List the running children as (id, pid) pairs, in start order" },
+    BuiltinInfo { name: "Supervisor.stop", signature: "Pid -> Bool", doc: "Stop a supervisor and all of its children" },
 
     // === Garbage Collection ===
     BuiltinInfo { name: "Gc.collect", signature: "() -> GcResult", doc: "Force garbage collection, returns { collected: Int, live: Int }" },
-    BuiltinInfo { name: "Gc.stats", signature: "() -> GcStats", doc: "Get GC statistics: { live, totalAllocated, totalFreed, collections }" },
+    BuiltinInfo { name: "Gc.stats", signature: "() -> GcStats", doc: "Get GC statistics: { live, totalAllocated, totalFreed, collections, minorCollections, majorCollections }" },
+    BuiltinInfo { name: "Gc.snapshot", signature: "Pid -> String -> Int", doc: "Collect a process's heap and write a snapshot of it to a file for `nostos heap`, returns the number of objects written, throws on error" },
 
     // === Panel (TUI) ===
     BuiltinInfo { name: "Panel.create", signature: "String -> Int", doc: "Create a panel with title, returns panel ID" },
@@ -1534,7 +1588,7 @@ impl Compiler {
             "Base64", "Url", "Encoding", "Server", "Exec", "Random", "Path", "Panel",
             "Pg", "Uuid", "Crypto", "Float64Array", "Int64Array", "Float32Array", "Buffer",
             "Runtime", "WebSocket", "RenderStack", "RenderContext", "Reactive", "Gc",
-            "Selenium", "Tcp",
+            "Selenium", "Tcp", "Supervisor", "Timer", "Node", "Table", "Code", "Trace",
         ].iter().map(|s| s.to_string()).collect();
 
         let mut this = Self {
@@ -1675,6 +1729,23 @@ impl Compiler {
                         ("status".to_string(), "String".to_string()),
                         ("mailbox".to_string(), "Int".to_string()),
                         ("uptime".to_string(), "Int".to_string()),
+                        ("priority".to_string(), "String".to_string()),
+                        ("mailboxCapacity".to_string(), "Int".to_string()),
+                    ],
+                    mutable: false,
+                },
+                visibility: Visibility::Public,
+            },
+        );
+
+        // TimerRef: returned by Timer.sendAfter and Timer.interval
+        self.builtin_types.insert(
+            "TimerRef".to_string(),
+            TypeInfo {
+                name: "TimerRef".to_string(),
+                kind: TypeInfoKind::Record {
+                    fields: vec![
+                        ("id".to_string(), "Int".to_string()),
                     ],
                     mutable: false,
                 },
@@ -3817,6 +3888,7 @@ impl Compiler {
     /// before any use statements are processed.
     pub fn register_module_forward_declarations(&mut self, module: &Module, module_path: Vec<String>) -> Result<(), CompileError> {
         use nostos_syntax::ast::{Item, Visibility};
+        let items = lower_extern_decls(&module.items);
 
         // Register this module path as known
         if !module_path.is_empty() {
@@ -3834,7 +3906,7 @@ impl Compiler {
         let old_module_path = std::mem::replace(&mut self.module_path, module_path);
 
         // Register all public function names
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::FnDef(fn_def) = item {
                 if !fn_def.is_template {
                     let qualified_name = self.qualify_name(&fn_def.name.node);
@@ -3994,10 +4066,11 @@ impl Compiler {
     /// its use statement.
     pub fn pre_register_module_type_names(&mut self, module: &Module, module_path: Vec<String>) {
         use nostos_syntax::ast::{Item, Visibility};
+        let items = lower_extern_decls(&module.items);
 
         let old_module_path = std::mem::replace(&mut self.module_path, module_path);
 
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::TypeDef(type_def) = item {
                 let qualified_name = self.qualify_name(&type_def.name.node);
                 // Register visibility so compile_use_stmt can find this type
@@ -4066,6 +4139,7 @@ impl Compiler {
     /// across modules before any function bodies are compiled.
     pub fn pre_register_module_metadata(&mut self, module: &Module, module_path: Vec<String>, source: std::sync::Arc<String>, source_name: String) -> Result<(), CompileError> {
         use nostos_syntax::ast::Item;
+        let items = lower_extern_decls(&module.items);
 
         // Update line_starts for error reporting
         self.line_starts = vec![0];
@@ -4093,7 +4167,7 @@ impl Compiler {
         let old_module_path = std::mem::replace(&mut self.module_path, module_path);
 
         // Pre-pass: collect names of local (inline) modules
-        let local_module_names: std::collections::HashSet<String> = module.items.iter()
+        let local_module_names: std::collections::HashSet<String> = items.iter()
             .filter_map(|item| {
                 if let Item::ModuleDef(module_def) = item {
                     Some(module_def.name.node.clone())
@@ -4105,7 +4179,7 @@ impl Compiler {
 
         // Process use statements (needed for import resolution in trait impls)
         let mut deferred_use_stmts: Vec<&nostos_syntax::ast::UseStmt> = Vec::new();
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::Use(use_stmt) = item {
                 let module_path_str = use_stmt.path.first().map(|id| id.node.as_str()).unwrap_or("");
                 if local_module_names.contains(module_path_str) {
@@ -4117,7 +4191,7 @@ impl Compiler {
         }
 
         // Register templates (needed for type decorators)
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::FnDef(fn_def) = item {
                 if fn_def.is_template {
                     self.templates.insert(fn_def.name.node.clone(), fn_def.clone());
@@ -4127,14 +4201,14 @@ impl Compiler {
 
         // Process type definitions (needed for cross-module constructor visibility
         // in trait impls - compile_type_def is idempotent so Pass 2 won't re-process)
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::TypeDef(type_def) = item {
                 self.compile_type_def(type_def)?;
             }
         }
 
         // Process trait definitions
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::TraitDef(trait_def) = item {
                 self.compile_trait_def(trait_def)?;
             }
@@ -4144,7 +4218,7 @@ impl Compiler {
         // This must happen BEFORE deferred use stmts and top-level trait impls,
         // because `use Geo.*` needs nested traits registered, and top-level trait impls
         // like `Shape: HasArea` need to find traits imported from nested modules.
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::ModuleDef(module_def) = item {
                 self.pre_register_nested_module_metadata(module_def)?;
             }
@@ -4158,7 +4232,7 @@ impl Compiler {
         // Pre-register trait implementations at top level (register type_traits + forward
         // declare methods, but do NOT compile method bodies). This runs AFTER nested modules
         // and deferred use stmts so that traits from nested modules are visible.
-        for item in &module.items {
+        for item in items.iter() {
             if let Item::TraitImpl(trait_impl) = item {
                 self.pre_register_trait_impl(trait_impl)?;
             }
@@ -4213,6 +4287,8 @@ impl Compiler {
     /// Skips function definitions - used when loading from cache.
     fn compile_items_metadata_only(&mut self, items: &[Item]) -> Result<(), CompileError> {
         use nostos_syntax::ast::Item;
+        let items = lower_extern_decls(items);
+        let items: &[Item] = &items;
 
         // Pre-pass: collect names of local (inline) modules
         let local_module_names: std::collections::HashSet<String> = items.iter()
@@ -4338,6 +4414,8 @@ impl Compiler {
         use std::sync::Arc;
         use std::sync::atomic::AtomicU32;
         use nostos_syntax::ast::Item;
+        let items = lower_extern_decls(items);
+        let items: &[Item] = &items;
 
         // Phase 1: Group function definitions by BASE name only
         let mut fn_defs_by_base: std::collections::HashMap<String, Vec<&FnDef>> = std::collections::HashMap::new();
@@ -4516,6 +4594,9 @@ impl Compiler {
                     module: if self.module_path.is_empty() { None } else { Some(self.module_path.join(".")) },
                     source_span: None,
                     jit_code: None,
+                    jit_entry: Default::default(),
+                    opt_entry: Default::default(),
+                    type_profile: Default::default(),
                     call_count: AtomicU32::new(0),
                     debug_symbols: vec![],
                     source_code: None,
@@ -4758,7 +4839,7 @@ impl Compiler {
             "Base64", "Url", "Encoding", "Server", "Exec", "Random", "Path", "Panel",
             "Pg", "Uuid", "Crypto", "Float64Array", "Int64Array", "Float32Array", "Buffer",
             "Runtime", "WebSocket", "RenderStack", "RenderContext", "Reactive", "Gc",
-            "Selenium", "Tcp",
+            "Selenium", "Tcp", "Supervisor", "Timer", "Node", "Table", "Code", "Trace",
         ].iter().map(|s| s.to_string()).collect();
 
         Self {
@@ -10337,6 +10418,9 @@ impl Compiler {
                     module: if self.module_path.is_empty() { None } else { Some(self.module_path.join(".")) },
                     source_span: None,
                     jit_code: None,
+                    jit_entry: Default::default(),
+                    opt_entry: Default::default(),
+                    type_profile: Default::default(),
                     call_count: std::sync::atomic::AtomicU32::new(0),
                     debug_symbols: vec![],
                     source_code: None,
@@ -10696,6 +10780,9 @@ impl Compiler {
                                 module: if self.module_path.is_empty() { None } else { Some(self.module_path.join(".")) },
                                 source_span: None,
                                 jit_code: None,
+                                jit_entry: Default::default(),
+                                opt_entry: Default::default(),
+                                type_profile: Default::default(),
                                 call_count: std::sync::atomic::AtomicU32::new(0),
                                 debug_symbols: vec![],
                                 source_code: None,
@@ -12657,6 +12744,9 @@ impl Compiler {
                         module: old_func.module.clone(),
                         source_span: None,
                         jit_code: None,
+                        jit_entry: Default::default(),
+                        opt_entry: Default::default(),
+                        type_profile: Default::default(),
                         call_count: std::sync::atomic::AtomicU32::new(0),
                         debug_symbols: vec![],
                         source_code: None,
@@ -13111,6 +13201,9 @@ impl Compiler {
             module: if self.module_path.is_empty() { None } else { Some(self.module_path.join(".")) },
             source_span: Some((def.span.start, def.span.end)),
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols,
             // REPL introspection fields
@@ -14120,6 +14213,14 @@ impl Compiler {
                             self.chunk.emit(Instruction::ServerBind(dst, port_reg), line);
                             return Ok(dst);
                         }
+                        "Server.bindTls" if args.len() == 3 => {
+                            let port_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let cert_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let key_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ServerBindTls(dst, port_reg, cert_reg, key_reg), line);
+                            return Ok(dst);
+                        }
                         "Server.accept" if args.len() == 1 => {
                             let handle_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
                             let dst = self.alloc_reg();
@@ -14207,6 +14308,22 @@ impl Compiler {
                             self.chunk.emit(Instruction::TcpListen(dst, port_reg), line);
                             return Ok(dst);
                         }
+                        "Tcp.connectTls" if args.len() == 3 => {
+                            let host_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let port_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let opts_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TcpConnectTls(dst, host_reg, port_reg, opts_reg), line);
+                            return Ok(dst);
+                        }
+                        "Tcp.listenTls" if args.len() == 3 => {
+                            let port_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let cert_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let key_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TcpListenTls(dst, port_reg, cert_reg, key_reg), line);
+                            return Ok(dst);
+                        }
                         "Tcp.accept" if args.len() == 1 => {
                             let handle_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
                             let dst = self.alloc_reg();
@@ -15214,6 +15331,14 @@ impl Compiler {
                             self.chunk.emit(Instruction::ServerBind(dst, port_reg), line);
                             return Ok(dst);
                         }
+                        "Server.bindTls" if args.len() == 3 => {
+                            let port_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let cert_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let key_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ServerBindTls(dst, port_reg, cert_reg, key_reg), line);
+                            return Ok(dst);
+                        }
                         "Server.accept" if args.len() == 1 => {
                             let server_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
                             let dst = self.alloc_reg();
@@ -15301,6 +15426,22 @@ impl Compiler {
                             self.chunk.emit(Instruction::TcpListen(dst, port_reg), line);
                             return Ok(dst);
                         }
+                        "Tcp.connectTls" if args.len() == 3 => {
+                            let host_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let port_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let opts_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TcpConnectTls(dst, host_reg, port_reg, opts_reg), line);
+                            return Ok(dst);
+                        }
+                        "Tcp.listenTls" if args.len() == 3 => {
+                            let port_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let cert_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let key_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TcpListenTls(dst, port_reg, cert_reg, key_reg), line);
+                            return Ok(dst);
+                        }
                         "Tcp.accept" if args.len() == 1 => {
                             let handle_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
                             let dst = self.alloc_reg();
@@ -15708,6 +15849,236 @@ impl Compiler {
                             self.chunk.emit(Instruction::ProcessKill(dst, pid_reg), line);
                             return Ok(dst);
                         }
+                        "Process.spawnLimited" if args.len() == 4 => {
+                            let func_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let heap_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let mailbox_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let linkage_reg = self.compile_expr_tail(Self::call_arg_expr(&args[3]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SpawnLimited(dst, func_reg, heap_reg, mailbox_reg, linkage_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.spawnPriority" if args.len() == 2 => {
+                            let func_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let priority_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SpawnPriority(dst, func_reg, priority_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.spawnBounded" if args.len() == 3 => {
+                            let func_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let capacity_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let policy_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SpawnBounded(dst, func_reg, capacity_reg, policy_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.setPriority" if args.len() == 2 => {
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let priority_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ProcessSetPriority(dst, pid_reg, priority_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.register" if args.len() == 2 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ProcessRegister(dst, name_reg, pid_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.whereis" if args.len() == 1 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ProcessWhereis(dst, name_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.unregister" if args.len() == 1 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ProcessUnregister(dst, name_reg), line);
+                            return Ok(dst);
+                        }
+                        "Process.registered" if args.is_empty() => {
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ProcessRegistered(dst), line);
+                            return Ok(dst);
+                        }
+                        "Process.send" if args.len() == 2 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let msg_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::ProcessSendNamed(dst, name_reg, msg_reg), line);
+                            return Ok(dst);
+                        }
+                        // === Distributed nodes ===
+                        "Node.self" if args.is_empty() => {
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::NodeSelf(dst), line);
+                            return Ok(dst);
+                        }
+                        "Node.connect" | "Node.disconnect" if args.len() == 1 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            if qualified_name == "Node.connect" {
+                                self.chunk.emit(Instruction::NodeConnect(dst, name_reg), line);
+                            } else {
+                                self.chunk.emit(Instruction::NodeDisconnect(dst, name_reg), line);
+                            }
+                            return Ok(dst);
+                        }
+                        "Node.list" if args.is_empty() => {
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::NodeList(dst), line);
+                            return Ok(dst);
+                        }
+                        "Node.of" if args.len() == 1 => {
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::NodeOf(dst, pid_reg), line);
+                            return Ok(dst);
+                        }
+                        "Node.spawnOn" if args.len() == 3 => {
+                            let node_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let func_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let linkage_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::NodeSpawn(dst, node_reg, func_reg, linkage_reg), line);
+                            return Ok(dst);
+                        }
+                        "Node.send" if args.len() == 3 => {
+                            let node_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let msg_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::NodeSend(dst, node_reg, name_reg, msg_reg), line);
+                            return Ok(dst);
+                        }
+                        // === Shared tables ===
+                        "Table.new" | "Table.lookup" | "Table.delete" | "Table.matchKeys" if args.len() == 2 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let arg_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            let instr = match qualified_name.as_str() {
+                                "Table.new" => Instruction::TableNew(dst, name_reg, arg_reg),
+                                "Table.lookup" => Instruction::TableLookup(dst, name_reg, arg_reg),
+                                "Table.delete" => Instruction::TableDelete(dst, name_reg, arg_reg),
+                                _ => Instruction::TableMatch(dst, name_reg, arg_reg),
+                            };
+                            self.chunk.emit(instr, line);
+                            return Ok(dst);
+                        }
+                        "Table.insert" if args.len() == 3 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let key_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let value_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TableInsert(dst, name_reg, key_reg, value_reg), line);
+                            return Ok(dst);
+                        }
+                        "Table.size" | "Table.drop" if args.len() == 1 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            if qualified_name == "Table.size" {
+                                self.chunk.emit(Instruction::TableSize(dst, name_reg), line);
+                            } else {
+                                self.chunk.emit(Instruction::TableDrop(dst, name_reg), line);
+                            }
+                            return Ok(dst);
+                        }
+                        "Table.update" if args.len() == 4 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let key_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let fn_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let default_reg = self.compile_expr_tail(Self::call_arg_expr(&args[3]), false)?;
+                            return Ok(self.emit_table_update(name_reg, key_reg, fn_reg, default_reg, line));
+                        }
+                        "Table.select" if args.len() == 2 => {
+                            let name_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let pred_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            return Ok(self.emit_table_select(name_reg, pred_reg, line));
+                        }
+                        // === Hot code upgrade ===
+                        "Code.reload" | "Code.version" if args.len() == 1 => {
+                            let module_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            if qualified_name == "Code.reload" {
+                                self.chunk.emit(Instruction::CodeReload(dst, module_reg), line);
+                            } else {
+                                self.chunk.emit(Instruction::CodeVersion(dst, module_reg), line);
+                            }
+                            return Ok(dst);
+                        }
+                        // === Process tracing ===
+                        "Trace.start" if args.len() == 2 => {
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let flags_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TraceStart(dst, pid_reg, flags_reg), line);
+                            return Ok(dst);
+                        }
+                        "Trace.stop" if args.len() == 1 => {
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TraceStop(dst, pid_reg), line);
+                            return Ok(dst);
+                        }
+                        // === Timers ===
+                        "Timer.sendAfter" | "Timer.interval" if args.len() == 3 => {
+                            let ms_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let msg_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            if qualified_name == "Timer.sendAfter" {
+                                self.chunk.emit(Instruction::TimerSendAfter(dst, ms_reg, pid_reg, msg_reg), line);
+                            } else {
+                                self.chunk.emit(Instruction::TimerInterval(dst, ms_reg, pid_reg, msg_reg), line);
+                            }
+                            return Ok(dst);
+                        }
+                        "Timer.cancel" if args.len() == 1 => {
+                            let ref_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::TimerCancel(dst, ref_reg), line);
+                            return Ok(dst);
+                        }
+                        // === Supervision ===
+                        "Supervisor.start" if args.len() == 3 => {
+                            let strategy_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let max_restarts_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let max_seconds_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SupervisorStart(dst, strategy_reg, max_restarts_reg, max_seconds_reg), line);
+                            return Ok(dst);
+                        }
+                        "Supervisor.startChild" if args.len() == 4 => {
+                            let sup_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let id_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let func_reg = self.compile_expr_tail(Self::call_arg_expr(&args[2]), false)?;
+                            let restart_reg = self.compile_expr_tail(Self::call_arg_expr(&args[3]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SupervisorStartChild(dst, sup_reg, id_reg, func_reg, restart_reg), line);
+                            return Ok(dst);
+                        }
+                        "Supervisor.terminateChild" if args.len() == 2 => {
+                            let sup_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let id_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SupervisorTerminateChild(dst, sup_reg, id_reg), line);
+                            return Ok(dst);
+                        }
+                        "Supervisor.whichChildren" if args.len() == 1 => {
+                            let sup_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SupervisorWhichChildren(dst, sup_reg), line);
+                            return Ok(dst);
+                        }
+                        "Supervisor.stop" if args.len() == 1 => {
+                            let sup_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::SupervisorStop(dst, sup_reg), line);
+                            return Ok(dst);
+                        }
                         // === Garbage collection ===
                         "Gc.collect" if args.is_empty() => {
                             let dst = self.alloc_reg();
@@ -15719,6 +16090,13 @@ impl Compiler {
                             self.chunk.emit(Instruction::GcStats(dst), line);
                             return Ok(dst);
                         }
+                        "Gc.snapshot" if args.len() == 2 => {
+                            let pid_reg = self.compile_expr_tail(Self::call_arg_expr(&args[0]), false)?;
+                            let path_reg = self.compile_expr_tail(Self::call_arg_expr(&args[1]), false)?;
+                            let dst = self.alloc_reg();
+                            self.chunk.emit(Instruction::GcSnapshot(dst, pid_reg, path_reg), line);
+                            return Ok(dst);
+                        }
                         // === Runtime info ===
                         "Runtime.isInteractive" if args.is_empty() => {
                             let dst = self.alloc_reg();
@@ -16221,6 +16599,13 @@ impl Compiler {
                         if let Some(&func_idx) = self.function_indices.get(&final_call_name) {
                             // Track function call for deadlock detection
                             self.current_fn_calls.insert(final_call_name.clone());
+                            // A qualified self-call is where a loop moves to reloaded code;
+                            // its first argument is the state codeChange migrates
+                            if self.current_function_name.as_ref() == Some(&final_call_name) {
+                                if let Some(&state_reg) = arg_regs.first() {
+                                    self.chunk.emit(Instruction::CodeChange(state_reg, func_idx), line);
+                                }
+                            }
                             if is_tail {
                                 // Emit MvarUnlock for all held locks before tail call
                                 for (_, name_idx, is_write) in self.current_fn_mvar_locks.iter().rev() {
@@ -17304,6 +17689,54 @@ impl Compiler {
         }
     }
 
+    /// Emit `Table.update(name, key, f, default)`: read the value (or the
+    /// default) with its version, call `f` and commit the result, starting over
+    /// if another process wrote the key in between.
+    fn emit_table_update(&mut self, name_reg: Reg, key_reg: Reg, fn_reg: Reg, default_reg: Reg, line: usize) -> Reg {
+        let old_val_reg = self.alloc_reg();
+        let version_reg = self.alloc_reg();
+        let new_val_reg = self.alloc_reg();
+        let stored_reg = self.alloc_reg();
+
+        let retry_start = self.chunk.code.len();
+        self.chunk.emit(Instruction::TableRead(old_val_reg, version_reg, name_reg, key_reg, default_reg), line);
+        self.chunk.emit(Instruction::Call(new_val_reg, fn_reg, vec![old_val_reg].into()), line);
+        self.chunk.emit(Instruction::TableCommit(stored_reg, name_reg, key_reg, version_reg, new_val_reg), line);
+        let jump_offset = retry_start as i16 - self.chunk.code.len() as i16 - 1;
+        self.chunk.emit(Instruction::JumpIfFalse(stored_reg, jump_offset), line);
+        new_val_reg
+    }
+
+    /// Emit `Table.select(name, pred)`: take all entries of the table and keep
+    /// those whose key satisfies `pred`.
+    fn emit_table_select(&mut self, name_reg: Reg, pred_reg: Reg, line: usize) -> Reg {
+        let all_reg = self.alloc_reg();
+        let entries_reg = self.alloc_reg();
+        let dst = self.alloc_reg();
+        self.chunk.emit(Instruction::LoadUnit(all_reg), line);
+        self.chunk.emit(Instruction::TableMatch(entries_reg, name_reg, all_reg), line);
+        self.chunk.emit(Instruction::MakeList(dst, vec![].into()), line);
+
+        let done_reg = self.alloc_reg();
+        let entry_reg = self.alloc_reg();
+        let key_reg = self.alloc_reg();
+        let keep_reg = self.alloc_reg();
+        let loop_start = self.chunk.code.len();
+        self.chunk.emit(Instruction::ListIsEmpty(done_reg, entries_reg), line);
+        let exit_jump = self.chunk.emit(Instruction::JumpIfTrue(done_reg, 0), line);
+        self.chunk.emit(Instruction::ListHead(entry_reg, entries_reg), line);
+        self.chunk.emit(Instruction::ListTail(entries_reg, entries_reg), line);
+        self.chunk.emit(Instruction::GetTupleField(key_reg, entry_reg, 0), line);
+        self.chunk.emit(Instruction::Call(keep_reg, pred_reg, vec![key_reg].into()), line);
+        let jump_offset = loop_start as i16 - self.chunk.code.len() as i16 - 1;
+        self.chunk.emit(Instruction::JumpIfFalse(keep_reg, jump_offset), line);
+        self.chunk.emit(Instruction::Cons(dst, entry_reg, dst), line);
+        let jump_offset = loop_start as i16 - self.chunk.code.len() as i16 - 1;
+        self.chunk.emit(Instruction::Jump(jump_offset), line);
+        self.chunk.patch_jump(exit_jump, self.chunk.code.len());
+        dst
+    }
+
     /// Compile a while loop.
     fn compile_while(&mut self, cond: &Expr, body: &Expr) -> Result<Reg, CompileError> {
         let dst = self.alloc_reg();
@@ -18060,6 +18493,31 @@ impl Compiler {
                 self.emit_call_extension(dst, &ext_func_name, arg_regs.into(), line);
                 return Ok(dst);
             }
+            // Handle __extern__ - call a C function (generated by extern declarations)
+            // Syntax: __extern__("<descriptor>", arg1, arg2, ...)
+            if name == EXTERN_CALL_BUILTIN && !args.is_empty() {
+                let first_arg = Self::call_arg_expr(&args[0]);
+                let descriptor = match first_arg {
+                    Expr::String(StringLit::Plain(s), _) => s.clone(),
+                    _ => {
+                        return Err(CompileError::TypeError {
+                            message: "__extern__ first argument must be a plain string literal".to_string(),
+                            span: first_arg.span(),
+                        });
+                    }
+                };
+
+                let mut arg_regs = Vec::new();
+                for arg in &args[1..] {
+                    let reg = self.compile_expr_tail(Self::call_arg_expr(arg), false)?;
+                    arg_regs.push(reg);
+                }
+
+                let dst = self.alloc_reg();
+                let desc_idx = self.chunk.add_constant(Value::String(Arc::new(descriptor)));
+                self.chunk.emit(Instruction::CallExtern(dst, desc_idx, arg_regs.into()), line);
+                return Ok(dst);
+            }
             // Handle self() - get current process ID
             if name == "self" && args.is_empty() {
                 let dst = self.alloc_reg();
@@ -18566,6 +19024,11 @@ impl Compiler {
                         self.chunk.emit(Instruction::LoadUnit(dst), line);
                         return Ok(dst);
                     }
+                    "trySend" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TrySend(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
                     "vmStats" if arg_regs.is_empty() => {
                         // vmStats() - get process stats
                         let dst = self.alloc_reg();
@@ -19039,6 +19502,193 @@ impl Compiler {
                         self.chunk.emit(Instruction::ProcessKill(dst, arg_regs[0]), line);
                         return Ok(dst);
                     }
+                    "Process.spawnLimited" if arg_regs.len() == 4 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SpawnLimited(dst, arg_regs[0], arg_regs[1], arg_regs[2], arg_regs[3]), line);
+                        return Ok(dst);
+                    }
+                    "Process.spawnPriority" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SpawnPriority(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Process.spawnBounded" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SpawnBounded(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    "Process.setPriority" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::ProcessSetPriority(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Process.register" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::ProcessRegister(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Process.whereis" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::ProcessWhereis(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Process.unregister" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::ProcessUnregister(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Process.registered" if arg_regs.is_empty() => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::ProcessRegistered(dst), line);
+                        return Ok(dst);
+                    }
+                    "Process.send" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::ProcessSendNamed(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    // === Distributed nodes ===
+                    "Node.self" if arg_regs.is_empty() => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeSelf(dst), line);
+                        return Ok(dst);
+                    }
+                    "Node.connect" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeConnect(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Node.disconnect" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeDisconnect(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Node.list" if arg_regs.is_empty() => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeList(dst), line);
+                        return Ok(dst);
+                    }
+                    "Node.of" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeOf(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Node.spawnOn" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeSpawn(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    "Node.send" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::NodeSend(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    // === Shared tables ===
+                    "Table.new" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableNew(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Table.insert" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableInsert(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    "Table.lookup" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableLookup(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Table.delete" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableDelete(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Table.matchKeys" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableMatch(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Table.size" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableSize(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Table.drop" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TableDrop(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Table.update" if arg_regs.len() == 4 => {
+                        return Ok(self.emit_table_update(arg_regs[0], arg_regs[1], arg_regs[2], arg_regs[3], line));
+                    }
+                    "Table.select" if arg_regs.len() == 2 => {
+                        return Ok(self.emit_table_select(arg_regs[0], arg_regs[1], line));
+                    }
+                    // === Hot code upgrade ===
+                    "Code.reload" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::CodeReload(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Code.version" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::CodeVersion(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    // === Process tracing ===
+                    "Trace.start" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TraceStart(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Trace.stop" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TraceStop(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    // === Timers ===
+                    "Timer.sendAfter" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TimerSendAfter(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    "Timer.interval" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TimerInterval(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    "Timer.cancel" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::TimerCancel(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    // === Supervision ===
+                    "Supervisor.start" if arg_regs.len() == 3 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SupervisorStart(dst, arg_regs[0], arg_regs[1], arg_regs[2]), line);
+                        return Ok(dst);
+                    }
+                    "Supervisor.startChild" if arg_regs.len() == 4 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SupervisorStartChild(dst, arg_regs[0], arg_regs[1], arg_regs[2], arg_regs[3]), line);
+                        return Ok(dst);
+                    }
+                    "Supervisor.terminateChild" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SupervisorTerminateChild(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
+                    "Supervisor.whichChildren" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SupervisorWhichChildren(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
+                    "Supervisor.stop" if arg_regs.len() == 1 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::SupervisorStop(dst, arg_regs[0]), line);
+                        return Ok(dst);
+                    }
                     // === Garbage collection ===
                     "Gc.collect" if arg_regs.is_empty() => {
                         let dst = self.alloc_reg();
@@ -19050,6 +19700,11 @@ impl Compiler {
                         self.chunk.emit(Instruction::GcStats(dst), line);
                         return Ok(dst);
                     }
+                    "Gc.snapshot" if arg_regs.len() == 2 => {
+                        let dst = self.alloc_reg();
+                        self.chunk.emit(Instruction::GcSnapshot(dst, arg_regs[0], arg_regs[1]), line);
+                        return Ok(dst);
+                    }
                     // === External process execution ===
                     "Exec.run" if arg_regs.len() == 2 => {
                         let dst = self.alloc_reg();
@@ -20887,6 +21542,9 @@ impl Compiler {
             module: if original_module_path.is_empty() { None } else { Some(original_module_path.join(".")) },
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             // REPL introspection fields - will be populated when compiled
@@ -21047,6 +21705,9 @@ impl Compiler {
             module: if original_module_path.is_empty() { None } else { Some(original_module_path.join(".")) },
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -21948,6 +22609,9 @@ impl Compiler {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols,
             source_code: None,
@@ -23240,20 +23904,24 @@ impl Compiler {
         // Check for variant types
         if let Some(type_info) = self.types.get(scrut_type) {
             if let TypeInfoKind::Variant { constructors } = &type_info.kind {
-                let all_ctors: std::collections::HashSet<&str> = constructors
-                    .iter()
-                    .map(|(name, _)| name.as_str())
-                    .collect();
-
                 let mut covered_names = Vec::new();
                 for arm in arms {
                     extract_variant_names(&arm.pattern, &mut covered_names);
                 }
                 let covered_ctors: std::collections::HashSet<&str> = covered_names.into_iter().collect();
 
-                let missing: Vec<String> = all_ctors
-                    .difference(&covered_ctors)
-                    .map(|s| s.to_string())
+                // Missing constructors in declaration order, written as patterns
+                // (`Rect(_, _)`, `Named{_}`) so they can be pasted as match arms
+                let missing: Vec<String> = constructors
+                    .iter()
+                    .filter(|(name, _)| !covered_ctors.contains(name.as_str()))
+                    .map(|(name, fields)| match fields {
+                        VariantFieldsInfo::Unit => name.clone(),
+                        VariantFieldsInfo::Positional(types) => {
+                            format!("{}({})", name, vec!["_"; types.len()].join(", "))
+                        }
+                        VariantFieldsInfo::Named(_) => format!("{}{{_}}", name),
+                    })
                     .collect();
 
                 if !missing.is_empty() {
@@ -25439,6 +26107,9 @@ impl Compiler {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols,
             // REPL introspection fields - lambdas don't have these
@@ -31860,6 +32531,174 @@ fn collect_pattern_vars(pat: &Pattern, vars: &mut std::collections::HashSet<Stri
     }
 }
 
+/// A `test "name" = expr` block lowered into a zero-argument function.
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    /// The test description from the source.
+    pub name: String,
+    /// Module the test was declared in (dot-separated, empty for top-level).
+    pub module: String,
+    /// Fully qualified function name to run, including the signature suffix (e.g. `math.__test_0/`).
+    pub function_name: String,
+    pub span: Span,
+}
+
+/// Prefix of the generated function names for lowered test blocks.
+pub const TEST_FN_PREFIX: &str = "__test_";
+
+/// Replace every `Item::Test` in a module (including nested `module` blocks)
+/// with a private zero-argument function, so tests are compiled like any other
+/// definition. Returns the tests in source order.
+///
+/// This must run before the module is registered with the compiler. Regular
+/// program runs never call it, so test bodies only cost anything under `nostos test`.
+pub fn lower_test_defs(module: &mut Module, module_path: &[String]) -> Vec<TestCase> {
+    fn lower_items(items: &mut [Item], module_path: &[String], tests: &mut Vec<TestCase>) {
+        for item in items.iter_mut() {
+            match item {
+                Item::Test(test) => {
+                    let local_name = format!("{}{}", TEST_FN_PREFIX, tests.len());
+                    let module = module_path.join(".");
+                    let function_name = if module.is_empty() {
+                        format!("{}/", local_name)
+                    } else {
+                        format!("{}.{}/", module, local_name)
+                    };
+                    tests.push(TestCase {
+                        name: test.name.clone(),
+                        module,
+                        function_name,
+                        span: test.span,
+                    });
+                    *item = Item::FnDef(FnDef {
+                        visibility: Visibility::Private,
+                        doc: None,
+                        decorators: vec![],
+                        name: Spanned::new(local_name, test.span),
+                        type_params: vec![],
+                        clauses: vec![FnClause {
+                            params: vec![],
+                            guard: None,
+                            return_type: None,
+                            body: test.body.clone(),
+                            span: test.span,
+                        }],
+                        is_template: false,
+                        span: test.span,
+                    });
+                }
+                Item::ModuleDef(module_def) => {
+                    let mut inner_path = module_path.to_vec();
+                    inner_path.push(module_def.name.node.clone());
+                    lower_items(&mut module_def.items, &inner_path, tests);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut tests = Vec::new();
+    lower_items(&mut module.items, module_path, &mut tests);
+    tests
+}
+
+/// Builtin emitted by extern lowering; compiles to `Instruction::CallExtern`.
+const EXTERN_CALL_BUILTIN: &str = "__extern__";
+
+/// Lower `extern` declarations into ordinary items.
+///
+/// `extern f(x: T) -> R from "lib"` becomes a public function with the same
+/// annotated signature whose body is `__extern__("<descriptor>", x)`, so callers
+/// are type checked like any other function. `extern type T` becomes a public
+/// opaque type whose values are raw pointers at runtime.
+///
+/// Returns the items unchanged (borrowed) when there are no extern declarations,
+/// so every entry point can call this cheaply.
+fn lower_extern_decls(items: &[Item]) -> std::borrow::Cow<'_, [Item]> {
+    use std::borrow::Cow;
+    use nostos_vm::ffi::{ExternSignature, FfiType};
+
+    fn contains_extern(items: &[Item]) -> bool {
+        items.iter().any(|item| match item {
+            Item::Extern(_) => true,
+            Item::ModuleDef(module_def) => contains_extern(&module_def.items),
+            _ => false,
+        })
+    }
+
+    fn ffi_type(ty: &TypeExpr) -> FfiType {
+        match ty {
+            TypeExpr::Unit => FfiType::Unit,
+            TypeExpr::Tuple(elems) if elems.is_empty() => FfiType::Unit,
+            // Qualified extern types (`lib.Handle`) map through their last segment
+            TypeExpr::Name(name) => FfiType::from_type_name(name.node.rsplit('.').next().unwrap_or(&name.node)),
+            _ => FfiType::Pointer,
+        }
+    }
+
+    fn lower(item: &Item) -> Item {
+        match item {
+            Item::Extern(ExternDecl { kind: ExternKind::Function { name, params, return_type, from }, span }) => {
+                let signature = ExternSignature {
+                    library: from.clone(),
+                    symbol: name.node.clone(),
+                    params: params.iter().map(|(_, ty)| ffi_type(ty)).collect(),
+                    ret: ffi_type(return_type),
+                };
+                let mut args = vec![CallArg::Positional(Expr::String(StringLit::Plain(signature.to_descriptor()), *span))];
+                args.extend(params.iter().map(|(p, _)| CallArg::Positional(Expr::Var(p.clone()))));
+                Item::FnDef(FnDef {
+                    visibility: Visibility::Public,
+                    doc: None,
+                    decorators: vec![],
+                    name: name.clone(),
+                    type_params: vec![],
+                    clauses: vec![FnClause {
+                        params: params.iter().map(|(p, ty)| FnParam {
+                            pattern: Pattern::Var(p.clone()),
+                            ty: Some(ty.clone()),
+                            default: None,
+                        }).collect(),
+                        guard: None,
+                        return_type: Some(return_type.clone()),
+                        body: Expr::Call(
+                            Box::new(Expr::Var(Spanned::new(EXTERN_CALL_BUILTIN.to_string(), name.span))),
+                            vec![],
+                            args,
+                            *span,
+                        ),
+                        span: *span,
+                    }],
+                    is_template: false,
+                    span: *span,
+                })
+            }
+            Item::Extern(ExternDecl { kind: ExternKind::Type { name }, span }) => Item::TypeDef(TypeDef {
+                visibility: Visibility::Public,
+                doc: None,
+                decorators: vec![],
+                mutable: false,
+                reactive: false,
+                name: name.clone(),
+                type_params: vec![],
+                body: TypeBody::Record(vec![]),
+                span: *span,
+            }),
+            Item::ModuleDef(module_def) if contains_extern(&module_def.items) => {
+                let mut module_def = module_def.clone();
+                module_def.items = module_def.items.iter().map(lower).collect();
+                Item::ModuleDef(module_def)
+            }
+            other => other.clone(),
+        }
+    }
+
+    if !contains_extern(items) {
+        return Cow::Borrowed(items);
+    }
+    Cow::Owned(items.iter().map(lower).collect())
+}
+
 /// Compile a complete module.
 pub fn compile_module(module: &Module, source: &str) -> Result<Compiler, CompileError> {
     let mut compiler = Compiler::new(source);
@@ -32038,6 +32877,8 @@ fn load_stdlib_into_compiler(compiler: &mut Compiler, stdlib_path: &std::path::P
 impl Compiler {
     /// Compile a list of items (can be called recursively for nested modules).
     fn compile_items(&mut self, items: &[Item]) -> Result<(), CompileError> {
+        let items = lower_extern_decls(items);
+        let items: &[Item] = &items;
         // Pre-pass: collect names of local (inline) modules defined in this scope
         // This allows us to defer use statements that reference them
         let local_module_names: std::collections::HashSet<String> = items.iter()
@@ -32279,6 +33120,9 @@ impl Compiler {
                     module: if self.module_path.is_empty() { None } else { Some(self.module_path.join(".")) },
                     source_span: None,
                     jit_code: None,
+                    jit_entry: Default::default(),
+                    opt_entry: Default::default(),
+                    type_profile: Default::default(),
                     call_count: AtomicU32::new(0),
                     debug_symbols: vec![],
                     // REPL introspection fields - will be populated when compiled
@@ -32731,6 +33575,208 @@ mod tests {
         Ok(result.to_value())
     }
 
+    // ========== Test Block Lowering ==========
+
+    #[test]
+    fn test_lower_test_defs_names_and_modules() {
+        let source = r#"
+            add(a, b) = a + b
+            test "adds" = add(1, 2) == 3
+            module Inner
+                test "nested" = true
+            end
+        "#;
+        let (module_opt, errors) = parse(source);
+        assert!(errors.is_empty(), "Parse errors: {:?}", errors);
+        let mut module = module_opt.unwrap();
+        let tests = lower_test_defs(&mut module, &["math".to_string()]);
+
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[0].name, "adds");
+        assert_eq!(tests[0].module, "math");
+        assert_eq!(tests[0].function_name, "math.__test_0/");
+        assert_eq!(tests[1].module, "math.Inner");
+        assert_eq!(tests[1].function_name, "math.Inner.__test_1/");
+        assert!(!module.items.iter().any(|item| matches!(item, Item::Test(_))));
+    }
+
+    #[test]
+    fn test_lowered_test_def_runs() {
+        let source = r#"
+            add(a, b) = a + b
+            test "adds" = add(20, 22)
+            main() = 0
+        "#;
+        let (module_opt, _) = parse(source);
+        let mut module = module_opt.unwrap();
+        let tests = lower_test_defs(&mut module, &[]);
+        assert_eq!(tests[0].function_name, "__test_0/");
+
+        let compiler = compile_module(&module, source).expect("compile failed");
+        let mut vm = AsyncVM::new(AsyncConfig { num_threads: 1, ..Default::default() });
+        vm.register_default_natives();
+        for (name, func) in compiler.get_all_functions() {
+            vm.register_function(&name, func.clone());
+        }
+        vm.set_function_list(compiler.get_function_list());
+        let result = vm.run(&tests[0].function_name).expect("test function failed");
+        assert_eq!(result.to_value(), Value::Int64(42));
+    }
+
+    #[test]
+    fn test_supervisor_restarts_child_until_intensity_exceeded() {
+        let source = r#"
+            worker() = receive {
+                "crash" -> 1 / 0
+                _ -> worker()
+            }
+
+            awaitRestart(sup, old) = {
+                (_, pid) = Supervisor.whichChildren(sup)[0]
+                if pid == old then {
+                    sleep(1)
+                    awaitRestart(sup, old)
+                } else pid
+            }
+
+            main() = {
+                sup = Supervisor.start("oneForOne", 1, 60)
+                first = Supervisor.startChild(sup, "worker", () => worker(), "permanent")
+                first <- "crash"
+                second = awaitRestart(sup, first)
+                second <- "crash"
+                receive {
+                    ("EXIT", exited, _) -> if exited == sup then 1 else 0
+                }
+            }
+        "#;
+        let (module_opt, _) = parse(source);
+        let module = module_opt.unwrap();
+        let compiler = compile_module(&module, source).expect("compile failed");
+        let mut vm = AsyncVM::new(AsyncConfig { num_threads: 1, ..Default::default() });
+        vm.register_default_natives();
+        for (name, func) in compiler.get_all_functions() {
+            vm.register_function(&name, func.clone());
+        }
+        vm.set_function_list(compiler.get_function_list());
+        let result = vm.run("main/").expect("main failed");
+        assert_eq!(result.to_value(), Value::Int64(1));
+    }
+
+    #[test]
+    fn test_default_mailbox_limit_terminates_process() {
+        let source = r#"
+            spin(n) = if n > 0 then spin(n - 1) else ()
+
+            slowConsumer() = receive {
+                _ -> {
+                    spin(100000)
+                    slowConsumer()
+                }
+            }
+
+            flood(pid, n) = if n > 0 then {
+                pid <- n
+                flood(pid, n - 1)
+            } else ()
+
+            main() = {
+                busy = spawn_monitor(() => slowConsumer())
+                flood(busy, 50)
+                receive {
+                    ("DOWN", _, "mailbox_limit") -> 1
+                    _ -> 0
+                }
+            }
+        "#;
+        let (module_opt, _) = parse(source);
+        let module = module_opt.unwrap();
+        let compiler = compile_module(&module, source).expect("compile failed");
+        let gc_config = nostos_vm::GcConfig { max_mailbox_len: Some(10), ..Default::default() };
+        let mut vm = AsyncVM::new(AsyncConfig { num_threads: 1, gc_config, ..Default::default() });
+        vm.register_default_natives();
+        for (name, func) in compiler.get_all_functions() {
+            vm.register_function(&name, func.clone());
+        }
+        vm.set_function_list(compiler.get_function_list());
+        let result = vm.run("main/").expect("main failed");
+        assert_eq!(result.to_value(), Value::Int64(1));
+    }
+
+    #[test]
+    fn test_sampler_records_process_stacks() {
+        let source = r#"
+            spin(n) = if n > 0 then spin(n - 1) else ()
+
+            work(n) = if n > 0 then {
+                spin(1000)
+                work(n - 1)
+            } else ()
+
+            main() = {
+                work(2000)
+                0
+            }
+        "#;
+        let (module_opt, _) = parse(source);
+        let module = module_opt.unwrap();
+        let compiler = compile_module(&module, source).expect("compile failed");
+        let sampler = nostos_vm::SamplerConfig { interval: std::time::Duration::from_micros(100), pids: None };
+        let mut vm = AsyncVM::new(AsyncConfig { num_threads: 1, sampler: Some(sampler), ..Default::default() });
+        vm.register_default_natives();
+        for (name, func) in compiler.get_all_functions() {
+            vm.register_function(&name, func.clone());
+        }
+        vm.set_function_list(compiler.get_function_list());
+        vm.run("main/").expect("main failed");
+
+        let samples = vm.stack_samples().expect("sampler enabled");
+        assert!(samples.total() > 0);
+        let folded = samples.to_folded();
+        assert!(folded.lines().all(|line| line.starts_with("pid 1;main/;work/_")), "{}", folded);
+        assert!(folded.contains(";spin/_ "), "{}", folded);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_extern_functions_call_libc() {
+        let source = r#"
+            extern labs(x: Int) -> Int from "c"
+            extern strlen(s: String) -> Int from "c"
+            extern type Handle
+            extern getenv(name: String) -> Handle from "c"
+            main() = labs(-40) + strlen("ab")
+        "#;
+        let (module_opt, _) = parse(source);
+        let module = module_opt.unwrap();
+        let compiler = compile_module(&module, source).expect("compile failed");
+        let labs = compiler.get_all_functions().into_iter()
+            .find(|(name, _)| *name == "labs/Int")
+            .map(|(_, f)| f)
+            .expect("extern wrapper not compiled");
+        assert!(labs.code.code.iter().any(|i| matches!(i, Instruction::CallExtern(..))));
+
+        let mut vm = AsyncVM::new(AsyncConfig { num_threads: 1, ..Default::default() });
+        vm.register_default_natives();
+        for (name, func) in compiler.get_all_functions() {
+            vm.register_function(&name, func.clone());
+        }
+        vm.set_function_list(compiler.get_function_list());
+        let result = vm.run("main/").expect("main failed");
+        assert_eq!(result.to_value(), Value::Int64(42));
+    }
+
+    #[test]
+    fn test_extern_function_argument_type_checked() {
+        let source = r#"
+            extern labs(x: Int) -> Int from "c"
+            main() = labs("seven")
+        "#;
+        let (module_opt, _) = parse(source);
+        let module = module_opt.unwrap();
+        assert!(compile_module(&module, source).is_err());
+    }
+
     // ========== Doc Comment Tests ==========
 
     #[test]
@@ -876,6 +876,30 @@ mod concurrency {
 
     #[test]
     fn multiple_spawns() { run_category_test("multiple_spawns"); }
+
+    #[test]
+    fn link_monitor_exit() { run_category_test("link_monitor_exit"); }
+
+    #[test]
+    fn supervisor_one_for_all() { run_category_test("supervisor_one_for_all"); }
+
+    #[test]
+    fn process_registry() { run_category_test("process_registry"); }
+
+    #[test]
+    fn timers() { run_category_test("timers"); }
+
+    #[test]
+    fn node_local() { run_category_test("node_local"); }
+
+    #[test]
+    fn tables() { run_category_test("tables"); }
+
+    #[test]
+    fn code_reload() { run_category_test("code_reload"); }
+
+    #[test]
+    fn process_trace() { run_category_test("process_trace"); }
 }
 
 /// Tests for source code display (multi-clause functions)
@@ -0,0 +1,413 @@
+//! Maps Debug Adapter Protocol requests onto a Nostos debug session.
+//!
+//! Every process of the debugged program is a DAP thread, identified by its
+//! pid. Processes spawned by the program attach to the session's `DebugHub`
+//! and are reported with `thread` events.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use nostos_repl::{ReplConfig, ReplEngine};
+use nostos_vm::shared_types::{Breakpoint, DebugCommand, DebugEvent};
+use nostos_vm::DebugSession;
+use serde_json::{json, Value};
+
+use crate::protocol::Outbox;
+
+/// How long a paused process gets to answer a stack or locals query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Adapter<W: Write> {
+    out: Outbox<W>,
+    engine: Option<ReplEngine>,
+    session: Option<DebugSession>,
+    /// Entry function, e.g. `main.main`
+    entry: String,
+    stop_on_entry: bool,
+    /// Whether `configurationDone` was received
+    configured: bool,
+    /// Whether the main process reached its initial pause
+    entry_paused: bool,
+    /// Whether the main process was let run
+    started: bool,
+    /// Thread names by pid
+    threads: BTreeMap<u64, String>,
+    paused: HashSet<u64>,
+    /// Reason to report for the next stop of a process (default: breakpoint)
+    stop_reasons: HashMap<u64, &'static str>,
+    /// Line breakpoints by source path
+    line_breakpoints: HashMap<String, Vec<Breakpoint>>,
+    function_breakpoints: Vec<Breakpoint>,
+    /// Stack frame ids handed out by `stackTrace`: (pid, frame index)
+    frames: HashMap<i64, (u64, usize)>,
+    next_frame_id: i64,
+    /// Events received while waiting for the answer to a query
+    pending_events: VecDeque<DebugEvent>,
+    done: bool,
+}
+
+impl<W: Write> Adapter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            out: Outbox::new(writer),
+            engine: None,
+            session: None,
+            entry: String::new(),
+            stop_on_entry: false,
+            configured: false,
+            entry_paused: false,
+            started: false,
+            threads: BTreeMap::new(),
+            paused: HashSet::new(),
+            stop_reasons: HashMap::new(),
+            line_breakpoints: HashMap::new(),
+            function_breakpoints: Vec::new(),
+            frames: HashMap::new(),
+            next_frame_id: 1,
+            pending_events: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Whether the client disconnected.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Debug events of the running session.
+    pub fn events(&self) -> crossbeam::channel::Receiver<DebugEvent> {
+        match &self.session {
+            Some(session) => session.event_receiver.clone(),
+            None => crossbeam::channel::never(),
+        }
+    }
+
+    pub fn handle_request(&mut self, request: Value) -> io::Result<()> {
+        if request["type"] != "request" {
+            return Ok(());
+        }
+        let args = request.get("arguments").cloned().unwrap_or(Value::Null);
+        let result = match request["command"].as_str().unwrap_or_default() {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsTerminateRequest": true,
+            })),
+            "launch" => {
+                let result = self.launch(&args);
+                if result.is_ok() {
+                    self.out.respond(&request, Ok(Value::Null))?;
+                    return self.out.event("initialized", Value::Null);
+                }
+                result
+            }
+            "configurationDone" => {
+                self.configured = true;
+                self.start()?;
+                Ok(Value::Null)
+            }
+            "setBreakpoints" => Ok(self.set_breakpoints(&args)),
+            "setFunctionBreakpoints" => Ok(self.set_function_breakpoints(&args)),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "threads" => Ok(self.threads()),
+            "continue" => self.resume(&args, DebugCommand::Continue, None)
+                .map(|()| json!({ "allThreadsContinued": false })),
+            "next" => self.resume(&args, DebugCommand::StepOver, Some("step")).map(|()| Value::Null),
+            "stepIn" => self.resume(&args, DebugCommand::StepLine, Some("step")).map(|()| Value::Null),
+            "stepOut" => self.resume(&args, DebugCommand::StepOut, Some("step")).map(|()| Value::Null),
+            "pause" => self.pause(&args).map(|()| Value::Null),
+            "stackTrace" => self.stack_trace(&args),
+            "scopes" => Ok(json!({
+                "scopes": [{
+                    "name": "Locals",
+                    "presentationHint": "locals",
+                    "variablesReference": args["frameId"],
+                    "expensive": false,
+                }]
+            })),
+            "variables" => self.variables(&args),
+            "disconnect" | "terminate" => {
+                self.done = true;
+                Ok(Value::Null)
+            }
+            command => Err(format!("Unsupported request '{}'", command)),
+        };
+        self.out.respond(&request, result)
+    }
+
+    /// Handle events queued up while waiting for a query answer.
+    pub fn handle_pending_events(&mut self) -> io::Result<()> {
+        while let Some(event) = self.pending_events.pop_front() {
+            self.handle_event(event)?;
+        }
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, event: DebugEvent) -> io::Result<()> {
+        let Some(main_pid) = self.session.as_ref().map(|s| s.pid) else { return Ok(()) };
+        match event {
+            DebugEvent::Paused { pid, .. } => {
+                if pid == main_pid && !self.started {
+                    // The initial pause before the entry function runs
+                    self.entry_paused = true;
+                    return self.start();
+                }
+                let reason = self.stop_reasons.remove(&pid).unwrap_or("breakpoint");
+                if reason == "entry" {
+                    self.remove_entry_breakpoint(pid);
+                }
+                self.paused.insert(pid);
+                self.flush_output()?;
+                self.out.event("stopped", json!({
+                    "reason": reason,
+                    "threadId": pid,
+                    "allThreadsStopped": false,
+                }))?;
+            }
+            DebugEvent::ProcessStarted { pid, function } => {
+                self.threads.insert(pid, format!("{} ({})", display_name(&function), pid));
+                self.out.event("thread", json!({ "reason": "started", "threadId": pid }))?;
+            }
+            DebugEvent::Exited { pid, .. } if pid == main_pid => {
+                let session = self.session.take().expect("session is running");
+                let result = session.wait();
+                self.flush_output()?;
+                if let Err(message) = &result {
+                    self.out.event("output", json!({ "category": "stderr", "output": format!("{}\n", message) }))?;
+                }
+                self.out.event("exited", json!({ "exitCode": if result.is_ok() { 0 } else { 1 } }))?;
+                self.out.event("terminated", Value::Null)?;
+            }
+            DebugEvent::Exited { pid, .. } => {
+                self.threads.remove(&pid);
+                self.paused.remove(&pid);
+                self.out.event("thread", json!({ "reason": "exited", "threadId": pid }))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Forward the program's output as `output` events.
+    pub fn flush_output(&mut self) -> io::Result<()> {
+        let Some(engine) = &self.engine else { return Ok(()) };
+        for line in engine.drain_output() {
+            self.out.event("output", json!({ "category": "stdout", "output": format!("{}\n", line) }))?;
+        }
+        Ok(())
+    }
+
+    /// Load the program and start it paused. `program` is a `.nos` file or a
+    /// project directory.
+    fn launch(&mut self, args: &Value) -> Result<Value, String> {
+        if self.session.is_some() {
+            return Err("Already launched".to_string());
+        }
+        let program = args["program"].as_str().ok_or("Missing 'program' launch argument")?;
+        let path = Path::new(program).canonicalize()
+            .map_err(|e| format!("Cannot open {}: {}", program, e))?;
+        let config = ReplConfig { enable_jit: false, ..ReplConfig::default() };
+
+        let (mut engine, default_entry) = if path.is_dir() {
+            (ReplEngine::init_with_project(config, Some(&path))?, "main.main".to_string())
+        } else {
+            let mut engine = ReplEngine::new(config);
+            engine.load_stdlib()?;
+            engine.load_file(&path.to_string_lossy())?;
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("main");
+            (engine, format!("{}.main", stem))
+        };
+        self.entry = args["entry"].as_str().map(str::to_string).unwrap_or(default_entry);
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+
+        let session = engine.start_debug_session(&format!("{}()", self.entry), true)?;
+        let hub = &session.hub;
+        for bp in self.line_breakpoints.values().flatten().chain(&self.function_breakpoints) {
+            hub.add_breakpoint(bp.clone());
+        }
+        self.threads.insert(session.pid, "main".to_string());
+        self.engine = Some(engine);
+        self.session = Some(session);
+        Ok(Value::Null)
+    }
+
+    /// Let the main process run once the client is configured and the
+    /// process reached its initial pause. With `stopOnEntry`, it stops again
+    /// when the entry function is called.
+    fn start(&mut self) -> io::Result<()> {
+        if !self.configured || !self.entry_paused || self.started {
+            return Ok(());
+        }
+        self.started = true;
+        let Some(session) = &self.session else { return Ok(()) };
+        if self.stop_on_entry {
+            let _ = session.hub.send(session.pid, DebugCommand::AddBreakpoint(self.entry_breakpoint()));
+            self.stop_reasons.insert(session.pid, "entry");
+        }
+        let _ = session.hub.send(session.pid, DebugCommand::Continue);
+        Ok(())
+    }
+
+    fn entry_breakpoint(&self) -> Breakpoint {
+        Breakpoint::function(self.entry.clone())
+    }
+
+    /// Drop the breakpoint `start` set for `stopOnEntry`, unless the user set it too.
+    fn remove_entry_breakpoint(&self, pid: u64) {
+        let bp = self.entry_breakpoint();
+        if let Some(session) = &self.session {
+            if !self.function_breakpoints.contains(&bp) {
+                let _ = session.hub.send(pid, DebugCommand::RemoveBreakpoint(bp));
+            }
+        }
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Value {
+        let path = args["source"]["path"].as_str().unwrap_or_default();
+        let path = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+        let file = path.to_string_lossy().to_string();
+        let lines: Vec<u64> = args["breakpoints"].as_array().into_iter().flatten()
+            .filter_map(|bp| bp["line"].as_u64())
+            .collect();
+        let new: Vec<Breakpoint> = lines.iter()
+            .map(|&line| Breakpoint::with_file(file.clone(), line as usize))
+            .collect();
+        let old = self.line_breakpoints.insert(file, new.clone()).unwrap_or_default();
+        self.update_breakpoints(&old, &new);
+
+        let breakpoints: Vec<Value> = lines.iter()
+            .map(|line| json!({ "verified": true, "line": line }))
+            .collect();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn set_function_breakpoints(&mut self, args: &Value) -> Value {
+        let new: Vec<Breakpoint> = args["breakpoints"].as_array().into_iter().flatten()
+            .filter_map(|bp| bp["name"].as_str())
+            .map(|name| Breakpoint::function(name.to_string()))
+            .collect();
+        let old = std::mem::replace(&mut self.function_breakpoints, new.clone());
+        self.update_breakpoints(&old, &new);
+
+        let breakpoints: Vec<Value> = new.iter().map(|_| json!({ "verified": true })).collect();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn update_breakpoints(&self, old: &[Breakpoint], new: &[Breakpoint]) {
+        let Some(session) = &self.session else { return };
+        for bp in old.iter().filter(|bp| !new.contains(bp)) {
+            session.hub.remove_breakpoint(bp.clone());
+        }
+        for bp in new.iter().filter(|bp| !old.contains(bp)) {
+            session.hub.add_breakpoint(bp.clone());
+        }
+    }
+
+    fn threads(&self) -> Value {
+        let threads: Vec<Value> = self.threads.iter()
+            .filter(|(pid, _)| self.session.as_ref().is_some_and(|s| s.hub.pids().contains(pid)))
+            .map(|(pid, name)| json!({ "id": pid, "name": name }))
+            .collect();
+        json!({ "threads": threads })
+    }
+
+    /// Resume a paused thread with a continue or step command.
+    fn resume(&mut self, args: &Value, command: DebugCommand, reason: Option<&'static str>) -> Result<(), String> {
+        let pid = thread_id(args)?;
+        let session = self.session.as_ref().ok_or("Program is not running")?;
+        session.hub.send(pid, command)?;
+        self.paused.remove(&pid);
+        self.frames.retain(|_, (frame_pid, _)| *frame_pid != pid);
+        match reason {
+            Some(reason) => self.stop_reasons.insert(pid, reason),
+            None => self.stop_reasons.remove(&pid),
+        };
+        Ok(())
+    }
+
+    fn pause(&mut self, args: &Value) -> Result<(), String> {
+        let pid = thread_id(args)?;
+        if self.paused.contains(&pid) {
+            return Ok(());
+        }
+        let session = self.session.as_ref().ok_or("Program is not running")?;
+        session.hub.send(pid, DebugCommand::Pause)?;
+        self.stop_reasons.insert(pid, "pause");
+        Ok(())
+    }
+
+    fn stack_trace(&mut self, args: &Value) -> Result<Value, String> {
+        let pid = thread_id(args)?;
+        if !self.paused.contains(&pid) {
+            return Ok(json!({ "stackFrames": [], "totalFrames": 0 }));
+        }
+        let frames = match self.query(pid, DebugCommand::PrintStack, |e| matches!(e, DebugEvent::Stack { .. }))? {
+            DebugEvent::Stack { frames } => frames,
+            _ => unreachable!(),
+        };
+
+        let mut stack_frames = Vec::new();
+        for (index, frame) in frames.iter().enumerate() {
+            // The wrapper that calls the entry function
+            if frame.function.starts_with("__repl_eval_") {
+                continue;
+            }
+            let id = self.next_frame_id;
+            self.next_frame_id += 1;
+            self.frames.insert(id, (pid, index));
+            let mut stack_frame = json!({
+                "id": id,
+                "name": display_name(&frame.function),
+                "line": frame.line,
+                "column": 1,
+            });
+            if let Some(file) = &frame.file {
+                let name = Path::new(file).file_name().map(|n| n.to_string_lossy().to_string());
+                stack_frame["source"] = json!({ "name": name, "path": file });
+            }
+            stack_frames.push(stack_frame);
+        }
+        Ok(json!({ "totalFrames": stack_frames.len(), "stackFrames": stack_frames }))
+    }
+
+    fn variables(&mut self, args: &Value) -> Result<Value, String> {
+        let reference = args["variablesReference"].as_i64().unwrap_or_default();
+        let &(pid, index) = self.frames.get(&reference).ok_or("Unknown variables reference")?;
+        let variables = match self.query(pid, DebugCommand::PrintLocalsForFrame(index),
+            |e| matches!(e, DebugEvent::LocalsForFrame { frame_index, .. } if *frame_index == index))?
+        {
+            DebugEvent::LocalsForFrame { variables, .. } => variables,
+            _ => unreachable!(),
+        };
+        let variables: Vec<Value> = variables.into_iter()
+            .map(|(name, value, _)| json!({ "name": name, "value": value, "variablesReference": 0 }))
+            .collect();
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Send a query to a paused process and wait for the event answering it.
+    fn query(&mut self, pid: u64, command: DebugCommand, answers: impl Fn(&DebugEvent) -> bool) -> Result<DebugEvent, String> {
+        let session = self.session.as_ref().ok_or("Program is not running")?;
+        session.hub.send(pid, command)?;
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match session.event_receiver.recv_timeout(timeout) {
+                Ok(event) if answers(&event) => return Ok(event),
+                Ok(event) => self.pending_events.push_back(event),
+                Err(_) => return Err(format!("Process {} did not respond", pid)),
+            }
+        }
+    }
+}
+
+fn thread_id(args: &Value) -> Result<u64, String> {
+    args["threadId"].as_u64().ok_or_else(|| "Missing 'threadId' argument".to_string())
+}
+
+/// Function name without its signature suffix (`main.add/Int,Int` -> `main.add`).
+fn display_name(function: &str) -> &str {
+    function.split('/').next().unwrap_or(function)
+}
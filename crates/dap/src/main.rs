@@ -0,0 +1,58 @@
+//! `nostos-dap`: a Debug Adapter Protocol server for Nostos programs.
+//!
+//! Speaks DAP over stdin/stdout. The program's output is forwarded as
+//! `output` events since stdout carries the protocol.
+
+use std::io::{self, BufReader};
+use std::time::Duration;
+
+mod adapter;
+mod protocol;
+
+use adapter::Adapter;
+
+/// How often the program's output is forwarded.
+const OUTPUT_INTERVAL: Duration = Duration::from_millis(50);
+
+fn main() {
+    let (request_sender, requests) = crossbeam::channel::unbounded();
+    std::thread::spawn(move || {
+        let mut stdin = BufReader::new(io::stdin());
+        while let Ok(Some(message)) = protocol::read_message(&mut stdin) {
+            if request_sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut adapter = Adapter::new(io::stdout());
+    let ticker = crossbeam::channel::tick(OUTPUT_INTERVAL);
+    if let Err(e) = run(&mut adapter, &requests, &ticker) {
+        eprintln!("nostos-dap: {}", e);
+    }
+}
+
+fn run(
+    adapter: &mut Adapter<io::Stdout>,
+    requests: &crossbeam::channel::Receiver<serde_json::Value>,
+    ticker: &crossbeam::channel::Receiver<std::time::Instant>,
+) -> io::Result<()> {
+    while !adapter.is_done() {
+        adapter.handle_pending_events()?;
+        let events = adapter.events();
+        crossbeam::channel::select! {
+            recv(requests) -> request => match request {
+                Ok(request) => adapter.handle_request(request)?,
+                // The client went away
+                Err(_) => break,
+            },
+            recv(events) -> event => {
+                if let Ok(event) = event {
+                    adapter.handle_event(event)?;
+                }
+            }
+            recv(ticker) -> _ => adapter.flush_output()?,
+        }
+    }
+    Ok(())
+}
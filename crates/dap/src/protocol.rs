@@ -0,0 +1,115 @@
+//! Debug Adapter Protocol framing: JSON messages with a `Content-Length` header.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+/// Read one message. Returns `None` at end of input.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write one message.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Numbers outgoing messages.
+pub struct Outbox<W: Write> {
+    writer: W,
+    seq: i64,
+}
+
+impl<W: Write> Outbox<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, seq: 0 }
+    }
+
+    /// Respond to `request`, successfully with `body` or with an error message.
+    pub fn respond(&mut self, request: &Value, result: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(Value::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = Value::String(message),
+        }
+        self.send(response)
+    }
+
+    pub fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = Value::from(self.seq);
+        write_message(&mut self.writer, &message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, &json!({"command": "threads"})).unwrap();
+        write_message(&mut buf, &json!({"command": "next", "arguments": {"threadId": 2}})).unwrap();
+
+        let mut reader = io::Cursor::new(buf);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!({"command": "threads"})));
+        let next = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(next["arguments"]["threadId"], 2);
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_outbox_numbers_messages() {
+        let mut outbox = Outbox::new(Vec::new());
+        let request = json!({"seq": 7, "type": "request", "command": "pause"});
+        outbox.respond(&request, Err("no such thread".into())).unwrap();
+        outbox.event("stopped", json!({"reason": "pause"})).unwrap();
+
+        let mut reader = io::Cursor::new(outbox.writer);
+        let response = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(response["seq"], 1);
+        assert_eq!(response["request_seq"], 7);
+        assert_eq!(response["success"], false);
+        assert_eq!(response["message"], "no such thread");
+        let event = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(event["seq"], 2);
+        assert_eq!(event["event"], "stopped");
+    }
+}
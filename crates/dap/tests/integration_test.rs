@@ -0,0 +1,233 @@
+//! Integration tests for the Nostos debug adapter.
+//! These tests run the `nostos-dap` binary and talk to it over the Debug Adapter Protocol.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+struct DapClient {
+    process: Child,
+    stdin: ChildStdin,
+    messages: mpsc::Receiver<Value>,
+    /// Events received while waiting for responses
+    events: Vec<Value>,
+    seq: i64,
+}
+
+impl DapClient {
+    fn new() -> Self {
+        let mut process = Command::new(env!("CARGO_BIN_EXE_nostos-dap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start nostos-dap");
+        let stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Some(message) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        DapClient { process, stdin, messages, events: Vec::new(), seq: 0 }
+    }
+
+    fn next_message(&mut self) -> Value {
+        self.messages.recv_timeout(TIMEOUT).expect("Timed out waiting for the debug adapter")
+    }
+
+    /// Send a request and return its response, buffering events.
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let body = json!({ "seq": self.seq, "type": "request", "command": command, "arguments": arguments }).to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            let message = self.next_message();
+            if message["type"] == "response" && message["request_seq"] == self.seq {
+                assert_eq!(message["success"], true, "{} failed: {}", command, message);
+                return message["body"].clone();
+            }
+            self.events.push(message);
+        }
+        panic!("No response to {}", command);
+    }
+
+    /// Wait for an event, returning its body.
+    fn wait_event(&mut self, event: &str) -> Value {
+        if let Some(index) = self.events.iter().position(|m| m["event"] == event) {
+            return self.events.remove(index)["body"].clone();
+        }
+        loop {
+            let message = self.next_message();
+            if message["event"] == event {
+                return message["body"].clone();
+            }
+            self.events.push(message);
+        }
+    }
+
+    /// All output received so far.
+    fn output(&self) -> String {
+        self.events.iter()
+            .filter(|m| m["event"] == "output")
+            .filter_map(|m| m["body"]["output"].as_str())
+            .collect()
+    }
+
+    fn launch(&mut self, program: &std::path::Path, breakpoint_lines: &[u64]) {
+        let capabilities = self.request("initialize", json!({ "adapterID": "nostos" }));
+        assert_eq!(capabilities["supportsConfigurationDoneRequest"], true);
+        self.request("launch", json!({ "program": program }));
+        self.wait_event("initialized");
+        let breakpoints: Vec<Value> = breakpoint_lines.iter().map(|line| json!({ "line": line })).collect();
+        let set = self.request("setBreakpoints", json!({
+            "source": { "path": program },
+            "breakpoints": breakpoints,
+        }));
+        assert_eq!(set["breakpoints"].as_array().unwrap().len(), breakpoint_lines.len());
+        self.request("configurationDone", json!({}));
+    }
+}
+
+impl Drop for DapClient {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+    }
+}
+
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok()?;
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+#[test]
+fn test_dap_breakpoint_stack_and_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("demo.nos");
+    std::fs::write(&program, r#"add(a, b) = {
+    sum = a + b
+    sum
+}
+
+main() = {
+    x = 10
+    y = add(x, 5)
+    println(y)
+    y
+}
+"#).unwrap();
+
+    let mut client = DapClient::new();
+    client.launch(&program, &[2]);
+
+    let stopped = client.wait_event("stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    let thread_id = stopped["threadId"].clone();
+
+    let threads = client.request("threads", json!({}));
+    assert_eq!(threads["threads"][0]["id"], thread_id);
+    assert_eq!(threads["threads"][0]["name"], "main");
+
+    let stack = client.request("stackTrace", json!({ "threadId": thread_id }));
+    let frames = stack["stackFrames"].as_array().unwrap();
+    let names: Vec<&str> = frames.iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["demo.add", "demo.main"]);
+    assert_eq!(frames[0]["line"], 2);
+    assert_eq!(frames[1]["line"], 8);
+    assert!(frames[0]["source"]["path"].as_str().unwrap().ends_with("demo.nos"));
+
+    let scopes = client.request("scopes", json!({ "frameId": frames[0]["id"] }));
+    let reference = scopes["scopes"][0]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": reference }));
+    let a = variables["variables"].as_array().unwrap().iter()
+        .find(|v| v["name"] == "a")
+        .expect("a is a local of add");
+    assert!(a["value"].as_str().unwrap().contains("10"), "a = {}", a["value"]);
+
+    // Step out back into main
+    client.request("stepOut", json!({ "threadId": thread_id }));
+    let stopped = client.wait_event("stopped");
+    assert_eq!(stopped["reason"], "step");
+    let stack = client.request("stackTrace", json!({ "threadId": thread_id }));
+    assert_eq!(stack["stackFrames"][0]["name"], "demo.main");
+
+    client.request("continue", json!({ "threadId": thread_id }));
+    let exited = client.wait_event("exited");
+    assert_eq!(exited["exitCode"], 0);
+    client.wait_event("terminated");
+    assert!(client.output().contains("15\n"), "output: {:?}", client.output());
+    client.request("disconnect", json!({}));
+}
+
+#[test]
+fn test_dap_spawned_processes_are_threads() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("procs.nos");
+    std::fs::write(&program, r#"worker(parent) = {
+    n = 21 * 2
+    parent <- n
+}
+
+main() = {
+    me = self()
+    spawn { worker(me) }
+    receive {
+        n -> println(n)
+    }
+    0
+}
+"#).unwrap();
+
+    let mut client = DapClient::new();
+    client.launch(&program, &[2]);
+
+    let started = client.wait_event("thread");
+    assert_eq!(started["reason"], "started");
+    let worker = started["threadId"].clone();
+
+    let stopped = client.wait_event("stopped");
+    assert_eq!(stopped["threadId"], worker);
+    let threads = client.request("threads", json!({}));
+    let ids: Vec<&Value> = threads["threads"].as_array().unwrap().iter().map(|t| &t["id"]).collect();
+    assert_eq!(ids.len(), 2, "threads: {}", threads);
+    assert!(ids.contains(&&worker));
+
+    let stack = client.request("stackTrace", json!({ "threadId": worker }));
+    assert_eq!(stack["stackFrames"][0]["line"], 2);
+
+    client.request("continue", json!({ "threadId": worker }));
+    let exited = client.wait_event("thread");
+    assert_eq!(exited, json!({ "reason": "exited", "threadId": worker }));
+    client.wait_event("terminated");
+    assert!(client.output().contains("42\n"), "output: {:?}", client.output());
+    client.request("disconnect", json!({}));
+}
@@ -0,0 +1,482 @@
+//! Baseline tier: compiles any function to native code.
+//!
+//! Unlike the specialized compilers in this crate, the baseline tier makes no
+//! assumptions about value types. Values stay in the interpreter's register
+//! file. Jumps, branches, moves and Int64 arithmetic are compiled inline
+//! (calling small runtime helpers from `nostos_vm::jit_runtime`); everything
+//! else - records, variants, closures, calls - runs in the interpreter through
+//! `nos_jit_interpret` up to the next inline instruction. What goes away is
+//! the dispatch overhead of the interpreter loop around control flow.
+//!
+//! Instructions that may suspend the process (receive, sleep, I/O) exit back
+//! to the async interpreter loop, which re-enters the native code afterwards.
+//! Loop back-edges charge reductions so scheduling stays fair and interrupts
+//! are still noticed. Since the code can be entered at any ip, a frame that
+//! is already looping in the interpreter moves into it at its next loop head
+//! (on-stack replacement).
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::types::{I32, I64};
+use cranelift_codegen::ir::{AbiParam, Block, FuncRef, InstBuilder, JumpTableData, Signature, Value as CraneliftValue};
+use cranelift_codegen::Context;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::JITModule;
+use cranelift_module::{FuncId, Linkage, Module};
+
+use nostos_vm::jit_runtime::{self, JIT_EXIT_RESUME, JIT_OP_ADD, JIT_OP_EQ, JIT_OP_GE, JIT_OP_GT, JIT_OP_LE, JIT_OP_LT, JIT_OP_MUL, JIT_OP_SUB};
+use nostos_vm::value::{FunctionValue, Instruction};
+
+use crate::{jit_builder, JitConfig, JitError};
+
+/// Runtime helpers, declared once in the module.
+struct Helpers {
+    interpret: FuncId,
+    tick: FuncId,
+    move_: FuncId,
+    load_simple: FuncId,
+    test_bool: FuncId,
+    int_op: FuncId,
+}
+
+/// Helper references inside the function being compiled.
+struct HelperRefs {
+    interpret: FuncRef,
+    tick: FuncRef,
+    move_: FuncRef,
+    load_simple: FuncRef,
+    test_bool: FuncRef,
+    int_op: FuncRef,
+}
+
+/// The baseline compiler.
+pub struct BaselineCompiler {
+    /// Cranelift JIT module (owns the generated code)
+    module: JITModule,
+    /// Cranelift codegen context (reusable)
+    ctx: Context,
+    /// Function builder context (reusable)
+    builder_ctx: FunctionBuilderContext,
+    helpers: Helpers,
+    /// Number of functions compiled so far
+    compiled: usize,
+}
+
+impl BaselineCompiler {
+    /// Create a new baseline compiler
+    pub fn new(config: &JitConfig) -> Result<Self, JitError> {
+        let mut builder = jit_builder(config)?;
+        for (name, ptr) in jit_runtime::helper_symbols() {
+            builder.symbol(name, ptr);
+        }
+        let mut module = JITModule::new(builder);
+
+        let mut declare = |name: &str, params: usize, returns: bool| -> Result<FuncId, JitError> {
+            let mut sig = module.make_signature();
+            for _ in 0..params {
+                sig.params.push(AbiParam::new(I64));
+            }
+            if returns {
+                sig.returns.push(AbiParam::new(I64));
+            }
+            module
+                .declare_function(name, Linkage::Import, &sig)
+                .map_err(|e| JitError::Module(e.to_string()))
+        };
+        let helpers = Helpers {
+            interpret: declare("nos_jit_interpret", 2, true)?,
+            tick: declare("nos_jit_tick", 3, true)?,
+            move_: declare("nos_jit_move", 3, false)?,
+            load_simple: declare("nos_jit_load_simple", 3, false)?,
+            test_bool: declare("nos_jit_test_bool", 2, true)?,
+            int_op: declare("nos_jit_int_op", 6, true)?,
+        };
+
+        Ok(Self {
+            module,
+            ctx: Context::new(),
+            builder_ctx: FunctionBuilderContext::new(),
+            helpers,
+            compiled: 0,
+        })
+    }
+
+    /// Number of functions compiled so far
+    pub fn compiled_count(&self) -> usize {
+        self.compiled
+    }
+
+    /// Compile a function and install its native code in `func.jit_entry`.
+    pub fn compile(&mut self, func: &FunctionValue) -> Result<(), JitError> {
+        let code = &func.code.code;
+        if code.is_empty() {
+            return Err(JitError::NotSuitable("empty function".to_string()));
+        }
+        if code.len() > i32::MAX as usize {
+            return Err(JitError::NotSuitable("function too large".to_string()));
+        }
+
+        // (process, entry_ip) -> exit code
+        let mut sig: Signature = self.module.make_signature();
+        sig.params.push(AbiParam::new(I64));
+        sig.params.push(AbiParam::new(I64));
+        sig.returns.push(AbiParam::new(I64));
+        let func_id = self.module
+            .declare_anonymous_function(&sig)
+            .map_err(|e| JitError::Module(e.to_string()))?;
+
+        self.ctx.func.signature = sig;
+        if let Err(e) = self.build(code) {
+            self.module.clear_context(&mut self.ctx);
+            return Err(e);
+        }
+
+        self.module
+            .define_function(func_id, &mut self.ctx)
+            .map_err(|e| {
+                self.module.clear_context(&mut self.ctx);
+                JitError::Module(format!("define_function error: {}", e))
+            })?;
+        self.module.clear_context(&mut self.ctx);
+        self.module.finalize_definitions()
+            .map_err(|e| JitError::Module(e.to_string()))?;
+
+        let code_ptr = self.module.get_finalized_function(func_id);
+        // SAFETY: generated with the JitEntryFn signature; the module is never
+        // freed, so the code stays valid
+        unsafe { func.jit_entry.set(code_ptr) };
+        self.compiled += 1;
+        Ok(())
+    }
+
+    /// Generate the function body: one block per instruction, entered
+    /// through a jump table on `entry_ip`.
+    fn build(&mut self, code: &[Instruction]) -> Result<(), JitError> {
+        let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_ctx);
+        let helpers = HelperRefs {
+            interpret: self.module.declare_func_in_func(self.helpers.interpret, builder.func),
+            tick: self.module.declare_func_in_func(self.helpers.tick, builder.func),
+            move_: self.module.declare_func_in_func(self.helpers.move_, builder.func),
+            load_simple: self.module.declare_func_in_func(self.helpers.load_simple, builder.func),
+            test_bool: self.module.declare_func_in_func(self.helpers.test_bool, builder.func),
+            int_op: self.module.declare_func_in_func(self.helpers.int_op, builder.func),
+        };
+
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        let blocks: Vec<Block> = code.iter().map(|_| builder.create_block()).collect();
+        // Returns its argument as the exit code
+        let exit = builder.create_block();
+        builder.append_block_param(exit, I64);
+
+        // Jumps to the block of an ip computed at run time
+        let dispatch = builder.create_block();
+        builder.append_block_param(dispatch, I64);
+
+        builder.switch_to_block(entry);
+        let process = builder.block_params(entry)[0];
+        let entry_ip = builder.block_params(entry)[1];
+        builder.ins().jump(dispatch, &[entry_ip]);
+
+        builder.switch_to_block(dispatch);
+        let ip = builder.block_params(dispatch)[0];
+        let table: Vec<_> = blocks.iter().map(|b| builder.func.dfg.block_call(*b, &[])).collect();
+        // Out-of-range ips go to the interpreter, which reports them
+        let default = builder.func.dfg.block_call(exit, &[ip]);
+        let jump_table = builder.create_jump_table(JumpTableData::new(default, &table));
+        let index = builder.ins().ireduce(I32, ip);
+        builder.ins().br_table(index, jump_table);
+
+        let end = builder.create_block();
+
+        let mut emitter = Emitter { builder, helpers, process, blocks, dispatch, exit, end };
+        for (ip, instruction) in code.iter().enumerate() {
+            emitter.instruction(ip, instruction);
+        }
+
+        // Let the interpreter report running off the end
+        emitter.builder.switch_to_block(end);
+        emitter.exit_with(code.len() as i64);
+
+        emitter.builder.switch_to_block(exit);
+        let code = emitter.builder.block_params(exit)[0];
+        emitter.builder.ins().return_(&[code]);
+
+        emitter.builder.seal_all_blocks();
+        emitter.builder.finalize();
+        Ok(())
+    }
+}
+
+/// Emits the blocks of one function.
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    helpers: HelperRefs,
+    process: CraneliftValue,
+    /// Block of each instruction
+    blocks: Vec<Block>,
+    dispatch: Block,
+    exit: Block,
+    /// Reached by falling off the end of the code
+    end: Block,
+}
+
+impl Emitter<'_> {
+    fn instruction(&mut self, ip: usize, instruction: &Instruction) {
+        self.builder.switch_to_block(self.blocks[ip]);
+        match instruction {
+            // Leave suspension points to the async interpreter loop
+            i if i.may_suspend() => self.exit_with(ip as i64),
+
+            Instruction::Nop => self.fall_through(ip),
+            Instruction::Jump(offset) => {
+                let target = Self::target(ip, *offset as isize);
+                self.branch(ip, target);
+            }
+            Instruction::JumpIfTrue(reg, offset) | Instruction::JumpIfFalse(reg, offset) => {
+                let expected = matches!(instruction, Instruction::JumpIfTrue(..)) as i64;
+                let reg = self.iconst(*reg as i64);
+                let call = self.builder.ins().call(self.helpers.test_bool, &[self.process, reg]);
+                let test = self.builder.inst_results(call)[0];
+                let taken = self.builder.ins().icmp_imm(IntCC::Equal, test, expected);
+
+                let taken_block = self.builder.create_block();
+                let next = self.next(ip);
+                self.builder.ins().brif(taken, taken_block, &[], next, &[]);
+                self.builder.switch_to_block(taken_block);
+                let target = Self::target(ip, *offset as isize);
+                self.branch(ip, target);
+            }
+
+            Instruction::Move(dst, src) => {
+                let args = [self.process, self.iconst(*dst as i64), self.iconst(*src as i64)];
+                self.builder.ins().call(self.helpers.move_, &args);
+                self.fall_through(ip);
+            }
+            Instruction::LoadUnit(dst) => self.load_simple(ip, *dst, 0),
+            Instruction::LoadTrue(dst) => self.load_simple(ip, *dst, 1),
+            Instruction::LoadFalse(dst) => self.load_simple(ip, *dst, 2),
+
+            Instruction::AddInt(dst, a, b) => self.int_op(ip, JIT_OP_ADD, *dst, *a, *b),
+            Instruction::SubInt(dst, a, b) => self.int_op(ip, JIT_OP_SUB, *dst, *a, *b),
+            Instruction::MulInt(dst, a, b) => self.int_op(ip, JIT_OP_MUL, *dst, *a, *b),
+            Instruction::EqInt(dst, a, b) => self.int_op(ip, JIT_OP_EQ, *dst, *a, *b),
+            Instruction::LtInt(dst, a, b) => self.int_op(ip, JIT_OP_LT, *dst, *a, *b),
+            Instruction::LeInt(dst, a, b) => self.int_op(ip, JIT_OP_LE, *dst, *a, *b),
+            Instruction::GtInt(dst, a, b) => self.int_op(ip, JIT_OP_GT, *dst, *a, *b),
+            Instruction::GeInt(dst, a, b) => self.int_op(ip, JIT_OP_GE, *dst, *a, *b),
+
+            // Everything else (heap operations, calls, returns, exceptions, ...)
+            // runs in the interpreter up to the next inline instruction
+            _ => {
+                debug_assert!(!jit_runtime::compiled_inline(instruction));
+                let args = [self.process, self.iconst(ip as i64)];
+                let call = self.builder.ins().call(self.helpers.interpret, &args);
+                let status = self.builder.inst_results(call)[0];
+                self.continue_at(ip, status);
+            }
+        }
+    }
+
+    /// Jump target of a relative offset (relative to the next instruction).
+    fn target(ip: usize, offset: isize) -> i64 {
+        ip as i64 + 1 + offset as i64
+    }
+
+    fn iconst(&mut self, value: i64) -> CraneliftValue {
+        self.builder.ins().iconst(I64, value)
+    }
+
+    fn exit_with(&mut self, code: i64) {
+        let code = self.iconst(code);
+        self.builder.ins().jump(self.exit, &[code]);
+    }
+
+    /// Block of the instruction following `ip`.
+    fn next(&self, ip: usize) -> Block {
+        self.blocks.get(ip + 1).copied().unwrap_or(self.end)
+    }
+
+    fn fall_through(&mut self, ip: usize) {
+        let block = self.next(ip);
+        self.builder.ins().jump(block, &[]);
+    }
+
+    /// Jump to `target`. Back-edges charge the loop's reductions and return to
+    /// the interpreter loop, with the frame at the loop head, when the
+    /// process must yield.
+    fn branch(&mut self, ip: usize, target: i64) {
+        let Some(&block) = usize::try_from(target).ok().and_then(|t| self.blocks.get(t)) else {
+            self.exit_with(target);
+            return;
+        };
+        if target > ip as i64 {
+            self.builder.ins().jump(block, &[]);
+            return;
+        }
+        let args = [self.process, self.iconst(ip as i64 - target + 1), self.iconst(target)];
+        let call = self.builder.ins().call(self.helpers.tick, &args);
+        let must_yield = self.builder.inst_results(call)[0];
+        let yield_block = self.builder.create_block();
+        self.builder.ins().brif(must_yield, yield_block, &[], block, &[]);
+        self.builder.switch_to_block(yield_block);
+        self.exit_with(JIT_EXIT_RESUME);
+    }
+
+    fn load_simple(&mut self, ip: usize, dst: u8, kind: i64) {
+        let args = [self.process, self.iconst(dst as i64), self.iconst(kind)];
+        self.builder.ins().call(self.helpers.load_simple, &args);
+        self.fall_through(ip);
+    }
+
+    fn int_op(&mut self, ip: usize, op: i64, dst: u8, a: u8, b: u8) {
+        let args = [
+            self.process,
+            self.iconst(ip as i64),
+            self.iconst(op),
+            self.iconst(dst as i64),
+            self.iconst(a as i64),
+            self.iconst(b as i64),
+        ];
+        let call = self.builder.ins().call(self.helpers.int_op, &args);
+        let status = self.builder.inst_results(call)[0];
+        self.continue_at(ip, status);
+    }
+
+    /// Continue at the ip a helper returned (usually the next instruction),
+    /// or exit with its status.
+    fn continue_at(&mut self, ip: usize, status: CraneliftValue) {
+        let next = self.next(ip);
+        let is_next = self.builder.ins().icmp_imm(IntCC::Equal, status, ip as i64 + 1);
+        let elsewhere = self.builder.create_block();
+        self.builder.ins().brif(is_next, next, &[], elsewhere, &[]);
+
+        self.builder.switch_to_block(elsewhere);
+        let is_exit = self.builder.ins().icmp_imm(IntCC::SignedLessThan, status, 0);
+        let (exit, dispatch) = (self.exit, self.dispatch);
+        self.builder.ins().brif(is_exit, exit, &[status], dispatch, &[status]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use nostos_vm::async_vm::{AsyncConfig, AsyncVM};
+    use nostos_vm::cache::JitCache;
+    use nostos_vm::jit_runtime::JitRequest;
+    use nostos_vm::value::{Chunk, Value};
+
+    /// `sum()`: adds 1..=n in a loop (with an optional sleep in the loop
+    /// body) and returns `(total, counter)` as a tuple.
+    fn make_sum_function(n: i64, sleep: bool) -> FunctionValue {
+        let mut chunk = Chunk::new();
+        let zero = chunk.add_constant(Value::Int64(0));
+        let one = chunk.add_constant(Value::Int64(1));
+        let n = chunk.add_constant(Value::Int64(n));
+        chunk.code = vec![
+            Instruction::LoadConst(0, n),
+            Instruction::LoadConst(1, zero),
+            Instruction::LoadConst(2, one),
+            Instruction::LoadConst(3, zero),
+            Instruction::GtInt(4, 0, 3),                    // 4: loop head
+            Instruction::JumpIfFalse(4, 4),                 // -> 10
+            Instruction::AddInt(1, 1, 0),
+            Instruction::SubInt(0, 0, 2),
+            if sleep { Instruction::Sleep(3) } else { Instruction::Nop },
+            Instruction::Jump(-6),                          // -> 4
+            Instruction::MakeTuple(5, vec![1, 0].into()),   // 10
+            Instruction::Return(5),
+        ];
+        chunk.register_count = 6;
+        FunctionValue::new_simple("sum".to_string(), 0, vec![], Arc::new(chunk))
+    }
+
+    fn run(func: FunctionValue, jit: bool) -> String {
+        let func = Arc::new(func);
+        let mut vm = AsyncVM::new(AsyncConfig::default());
+        if jit {
+            let mut compiler = BaselineCompiler::new(&JitConfig::default()).unwrap();
+            compiler.compile(&func).expect("baseline compilation failed");
+            assert!(func.jit_entry.is_set());
+            assert_eq!(compiler.compiled_count(), 1);
+            std::mem::forget(compiler);
+            let (sender, _receiver) = std::sync::mpsc::channel();
+            vm.enable_tiered_jit(sender, 1);
+        }
+        vm.register_function("sum", func);
+        format!("{:?}", vm.run("sum").expect("run failed"))
+    }
+
+    #[test]
+    fn test_baseline_loop_matches_interpreter() {
+        let expected = run(make_sum_function(5000, false), false);
+        assert!(expected.contains("12502500"), "{}", expected);
+        assert_eq!(run(make_sum_function(5000, false), true), expected);
+    }
+
+    #[test]
+    fn test_baseline_exits_at_suspension_points() {
+        let expected = run(make_sum_function(20, true), false);
+        assert!(expected.contains("210"), "{}", expected);
+        assert_eq!(run(make_sum_function(20, true), true), expected);
+    }
+
+    #[test]
+    fn test_loops_queue_functions_called_once() {
+        let func = Arc::new(make_sum_function(500, false));
+        let mut vm = AsyncVM::new(AsyncConfig::default());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        // Back-edges queue for the baseline tier after 20, the optimizing tier after 200
+        vm.enable_tiered_jit(sender, 2);
+        vm.register_function("sum", func.clone());
+        vm.run("sum").expect("run failed");
+        let requests: Vec<_> = receiver.try_iter().map(|r| (r.function.name.clone(), r.optimize)).collect();
+        assert_eq!(requests, vec![("sum".to_string(), false), ("sum".to_string(), true)]);
+    }
+
+    #[test]
+    fn test_spawn_jit_compiler_installs_code() {
+        let sender = crate::spawn_jit_compiler(JitConfig::default()).unwrap();
+        let func = Arc::new(make_sum_function(10, false));
+        sender.send(JitRequest { function: func.clone(), callees: Default::default(), optimize: false }).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !func.jit_entry.is_set() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(func.jit_entry.is_set());
+    }
+
+    #[test]
+    fn test_jit_cache_warm_starts_next_run() {
+        // First run: `sum` gets hot and is compiled by the baseline tier
+        let func = Arc::new(make_sum_function(500, false));
+        let mut vm = AsyncVM::new(AsyncConfig::default());
+        vm.enable_tiered_jit(crate::spawn_jit_compiler(JitConfig::default()).unwrap(), 2);
+        vm.register_function("sum", func.clone());
+        vm.set_function_list(vec![func.clone()]);
+        vm.run("sum").expect("run failed");
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !func.jit_entry.is_set() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let mut cache = JitCache::new("test");
+        assert!(vm.record_jit_decisions(&mut cache));
+        assert!(!vm.record_jit_decisions(&mut cache));
+
+        // Next run: `sum` is queued before it is called; a changed `sum` is not
+        let path = std::env::temp_dir().join(format!("nostos-jit-cache-{}.bin", std::process::id()));
+        cache.save(&path).unwrap();
+        let cache = JitCache::load(&path, "test");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(cache.functions.len(), 1);
+        for (n, expected) in [(500, vec![("sum".to_string(), false)]), (501, vec![])] {
+            let mut vm = AsyncVM::new(AsyncConfig::default());
+            let (sender, receiver) = std::sync::mpsc::channel();
+            vm.enable_tiered_jit(sender, 1000);
+            vm.set_function_list(vec![Arc::new(make_sum_function(n, false))]);
+            assert_eq!(vm.jit_warm_start(&cache), expected.len());
+            let requests: Vec<_> = receiver.try_iter().map(|r| (r.function.name.clone(), r.optimize)).collect();
+            assert_eq!(requests, expected);
+        }
+    }
+}
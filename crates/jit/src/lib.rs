@@ -18,16 +18,25 @@
 //! Tiered compilation strategy:
 //! - Tier 0: Interpreter (existing Runtime)
 //! - Tier 1: Baseline JIT (this crate) - eliminate dispatch overhead
-//! - Tier 2: Optimizing JIT (future) - type specialization, inlining
+//! - Tier 2: Optimizing JIT (`optimizing`) - type specialization, inlining
 //!
 //! Uses Cranelift as the code generation backend.
 //!
-//! Current implementation: Specializes for pure numeric functions.
+//! `JitCompiler` specializes pure numeric, array, list-sum, tuple and
+//! string-match functions ahead of time.
 //! Supported types: Int8, Int16, Int32, Int64, UInt8, UInt16, UInt32, UInt64, Float32, Float64
+//!
+//! Everything else is compiled by the `baseline` tier once it gets hot, and
+//! by the `optimizing` tier once its argument types are known (see
+//! `spawn_jit_compiler`).
+
+pub mod baseline;
+pub mod optimizing;
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
 
 use cranelift_codegen::ir::{AbiParam, Block, FuncRef, InstBuilder, UserFuncName, Value as CraneliftValue};
 use cranelift_codegen::ir::condcodes::{IntCC, FloatCC};
@@ -40,6 +49,8 @@ use cranelift_module::{FuncId, Linkage, Module};
 
 #[allow(unused_imports)]
 use nostos_vm::value::{ConstIdx, FunctionValue, Instruction, RegList, Value};
+use nostos_vm::ffi::{ExternFn, FfiType};
+use nostos_vm::jit_runtime::JitRequest;
 
 /// Array element types supported by JIT
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -61,6 +72,23 @@ impl ArrayElementType {
     }
 }
 
+/// Resolve the C function behind a `CallExtern` and the numeric type shared by
+/// all of its parameters and its result.
+fn extern_call_target(func: &FunctionValue, idx: ConstIdx) -> Result<(NumericType, Arc<ExternFn>), JitError> {
+    let descriptor = match func.code.constants.get(idx as usize) {
+        Some(Value::String(s)) => s,
+        _ => return Err(JitError::NotSuitable("CallExtern without descriptor constant".to_string())),
+    };
+    // Unresolvable externs stay in the interpreter, which reports the error on call
+    let extern_fn = nostos_vm::ffi::resolve(descriptor)
+        .map_err(|e| JitError::NotSuitable(format!("extern not resolvable: {}", e)))?;
+    let sig = &extern_fn.signature;
+    let ty = NumericType::from_ffi(sig.ret)
+        .filter(|t| sig.params.iter().all(|p| NumericType::from_ffi(*p) == Some(*t)))
+        .ok_or_else(|| JitError::NotSuitable(format!("extern `{}` has non-uniform numeric signature", sig.symbol)))?;
+    Ok((ty, extern_fn))
+}
+
 /// Numeric types supported by the JIT
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum NumericType {
@@ -94,6 +122,18 @@ impl NumericType {
         matches!(self, NumericType::Float32 | NumericType::Float64)
     }
 
+    /// Numeric type for a C extern parameter/return, if it can be passed
+    /// without ABI extension (narrow integers are left to the interpreter).
+    fn from_ffi(ty: FfiType) -> Option<NumericType> {
+        match ty {
+            FfiType::Int64 => Some(NumericType::Int64),
+            FfiType::UInt64 => Some(NumericType::UInt64),
+            FfiType::Float32 => Some(NumericType::Float32),
+            FfiType::Float64 => Some(NumericType::Float64),
+            _ => None,
+        }
+    }
+
     /// Check if this type is unsigned
     fn is_unsigned(&self) -> bool {
         matches!(self, NumericType::UInt8 | NumericType::UInt16 | NumericType::UInt32 | NumericType::UInt64)
@@ -259,6 +299,74 @@ pub unsafe extern "C" fn nos_str_eq(a_ptr: *const u8, a_len: i64, b_ptr: *const
     }
 }
 
+/// Cranelift JIT builder for the native target.
+fn jit_builder(config: &JitConfig) -> Result<JITBuilder, JitError> {
+    // Set up Cranelift with native target
+    let mut flag_builder = settings::builder();
+    flag_builder.set("opt_level", match config.opt_level {
+        0 => "none",
+        1 => "speed",
+        _ => "speed",  // Use pure speed optimization, not speed_and_size
+    }).map_err(|e| JitError::Cranelift(e.to_string()))?;
+
+    // Enable frame pointers (required for tail calls)
+    flag_builder.set("preserve_frame_pointers", "true")
+        .map_err(|e| JitError::Cranelift(e.to_string()))?;
+
+    let isa_builder = cranelift_native::builder()
+        .map_err(|e| JitError::Cranelift(e.to_string()))?;
+
+    let isa = isa_builder
+        .finish(settings::Flags::new(flag_builder))
+        .map_err(|e| JitError::Cranelift(e.to_string()))?;
+
+    Ok(JITBuilder::with_isa(isa, cranelift_module::default_libcall_names()))
+}
+
+/// Start a background thread compiling the functions sent to the returned
+/// queue (see `AsyncVM::enable_tiered_jit`) with the baseline or the
+/// optimizing tier. The thread ends when the queue is dropped; generated code
+/// is never freed.
+pub fn spawn_jit_compiler(config: JitConfig) -> Result<Sender<JitRequest>, JitError> {
+    let (sender, receiver) = mpsc::channel::<JitRequest>();
+    let (ready_sender, ready) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("nostos-jit".to_string())
+        .spawn(move || {
+            let compilers = baseline::BaselineCompiler::new(&config)
+                .and_then(|b| Ok((b, optimizing::OptimizingCompiler::new(&config)?)));
+            let (mut baseline, mut optimizing) = match compilers {
+                Ok(compilers) => {
+                    let _ = ready_sender.send(Ok(()));
+                    compilers
+                }
+                Err(e) => {
+                    let _ = ready_sender.send(Err(e));
+                    return;
+                }
+            };
+            // Functions that fail to compile stay with the lower tier
+            for request in receiver {
+                let func = &request.function;
+                if !request.optimize {
+                    if !func.jit_entry.is_set() {
+                        let _ = baseline.compile(func);
+                    }
+                } else if !func.opt_entry.is_set()
+                    && func.type_profile.deopts.load(Ordering::Relaxed) < nostos_vm::jit_runtime::MAX_DEOPTS
+                {
+                    let _ = optimizing.compile(func, &request.callees);
+                }
+            }
+            // Keep the generated code alive for functions that outlive the queue
+            std::mem::forget(baseline);
+            std::mem::forget(optimizing);
+        })
+        .map_err(|e| JitError::Module(e.to_string()))?;
+    ready.recv().map_err(|e| JitError::Module(e.to_string()))??;
+    Ok(sender)
+}
+
 /// The JIT compiler
 pub struct JitCompiler {
     /// Cranelift JIT module
@@ -301,27 +409,8 @@ pub struct JitCompiler {
 impl JitCompiler {
     /// Create a new JIT compiler
     pub fn new(config: JitConfig) -> Result<Self, JitError> {
-        // Set up Cranelift with native target
-        let mut flag_builder = settings::builder();
-        flag_builder.set("opt_level", match config.opt_level {
-            0 => "none",
-            1 => "speed",
-            _ => "speed",  // Use pure speed optimization, not speed_and_size
-        }).map_err(|e| JitError::Cranelift(e.to_string()))?;
-
-        // Enable frame pointers (required for tail calls)
-        flag_builder.set("preserve_frame_pointers", "true")
-            .map_err(|e| JitError::Cranelift(e.to_string()))?;
-
-        let isa_builder = cranelift_native::builder()
-            .map_err(|e| JitError::Cranelift(e.to_string()))?;
-
-        let isa = isa_builder
-            .finish(settings::Flags::new(flag_builder))
-            .map_err(|e| JitError::Cranelift(e.to_string()))?;
-
         // Create JIT module with external symbols for string operations
-        let mut builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
+        let mut builder = jit_builder(&config)?;
         // Register external string comparison helper
         builder.symbol("nos_str_eq", nos_str_eq as *const u8);
         let module = JITModule::new(builder);
@@ -826,6 +915,20 @@ impl JitCompiler {
                     ));
                 }
 
+                // Extern C call - allowed when its parameters and result all share one numeric type
+                Instruction::CallExtern(_, idx, _) => {
+                    let (extern_type, _) = extern_call_target(func, *idx)?;
+                    if let Some(existing) = detected_type {
+                        if existing != extern_type {
+                            return Err(JitError::NotSuitable(
+                                format!("mixed types: {:?} and {:?}", existing, extern_type)
+                            ));
+                        }
+                    } else {
+                        detected_type = Some(extern_type);
+                    }
+                }
+
                 // CallDirect to other compiled functions - allowed if callee is already JIT-compiled
                 Instruction::CallDirect(_, func_idx, args) => {
                     // Check if callee is already compiled (for Int64 - we only support same-type calls for now)
@@ -1611,6 +1714,25 @@ impl JitCompiler {
                         }
                     }
 
+                    // Direct call into a C function from an extern declaration
+                    Instruction::CallExtern(dst, idx, arg_regs) => {
+                        let (_, extern_fn) = extern_call_target(func, *idx)?;
+                        let mut extern_sig = self.module.make_signature();
+                        for _ in arg_regs.iter() {
+                            extern_sig.params.push(AbiParam::new(cl_type));
+                        }
+                        extern_sig.returns.push(AbiParam::new(cl_type));
+                        let sig_ref = builder.import_signature(extern_sig);
+                        let ptr_type = self.module.target_config().pointer_type();
+                        let callee = builder.ins().iconst(ptr_type, extern_fn.address as i64);
+                        let args: Vec<CraneliftValue> = arg_regs.iter()
+                            .map(|&r| builder.use_var(regs[r as usize]))
+                            .collect();
+                        let call = builder.ins().call_indirect(sig_ref, callee, &args);
+                        let result = builder.inst_results(call)[0];
+                        builder.def_var(regs[*dst as usize], result);
+                    }
+
                     other => {
                         return Err(JitError::UnsupportedInstruction(format!("{:?}", other)));
                     }
@@ -4676,6 +4798,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -4707,6 +4832,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -4748,6 +4876,58 @@ mod tests {
         assert_eq!(native_fn(-1), 0);
     }
 
+    /// Create an extern wrapper: labs(n) calling libc directly
+    fn make_labs_extern_function() -> FunctionValue {
+        use nostos_vm::ffi::ExternSignature;
+        let mut chunk = Chunk::new();
+        let descriptor = ExternSignature {
+            library: "c".to_string(),
+            symbol: "labs".to_string(),
+            params: vec![FfiType::Int64],
+            ret: FfiType::Int64,
+        }.to_descriptor();
+        chunk.constants.push(Value::String(Arc::new(descriptor)));
+        // r1 = labs(r0)
+        chunk.code.push(Instruction::CallExtern(1, 0, RegList(Arc::new([0]))));
+        chunk.code.push(Instruction::Return(1));
+        chunk.register_count = 2;
+
+        FunctionValue {
+            name: "labs".to_string(),
+            arity: 1,
+            param_names: vec!["n".to_string()],
+            code: Arc::new(chunk),
+            module: None,
+            source_span: None,
+            jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
+            call_count: AtomicU32::new(0),
+            debug_symbols: vec![],
+            source_code: None,
+            source_file: None,
+            doc: None,
+            signature: None,
+            param_types: vec![],
+            return_type: None, required_params: None,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_jit_extern_call() {
+        let config = JitConfig::default();
+        let mut jit = JitCompiler::new(config).unwrap();
+
+        let func = make_labs_extern_function();
+        jit.compile_int_function(0, &func).expect("JIT compilation failed");
+
+        let native_fn = jit.get_int_function(0).expect("Function not compiled");
+        assert_eq!(native_fn(-42), 42);
+        assert_eq!(native_fn(7), 7);
+    }
+
     /// Create a simple recursive function: fib(n) = if n <= 1 then n else fib(n-1) + fib(n-2)
     fn make_fib_function() -> FunctionValue {
         let mut chunk = Chunk::new();
@@ -4798,6 +4978,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -4921,6 +5104,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -5000,6 +5186,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -5113,6 +5302,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -5198,6 +5390,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -5309,6 +5504,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -5439,6 +5637,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -5554,6 +5755,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
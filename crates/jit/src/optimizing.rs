@@ -0,0 +1,1145 @@
+//! Optimizing tier: type-specialized native code from a function's profile.
+//!
+//! Functions whose arguments were always `Int64`, `Float64` or `Bool` (see
+//! `TypeProfile`) and whose code only computes with such values are compiled
+//! with every register unboxed in a Cranelift variable. The generic numeric
+//! instructions (`AddInt` on floats, `Eq`, `Lt`, ...) become single machine
+//! instructions for the kinds the profile saw. Self tail calls become loops,
+//! and small leaf functions called with `CallDirect` are inlined.
+//!
+//! Guards on entry check the arguments against the profile. When one fails,
+//! nothing has happened yet and the call deoptimizes to the baseline tier
+//! (`JIT_EXIT_DEOPT`). Loop heads are entries as well, guarding the registers
+//! live there, so frames that are already running move into optimized code
+//! (on-stack replacement). Every other exit - returns, back-edges that must
+//! yield, division by zero - boxes the registers back into the frame and
+//! lets the interpreter continue at that instruction.
+//!
+//! Heap values, other calls and suspension points keep a function in the
+//! baseline tier.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::types::{F64, I32, I64};
+use cranelift_codegen::ir::{AbiParam, Block, FuncRef, InstBuilder, JumpTableData, MemFlags, Signature, Type as CraneliftType, Value as CraneliftValue};
+use cranelift_codegen::Context;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::JITModule;
+use cranelift_module::{FuncId, Linkage, Module};
+
+use nostos_vm::jit_runtime::{self, JIT_EXIT_DEOPT, JIT_EXIT_RESUME};
+use nostos_vm::value::{FunctionValue, Instruction, TypeProfile, Value};
+
+use crate::{jit_builder, JitConfig, JitError};
+
+/// Larger functions stay in the baseline tier.
+const MAX_INSTRUCTIONS: usize = 512;
+
+/// Larger callees are called, not inlined.
+const INLINE_MAX_INSTRUCTIONS: usize = 32;
+
+/// Kind of an unboxed register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Float,
+    /// 0 or 1 in an I64
+    Bool,
+}
+
+impl Kind {
+    /// The kind of a profile entry that saw exactly one supported kind.
+    fn from_profile(kinds: u8) -> Option<Kind> {
+        match kinds {
+            TypeProfile::INT => Some(Kind::Int),
+            TypeProfile::FLOAT => Some(Kind::Float),
+            TypeProfile::BOOL => Some(Kind::Bool),
+            _ => None,
+        }
+    }
+
+    fn profile_bits(self) -> u8 {
+        match self {
+            Kind::Int => TypeProfile::INT,
+            Kind::Float => TypeProfile::FLOAT,
+            Kind::Bool => TypeProfile::BOOL,
+        }
+    }
+
+    fn ir_type(self) -> CraneliftType {
+        match self {
+            Kind::Float => F64,
+            Kind::Int | Kind::Bool => I64,
+        }
+    }
+}
+
+/// Register kinds of a function body, inferred from its argument kinds.
+struct Typing {
+    regs: Vec<Option<Kind>>,
+    returns: Option<Kind>,
+}
+
+/// What an instruction does to the typing.
+enum Effect {
+    None,
+    /// Operand kinds are not known yet
+    Pending,
+    Def(u8, Kind),
+    Return(Kind),
+}
+
+fn not_suitable(instruction: &Instruction) -> JitError {
+    JitError::NotSuitable(format!("optimizing tier does not support {:?}", instruction))
+}
+
+/// Infer the kind of every register of `func` called with `params`, or fail
+/// if some instruction is not supported for the kinds it gets. `inlined`
+/// bodies must not leave native code, so they cannot call, loop or divide
+/// integers.
+fn infer(
+    func: &FunctionValue,
+    params: &[Kind],
+    callees: &HashMap<u16, Arc<FunctionValue>>,
+    inlined: bool,
+) -> Result<Typing, JitError> {
+    let code = &func.code.code;
+    let mut typing = Typing {
+        regs: vec![None; func.code.register_count.max(params.len())],
+        returns: None,
+    };
+    for (reg, kind) in typing.regs.iter_mut().zip(params) {
+        *reg = Some(*kind);
+    }
+
+    // Registers keep one kind for the whole function, so iterate to a fixed point
+    loop {
+        let mut changed = false;
+        for instruction in code {
+            match effect(func, instruction, params, &typing.regs, callees, inlined)? {
+                Effect::None | Effect::Pending => {}
+                Effect::Def(dst, kind) => match typing.regs.get_mut(dst as usize) {
+                    Some(slot @ None) => {
+                        *slot = Some(kind);
+                        changed = true;
+                    }
+                    Some(Some(existing)) if *existing == kind => {}
+                    _ => return Err(JitError::NotSuitable(format!("register {} changes kind", dst))),
+                },
+                Effect::Return(kind) => match typing.returns {
+                    None => typing.returns = Some(kind),
+                    Some(existing) if existing == kind => {}
+                    Some(_) => return Err(JitError::NotSuitable("return kind changes".to_string())),
+                },
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for instruction in code {
+        if let Effect::Pending = effect(func, instruction, params, &typing.regs, callees, inlined)? {
+            return Err(JitError::NotSuitable(format!("untyped operand in {:?}", instruction)));
+        }
+    }
+    Ok(typing)
+}
+
+fn effect(
+    func: &FunctionValue,
+    instruction: &Instruction,
+    params: &[Kind],
+    regs: &[Option<Kind>],
+    callees: &HashMap<u16, Arc<FunctionValue>>,
+    inlined: bool,
+) -> Result<Effect, JitError> {
+    use Instruction::*;
+    use Kind::*;
+
+    let kind = |r: &u8| regs.get(*r as usize).copied().flatten();
+    let two = |a: &u8, b: &u8| match (kind(a), kind(b)) {
+        (Some(x), Some(y)) => Some((x, y)),
+        _ => None,
+    };
+    let unsupported = || Err(not_suitable(instruction));
+
+    Ok(match instruction {
+        Nop | Jump(_) => Effect::None,
+        JumpIfTrue(r, _) | JumpIfFalse(r, _) => match kind(r) {
+            Some(Bool) => Effect::None,
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+
+        LoadConst(dst, idx) => match func.code.constants.get(*idx as usize) {
+            Some(Value::Int64(_)) => Effect::Def(*dst, Int),
+            Some(Value::Float64(_)) => Effect::Def(*dst, Float),
+            Some(Value::Bool(_)) => Effect::Def(*dst, Bool),
+            _ => return unsupported(),
+        },
+        LoadTrue(dst) | LoadFalse(dst) => Effect::Def(*dst, Bool),
+        Move(dst, src) => match kind(src) {
+            Some(k) => Effect::Def(*dst, k),
+            None => Effect::Pending,
+        },
+
+        // The "Int" arithmetic instructions work on any matching numeric kinds
+        AddInt(dst, a, b) | SubInt(dst, a, b) | MulInt(dst, a, b) => match two(a, b) {
+            Some((Int, Int)) => Effect::Def(*dst, Int),
+            Some((Float, Float)) => Effect::Def(*dst, Float),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        DivInt(dst, a, b) => match two(a, b) {
+            // Division by zero leaves native code
+            Some((Int, Int)) if !inlined => Effect::Def(*dst, Int),
+            Some((Float, Float)) => Effect::Def(*dst, Float),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        ModInt(dst, a, b) => match two(a, b) {
+            Some((Int, Int)) if !inlined => Effect::Def(*dst, Int),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        NegInt(dst, src) => match kind(src) {
+            Some(k @ (Int | Float)) => Effect::Def(*dst, k),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        AddFloat(dst, a, b) | SubFloat(dst, a, b) | MulFloat(dst, a, b) | DivFloat(dst, a, b) => match two(a, b) {
+            Some((Float, Float)) => Effect::Def(*dst, Float),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        NegFloat(dst, src) => match kind(src) {
+            Some(Float) => Effect::Def(*dst, Float),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+
+        EqInt(dst, a, b) | LtInt(dst, a, b) | LeInt(dst, a, b) | GtInt(dst, a, b) | GeInt(dst, a, b)
+        | Lt(dst, a, b) | Le(dst, a, b) | Gt(dst, a, b) | Ge(dst, a, b) => match two(a, b) {
+            Some((Int, Int)) => Effect::Def(*dst, Bool),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        EqFloat(dst, a, b) | LtFloat(dst, a, b) | LeFloat(dst, a, b) => match two(a, b) {
+            Some((Float, Float)) => Effect::Def(*dst, Bool),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        EqBool(dst, a, b) | And(dst, a, b) | Or(dst, a, b) => match two(a, b) {
+            Some((Bool, Bool)) => Effect::Def(*dst, Bool),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        Eq(dst, a, b) => match two(a, b) {
+            Some((x, y)) if x == y => Effect::Def(*dst, Bool),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+        Not(dst, src) => match kind(src) {
+            Some(Bool) => Effect::Def(*dst, Bool),
+            Some(_) => return unsupported(),
+            None => Effect::Pending,
+        },
+
+        Return(src) => match kind(src) {
+            Some(k) => Effect::Return(k),
+            None => Effect::Pending,
+        },
+        TailCallSelf(args) if !inlined && args.len() == params.len() => {
+            for (arg, param) in args.iter().zip(params) {
+                match kind(arg) {
+                    Some(k) if k == *param => {}
+                    Some(_) => return unsupported(),
+                    None => return Ok(Effect::Pending),
+                }
+            }
+            Effect::None
+        }
+        CallDirect(dst, idx, args) if !inlined => {
+            let Some(callee) = callees.get(idx) else {
+                return unsupported();
+            };
+            let Some(kinds) = args.iter().map(kind).collect::<Option<Vec<_>>>() else {
+                return Ok(Effect::Pending);
+            };
+            match inline_typing(callee, &kinds, callees)?.returns {
+                Some(k) => Effect::Def(*dst, k),
+                None => return unsupported(),
+            }
+        }
+
+        _ => return unsupported(),
+    })
+}
+
+/// Typing of `callee` inlined with arguments of `kinds`, if it can be inlined.
+fn inline_typing(
+    callee: &FunctionValue,
+    kinds: &[Kind],
+    callees: &HashMap<u16, Arc<FunctionValue>>,
+) -> Result<Typing, JitError> {
+    let code = &callee.code.code;
+    if callee.arity != kinds.len() || code.len() > INLINE_MAX_INSTRUCTIONS {
+        return Err(JitError::NotSuitable(format!("{} is not inlinable", callee.name)));
+    }
+    check_jumps(code, false)?;
+    infer(callee, kinds, callees, true)
+}
+
+/// Jumps must stay inside the code (or go to its end), bodies must not fall
+/// off the end, and inlined bodies must not loop.
+fn check_jumps(code: &[Instruction], allow_backward: bool) -> Result<(), JitError> {
+    for (ip, instruction) in code.iter().enumerate() {
+        let offset = match instruction {
+            Instruction::Jump(offset) | Instruction::JumpIfTrue(_, offset) | Instruction::JumpIfFalse(_, offset) => *offset,
+            _ => continue,
+        };
+        let target = ip as i64 + 1 + offset as i64;
+        if target < 0 || target >= code.len() as i64 || (!allow_backward && target <= ip as i64) {
+            return Err(JitError::NotSuitable(format!("jump from {} to {}", ip, target)));
+        }
+    }
+    match code.last() {
+        Some(Instruction::Return(_) | Instruction::Jump(_) | Instruction::TailCallSelf(_)) => Ok(()),
+        _ => Err(JitError::NotSuitable("code may fall off its end".to_string())),
+    }
+}
+
+/// Registers read and written by a supported instruction.
+fn operands(instruction: &Instruction, arity: usize) -> (Vec<u8>, Vec<u8>) {
+    use Instruction::*;
+    match instruction {
+        JumpIfTrue(r, _) | JumpIfFalse(r, _) | Return(r) => (vec![*r], vec![]),
+        LoadConst(dst, _) | LoadTrue(dst) | LoadFalse(dst) => (vec![], vec![*dst]),
+        Move(dst, src) | NegInt(dst, src) | NegFloat(dst, src) | Not(dst, src) => (vec![*src], vec![*dst]),
+        AddInt(dst, a, b) | SubInt(dst, a, b) | MulInt(dst, a, b) | DivInt(dst, a, b) | ModInt(dst, a, b)
+        | AddFloat(dst, a, b) | SubFloat(dst, a, b) | MulFloat(dst, a, b) | DivFloat(dst, a, b)
+        | EqInt(dst, a, b) | LtInt(dst, a, b) | LeInt(dst, a, b) | GtInt(dst, a, b) | GeInt(dst, a, b)
+        | EqFloat(dst, a, b) | LtFloat(dst, a, b) | LeFloat(dst, a, b)
+        | EqBool(dst, a, b) | And(dst, a, b) | Or(dst, a, b)
+        | Eq(dst, a, b) | Lt(dst, a, b) | Le(dst, a, b) | Gt(dst, a, b) | Ge(dst, a, b) => (vec![*a, *b], vec![*dst]),
+        TailCallSelf(args) => (args.to_vec(), (0..arity as u8).collect()),
+        CallDirect(dst, _, args) => (args.to_vec(), vec![*dst]),
+        _ => (vec![], vec![]),
+    }
+}
+
+/// Instructions control may continue at after the one at `ip`.
+fn successors(code: &[Instruction], ip: usize) -> Vec<usize> {
+    let target = |offset: i16| (ip as i64 + 1 + offset as i64) as usize;
+    match &code[ip] {
+        Instruction::Jump(offset) => vec![target(*offset)],
+        Instruction::JumpIfTrue(_, offset) | Instruction::JumpIfFalse(_, offset) => vec![ip + 1, target(*offset)],
+        Instruction::Return(_) => vec![],
+        Instruction::TailCallSelf(_) => vec![0],
+        _ if ip + 1 < code.len() => vec![ip + 1],
+        _ => vec![],
+    }
+}
+
+/// Targets of backward jumps, where running frames enter optimized code.
+/// The start of the function is always an entry.
+fn loop_heads(code: &[Instruction]) -> Vec<usize> {
+    let mut heads: Vec<usize> = (0..code.len())
+        .flat_map(|ip| successors(code, ip).into_iter().filter(move |target| *target <= ip && *target > 0))
+        .collect();
+    heads.sort_unstable();
+    heads.dedup();
+    heads
+}
+
+/// A set of registers.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct RegSet([u64; 4]);
+
+impl RegSet {
+    fn contains(&self, reg: u8) -> bool {
+        self.0[reg as usize / 64] & (1 << (reg % 64)) != 0
+    }
+
+    fn insert(&mut self, reg: u8) {
+        self.0[reg as usize / 64] |= 1 << (reg % 64);
+    }
+
+    fn remove(&mut self, reg: u8) {
+        self.0[reg as usize / 64] &= !(1 << (reg % 64));
+    }
+
+    fn union(&mut self, other: &RegSet) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a |= b;
+        }
+    }
+}
+
+/// Registers live at the start of each instruction.
+fn liveness(code: &[Instruction], arity: usize) -> Vec<RegSet> {
+    let mut live = vec![RegSet::default(); code.len()];
+    loop {
+        let mut changed = false;
+        for ip in (0..code.len()).rev() {
+            let mut set = RegSet::default();
+            for next in successors(code, ip) {
+                set.union(&live[next]);
+            }
+            let (uses, defs) = operands(&code[ip], arity);
+            for reg in defs {
+                set.remove(reg);
+            }
+            for reg in uses {
+                set.insert(reg);
+            }
+            if set != live[ip] {
+                live[ip] = set;
+                changed = true;
+            }
+        }
+        if !changed {
+            return live;
+        }
+    }
+}
+
+/// Runtime helpers, declared once in the module.
+struct Helpers {
+    tick: FuncId,
+    guard: FuncId,
+    unbox: FuncId,
+    box_: FuncId,
+}
+
+/// Helper references inside the function being compiled.
+struct HelperRefs {
+    tick: FuncRef,
+    guard: FuncRef,
+    unbox: FuncRef,
+    box_: FuncRef,
+}
+
+/// The optimizing compiler.
+pub struct OptimizingCompiler {
+    /// Cranelift JIT module (owns the generated code)
+    module: JITModule,
+    /// Cranelift codegen context (reusable)
+    ctx: Context,
+    /// Function builder context (reusable)
+    builder_ctx: FunctionBuilderContext,
+    helpers: Helpers,
+    /// Number of functions compiled so far
+    compiled: usize,
+}
+
+impl OptimizingCompiler {
+    /// Create a new optimizing compiler
+    pub fn new(config: &JitConfig) -> Result<Self, JitError> {
+        let mut builder = jit_builder(config)?;
+        for (name, ptr) in jit_runtime::helper_symbols() {
+            builder.symbol(name, ptr);
+        }
+        let mut module = JITModule::new(builder);
+
+        let mut declare = |name: &str, params: usize, returns: bool| -> Result<FuncId, JitError> {
+            let mut sig = module.make_signature();
+            for _ in 0..params {
+                sig.params.push(AbiParam::new(I64));
+            }
+            if returns {
+                sig.returns.push(AbiParam::new(I64));
+            }
+            module
+                .declare_function(name, Linkage::Import, &sig)
+                .map_err(|e| JitError::Module(e.to_string()))
+        };
+        let helpers = Helpers {
+            tick: declare("nos_jit_tick", 3, true)?,
+            guard: declare("nos_jit_guard", 3, true)?,
+            unbox: declare("nos_jit_unbox", 2, true)?,
+            box_: declare("nos_jit_box", 4, false)?,
+        };
+
+        Ok(Self {
+            module,
+            ctx: Context::new(),
+            builder_ctx: FunctionBuilderContext::new(),
+            helpers,
+            compiled: 0,
+        })
+    }
+
+    /// Number of functions compiled so far
+    pub fn compiled_count(&self) -> usize {
+        self.compiled
+    }
+
+    /// Compile `func` for the argument kinds in its type profile and install
+    /// the code in `func.opt_entry`. `callees` are the functions it calls
+    /// with `CallDirect`, by index; small leaf ones are inlined.
+    pub fn compile(&mut self, func: &FunctionValue, callees: &HashMap<u16, Arc<FunctionValue>>) -> Result<(), JitError> {
+        let code = &func.code.code;
+        if code.is_empty() || code.len() > MAX_INSTRUCTIONS {
+            return Err(JitError::NotSuitable("function size".to_string()));
+        }
+        if func.arity > TypeProfile::MAX_ARGS {
+            return Err(JitError::NotSuitable("too many parameters to profile".to_string()));
+        }
+        let params = (0..func.arity)
+            .map(|i| Kind::from_profile(func.type_profile.arg(i)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| JitError::NotSuitable("argument kinds are not monomorphic".to_string()))?;
+        check_jumps(code, true)?;
+        let typing = infer(func, &params, callees, false)?;
+        // Functions entered through on-stack replacement may not have returned yet
+        let returned = func.type_profile.returns();
+        match typing.returns {
+            Some(kind) if returned == 0 || returned == kind.profile_bits() => {}
+            _ => return Err(JitError::NotSuitable("return kind differs from the profile".to_string())),
+        }
+
+        // (process, entry_ip) -> exit code, like baseline code
+        let mut sig: Signature = self.module.make_signature();
+        sig.params.push(AbiParam::new(I64));
+        sig.params.push(AbiParam::new(I64));
+        sig.returns.push(AbiParam::new(I64));
+        let func_id = self.module
+            .declare_anonymous_function(&sig)
+            .map_err(|e| JitError::Module(e.to_string()))?;
+
+        self.ctx.func.signature = sig;
+        if let Err(e) = self.build(func, &params, &typing, callees) {
+            self.module.clear_context(&mut self.ctx);
+            return Err(e);
+        }
+
+        self.module
+            .define_function(func_id, &mut self.ctx)
+            .map_err(|e| {
+                self.module.clear_context(&mut self.ctx);
+                JitError::Module(format!("define_function error: {}", e))
+            })?;
+        self.module.clear_context(&mut self.ctx);
+        self.module.finalize_definitions()
+            .map_err(|e| JitError::Module(e.to_string()))?;
+
+        let code_ptr = self.module.get_finalized_function(func_id);
+        // SAFETY: generated with the JitEntryFn signature; the module is never
+        // freed, so the code stays valid
+        unsafe { func.opt_entry.set(code_ptr) };
+        self.compiled += 1;
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        func: &FunctionValue,
+        params: &[Kind],
+        typing: &Typing,
+        callees: &HashMap<u16, Arc<FunctionValue>>,
+    ) -> Result<(), JitError> {
+        let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_ctx);
+        let helpers = HelperRefs {
+            tick: self.module.declare_func_in_func(self.helpers.tick, builder.func),
+            guard: self.module.declare_func_in_func(self.helpers.guard, builder.func),
+            unbox: self.module.declare_func_in_func(self.helpers.unbox, builder.func),
+            box_: self.module.declare_func_in_func(self.helpers.box_, builder.func),
+        };
+
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let process = builder.block_params(entry)[0];
+        let entry_ip = builder.block_params(entry)[1];
+
+        let mut emitter = Emitter { builder, helpers, process, frame: Vec::new(), next_var: 0 };
+        let frame = emitter.declare_vars(&typing.regs);
+        emitter.frame = frame.clone();
+        let code = &func.code.code;
+        let body = Body {
+            code,
+            constants: &func.code.constants,
+            vars: frame,
+            blocks: code.iter().map(|_| emitter.builder.create_block()).collect(),
+            inlined_into: None,
+        };
+
+        // Entries: the start of a call, guarding the arguments, and loop heads
+        // for on-stack replacement, guarding the registers live there. A failed
+        // guard deoptimizes before anything happened.
+        let deopt = emitter.builder.create_block();
+        let live = liveness(code, params.len());
+        let mut entries = vec![(0, (0..params.len() as u8).collect::<Vec<_>>())];
+        for head in loop_heads(code) {
+            let regs = (0..=u8::MAX).filter(|r| live[head].contains(*r)).collect();
+            entries.push((head, regs));
+        }
+        let entry_blocks: Vec<Block> = entries.iter().map(|_| emitter.builder.create_block()).collect();
+        let default = emitter.builder.func.dfg.block_call(deopt, &[]);
+        let mut table = vec![default; code.len()];
+        for ((ip, _), block) in entries.iter().zip(&entry_blocks) {
+            table[*ip] = emitter.builder.func.dfg.block_call(*block, &[]);
+        }
+        let jump_table = emitter.builder.create_jump_table(JumpTableData::new(default, &table));
+        let index = emitter.builder.ins().ireduce(I32, entry_ip);
+        emitter.builder.ins().br_table(index, jump_table);
+
+        for ((ip, regs), block) in entries.iter().zip(entry_blocks) {
+            emitter.builder.switch_to_block(block);
+            emitter.unbox_registers(regs, &body, deopt);
+            emitter.builder.ins().jump(body.blocks[*ip], &[]);
+        }
+
+        emitter.builder.switch_to_block(deopt);
+        let exit = emitter.iconst(JIT_EXIT_DEOPT);
+        emitter.builder.ins().return_(&[exit]);
+
+        emitter.body(&body, callees)?;
+
+        emitter.builder.seal_all_blocks();
+        emitter.builder.finalize();
+        Ok(())
+    }
+}
+
+/// Code being emitted: the compiled function itself or an inlined callee.
+struct Body<'a> {
+    code: &'a [Instruction],
+    constants: &'a [Value],
+    /// Variable of each register
+    vars: Vec<Option<(Variable, Kind)>>,
+    /// Block of each instruction
+    blocks: Vec<Block>,
+    /// For inlined callees: the caller's destination variable and the block
+    /// to continue at after returning
+    inlined_into: Option<(Variable, Block)>,
+}
+
+/// Emits the blocks of one function and its inlined callees.
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    helpers: HelperRefs,
+    process: CraneliftValue,
+    /// Variables of the compiled function's registers, boxed back into the
+    /// frame when leaving native code
+    frame: Vec<Option<(Variable, Kind)>>,
+    next_var: u32,
+}
+
+impl Emitter<'_> {
+    /// Declare a variable per typed register, starting out as zero.
+    fn declare_vars(&mut self, kinds: &[Option<Kind>]) -> Vec<Option<(Variable, Kind)>> {
+        kinds.iter().map(|kind| {
+            let kind = (*kind)?;
+            let var = Variable::from_u32(self.next_var);
+            self.next_var += 1;
+            self.builder.declare_var(var, kind.ir_type());
+            let zero = match kind {
+                Kind::Float => self.builder.ins().f64const(0.0),
+                Kind::Int | Kind::Bool => self.builder.ins().iconst(I64, 0),
+            };
+            self.builder.def_var(var, zero);
+            Some((var, kind))
+        }).collect()
+    }
+
+    fn iconst(&mut self, value: i64) -> CraneliftValue {
+        self.builder.ins().iconst(I64, value)
+    }
+
+    fn get(&mut self, body: &Body, reg: u8) -> CraneliftValue {
+        let (var, _) = body.vars[reg as usize].expect("operand registers are typed");
+        self.builder.use_var(var)
+    }
+
+    fn set(&mut self, body: &Body, reg: u8, value: CraneliftValue) {
+        let (var, _) = body.vars[reg as usize].expect("destination registers are typed");
+        self.builder.def_var(var, value);
+    }
+
+    fn kind(body: &Body, reg: u8) -> Kind {
+        body.vars[reg as usize].expect("operand registers are typed").1
+    }
+
+    /// Box `reg` of the compiled function into the frame.
+    fn store(&mut self, reg: usize) {
+        let Some((var, kind)) = self.frame[reg] else {
+            return;
+        };
+        let value = self.builder.use_var(var);
+        let bits = match kind {
+            Kind::Float => self.builder.ins().bitcast(I64, MemFlags::new(), value),
+            Kind::Int | Kind::Bool => value,
+        };
+        let args = [self.process, self.iconst(reg as i64), self.iconst(kind.profile_bits() as i64), bits];
+        self.builder.ins().call(self.helpers.box_, &args);
+    }
+
+    /// Guard and unbox `regs` from the frame, jumping to `deopt` if one of
+    /// them does not hold the kind the code was compiled for.
+    fn unbox_registers(&mut self, regs: &[u8], body: &Body, deopt: Block) {
+        for &reg in regs {
+            let Some((var, kind)) = body.vars[reg as usize] else {
+                continue;
+            };
+            let args = [self.process, self.iconst(reg as i64), self.iconst(kind.profile_bits() as i64)];
+            let call = self.builder.ins().call(self.helpers.guard, &args);
+            let ok = self.builder.inst_results(call)[0];
+            let unbox = self.builder.create_block();
+            self.builder.ins().brif(ok, unbox, &[], deopt, &[]);
+            self.builder.switch_to_block(unbox);
+
+            let args = [self.process, self.iconst(reg as i64)];
+            let call = self.builder.ins().call(self.helpers.unbox, &args);
+            let bits = self.builder.inst_results(call)[0];
+            let value = match kind {
+                Kind::Float => self.builder.ins().bitcast(F64, MemFlags::new(), bits),
+                Kind::Int | Kind::Bool => bits,
+            };
+            self.builder.def_var(var, value);
+        }
+    }
+
+    /// Box every register and return `exit`.
+    fn leave(&mut self, exit: i64) {
+        for reg in 0..self.frame.len() {
+            self.store(reg);
+        }
+        let exit = self.iconst(exit);
+        self.builder.ins().return_(&[exit]);
+    }
+
+    /// Block of the instruction following `ip`.
+    fn next(body: &Body, ip: usize) -> Block {
+        // `check_jumps` makes sure the last instruction does not fall through
+        body.blocks[(ip + 1).min(body.blocks.len() - 1)]
+    }
+
+    fn body(&mut self, body: &Body, callees: &HashMap<u16, Arc<FunctionValue>>) -> Result<(), JitError> {
+        for (ip, instruction) in body.code.iter().enumerate() {
+            self.builder.switch_to_block(body.blocks[ip]);
+            self.instruction(body, ip, instruction, callees)?;
+        }
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        body: &Body,
+        ip: usize,
+        instruction: &Instruction,
+        callees: &HashMap<u16, Arc<FunctionValue>>,
+    ) -> Result<(), JitError> {
+        use Instruction::*;
+
+        match instruction {
+            Nop => {}
+            Jump(offset) => {
+                self.branch(body, ip, ip as i64 + 1 + *offset as i64);
+                return Ok(());
+            }
+            JumpIfTrue(reg, offset) | JumpIfFalse(reg, offset) => {
+                let cond = self.get(body, *reg);
+                let taken = self.builder.create_block();
+                let next = Self::next(body, ip);
+                if matches!(instruction, JumpIfTrue(..)) {
+                    self.builder.ins().brif(cond, taken, &[], next, &[]);
+                } else {
+                    self.builder.ins().brif(cond, next, &[], taken, &[]);
+                }
+                self.builder.switch_to_block(taken);
+                self.branch(body, ip, ip as i64 + 1 + *offset as i64);
+                return Ok(());
+            }
+
+            LoadConst(dst, idx) => {
+                let value = match &body.constants[*idx as usize] {
+                    Value::Int64(n) => self.iconst(*n),
+                    Value::Float64(f) => self.builder.ins().f64const(*f),
+                    Value::Bool(b) => self.iconst(*b as i64),
+                    _ => return Err(not_suitable(instruction)),
+                };
+                self.set(body, *dst, value);
+            }
+            LoadTrue(dst) => {
+                let value = self.iconst(1);
+                self.set(body, *dst, value);
+            }
+            LoadFalse(dst) => {
+                let value = self.iconst(0);
+                self.set(body, *dst, value);
+            }
+            Move(dst, src) => {
+                let value = self.get(body, *src);
+                self.set(body, *dst, value);
+            }
+
+            AddInt(dst, a, b) | SubInt(dst, a, b) | MulInt(dst, a, b)
+            | AddFloat(dst, a, b) | SubFloat(dst, a, b) | MulFloat(dst, a, b) | DivFloat(dst, a, b) => {
+                let float = Self::kind(body, *a) == Kind::Float;
+                let (x, y) = (self.get(body, *a), self.get(body, *b));
+                let ins = self.builder.ins();
+                let value = match (instruction, float) {
+                    (AddInt(..), false) => ins.iadd(x, y),
+                    (SubInt(..), false) => ins.isub(x, y),
+                    (MulInt(..), false) => ins.imul(x, y),
+                    (AddInt(..) | AddFloat(..), true) => ins.fadd(x, y),
+                    (SubInt(..) | SubFloat(..), true) => ins.fsub(x, y),
+                    (MulInt(..) | MulFloat(..), true) => ins.fmul(x, y),
+                    _ => ins.fdiv(x, y),
+                };
+                self.set(body, *dst, value);
+            }
+            DivInt(dst, a, b) | ModInt(dst, a, b) => {
+                let (x, y) = (self.get(body, *a), self.get(body, *b));
+                let value = if Self::kind(body, *a) == Kind::Float {
+                    self.builder.ins().fdiv(x, y)
+                } else {
+                    // Division by zero throws in the interpreter
+                    let zero = self.builder.ins().icmp_imm(IntCC::Equal, y, 0);
+                    let throws = self.builder.create_block();
+                    let divide = self.builder.create_block();
+                    self.builder.ins().brif(zero, throws, &[], divide, &[]);
+                    self.builder.switch_to_block(throws);
+                    self.leave(ip as i64);
+                    self.builder.switch_to_block(divide);
+
+                    // Wrapping semantics: MIN / -1 would trap
+                    let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, y, -1);
+                    let one = self.iconst(1);
+                    let divisor = self.builder.ins().select(minus_one, one, y);
+                    if matches!(instruction, DivInt(..)) {
+                        let quotient = self.builder.ins().sdiv(x, divisor);
+                        let negated = self.builder.ins().ineg(x);
+                        self.builder.ins().select(minus_one, negated, quotient)
+                    } else {
+                        let remainder = self.builder.ins().srem(x, divisor);
+                        let zero = self.iconst(0);
+                        self.builder.ins().select(minus_one, zero, remainder)
+                    }
+                };
+                self.set(body, *dst, value);
+            }
+            NegInt(dst, src) | NegFloat(dst, src) => {
+                let x = self.get(body, *src);
+                let value = match Self::kind(body, *src) {
+                    Kind::Float => self.builder.ins().fneg(x),
+                    _ => self.builder.ins().ineg(x),
+                };
+                self.set(body, *dst, value);
+            }
+
+            EqInt(dst, a, b) | LtInt(dst, a, b) | LeInt(dst, a, b) | GtInt(dst, a, b) | GeInt(dst, a, b)
+            | Lt(dst, a, b) | Le(dst, a, b) | Gt(dst, a, b) | Ge(dst, a, b)
+            | EqBool(dst, a, b) | EqFloat(dst, a, b) | LtFloat(dst, a, b) | LeFloat(dst, a, b)
+            | Eq(dst, a, b) => {
+                let (x, y) = (self.get(body, *a), self.get(body, *b));
+                let test = if Self::kind(body, *a) == Kind::Float {
+                    let cc = match instruction {
+                        LtFloat(..) => FloatCC::LessThan,
+                        LeFloat(..) => FloatCC::LessThanOrEqual,
+                        _ => FloatCC::Equal,
+                    };
+                    self.builder.ins().fcmp(cc, x, y)
+                } else {
+                    let cc = match instruction {
+                        LtInt(..) | Lt(..) => IntCC::SignedLessThan,
+                        LeInt(..) | Le(..) => IntCC::SignedLessThanOrEqual,
+                        GtInt(..) | Gt(..) => IntCC::SignedGreaterThan,
+                        GeInt(..) | Ge(..) => IntCC::SignedGreaterThanOrEqual,
+                        _ => IntCC::Equal,
+                    };
+                    self.builder.ins().icmp(cc, x, y)
+                };
+                let value = self.builder.ins().uextend(I64, test);
+                self.set(body, *dst, value);
+            }
+            And(dst, a, b) | Or(dst, a, b) => {
+                let (x, y) = (self.get(body, *a), self.get(body, *b));
+                let value = match instruction {
+                    And(..) => self.builder.ins().band(x, y),
+                    _ => self.builder.ins().bor(x, y),
+                };
+                self.set(body, *dst, value);
+            }
+            Not(dst, src) => {
+                let x = self.get(body, *src);
+                let value = self.builder.ins().bxor_imm(x, 1);
+                self.set(body, *dst, value);
+            }
+
+            Return(src) => {
+                match body.inlined_into {
+                    Some((dst, after)) => {
+                        let value = self.get(body, *src);
+                        self.builder.def_var(dst, value);
+                        self.builder.ins().jump(after, &[]);
+                    }
+                    // The interpreter pops the frame
+                    None => {
+                        self.store(*src as usize);
+                        let code = self.iconst(ip as i64);
+                        self.builder.ins().return_(&[code]);
+                    }
+                }
+                return Ok(());
+            }
+            TailCallSelf(args) => {
+                let values: Vec<_> = args.iter().map(|r| self.get(body, *r)).collect();
+                for (reg, value) in values.into_iter().enumerate() {
+                    self.set(body, reg as u8, value);
+                }
+                self.branch(body, ip, 0);
+                return Ok(());
+            }
+            CallDirect(dst, idx, args) => {
+                let callee = callees.get(idx).ok_or_else(|| not_suitable(instruction))?;
+                let kinds: Vec<_> = args.iter().map(|r| Self::kind(body, *r)).collect();
+                let typing = inline_typing(callee, &kinds, callees)?;
+
+                let vars = self.declare_vars(&typing.regs);
+                for (param, arg) in args.iter().enumerate() {
+                    let value = self.get(body, *arg);
+                    let (var, _) = vars[param].expect("parameters are typed");
+                    self.builder.def_var(var, value);
+                }
+                let (dst, _) = body.vars[*dst as usize].expect("destination registers are typed");
+                let inlined = Body {
+                    code: &callee.code.code,
+                    constants: &callee.code.constants,
+                    vars,
+                    blocks: callee.code.code.iter().map(|_| self.builder.create_block()).collect(),
+                    inlined_into: Some((dst, Self::next(body, ip))),
+                };
+                self.builder.ins().jump(inlined.blocks[0], &[]);
+                self.body(&inlined, callees)?;
+                return Ok(());
+            }
+
+            _ => return Err(not_suitable(instruction)),
+        }
+
+        let next = Self::next(body, ip);
+        self.builder.ins().jump(next, &[]);
+        Ok(())
+    }
+
+    /// Jump to `target`. Back-edges charge the loop's reductions and leave
+    /// native code, with the frame at the loop head, when the process must
+    /// yield.
+    fn branch(&mut self, body: &Body, ip: usize, target: i64) {
+        let block = body.blocks[target as usize];
+        if target > ip as i64 {
+            self.builder.ins().jump(block, &[]);
+            return;
+        }
+        let args = [self.process, self.iconst(ip as i64 - target + 1), self.iconst(target)];
+        let call = self.builder.ins().call(self.helpers.tick, &args);
+        let must_yield = self.builder.inst_results(call)[0];
+        let yield_block = self.builder.create_block();
+        self.builder.ins().brif(must_yield, yield_block, &[], block, &[]);
+        self.builder.switch_to_block(yield_block);
+        self.leave(JIT_EXIT_RESUME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostos_vm::async_vm::{AsyncConfig, AsyncVM};
+    use std::sync::atomic::Ordering;
+    use nostos_vm::value::Chunk;
+
+    fn function(name: &str, arity: usize, constants: Vec<Value>, code: Vec<Instruction>, registers: usize) -> FunctionValue {
+        let mut chunk = Chunk::new();
+        for constant in constants {
+            chunk.add_constant(constant);
+        }
+        chunk.code = code;
+        chunk.register_count = registers;
+        FunctionValue::new_simple(name.to_string(), arity, vec![], Arc::new(chunk))
+    }
+
+    /// `main()`: calls function 1 with the given constants and returns the
+    /// results as a tuple.
+    fn main_calling(calls: Vec<Vec<Value>>) -> FunctionValue {
+        let mut constants = Vec::new();
+        let mut code = Vec::new();
+        let mut results = Vec::new();
+        let mut reg = 0u8;
+        for args in calls {
+            let mut arg_regs = Vec::new();
+            for arg in args {
+                constants.push(arg);
+                code.push(Instruction::LoadConst(reg, constants.len() as u16 - 1));
+                arg_regs.push(reg);
+                reg += 1;
+            }
+            code.push(Instruction::CallDirect(reg, 1, arg_regs.into()));
+            results.push(reg);
+            reg += 1;
+        }
+        code.push(Instruction::MakeTuple(reg, results.into()));
+        code.push(Instruction::Return(reg));
+        function("main", 0, constants, code, reg as usize + 1)
+    }
+
+    /// `sum_to(n, acc) = if n == 0 then acc else sum_to(n - 1, acc + square(n))`
+    /// with `square(x) = x * x` as function 2.
+    fn sum_to() -> FunctionValue {
+        function("sum_to", 2, vec![Value::Int64(0), Value::Int64(1)], vec![
+            Instruction::LoadConst(2, 0),
+            Instruction::Eq(3, 0, 2),
+            Instruction::JumpIfFalse(3, 1),                 // -> 4
+            Instruction::Return(1),
+            Instruction::LoadConst(4, 1),                   // 4
+            Instruction::SubInt(5, 0, 4),
+            Instruction::CallDirect(6, 2, vec![0].into()),
+            Instruction::AddInt(7, 1, 6),
+            Instruction::TailCallSelf(vec![5, 7].into()),
+        ], 8)
+    }
+
+    fn square() -> FunctionValue {
+        function("square", 1, vec![], vec![
+            Instruction::MulInt(1, 0, 0),
+            Instruction::Return(1),
+        ], 2)
+    }
+
+    fn callees(functions: &[Arc<FunctionValue>]) -> HashMap<u16, Arc<FunctionValue>> {
+        functions.iter().enumerate().map(|(i, f)| (i as u16, f.clone())).collect()
+    }
+
+    /// Run `functions[0]`, optionally with `functions[1]` optimized for the
+    /// profile `(args, returns)`.
+    fn run(functions: Vec<FunctionValue>, profile: Option<(&[u8], u8)>) -> (String, Vec<Arc<FunctionValue>>) {
+        let functions: Vec<_> = functions.into_iter().map(Arc::new).collect();
+        if let Some((args, returns)) = profile {
+            let target = &functions[1];
+            for (i, kind) in args.iter().enumerate() {
+                target.type_profile.record_arg(i, *kind);
+            }
+            target.type_profile.record_return(returns);
+            let mut compiler = OptimizingCompiler::new(&JitConfig::default()).unwrap();
+            compiler.compile(target, &callees(&functions)).expect("optimizing compilation failed");
+            assert!(target.opt_entry.is_set());
+            assert_eq!(compiler.compiled_count(), 1);
+            std::mem::forget(compiler);
+        }
+        let mut vm = AsyncVM::new(AsyncConfig::default());
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        // Never queue anything: only the code compiled above runs natively
+        vm.enable_tiered_jit(sender, u32::MAX);
+        vm.set_function_list(functions.clone());
+        vm.register_function("main", functions[0].clone());
+        let result = format!("{:?}", vm.run("main").expect("run failed"));
+        (result, functions)
+    }
+
+    #[test]
+    fn test_optimized_loop_with_inlined_callee_matches_interpreter() {
+        let program = || vec![
+            main_calling(vec![vec![Value::Int64(100_000), Value::Int64(0)]]),
+            sum_to(),
+            square(),
+        ];
+        let (expected, _) = run(program(), None);
+        assert!(expected.contains("333338333350000"), "{}", expected);
+        let int = TypeProfile::INT;
+        let (result, functions) = run(program(), Some((&[int, int], int)));
+        assert_eq!(result, expected);
+        assert_eq!(functions[1].type_profile.deopts.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_guard_failure_deoptimizes() {
+        // double(x) = x + x, profiled with floats but also called with an int
+        let double = || function("double", 1, vec![], vec![
+            Instruction::AddInt(1, 0, 0),
+            Instruction::Return(1),
+        ], 2);
+        let program = || vec![
+            main_calling(vec![vec![Value::Float64(1.25)], vec![Value::Int64(21)]]),
+            double(),
+        ];
+        let (expected, _) = run(program(), None);
+        assert!(expected.contains("2.5") && expected.contains("42"), "{}", expected);
+        let (result, functions) = run(program(), Some((&[TypeProfile::FLOAT], TypeProfile::FLOAT)));
+        assert_eq!(result, expected);
+        assert_eq!(functions[1].type_profile.deopts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_division_by_zero_leaves_native_code() {
+        // quot(a, b) = a / b, caught by the interpreter's exception handling
+        let quot = || function("quot", 2, vec![], vec![
+            Instruction::DivInt(2, 0, 1),
+            Instruction::Return(2),
+        ], 3);
+        let program = || vec![
+            main_calling(vec![vec![Value::Int64(i64::MIN), Value::Int64(-1)], vec![Value::Int64(7), Value::Int64(0)]]),
+            quot(),
+        ];
+        let int = TypeProfile::INT;
+        let functions: Vec<_> = program().into_iter().map(Arc::new).collect();
+        functions[1].type_profile.record_arg(0, int);
+        functions[1].type_profile.record_arg(1, int);
+        functions[1].type_profile.record_return(int);
+        let mut compiler = OptimizingCompiler::new(&JitConfig::default()).unwrap();
+        compiler.compile(&functions[1], &callees(&functions)).unwrap();
+        std::mem::forget(compiler);
+
+        let mut vm = AsyncVM::new(AsyncConfig::default());
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        vm.enable_tiered_jit(sender, u32::MAX);
+        vm.set_function_list(functions.clone());
+        vm.register_function("main", functions[0].clone());
+        let error = vm.run("main").expect_err("division by zero must fail");
+        assert!(error.contains("ivision by zero"), "{}", error);
+    }
+
+    #[test]
+    fn test_running_loop_reenters_at_loop_head() {
+        // count() = { var i = 0; var total = 0; while i < n { total += i; i += 1 }; total }
+        // runs long enough to yield many times
+        let n = 300_000;
+        let count = || function("count", 0, vec![Value::Int64(0), Value::Int64(n), Value::Int64(1)], vec![
+            Instruction::LoadConst(0, 0),
+            Instruction::LoadConst(1, 0),
+            Instruction::LoadConst(2, 1),
+            Instruction::LoadConst(3, 2),
+            Instruction::LtInt(4, 0, 2),                    // 4: loop head
+            Instruction::JumpIfFalse(4, 3),                 // -> 9
+            Instruction::AddInt(1, 1, 0),
+            Instruction::AddInt(0, 0, 3),
+            Instruction::Jump(-5),                          // -> 4
+            Instruction::Return(1),
+        ], 5);
+        let program = || vec![main_calling(vec![vec![]]), count()];
+        let (expected, _) = run(program(), None);
+        assert!(expected.contains(&(n * (n - 1) / 2).to_string()), "{}", expected);
+        let (result, functions) = run(program(), Some((&[], TypeProfile::INT)));
+        assert_eq!(result, expected);
+        // Every back-edge ran natively: after each yield the frame came back
+        // in at the loop head instead of finishing in the interpreter
+        assert_eq!(functions[1].type_profile.backedges.load(Ordering::Relaxed), n as u32);
+    }
+
+    #[test]
+    fn test_heap_values_are_not_suitable() {
+        let list = function("list", 1, vec![], vec![
+            Instruction::MakeList(1, vec![0].into()),
+            Instruction::Return(1),
+        ], 2);
+        list.type_profile.record_arg(0, TypeProfile::INT);
+        list.type_profile.record_return(TypeProfile::OTHER);
+        let mut compiler = OptimizingCompiler::new(&JitConfig::default()).unwrap();
+        assert!(matches!(compiler.compile(&list, &HashMap::new()), Err(JitError::NotSuitable(_))));
+        assert!(!list.opt_entry.is_set());
+    }
+}
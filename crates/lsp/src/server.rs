@@ -10,6 +10,8 @@ use tower_lsp::{Client, LanguageServer};
 
 use nostos_repl::{ReplEngine, ReplConfig};
 use nostos_repl::inference;
+use nostos_repl::symbols::{self, SourceModule};
+use nostos_repl::quickfix::{self, QuickFix};
 use tower_lsp::lsp_types::notification::Notification;
 
 /// Custom notification for file status updates (for VS Code file decorations)
@@ -39,6 +41,13 @@ struct BindingInfo {
     rhs_start: usize,  // Column where RHS expression starts
 }
 
+/// A project source file, as seen by rename
+struct ProjectFile {
+    uri: Url,
+    module_name: String,
+    content: String,
+}
+
 pub struct NostosLanguageServer {
     client: Client,
     engine: Mutex<Option<ReplEngine>>,
@@ -583,6 +592,19 @@ impl LanguageServer for NostosLanguageServer {
                 document_symbol_provider: Some(OneOf::Left(true)),
                 // Find references
                 references_provider: Some(OneOf::Left(true)),
+                // Rename across the project
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
+                // Formatting (same rules as `nostos fmt`)
+                document_formatting_provider: Some(OneOf::Left(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
+                // Quick fixes for compile errors and type annotations
+                code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                    code_action_kinds: Some(vec![CodeActionKind::QUICKFIX, CodeActionKind::REFACTOR_REWRITE]),
+                    ..Default::default()
+                })),
                 // Inlay hints disabled for now - needs more work
                 // inlay_hint_provider: Some(OneOf::Right(InlayHintServerCapabilities::Options(
                 //     InlayHintOptions {
@@ -1703,9 +1725,238 @@ impl LanguageServer for NostosLanguageServer {
             Ok(Some(locations))
         }
     }
+
+    async fn prepare_rename(&self, params: TextDocumentPositionParams) -> Result<Option<PrepareRenameResponse>> {
+        let uri = &params.text_document.uri;
+        let position = params.position;
+
+        let engine_guard = self.engine.lock().unwrap();
+        let (files, modules, file) = self.project_modules(engine_guard.as_ref(), uri)
+            .map_err(tower_lsp::jsonrpc::Error::invalid_params)?;
+        let content = &files[file].content;
+        let offset = Self::line_col_to_byte_offset(content, position.line as usize, position.character as usize);
+
+        let receiver_type = Self::receiver_type_lookup(engine_guard.as_ref(), &files);
+        let occurrences = symbols::find_occurrences(&modules, &receiver_type);
+        let Some(occurrence) = symbols::symbol_at(&occurrences, file, offset) else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params("This symbol can't be renamed"));
+        };
+
+        let range = Range::new(
+            Self::byte_offset_to_position(content, occurrence.span.start),
+            Self::byte_offset_to_position(content, occurrence.span.end),
+        );
+        Ok(Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder: occurrence.name.clone() }))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        eprintln!("Rename at {:?} to '{}'", position, params.new_name);
+
+        let engine_guard = self.engine.lock().unwrap();
+        let (files, modules, file) = self.project_modules(engine_guard.as_ref(), uri)
+            .map_err(tower_lsp::jsonrpc::Error::invalid_params)?;
+        let offset = Self::line_col_to_byte_offset(&files[file].content, position.line as usize, position.character as usize);
+
+        let receiver_type = Self::receiver_type_lookup(engine_guard.as_ref(), &files);
+        let edits = symbols::rename(&modules, file, offset, &params.new_name, &receiver_type)
+            .map_err(tower_lsp::jsonrpc::Error::invalid_params)?;
+
+        let mut changes: std::collections::HashMap<Url, Vec<TextEdit>> = std::collections::HashMap::new();
+        for edit in edits {
+            let target = &files[edit.file];
+            let range = Range::new(
+                Self::byte_offset_to_position(&target.content, edit.span.start),
+                Self::byte_offset_to_position(&target.content, edit.span.end),
+            );
+            changes.entry(target.uri.clone()).or_default().push(TextEdit::new(range, edit.new_text));
+        }
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+
+        let content = match self.documents.get(uri) {
+            Some(c) => c.clone(),
+            None => return Ok(None),
+        };
+
+        let formatted = match nostos_syntax::format_source(&content) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("Not formatting {}: {}", uri, e);
+                return Ok(None);
+            }
+        };
+        if formatted == content {
+            return Ok(Some(vec![]));
+        }
+
+        // Replace the whole document
+        let range = Range::new(Position::new(0, 0), Self::byte_offset_to_position(&content, content.len()));
+        Ok(Some(vec![TextEdit::new(range, formatted)]))
+    }
+
+    async fn range_formatting(&self, params: DocumentRangeFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+
+        let content = match self.documents.get(uri) {
+            Some(c) => c.clone(),
+            None => return Ok(None),
+        };
+
+        let start = Self::line_col_to_byte_offset(&content, params.range.start.line as usize, params.range.start.character as usize);
+        let end = Self::line_col_to_byte_offset(&content, params.range.end.line as usize, params.range.end.character as usize);
+
+        // The formatter works on whole lines, so the edit covers every line the selection touches
+        let (lines, formatted) = match nostos_syntax::format_range(&content, start.min(content.len())..end.min(content.len())) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Not formatting {}: {}", uri, e);
+                return Ok(None);
+            }
+        };
+        if content[lines.clone()] == formatted {
+            return Ok(Some(vec![]));
+        }
+
+        let range = Range::new(
+            Self::byte_offset_to_position(&content, lines.start),
+            Self::byte_offset_to_position(&content, lines.end),
+        );
+        Ok(Some(vec![TextEdit::new(range, formatted)]))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = &params.text_document.uri;
+
+        let content = match self.documents.get(uri) {
+            Some(c) => c.clone(),
+            None => return Ok(None),
+        };
+        let module = match nostos_syntax::parse(&content) {
+            (Some(module), errors) if errors.is_empty() => module,
+            _ => return Ok(None),
+        };
+
+        let engine_guard = self.engine.lock().unwrap();
+        let engine = engine_guard.as_ref();
+        let module_name = Self::module_name_for(engine, uri);
+        let mut actions = Vec::new();
+
+        for diagnostic in &params.context.diagnostics {
+            if diagnostic.source.as_deref() != Some("nostos") {
+                continue;
+            }
+            let exporters = |name: &str| engine.map(|e| e.get_modules_exporting(name, &module_name)).unwrap_or_default();
+            let fixes = quickfix::fixes_for_error(&content, &module, diagnostic.range.start.line as usize, &diagnostic.message, &exporters);
+            for fix in fixes {
+                let mut action = Self::code_action_for(uri, &content, fix, CodeActionKind::QUICKFIX);
+                action.diagnostics = Some(vec![diagnostic.clone()]);
+                actions.push(CodeActionOrCommand::CodeAction(action));
+            }
+        }
+
+        // Inferred types refer to the compiled source, so only annotate
+        // when the document has no unsaved changes
+        let saved = uri.to_file_path().ok().and_then(|path| std::fs::read_to_string(path).ok());
+        if let (Some(engine), true) = (engine, saved.as_deref() == Some(content.as_str())) {
+            let offset = Self::line_col_to_byte_offset(&content, params.range.start.line as usize, params.range.start.character as usize);
+            let type_of = |span: nostos_syntax::Span| engine.get_inferred_type_of_span(&module_name, span.start, span.end);
+            if let Some(fix) = quickfix::add_type_annotation(&module, offset, &type_of) {
+                actions.push(CodeActionOrCommand::CodeAction(Self::code_action_for(uri, &content, fix, CodeActionKind::REFACTOR_REWRITE)));
+            }
+        }
+
+        Ok(Some(actions))
+    }
 }
 
 impl NostosLanguageServer {
+    /// Name of the module compiled from `uri`: its project module name, or the
+    /// file name for files outside the project.
+    fn module_name_for(engine: Option<&ReplEngine>, uri: &Url) -> String {
+        let path = uri.to_file_path().ok();
+        let project_name = engine.and_then(|e| {
+            e.get_module_sources().into_iter()
+                .find(|(_, source)| path.as_deref() == Some(std::path::Path::new(source)))
+                .map(|(name, _)| name)
+        });
+        project_name.unwrap_or_else(|| {
+            path.as_ref()
+                .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+                .unwrap_or_else(|| "main".to_string())
+        })
+    }
+
+    /// A code action applying `fix` to the document `uri`.
+    fn code_action_for(uri: &Url, content: &str, fix: QuickFix, kind: CodeActionKind) -> CodeAction {
+        let edits = fix.edits.into_iter()
+            .map(|edit| {
+                let range = Range::new(
+                    Self::byte_offset_to_position(content, edit.span.start),
+                    Self::byte_offset_to_position(content, edit.span.end),
+                );
+                TextEdit::new(range, edit.new_text)
+            })
+            .collect();
+        CodeAction {
+            title: fix.title,
+            kind: Some(kind),
+            edit: Some(WorkspaceEdit::new(std::collections::HashMap::from([(uri.clone(), edits)]))),
+            ..Default::default()
+        }
+    }
+
+    /// Parse all project modules for rename, using the content of open documents
+    /// over the files on disk. Returns the files, their parsed modules (in the
+    /// same order) and the index of `uri`.
+    fn project_modules(&self, engine: Option<&ReplEngine>, uri: &Url) -> std::result::Result<(Vec<ProjectFile>, Vec<SourceModule>, usize), String> {
+        let mut files: Vec<ProjectFile> = Vec::new();
+        for (module_name, path) in engine.map(|e| e.get_module_sources()).unwrap_or_default() {
+            let Ok(file_uri) = Url::from_file_path(&path) else { continue };
+            let content = match self.documents.get(&file_uri) {
+                Some(content) => content.clone(),
+                None => match std::fs::read_to_string(&path) {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                },
+            };
+            files.push(ProjectFile { uri: file_uri, module_name, content });
+        }
+
+        // A file outside the project is its own module
+        if !files.iter().any(|f| &f.uri == uri) {
+            let content = self.documents.get(uri).map(|c| c.clone()).ok_or("Document is not open")?;
+            let module_name = uri.to_file_path().ok()
+                .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+                .unwrap_or_else(|| "main".to_string());
+            files.push(ProjectFile { uri: uri.clone(), module_name, content });
+        }
+
+        let mut modules = Vec::new();
+        for file in &files {
+            let (module, errors) = nostos_syntax::parse(&file.content);
+            match module {
+                Some(module) if errors.is_empty() => modules.push(SourceModule {
+                    path: file.module_name.split('.').map(String::from).collect(),
+                    module,
+                }),
+                _ => return Err(format!("Can't rename while {} has syntax errors", file.uri.path())),
+            }
+        }
+        let index = files.iter().position(|f| &f.uri == uri).unwrap();
+        Ok((files, modules, index))
+    }
+
+    /// Look up inferred types of field access receivers in the compiled project.
+    fn receiver_type_lookup<'a>(engine: Option<&'a ReplEngine>, files: &'a [ProjectFile]) -> impl Fn(usize, nostos_syntax::Span) -> Option<String> + 'a {
+        move |file, span| engine?.get_inferred_type_of_span(&files[file].module_name, span.start, span.end)
+    }
+
     /// Extract document symbols (functions, types, traits) from source content
     fn extract_document_symbols(content: &str) -> Vec<SymbolInformation> {
         let mut symbols = Vec::new();
@@ -2921,6 +3172,14 @@ impl NostosLanguageServer {
         offset
     }
 
+    /// Convert a byte offset in content to a line/column position (0-based)
+    fn byte_offset_to_position(content: &str, offset: usize) -> Position {
+        let before = &content[..offset.min(content.len())];
+        let line = before.matches('\n').count();
+        let col = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Position::new(line as u32, col as u32)
+    }
+
     /// Extract binding information from a line if it's a simple binding.
     /// Returns None for function definitions, comments, etc.
     fn extract_binding_from_line(line: &str) -> Option<BindingInfo> {
@@ -3181,6 +3440,17 @@ impl NostosLanguageServer {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_byte_offset_to_position() {
+        let content = "ab\ncd\n";
+        for offset in 0..=content.len() {
+            let position = NostosLanguageServer::byte_offset_to_position(content, offset);
+            let back = NostosLanguageServer::line_col_to_byte_offset(content, position.line as usize, position.character as usize);
+            assert_eq!(back, offset);
+        }
+        assert_eq!(NostosLanguageServer::byte_offset_to_position(content, content.len()), Position::new(2, 0));
+    }
+
     #[test]
     fn test_count_parameters_tuple_style() {
         // Empty params
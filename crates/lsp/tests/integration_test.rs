@@ -399,6 +399,105 @@ impl LspClient {
         refs
     }
 
+    /// Request formatting edits for a file, or for a range of it.
+    /// Returns the edits as (start line, end line, new text).
+    fn formatting(&mut self, uri: &str, range: Option<(u32, u32)>) -> Vec<(u32, u32, String)> {
+        let response = match range {
+            Some((start, end)) => self.send_request("textDocument/rangeFormatting", json!({
+                "textDocument": { "uri": uri },
+                "range": {
+                    "start": { "line": start, "character": 0 },
+                    "end": { "line": end, "character": 0 }
+                },
+                "options": { "tabSize": 4, "insertSpaces": true }
+            })),
+            None => self.send_request("textDocument/formatting", json!({
+                "textDocument": { "uri": uri },
+                "options": { "tabSize": 4, "insertSpaces": true }
+            })),
+        };
+
+        let mut edits = Vec::new();
+        if let Some(items) = response.get("result").and_then(|r| r.as_array()) {
+            for item in items {
+                let line = |end: &str| item.get("range")
+                    .and_then(|r| r.get(end))
+                    .and_then(|p| p.get("line"))
+                    .and_then(|l| l.as_u64())
+                    .unwrap_or(0) as u32;
+                let text = item.get("newText").and_then(|t| t.as_str()).unwrap_or("").to_string();
+                edits.push((line("start"), line("end"), text));
+            }
+        }
+        edits
+    }
+
+    /// Rename the symbol at a position. Returns the edits as (uri, line, character, new text),
+    /// or the error message if the server refused.
+    /// Request code actions for a range, passing `diagnostics` as (line, message).
+    /// Returns (title, edits) with edits as (line, character, new_text).
+    fn code_actions(&mut self, uri: &str, line: u32, character: u32, diagnostics: &[(u32, &str)]) -> Vec<(String, Vec<(u32, u32, String)>)> {
+        let diagnostics: Vec<Value> = diagnostics.iter().map(|(line, message)| json!({
+            "range": {
+                "start": { "line": line, "character": 0 },
+                "end": { "line": line, "character": 100 }
+            },
+            "severity": 1,
+            "source": "nostos",
+            "message": message
+        })).collect();
+        let response = self.send_request("textDocument/codeAction", json!({
+            "textDocument": { "uri": uri },
+            "range": {
+                "start": { "line": line, "character": character },
+                "end": { "line": line, "character": character }
+            },
+            "context": { "diagnostics": diagnostics }
+        }));
+
+        let mut actions = Vec::new();
+        for action in response.get("result").and_then(|r| r.as_array()).into_iter().flatten() {
+            let title = action.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string();
+            let mut edits = Vec::new();
+            let changes = action.get("edit").and_then(|e| e.get("changes")).and_then(|c| c.get(uri));
+            for item in changes.and_then(|c| c.as_array()).into_iter().flatten() {
+                let start = item.get("range").and_then(|r| r.get("start"));
+                let line = start.and_then(|s| s.get("line")).and_then(|l| l.as_u64()).unwrap_or(0) as u32;
+                let char = start.and_then(|s| s.get("character")).and_then(|c| c.as_u64()).unwrap_or(0) as u32;
+                let text = item.get("newText").and_then(|t| t.as_str()).unwrap_or("").to_string();
+                edits.push((line, char, text));
+            }
+            actions.push((title, edits));
+        }
+        actions
+    }
+
+    fn rename(&mut self, uri: &str, line: u32, character: u32, new_name: &str) -> std::result::Result<Vec<(String, u32, u32, String)>, String> {
+        let response = self.send_request("textDocument/rename", json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+            "newName": new_name
+        }));
+        if let Some(error) = response.get("error") {
+            return Err(error.get("message").and_then(|m| m.as_str()).unwrap_or("").to_string());
+        }
+
+        let mut edits = Vec::new();
+        if let Some(changes) = response.get("result").and_then(|r| r.get("changes")).and_then(|c| c.as_object()) {
+            for (edit_uri, items) in changes {
+                for item in items.as_array().into_iter().flatten() {
+                    let start = item.get("range").and_then(|r| r.get("start"));
+                    let line = start.and_then(|s| s.get("line")).and_then(|l| l.as_u64()).unwrap_or(0) as u32;
+                    let char = start.and_then(|s| s.get("character")).and_then(|c| c.as_u64()).unwrap_or(0) as u32;
+                    let text = item.get("newText").and_then(|t| t.as_str()).unwrap_or("").to_string();
+                    edits.push((edit_uri.clone(), line, char, text));
+                }
+            }
+        }
+        edits.sort();
+        Ok(edits)
+    }
+
     fn shutdown(&mut self) -> Value {
         self.send_request("shutdown", json!(null))
     }
@@ -4459,3 +4558,128 @@ fn get_nostos_binary() -> String {
         .to_string_lossy()
         .to_string()
 }
+
+/// Test document and range formatting
+#[test]
+fn test_lsp_formatting() {
+    let project_path = create_test_project("formatting");
+
+    let content = "double(x)=x*2\n\n\nmain() = {\n  y = double( 21 )\n  y\n}\n";
+    fs::write(project_path.join("main.nos"), content).unwrap();
+
+    let mut client = LspClient::new(&require_lsp_binary!());
+    let _ = client.initialize(project_path.to_str().unwrap());
+    client.initialized_and_wait();
+
+    let main_uri = format!("file://{}/main.nos", project_path.display());
+    client.did_open(&main_uri, content);
+    std::thread::sleep(Duration::from_millis(300));
+
+    let whole = client.formatting(&main_uri, None);
+    let range = client.formatting(&main_uri, Some((4, 5)));
+
+    let _ = client.shutdown();
+    client.exit();
+    cleanup_test_project(&project_path);
+
+    assert_eq!(
+        whole,
+        vec![(0, 7, "double(x) = x * 2\n\nmain() = {\n    y = double(21)\n    y\n}\n".to_string())],
+    );
+    // Only the selected line is touched
+    assert_eq!(range, vec![(4, 5, "    y = double(21)\n".to_string())]);
+}
+
+/// Test renaming a function used from another module
+#[test]
+fn test_lsp_rename_cross_file() {
+    let project_path = create_test_project("rename_cross");
+
+    let util_content = "pub double(x: Int) -> Int = x * 2\n\npub quad(x: Int) -> Int = double(double(x))\n";
+    fs::write(project_path.join("util.nos"), util_content).unwrap();
+    let main_content = "use util.{double}\n\nmain() = {\n    double = 3\n    println(util.double(double))\n}\n";
+    fs::write(project_path.join("main.nos"), main_content).unwrap();
+
+    let mut client = LspClient::new(&require_lsp_binary!());
+    let _ = client.initialize(project_path.to_str().unwrap());
+    client.initialized_and_wait();
+
+    let util_uri = format!("file://{}/util.nos", project_path.display());
+    let main_uri = format!("file://{}/main.nos", project_path.display());
+    client.did_open(&util_uri, util_content);
+    client.did_open(&main_uri, main_content);
+    std::thread::sleep(Duration::from_millis(300));
+
+    // Rename `double` from its definition
+    let edits = client.rename(&util_uri, 0, 5, "twice");
+    // Renaming a builtin is refused
+    let builtin = client.rename(&main_uri, 4, 6, "say");
+
+    let _ = client.shutdown();
+    client.exit();
+    cleanup_test_project(&project_path);
+
+    let twice = |uri: &str, line, character| (uri.to_string(), line, character, "twice".to_string());
+    assert_eq!(
+        edits.unwrap(),
+        vec![
+            // `use util.{double}` and `util.double(...)`, but not the local `double`
+            twice(&main_uri, 0, 10),
+            twice(&main_uri, 4, 17),
+            twice(&util_uri, 0, 4),
+            twice(&util_uri, 2, 26),
+            twice(&util_uri, 2, 33),
+        ],
+    );
+    assert!(builtin.is_err(), "renaming println should fail: {:?}", builtin);
+}
+
+#[test]
+fn test_lsp_code_actions() {
+    let project_path = create_test_project("code_actions");
+
+    let util_content = "pub helper(x: Int) -> Int = {\n    y = x + 1\n    y\n}\n";
+    fs::write(project_path.join("util.nos"), util_content).unwrap();
+    let main_content = "type Light = Red | Amber | Green\n\n\
+                        next(l) = match l {\n    Red -> Green\n}\n\n\
+                        main() = println(helper(42))\n";
+    fs::write(project_path.join("main.nos"), main_content).unwrap();
+
+    let mut client = LspClient::new(&require_lsp_binary!());
+    let _ = client.initialize(project_path.to_str().unwrap());
+    client.initialized_and_wait();
+
+    let util_uri = format!("file://{}/util.nos", project_path.display());
+    let main_uri = format!("file://{}/main.nos", project_path.display());
+    client.did_open(&util_uri, util_content);
+    client.did_open(&main_uri, main_content);
+    std::thread::sleep(Duration::from_millis(300));
+
+    let arms = client.code_actions(&main_uri, 2, 0, &[(2, "non-exhaustive patterns: `Amber`, `Green` not covered")]);
+    let unknown = client.code_actions(&main_uri, 6, 17, &[(6, "unknown function `helper`")]);
+    // Not tied to a diagnostic: annotate the binding under the cursor
+    let annotate = client.code_actions(&util_uri, 1, 4, &[]);
+
+    let _ = client.shutdown();
+    client.exit();
+    cleanup_test_project(&project_path);
+
+    assert_eq!(
+        arms,
+        vec![(
+            "Add missing match arms".to_string(),
+            vec![(3, 16, "\n    Amber -> panic(\"not implemented\")\n    Green -> panic(\"not implemented\")".to_string())],
+        )],
+    );
+    assert_eq!(
+        unknown,
+        vec![
+            ("Import `helper` from `util`".to_string(), vec![(0, 0, "use util.{helper}\n\n".to_string())]),
+            ("Create function `helper`".to_string(), vec![(6, 28, "\n\nhelper(arg1) = panic(\"not implemented\")".to_string())]),
+        ],
+    );
+    assert_eq!(
+        annotate,
+        vec![("Add type annotation `y: Int`".to_string(), vec![(1, 5, ": Int".to_string())])],
+    );
+}
@@ -1,12 +1,12 @@
 //! Core REPL engine logic (UI-agnostic).
 
-use std::collections::{HashMap, HashSet, BTreeSet};
+use std::collections::{HashMap, HashSet, BTreeSet, VecDeque};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use nostos_compiler::compile::{Compiler, MvarInitValue};
-use nostos_jit::{JitCompiler, JitConfig};
+use nostos_jit::{spawn_jit_compiler, JitCompiler, JitConfig, JIT_THRESHOLD};
 use nostos_source::SourceManager;
 use crate::CallGraph;
 use crate::session::{extract_dependencies_from_fn, extract_dependencies_from_type};
@@ -16,6 +16,9 @@ use nostos_vm::async_vm::{AsyncVM, AsyncConfig, AsyncSharedState};
 use nostos_vm::{InspectReceiver, InspectEntry, OutputReceiver, PanelCommand, PanelCommandReceiver, ExtensionManager, SendableValue};
 use nostos_vm::{enable_output_capture, disable_output_capture};
 use nostos_vm::process::ThreadSafeValue;
+use nostos_vm::code::ReloadedCode;
+use nostos_vm::trace::{TraceEvent, TraceFlags, TraceReceiver, TraceSender};
+use nostos_vm::Pid;
 use nostos_vm::{ModuleCache, CompiledModuleData};
 use nostos_vm::cache::{cached_to_function, function_to_cached_with_fn_list, CachedModule, CachedMvar, CachedMvarValue};
 use nostos_packages::PackageManager;
@@ -54,6 +57,9 @@ pub enum BrowserItem {
     Metadata { module: String },
 }
 
+/// Trace events kept for readers of [`ReplEngine::trace_events_since`]
+const TRACE_LOG_CAPACITY: usize = 1000;
+
 /// REPL configuration
 pub struct ReplConfig {
     pub enable_jit: bool,
@@ -208,6 +214,13 @@ pub struct ReplEngine {
     module_function_hashes: HashMap<String, HashMap<String, u64>>,
     /// Receiver for inspect() calls from VM
     inspect_receiver: Option<InspectReceiver>,
+    /// Channel for the events of processes traced from the TUI or `nostos connect`
+    trace_sender: TraceSender,
+    trace_receiver: TraceReceiver,
+    /// Recent trace events, shared by the trace panel and `nostos connect` clients
+    trace_log: VecDeque<TraceEvent>,
+    /// Sequence number of the first event in `trace_log`
+    trace_log_start: u64,
     /// Receiver for output (println) from all VM processes
     output_receiver: Option<OutputReceiver>,
     /// Receiver for panel commands from VM (Panel.* calls)
@@ -312,6 +325,7 @@ impl ReplEngine {
         // Setup TUI channels BEFORE getting native indices
         // This ensures the compiler uses the TUI versions of inspect/output/panel
         let inspect_receiver = Some(vm.setup_inspect());
+        let (trace_sender, trace_receiver) = nostos_vm::trace::channel();
         let output_receiver = Some(vm.setup_output());
         // Log the receiver pointer for debugging
         if let Some(ref r) = output_receiver {
@@ -327,6 +341,12 @@ impl ReplEngine {
         }
         let panel_receiver = Some(vm.setup_panel());
         vm.setup_eval();
+        // Baseline and optimizing JIT: hot functions get compiled in the background
+        if config.enable_jit {
+            if let Ok(sender) = spawn_jit_compiler(JitConfig::default()) {
+                vm.enable_tiered_jit(sender, JIT_THRESHOLD);
+            }
+        }
 
         // Initialize compiler with native indices AFTER all TUI setup is done
         // This ensures CallNativeIdx optimization uses the correct TUI versions
@@ -795,6 +815,10 @@ impl ReplEngine {
             last_known_signatures: HashMap::new(),
             module_function_hashes: HashMap::new(),
             inspect_receiver,
+            trace_sender,
+            trace_receiver,
+            trace_log: VecDeque::new(),
+            trace_log_start: 0,
             output_receiver,
             panel_receiver,
             registered_mvars: HashSet::new(),
@@ -1387,6 +1411,24 @@ impl ReplEngine {
         Ok(())
     }
 
+    /// Upgrade a file-backed module in place: recompile it from its source file
+    /// and install it into the running VM. Processes keep running; they move to
+    /// the new code at their next fully-qualified call (see `nostos_vm::code`).
+    /// Returns the module's new version.
+    pub fn upgrade_module(&mut self, module_name: &str) -> Result<u64, String> {
+        let path = self.module_sources.get(module_name).cloned()
+            .ok_or_else(|| format!("no module named {}", module_name))?;
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.recompile_module_with_content(module_name, &content)?;
+        let code = ReloadedCode {
+            function_names: self.compiler.get_function_list_names().to_vec(),
+            functions: self.compiler.get_all_functions().clone(),
+            types: self.compiler.get_vm_types(),
+        };
+        Ok(self.vm.install_code(module_name, code))
+    }
+
     /// Reload all previously loaded files
     pub fn reload_files(&mut self) -> Result<usize, String> {
         let files = self.loaded_files.clone();
@@ -3015,6 +3057,16 @@ impl ReplEngine {
         self.compiler.get_inferred_types_in_range(file_id, start_offset, end_offset)
     }
 
+    /// Get the inferred type of the expression spanning exactly `start..end` in a module.
+    /// Used by LSP rename to tell which record type a field access refers to.
+    pub fn get_inferred_type_of_span(&self, module_name: &str, start: usize, end: usize) -> Option<String> {
+        let file_id = self.get_file_id_for_module(module_name)?;
+        self.compiler.get_inferred_types_in_range(file_id, start, end)
+            .into_iter()
+            .find(|(span, _)| span.start == start && span.end == end)
+            .map(|(_, ty)| ty)
+    }
+
     /// Get all debug breakpoints
     pub fn get_breakpoints(&self) -> Vec<String> {
         self.debug_breakpoints.iter().cloned().collect()
@@ -6050,6 +6102,31 @@ impl ReplEngine {
             .collect()
     }
 
+    /// Get (module name, source file path) for all modules (for LSP rename)
+    pub fn get_module_sources(&self) -> Vec<(String, String)> {
+        self.module_sources.iter()
+            .map(|(name, path)| (name.clone(), path.to_string_lossy().to_string()))
+            .collect()
+    }
+
+    /// Modules other than `exclude` that define a public function `name`.
+    /// Used by the LSP quick fix that adds a missing `use`.
+    pub fn get_modules_exporting(&self, name: &str, exclude: &str) -> Vec<String> {
+        let mut modules: Vec<String> = self.compiler.get_function_names().into_iter()
+            .filter(|qualified| self.compiler.is_function_public(qualified))
+            .filter_map(|qualified| {
+                let base = qualified.split('/').next().unwrap_or(qualified);
+                let (module, fn_name) = base.rsplit_once('.')?;
+                // Skip builtins and type methods (`File.read`, `Shape.area`)
+                let is_module = module.split('.').all(|part| part.starts_with(|c: char| c.is_lowercase()));
+                (fn_name == name && is_module && module != exclude).then(|| module.to_string())
+            })
+            .collect();
+        modules.sort();
+        modules.dedup();
+        modules
+    }
+
     /// Get count of prelude imports (for debugging LSP)
     pub fn get_prelude_imports_count(&self) -> usize {
         self.compiler.get_prelude_imports().len()
@@ -7298,7 +7375,7 @@ impl ReplEngine {
                     }
                 }
                 "Server" => match method {
-                    "bind" => Some("Server".to_string()),
+                    "bind" | "bindTls" => Some("Server".to_string()),
                     "accept" => Some("HttpRequest".to_string()),
                     "close" => Some("()".to_string()),
                     _ => None,
@@ -9016,6 +9093,47 @@ impl ReplEngine {
         entries
     }
 
+    /// Trace a live process to the trace panel. `pid` is its number as shown by
+    /// `self()`; `flags` are `Trace.start` flags, all events if empty.
+    pub fn trace_process(&self, pid: &str, flags: &[&str]) -> Result<(), String> {
+        let pid = parse_pid(pid)?;
+        let flags = if flags.is_empty() {
+            TraceFlags::from_names(["all"])?
+        } else {
+            TraceFlags::from_names(flags.iter().copied())?
+        };
+        if self.vm.trace_process(pid, flags, self.trace_sender.clone()) {
+            Ok(())
+        } else {
+            Err(format!("no process {}", pid))
+        }
+    }
+
+    /// Stop tracing a process.
+    pub fn stop_trace(&self, pid: &str) -> Result<(), String> {
+        let pid = parse_pid(pid)?;
+        if self.vm.stop_trace(pid) {
+            Ok(())
+        } else {
+            Err(format!("no process {}", pid))
+        }
+    }
+
+    /// Trace events from sequence number `since` on (non-blocking), and the
+    /// sequence number to pass next time. Each reader (the trace panel, every
+    /// `nostos connect` client) keeps its own position; only the last
+    /// `TRACE_LOG_CAPACITY` events are kept.
+    pub fn trace_events_since(&mut self, since: u64) -> (Vec<TraceEvent>, u64) {
+        self.trace_log.extend(self.trace_receiver.try_iter());
+        while self.trace_log.len() > TRACE_LOG_CAPACITY {
+            self.trace_log.pop_front();
+            self.trace_log_start += 1;
+        }
+        let next = self.trace_log_start + self.trace_log.len() as u64;
+        let skip = since.saturating_sub(self.trace_log_start) as usize;
+        (self.trace_log.iter().skip(skip).cloned().collect(), next)
+    }
+
     /// Debug logging disabled. Uncomment to enable file logging.
     #[allow(unused)]
     fn output_log(_msg: &str) {
@@ -9250,6 +9368,25 @@ impl ReplEngine {
                     Err(format!("No breakpoint on: {}", args))
                 }
             }
+            ":trace" => {
+                let mut words = args.split_whitespace();
+                match words.next() {
+                    None => Err("Usage: :trace <pid> [send|receive|call|return|spawn|exit|all ...]".to_string()),
+                    Some(pid) => {
+                        let flags: Vec<&str> = words.collect();
+                        self.trace_process(pid, &flags)?;
+                        let shown = if flags.is_empty() { "all".to_string() } else { flags.join(", ") };
+                        Ok(format!("Tracing {} ({})", pid, shown))
+                    }
+                }
+            }
+            ":untrace" => {
+                if args.is_empty() {
+                    Err("Usage: :untrace <pid>".to_string())
+                } else {
+                    self.stop_trace(args).map(|_| format!("Stopped tracing {}", args))
+                }
+            }
             ":tutorial" | ":tut" => {
                 // Return a special signal that the TUI intercepts to open the tutorial panel
                 Ok("__OPEN_TUTORIAL__".to_string())
@@ -9638,6 +9775,12 @@ impl ReplEngine {
     /// Start async evaluation with debugging enabled
     /// Returns a DebugSession that can be used to control execution
     pub fn start_debug_async(&mut self, input: &str) -> Result<nostos_vm::DebugSession, String> {
+        self.start_debug_session(input, false)
+    }
+
+    /// Start async evaluation with debugging enabled. With `debug_spawned`,
+    /// processes spawned by the evaluation are debugged too (see `DebugHub`).
+    pub fn start_debug_session(&mut self, input: &str, debug_spawned: bool) -> Result<nostos_vm::DebugSession, String> {
         let input = input.trim();
 
         // Handle REPL commands synchronously
@@ -9717,7 +9860,7 @@ impl ReplEngine {
 
         // Start debug session
         let fn_name = format!("{}/", eval_name);
-        self.vm.run_debug(&fn_name)
+        self.vm.run_debug_with(&fn_name, debug_spawned)
     }
 
     /// Get the breakpoints as Breakpoint structs for the VM
@@ -9793,6 +9936,7 @@ impl ReplEngine {
             num_threads: self.config.num_threads,
             reductions_per_yield: 100,
             profiling_enabled: true,
+            ..Default::default()
         };
         let mut async_vm = AsyncVM::new(async_config);
         async_vm.register_default_natives();
@@ -9935,6 +10079,9 @@ impl ReplEngine {
   :demo            Load demo folder (demo/*.nos)
   :load <file>     Load a .nos file
   :profile <expr>  Run expression with profiling (JIT functions show as [JIT])
+  :trace <pid> [flags]  Trace a process to the trace panel (flags: send receive
+                   call return spawn exit all; default all)
+  :untrace <pid>   Stop tracing a process
   :tutorial, :tut  Open the tutorial (TUI only)
   :quit, :q        Exit (TUI only)
 
@@ -10174,6 +10321,7 @@ Keyboard shortcuts (TUI):
 mod tests {
     use super::*;
     use std::io::Write;
+    use nostos_vm::trace::TraceKind;
 
     #[test]
     fn test_single_file_compile_status() {
@@ -10446,6 +10594,66 @@ mod tests {
         fs::remove_dir_all(&temp_dir).ok();
     }
 
+    #[test]
+    fn test_upgrade_module_keeps_process_running() {
+        let temp_dir = std::env::temp_dir().join(format!("test_upgrade_{}", std::process::id()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("nostos.toml"), "[project]\nname = \"test\"\n").unwrap();
+        let v1 = "pub loop(n) = receive {\n    (\"ask\", pid) -> {\n        pid <- n\n        loop(n + 1)\n    }\n    (\"upgrade\", _) -> ticker.loop(n)\n}\n";
+        fs::write(temp_dir.join("ticker.nos"), v1).unwrap();
+
+        let config = ReplConfig { enable_jit: false, num_threads: 1 };
+        let mut engine = ReplEngine::new(config);
+        engine.load_stdlib().ok();
+        engine.load_directory(temp_dir.to_str().unwrap()).unwrap();
+
+        let ask = "match Process.whereis(\"t\") { Some(p) -> { p <- (\"ask\", self())\n receive { n -> n } }\n None -> 0 }";
+        engine.eval("Process.register(\"t\", spawn { ticker.loop(1) })").unwrap();
+        assert_eq!(engine.eval(ask).unwrap(), "1");
+
+        let v2 = format!("pub codeChange(n) = n * 1000\n\n{}", v1.replace("pid <- n", "pid <- 0 - n"));
+        fs::write(temp_dir.join("ticker.nos"), v2).unwrap();
+        assert_eq!(engine.upgrade_module("ticker"), Ok(1));
+
+        // The loop's unqualified self-call stays on the old code
+        assert_eq!(engine.eval(ask).unwrap(), "2");
+        // The qualified self-call moves it to the new code, migrating its state
+        engine.eval("match Process.whereis(\"t\") { Some(p) -> p <- (\"upgrade\", self())\n None -> () }").unwrap();
+        assert_eq!(engine.eval(ask).unwrap(), "-3000");
+
+        assert!(engine.upgrade_module("missing").is_err());
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_trace_process_to_panel_channel() {
+        let config = ReplConfig { enable_jit: false, num_threads: 1 };
+        let mut engine = ReplEngine::new(config);
+        engine.load_stdlib().ok();
+        engine.eval("echo() = receive { (pid, n) -> { pid <- n * 2\n echo() } }").unwrap();
+        engine.eval("Process.register(\"echo\", spawn { echo() })").unwrap();
+        let pid = engine.eval("match Process.whereis(\"echo\") { Some(p) -> p\n None -> self() }").unwrap();
+        let ask = |n: i64| format!("match Process.whereis(\"echo\") {{ Some(p) -> {{ p <- (self(), {})\n receive {{ n -> n }} }}\n None -> 0 }}", n);
+
+        engine.trace_process(&pid, &["receive", "send"]).unwrap();
+        assert_eq!(engine.eval(&ask(21)).unwrap(), "42");
+        let (events, next) = engine.trace_events_since(0);
+        let events: Vec<(TraceKind, String)> = events.into_iter()
+            .map(|event| (event.kind, event.detail))
+            .collect();
+        assert_eq!(events.len(), 2, "{:?}", events);
+        assert_eq!(events[0].0, TraceKind::Receive);
+        assert_eq!(events[1], (TraceKind::Send, "42".to_string()));
+
+        engine.stop_trace(&pid).unwrap();
+        assert_eq!(engine.eval(&ask(1)).unwrap(), "2");
+        assert!(engine.trace_events_since(next).0.is_empty());
+        assert_eq!(engine.trace_events_since(0).0.len(), 2);
+
+        assert!(engine.trace_process(&pid, &["bogus"]).is_err());
+        assert!(engine.trace_process("999999", &[]).is_err());
+    }
+
     #[test]
     fn test_qualified_call_dependency_propagation() {
         // Test that direct qualified calls (good.multiply()) track dependencies correctly
@@ -15563,6 +15771,12 @@ fn visit_dirs(dir: &std::path::Path, files: &mut Vec<PathBuf>) -> Result<(), Str
     Ok(())
 }
 
+/// A local pid as typed in the TUI: `5`, or `<pid:5>` as the REPL shows it.
+fn parse_pid(text: &str) -> Result<Pid, String> {
+    let digits = text.trim().trim_start_matches("<pid").trim_end_matches('>').trim_start_matches(':').trim();
+    digits.parse().map(Pid).map_err(|_| format!("not a pid: {}", text.trim()))
+}
+
 /// Sort stdlib files in dependency order based on `use stdlib.*` imports.
 /// Files with no stdlib dependencies come first, then files that depend on them, etc.
 fn sort_by_dependencies(files: Vec<PathBuf>) -> Vec<PathBuf> {
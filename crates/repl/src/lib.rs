@@ -28,6 +28,8 @@ pub mod callgraph;
 pub mod session;
 pub mod engine;
 pub mod inference;
+pub mod symbols;
+pub mod quickfix;
 
 #[cfg(test)]
 mod repl_tests;
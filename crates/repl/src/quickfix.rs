@@ -0,0 +1,547 @@
+//! Quick fixes for compile errors, for editor code actions.
+//!
+//! The compiler reports an error as a line and a message. A fix finds the AST
+//! node the error is about on that line (the incomplete `match`, the call of
+//! the unknown function, ...) and edits the source at that node's spans, so
+//! the result doesn't depend on how the code is laid out.
+
+use nostos_syntax::ast::*;
+use nostos_syntax::Span;
+
+/// A fix: a set of edits to one source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickFix {
+    pub title: String,
+    pub edits: Vec<FixEdit>,
+}
+
+/// Replace the text at `span` (empty for an insertion) with `new_text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixEdit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+/// Body of generated match arms and function stubs.
+const PLACEHOLDER: &str = "panic(\"not implemented\")";
+
+/// Quick fixes for the compile error `message` reported on `line` (0-based)
+/// of `source`, which parses to `module`.
+///
+/// `exporters(name)` lists the modules, other than this one, that export a
+/// function `name`; each one gets a fix that imports it.
+pub fn fixes_for_error(
+    source: &str,
+    module: &Module,
+    line: usize,
+    message: &str,
+    exporters: &dyn Fn(&str) -> Vec<String>,
+) -> Vec<QuickFix> {
+    let nodes = Nodes::collect(module);
+    let lines = LineIndex::new(source);
+    let mut fixes = Vec::new();
+
+    if let Some(patterns) = missing_patterns(message) {
+        fixes.extend(add_match_arms(source, &nodes, &lines, line, &patterns));
+    } else if let Some(name) = unknown_name(message) {
+        for module_path in exporters(name) {
+            fixes.push(add_import(source, module, &module_path, name));
+        }
+        fixes.extend(create_function(source, module, &nodes, &lines, line, name));
+    }
+    fixes
+}
+
+/// Annotate the binding or parameter at `offset` with its inferred type.
+/// `type_of(span)` is the inferred type of the expression or pattern at `span`.
+pub fn add_type_annotation(
+    module: &Module,
+    offset: usize,
+    type_of: &dyn Fn(Span) -> Option<String>,
+) -> Option<QuickFix> {
+    let nodes = Nodes::collect(module);
+    let contains = |span: Span| span.start <= offset && offset <= span.end;
+
+    let (name, ty) = if let Some(binding) = nodes.lets.iter().find(|b| b.ty.is_none() && contains(b.pattern.span())) {
+        let Pattern::Var(name) = &binding.pattern else { return None };
+        (name, type_of(binding.value.span())?)
+    } else {
+        let param = nodes.params.iter().find(|p| p.ty.is_none() && contains(p.pattern.span()))?;
+        let Pattern::Var(name) = &param.pattern else { return None };
+        (name, type_of(name.span)?)
+    };
+    // Unresolved type variables can't be written down
+    if ty.is_empty() || ty.contains('?') {
+        return None;
+    }
+
+    Some(QuickFix {
+        title: format!("Add type annotation `{}: {}`", name.node, ty),
+        edits: vec![insert(name.span.end, format!(": {}", ty))],
+    })
+}
+
+/// The patterns listed by a "non-exhaustive patterns: ... not covered" error.
+fn missing_patterns(message: &str) -> Option<Vec<String>> {
+    let list = message.strip_prefix("non-exhaustive patterns: ")?.strip_suffix(" not covered")?;
+    if list.contains('`') {
+        // `A(_, _)`, `B`
+        Some(list.split('`').skip(1).step_by(2).map(String::from).collect())
+    } else {
+        // true, false
+        Some(list.split(", ").map(String::from).collect())
+    }
+}
+
+/// The name in an unknown function or variable error, unless it's qualified.
+fn unknown_name(message: &str) -> Option<&str> {
+    let rest = ["unknown function `", "unknown variable `", "cannot find value `"]
+        .iter()
+        .find_map(|prefix| message.strip_prefix(prefix))?;
+    let name = &rest[..rest.find('`')?];
+    let is_ident = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    is_ident.then_some(name)
+}
+
+/// Add arms for `patterns` to the `match` on `line`.
+fn add_match_arms(source: &str, nodes: &Nodes, lines: &LineIndex, line: usize, patterns: &[String]) -> Option<QuickFix> {
+    // The error is reported at the start of the match; prefer the innermost
+    // match starting on the line, then the innermost one containing it.
+    let matches = nodes.exprs.iter().filter_map(|expr| match expr {
+        Expr::Match(_, arms, span) if !arms.is_empty() => Some((arms, *span)),
+        _ => None,
+    });
+    let line_span = lines.span(line);
+    let (arms, span) = matches
+        .clone()
+        .filter(|(_, span)| lines.line_of(span.start) == line)
+        .last()
+        .or_else(|| {
+            matches
+                .filter(|(_, span)| span.start <= line_span.start && line_span.end <= span.end)
+                .min_by_key(|(_, span)| span.end - span.start)
+        })?;
+
+    let last = arms.last()?;
+    let last_line = lines.line_of(last.pattern.span().start);
+    let new_arms: Vec<String> = patterns.iter().map(|p| format!("{} -> {}", p, PLACEHOLDER)).collect();
+    let new_text = if last_line == lines.line_of(span.start) {
+        // match x { A -> 1, B -> 2 }
+        format!(", {}", new_arms.join(", "))
+    } else {
+        let indent = lines.indent(source, last_line);
+        let separator = format!("\n{}", indent);
+        format!("{}{}", separator, new_arms.join(&separator))
+    };
+
+    let title = if patterns.len() == 1 { "Add missing match arm" } else { "Add missing match arms" };
+    Some(QuickFix { title: title.to_string(), edits: vec![insert(last.span.end, new_text)] })
+}
+
+/// Import `name` from `module_path`, extending an existing `use` of that module.
+fn add_import(source: &str, module: &Module, module_path: &str, name: &str) -> QuickFix {
+    let title = format!("Import `{}` from `{}`", name, module_path);
+    let uses: Vec<&UseStmt> = module.items.iter().filter_map(|item| match item {
+        Item::Use(stmt) => Some(stmt),
+        _ => None,
+    }).collect();
+
+    let same_module = uses.iter().find_map(|stmt| {
+        let path: Vec<&str> = stmt.path.iter().map(|p| p.node.as_str()).collect();
+        match &stmt.imports {
+            UseImports::Named(items) if path.join(".") == module_path => items.last(),
+            _ => None,
+        }
+    });
+    if let Some(last) = same_module {
+        let end = last.alias.as_ref().unwrap_or(&last.name).span.end;
+        return QuickFix { title, edits: vec![insert(end, format!(", {}", name))] };
+    }
+
+    let statement = format!("use {}.{{{}}}", module_path, name);
+    let edit = match uses.last() {
+        Some(stmt) => insert(stmt.span.end, format!("\n{}", statement)),
+        None => {
+            // Before the first item and the comments directly above it
+            let lines = LineIndex::new(source);
+            let first = module.items.iter().map(|item| item.span().start).min().unwrap_or(source.len());
+            let mut line = lines.line_of(first);
+            while line > 0 && lines.text(source, line - 1).trim_start().starts_with('#') {
+                line -= 1;
+            }
+            insert(lines.span(line).start, format!("{}\n\n", statement))
+        }
+    };
+    QuickFix { title, edits: vec![edit] }
+}
+
+/// Create a stub for the unknown function `name` called on `line`, after the
+/// definition containing the call.
+fn create_function(source: &str, module: &Module, nodes: &Nodes, lines: &LineIndex, line: usize, name: &str) -> Option<QuickFix> {
+    let args = nodes.exprs.iter().find_map(|expr| match expr {
+        Expr::Call(callee, _, args, span) if lines.line_of(span.start) == line || lines.line_of(callee.span().start) == line => {
+            matches!(&**callee, Expr::Var(ident) if ident.node == name).then_some((args, callee.span()))
+        }
+        _ => None,
+    });
+    let (args, call) = args?;
+
+    let mut params: Vec<String> = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let param = match arg {
+            CallArg::Named(name, _) => name.node.clone(),
+            CallArg::Positional(Expr::Var(var)) if var.node.starts_with(char::is_lowercase) => var.node.clone(),
+            CallArg::Positional(_) => format!("arg{}", i + 1),
+        };
+        params.push(if params.contains(&param) { format!("arg{}", i + 1) } else { param });
+    }
+
+    let item = innermost_item(&module.items, call.start)?;
+    let indent = lines.indent(source, lines.line_of(item.start));
+    let stub = format!("\n\n{}{}({}) = {}", indent, name, params.join(", "), PLACEHOLDER);
+    Some(QuickFix { title: format!("Create function `{}`", name), edits: vec![insert(item.end, stub)] })
+}
+
+/// Span of the innermost item (looking into nested modules) containing `offset`.
+fn innermost_item(items: &[Item], offset: usize) -> Option<Span> {
+    let item = items.iter().find(|item| item.span().start <= offset && offset < item.span().end)?;
+    match item {
+        Item::ModuleDef(def) => innermost_item(&def.items, offset),
+        _ => Some(item.span()),
+    }
+}
+
+fn insert(offset: usize, new_text: String) -> FixEdit {
+    FixEdit { span: Span::new(offset, offset), new_text }
+}
+
+/// Byte offsets of line starts.
+struct LineIndex {
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        let starts = std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        LineIndex { starts, len: source.len() }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// Span of `line`, without the newline.
+    fn span(&self, line: usize) -> Span {
+        let line = line.min(self.starts.len() - 1);
+        let end = self.starts.get(line + 1).map(|next| next - 1).unwrap_or(self.len);
+        Span::new(self.starts[line], end)
+    }
+
+    fn text<'s>(&self, source: &'s str, line: usize) -> &'s str {
+        let span = self.span(line);
+        &source[span.start..span.end]
+    }
+
+    fn indent<'s>(&self, source: &'s str, line: usize) -> &'s str {
+        let text = self.text(source, line);
+        &text[..text.len() - text.trim_start().len()]
+    }
+}
+
+/// The expressions, bindings and parameters of a module, in source order.
+#[derive(Default)]
+struct Nodes<'a> {
+    exprs: Vec<&'a Expr>,
+    lets: Vec<&'a Binding>,
+    params: Vec<&'a FnParam>,
+}
+
+impl<'a> Nodes<'a> {
+    fn collect(module: &'a Module) -> Self {
+        let mut nodes = Nodes::default();
+        nodes.items(&module.items);
+        nodes
+    }
+
+    fn items(&mut self, items: &'a [Item]) {
+        for item in items {
+            match item {
+                Item::FnDef(def) => self.fn_def(def),
+                Item::TraitDef(def) => {
+                    for method in &def.methods {
+                        self.params(&method.params);
+                        if let Some(body) = &method.default_impl {
+                            self.expr(body);
+                        }
+                    }
+                }
+                Item::TraitImpl(def) => {
+                    for method in &def.methods {
+                        self.fn_def(method);
+                    }
+                }
+                Item::ModuleDef(def) => self.items(&def.items),
+                Item::Binding(binding) => {
+                    self.lets.push(binding);
+                    self.expr(&binding.value);
+                }
+                Item::MvarDef(def) => self.expr(&def.value),
+                Item::ConstDef(def) => self.expr(&def.value),
+                Item::Test(test) => self.expr(&test.body),
+                Item::TypeDef(_) | Item::Use(_) | Item::Extern(_) => {}
+            }
+        }
+    }
+
+    fn fn_def(&mut self, def: &'a FnDef) {
+        for clause in &def.clauses {
+            self.params(&clause.params);
+            if let Some(guard) = &clause.guard {
+                self.expr(guard);
+            }
+            self.expr(&clause.body);
+        }
+    }
+
+    fn params(&mut self, params: &'a [FnParam]) {
+        for param in params {
+            self.params.push(param);
+            if let Some(default) = &param.default {
+                self.expr(default);
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: impl IntoIterator<Item = &'a Expr>) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn arms(&mut self, arms: &'a [MatchArm]) {
+        for arm in arms {
+            if let Some(guard) = &arm.guard {
+                self.expr(guard);
+            }
+            self.expr(&arm.body);
+        }
+    }
+
+    fn call_args(&mut self, args: &'a [CallArg]) {
+        for arg in args {
+            match arg {
+                CallArg::Positional(expr) | CallArg::Named(_, expr) => self.expr(expr),
+            }
+        }
+    }
+
+    fn record_fields(&mut self, fields: &'a [RecordField]) {
+        for field in fields {
+            match field {
+                RecordField::Positional(expr) | RecordField::Named(_, expr) => self.expr(expr),
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &'a Expr) {
+        self.exprs.push(expr);
+        match expr {
+            Expr::Record(_, fields, _) => self.record_fields(fields),
+            Expr::RecordUpdate(_, base, fields, _) => {
+                self.expr(base);
+                self.record_fields(fields);
+            }
+            Expr::FieldAccess(receiver, _, _) => self.expr(receiver),
+            Expr::MethodCall(receiver, _, args, _) => {
+                self.expr(receiver);
+                self.call_args(args);
+            }
+            Expr::Call(callee, _, args, _) => {
+                self.expr(callee);
+                self.call_args(args);
+            }
+            Expr::Lambda(_, body, _) => self.expr(body),
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                self.arms(arms);
+            }
+            Expr::Block(stmts, _) => {
+                for stmt in stmts {
+                    match stmt {
+                        Stmt::Expr(expr) => self.expr(expr),
+                        Stmt::Let(binding) => {
+                            self.lets.push(binding);
+                            self.expr(&binding.value);
+                        }
+                        Stmt::Assign(target, value, _) => {
+                            match target {
+                                AssignTarget::Var(_) => {}
+                                AssignTarget::Field(receiver, _) => self.expr(receiver),
+                                AssignTarget::Index(receiver, index) => self.exprs([&**receiver, index]),
+                            }
+                            self.expr(value);
+                        }
+                    }
+                }
+            }
+            Expr::Do(stmts, _) => {
+                for stmt in stmts {
+                    match stmt {
+                        DoStmt::Bind(_, expr) | DoStmt::Expr(expr) => self.expr(expr),
+                    }
+                }
+            }
+            Expr::Try(body, arms, finally, _) => {
+                self.expr(body);
+                self.arms(arms);
+                if let Some(finally) = finally {
+                    self.expr(finally);
+                }
+            }
+            Expr::Receive(arms, after, _) => {
+                self.arms(arms);
+                if let Some((timeout, body)) = after {
+                    self.exprs([&**timeout, body]);
+                }
+            }
+            Expr::For(_, start, end, body, _) => self.exprs([&**start, end, body]),
+            Expr::If(cond, then, otherwise, _) => self.exprs([&**cond, then, otherwise]),
+            Expr::BinOp(left, _, right, _) | Expr::Index(left, right, _) | Expr::Send(left, right, _)
+            | Expr::While(left, right, _) => self.exprs([&**left, right]),
+            Expr::UnaryOp(_, inner, _) | Expr::Try_(inner, _) | Expr::Quote(inner, _) | Expr::Splice(inner, _) => {
+                self.expr(inner)
+            }
+            Expr::Break(value, _) | Expr::Return(value, _) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Expr::Tuple(items, _) | Expr::Set(items, _) => self.exprs(items),
+            Expr::List(items, tail, _) => {
+                self.exprs(items);
+                if let Some(tail) = tail {
+                    self.expr(tail);
+                }
+            }
+            Expr::Map(entries, _) => {
+                for (key, value) in entries {
+                    self.exprs([key, value]);
+                }
+            }
+            Expr::Spawn(_, func, args, _) => {
+                self.expr(func);
+                self.exprs(args);
+            }
+            Expr::String(StringLit::Interpolated(parts), _) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(source: &str, fix: &QuickFix) -> String {
+        let mut result = source.to_string();
+        let mut edits = fix.edits.clone();
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.span.start));
+        for edit in edits {
+            result.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+        }
+        result
+    }
+
+    fn fixes_at(source: &str, line: usize, message: &str, exporters: &[&str]) -> Vec<QuickFix> {
+        let (module, errors) = nostos_syntax::parse(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let exporters = |_: &str| exporters.iter().map(|m| m.to_string()).collect();
+        fixes_for_error(source, &module.unwrap(), line, message, &exporters)
+    }
+
+    #[test]
+    fn test_add_missing_match_arms() {
+        let source = "type Shape = Circle(Float) | Rect(Float, Float) | Blank\n\
+                      \n\
+                      area(s) = match s {\n    Circle(r) -> r * r\n}\n";
+        let fixes = fixes_at(source, 2, "non-exhaustive patterns: `Rect(_, _)`, `Blank` not covered", &[]);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].title, "Add missing match arms");
+        assert_eq!(
+            apply(source, &fixes[0]),
+            "type Shape = Circle(Float) | Rect(Float, Float) | Blank\n\
+             \n\
+             area(s) = match s {\n    Circle(r) -> r * r\n    Rect(_, _) -> panic(\"not implemented\")\n    \
+             Blank -> panic(\"not implemented\")\n}\n"
+        );
+
+        let source = "f(b) = {\n    x = match b { true -> 1 }\n    x\n}\n";
+        let fixes = fixes_at(source, 1, "non-exhaustive patterns: false not covered", &[]);
+        assert_eq!(
+            apply(source, &fixes[0]),
+            "f(b) = {\n    x = match b { true -> 1, false -> panic(\"not implemented\") }\n    x\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_import_unknown_function() {
+        let source = "# Shapes\n\n# Entry point\nmain() = helper(1)\n";
+        let fixes = fixes_at(source, 3, "unknown function `helper`", &["utils"]);
+        assert_eq!(fixes[0].title, "Import `helper` from `utils`");
+        assert_eq!(apply(source, &fixes[0]), "# Shapes\n\nuse utils.{helper}\n\n# Entry point\nmain() = helper(1)\n");
+
+        let source = "use utils.{other}\nuse math.*\n\nmain() = helper(1)\n";
+        let fixes = fixes_at(source, 3, "unknown function `helper`", &["utils", "lib.extra"]);
+        assert_eq!(apply(source, &fixes[0]), "use utils.{other, helper}\nuse math.*\n\nmain() = helper(1)\n");
+        assert_eq!(
+            apply(source, &fixes[1]),
+            "use utils.{other}\nuse math.*\nuse lib.extra.{helper}\n\nmain() = helper(1)\n"
+        );
+    }
+
+    #[test]
+    fn test_create_function_stub() {
+        let source = "module Geo\n    main() = {\n        w = 2\n        area(w, 3, w)\n    }\nend\n";
+        let fixes = fixes_at(source, 3, "unknown function `area`", &[]);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].title, "Create function `area`");
+        assert_eq!(
+            apply(source, &fixes[0]),
+            "module Geo\n    main() = {\n        w = 2\n        area(w, 3, w)\n    }\n\n    \
+             area(w, arg2, arg3) = panic(\"not implemented\")\nend\n"
+        );
+
+        // Not called: nothing to create
+        let source = "main() = helper\n";
+        assert!(fixes_at(source, 0, "unknown variable `helper`", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_add_type_annotation() {
+        let source = "scale(k) = {\n    factor = k * 2\n    (a, b) = (1, 2)\n    factor\n}\n";
+        let (module, _) = nostos_syntax::parse(source);
+        let module = module.unwrap();
+        let type_of = |span: Span| match &source[span.start..span.end] {
+            "k * 2" | "k" => Some("Int".to_string()),
+            _ => Some("?3".to_string()),
+        };
+
+        let fix = add_type_annotation(&module, source.find("factor").unwrap() + 2, &type_of).unwrap();
+        assert_eq!(fix.title, "Add type annotation `factor: Int`");
+        assert!(apply(source, &fix).contains("    factor: Int = k * 2\n"));
+
+        let fix = add_type_annotation(&module, source.find('k').unwrap(), &type_of).unwrap();
+        assert!(apply(source, &fix).starts_with("scale(k: Int) = {"));
+
+        // Destructuring and unresolved types aren't annotated
+        assert!(add_type_annotation(&module, source.find("(a").unwrap() + 1, &type_of).is_none());
+    }
+}
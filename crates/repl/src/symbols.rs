@@ -0,0 +1,1115 @@
+//! Name resolution over the parsed modules of a project, for editor features
+//! like rename.
+//!
+//! Every identifier in the project is resolved to the symbol it refers to,
+//! following the same rules as the compiler: locals shadow module-level names,
+//! names are looked up in the enclosing (nested) modules, then in `use`
+//! imports, and `a.b.f` refers to `f` in module `a.b`. Record fields are
+//! resolved through the constructor or pattern they appear in; for field
+//! access (`p.name`) on a field name shared by several types, the caller
+//! provides the inferred type of the receiver.
+//!
+//! Names that resolve to something outside the given modules (stdlib,
+//! builtins) produce no occurrence, so they can't be renamed.
+
+use std::collections::{HashMap, HashSet};
+
+use nostos_syntax::ast::*;
+use nostos_syntax::{lex, Token};
+
+/// A parsed project module.
+pub struct SourceModule {
+    /// Module path, e.g. `["utils", "math"]` for `utils/math.nos`
+    pub path: Vec<String>,
+    pub module: Module,
+}
+
+/// What an identifier refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    /// Function (all clauses and overloads), mvar, constant or top-level binding
+    Value { module: Vec<String>, name: String },
+    Type { module: Vec<String>, name: String },
+    /// Variant constructor
+    Constructor { module: Vec<String>, name: String },
+    Trait { module: Vec<String>, name: String },
+    /// Field of a record type or of a variant with named fields
+    Field { module: Vec<String>, type_name: String, name: String },
+    /// Trait method, including its implementations
+    TraitMethod { module: Vec<String>, trait_name: String, name: String },
+    /// Local variable, identified by the span of its first binding
+    Local { file: usize, binding: Span },
+    /// Local name of an aliased import (`use m.{f as g}`)
+    ImportAlias { file: usize, name: String },
+}
+
+impl Symbol {
+    /// Whether names of this symbol start with an uppercase letter.
+    fn is_upper(&self) -> bool {
+        matches!(self, Symbol::Type { .. } | Symbol::Constructor { .. } | Symbol::Trait { .. })
+    }
+}
+
+/// How a symbol is written at an occurrence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccurrenceKind {
+    Plain,
+    /// The field of a punned record pattern field (`{name}`)
+    PunnedField,
+    /// The variable bound by a punned record pattern field
+    PunnedBinding,
+}
+
+/// An identifier in a module and the symbol it refers to.
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    /// Index into the modules passed to [`find_occurrences`]
+    pub file: usize,
+    pub span: Span,
+    /// The identifier as written
+    pub name: String,
+    pub symbol: Symbol,
+    pub kind: OccurrenceKind,
+}
+
+/// A text replacement produced by [`rename`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenameEdit {
+    pub file: usize,
+    pub span: Span,
+    pub new_text: String,
+}
+
+/// Resolve all identifiers in `modules`.
+///
+/// `receiver_type` returns the inferred type name of an expression (by file
+/// index and span), if known. It is only asked for field access on fields
+/// that more than one type has.
+pub fn find_occurrences(modules: &[SourceModule], receiver_type: &dyn Fn(usize, Span) -> Option<String>) -> Vec<Occurrence> {
+    let mut index = Index::default();
+    for module in modules {
+        index.add_items(&module.path, &module.module.items);
+    }
+
+    let mut out = Vec::new();
+    for (file, module) in modules.iter().enumerate() {
+        let mut walker = Walker {
+            index: &index,
+            file,
+            module: module.path.clone(),
+            file_module_len: module.path.len(),
+            imports: HashMap::new(),
+            globs: Vec::new(),
+            scopes: Vec::new(),
+            receiver_type,
+            out: &mut out,
+        };
+        walker.collect_imports(&module.module.items);
+        walker.items(&module.module.items);
+    }
+    out
+}
+
+/// The occurrence at byte `offset` of module `file`, if any.
+/// An offset just past the end of an identifier counts as on it.
+pub fn symbol_at(occurrences: &[Occurrence], file: usize, offset: usize) -> Option<&Occurrence> {
+    let in_file = || occurrences.iter().filter(|o| o.file == file);
+    in_file().find(|o| o.span.start <= offset && offset < o.span.end)
+        .or_else(|| in_file().find(|o| o.span.end == offset))
+}
+
+/// Compute the edits that rename the symbol at `offset` in module `file` to `new_name`.
+pub fn rename(
+    modules: &[SourceModule],
+    file: usize,
+    offset: usize,
+    new_name: &str,
+    receiver_type: &dyn Fn(usize, Span) -> Option<String>,
+) -> Result<Vec<RenameEdit>, String> {
+    let occurrences = find_occurrences(modules, receiver_type);
+    let target = symbol_at(&occurrences, file, offset)
+        .ok_or_else(|| "No renameable symbol at this position".to_string())?;
+    check_name(&target.symbol, new_name)?;
+
+    let mut edits: Vec<RenameEdit> = Vec::new();
+    for occurrence in occurrences.iter().filter(|o| o.symbol == target.symbol) {
+        let new_text = match occurrence.kind {
+            OccurrenceKind::Plain => new_name.to_string(),
+            // `{name}` becomes `{new: name}` or `{name: new}`
+            OccurrenceKind::PunnedField => format!("{}: {}", new_name, occurrence.name),
+            OccurrenceKind::PunnedBinding => format!("{}: {}", occurrence.name, new_name),
+        };
+        let edit = RenameEdit { file: occurrence.file, span: occurrence.span, new_text };
+        if !edits.contains(&edit) {
+            edits.push(edit);
+        }
+    }
+    Ok(edits)
+}
+
+/// Check that `name` is a valid new name for `symbol`.
+pub fn check_name(symbol: &Symbol, name: &str) -> Result<(), String> {
+    let tokens: Vec<_> = lex(name).collect();
+    let upper = match tokens.as_slice() {
+        [(Token::UpperIdent(_), span)] if span.len() == name.len() => true,
+        [(Token::LowerIdent(_), span)] if span.len() == name.len() => false,
+        _ => return Err(format!("'{}' is not a valid identifier", name)),
+    };
+    match (symbol.is_upper(), upper) {
+        (true, false) => Err(format!("'{}' must start with an uppercase letter", name)),
+        (false, true) => Err(format!("'{}' must start with a lowercase letter", name)),
+        _ => Ok(()),
+    }
+}
+
+/// Module-level definitions of all modules.
+#[derive(Default)]
+struct Index {
+    modules: HashSet<Vec<String>>,
+    values: HashSet<(Vec<String>, String)>,
+    types: HashSet<(Vec<String>, String)>,
+    /// Variant constructors and the type they belong to
+    constructors: HashMap<(Vec<String>, String), String>,
+    traits: HashSet<(Vec<String>, String)>,
+    /// Field name -> (module, type) of the types that have it
+    fields: HashMap<String, Vec<(Vec<String>, String)>>,
+    /// Method name -> (module, trait) of the traits that declare it
+    trait_methods: HashMap<String, Vec<(Vec<String>, String)>>,
+}
+
+impl Index {
+    fn add_items(&mut self, module: &[String], items: &[Item]) {
+        self.modules.insert(module.to_vec());
+        let key = |name: &Ident| (module.to_vec(), name.node.clone());
+        for item in items {
+            match item {
+                Item::FnDef(def) => {
+                    self.values.insert(key(&def.name));
+                }
+                Item::MvarDef(def) => {
+                    self.values.insert(key(&def.name));
+                }
+                Item::ConstDef(def) => {
+                    self.values.insert(key(&def.name));
+                }
+                Item::Binding(binding) => {
+                    if let Pattern::Var(name) = &binding.pattern {
+                        self.values.insert(key(name));
+                    }
+                }
+                Item::Extern(decl) => match &decl.kind {
+                    ExternKind::Function { name, .. } => {
+                        self.values.insert(key(name));
+                    }
+                    ExternKind::Type { name } => {
+                        self.types.insert(key(name));
+                    }
+                },
+                Item::TypeDef(def) => {
+                    self.types.insert(key(&def.name));
+                    let owner = (module.to_vec(), def.name.node.clone());
+                    match &def.body {
+                        TypeBody::Record(fields) => self.add_fields(fields, &owner),
+                        TypeBody::Variant(variants) => {
+                            for variant in variants {
+                                self.constructors.insert(key(&variant.name), def.name.node.clone());
+                                if let VariantFields::Named(fields) = &variant.fields {
+                                    self.add_fields(fields, &owner);
+                                }
+                            }
+                        }
+                        TypeBody::Alias(_) | TypeBody::Empty => {}
+                    }
+                }
+                Item::TraitDef(def) => {
+                    self.traits.insert(key(&def.name));
+                    for method in &def.methods {
+                        self.trait_methods.entry(method.name.node.clone()).or_default()
+                            .push((module.to_vec(), def.name.node.clone()));
+                    }
+                }
+                Item::ModuleDef(def) => {
+                    let mut nested = module.to_vec();
+                    nested.push(def.name.node.clone());
+                    self.add_items(&nested, &def.items);
+                }
+                Item::TraitImpl(_) | Item::Use(_) | Item::Test(_) => {}
+            }
+        }
+    }
+
+    fn add_fields(&mut self, fields: &[Field], owner: &(Vec<String>, String)) {
+        for field in fields {
+            let owners = self.fields.entry(field.name.node.clone()).or_default();
+            if !owners.contains(owner) {
+                owners.push(owner.clone());
+            }
+        }
+    }
+
+    /// Look up a module-level name in one module.
+    fn find(&self, module: &[String], name: &str, ns: Namespace) -> Option<Symbol> {
+        let key = (module.to_vec(), name.to_string());
+        let symbol = |make: fn(Vec<String>, String) -> Symbol| Some(make(module.to_vec(), name.to_string()));
+        match ns {
+            Namespace::Value if self.values.contains(&key) => symbol(|module, name| Symbol::Value { module, name }),
+            Namespace::Constructor if self.constructors.contains_key(&key) => symbol(|module, name| Symbol::Constructor { module, name }),
+            Namespace::Constructor | Namespace::Type if self.types.contains(&key) => symbol(|module, name| Symbol::Type { module, name }),
+            Namespace::Type if self.traits.contains(&key) => symbol(|module, name| Symbol::Trait { module, name }),
+            _ => None,
+        }
+    }
+
+    /// The type a constructor or record type name constructs.
+    fn constructed_type(&self, symbol: &Symbol) -> Option<(Vec<String>, String)> {
+        match symbol {
+            Symbol::Type { module, name } => Some((module.clone(), name.clone())),
+            Symbol::Constructor { module, name } => {
+                let type_name = self.constructors.get(&(module.clone(), name.clone()))?;
+                Some((module.clone(), type_name.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Which kind of module-level name an identifier can refer to.
+#[derive(Debug, Clone, Copy)]
+enum Namespace {
+    /// Functions, mvars, constants
+    Value,
+    /// Types and traits (in type expressions)
+    Type,
+    /// Constructors, then record types (in expressions and patterns)
+    Constructor,
+}
+
+/// How a pattern variable that is already in scope is treated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    /// Always a new variable (parameters, match arms, lambdas)
+    New,
+    /// Refers to the existing variable (`x = ...` in a block assigns it again)
+    Rebind,
+    /// Refers to a variable of the innermost scope (later alternatives of an or-pattern)
+    SameScope,
+}
+
+/// A name brought into a module by `use`.
+enum Import {
+    Item(Vec<String>, String),
+    Alias,
+}
+
+/// Walks one file, recording occurrences.
+struct Walker<'a> {
+    index: &'a Index,
+    file: usize,
+    /// Module of the code being walked: the file's module plus nested modules
+    module: Vec<String>,
+    file_module_len: usize,
+    imports: HashMap<String, Import>,
+    globs: Vec<Vec<String>>,
+    scopes: Vec<HashMap<String, Symbol>>,
+    receiver_type: &'a dyn Fn(usize, Span) -> Option<String>,
+    out: &'a mut Vec<Occurrence>,
+}
+
+impl Walker<'_> {
+    fn emit(&mut self, name: &Ident, symbol: Symbol) {
+        self.emit_kind(name, symbol, OccurrenceKind::Plain);
+    }
+
+    fn emit_kind(&mut self, name: &Ident, symbol: Symbol, kind: OccurrenceKind) {
+        self.out.push(Occurrence { file: self.file, span: name.span, name: name.node.clone(), symbol, kind });
+    }
+
+    // ---------------------------------------------------------------------
+    // Lookup
+    // ---------------------------------------------------------------------
+
+    fn collect_imports(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Use(stmt) => {
+                    let path: Vec<String> = stmt.path.iter().map(|p| p.node.clone()).collect();
+                    match &stmt.imports {
+                        UseImports::All => self.globs.push(path),
+                        UseImports::Named(items) => {
+                            for item in items {
+                                let import = match &item.alias {
+                                    Some(alias) => (alias.node.clone(), Import::Alias),
+                                    None => (item.name.node.clone(), Import::Item(path.clone(), item.name.node.clone())),
+                                };
+                                self.imports.insert(import.0, import.1);
+                            }
+                        }
+                    }
+                }
+                Item::ModuleDef(def) => self.collect_imports(&def.items),
+                _ => {}
+            }
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned())
+    }
+
+    /// The enclosing modules, innermost first.
+    fn enclosing_modules(&self) -> Vec<Vec<String>> {
+        (self.file_module_len..=self.module.len()).rev().map(|n| self.module[..n].to_vec()).collect()
+    }
+
+    /// Resolve a module-level name as written in the current module.
+    fn global(&self, name: &str, ns: Namespace) -> Option<Symbol> {
+        for module in self.enclosing_modules() {
+            if let Some(symbol) = self.index.find(&module, name, ns) {
+                return Some(symbol);
+            }
+        }
+        match self.imports.get(name) {
+            Some(Import::Item(module, item)) => return self.index.find(module, item, ns),
+            Some(Import::Alias) => return Some(Symbol::ImportAlias { file: self.file, name: name.to_string() }),
+            None => {}
+        }
+        self.globs.iter().find_map(|module| self.index.find(module, name, ns))
+    }
+
+    /// Resolve a name that is called like a function: a function, else a trait method.
+    fn callable(&self, name: &str) -> Option<Symbol> {
+        self.global(name, Namespace::Value).or_else(|| self.trait_method(name))
+    }
+
+    /// A trait method, if exactly one trait in the project declares it.
+    fn trait_method(&self, name: &str) -> Option<Symbol> {
+        match self.index.trait_methods.get(name).map(Vec::as_slice) {
+            Some([(module, trait_name)]) => Some(Symbol::TraitMethod {
+                module: module.clone(),
+                trait_name: trait_name.clone(),
+                name: name.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The module path an expression like `a.b` or `Outer.Inner` spells, if it is one.
+    fn module_path(&self, expr: &Expr) -> Option<Vec<String>> {
+        let written = self.written_path(expr)?;
+        let mut candidates = Vec::new();
+        for n in (0..=self.module.len()).rev() {
+            let mut candidate = self.module[..n].to_vec();
+            candidate.extend(written.iter().cloned());
+            candidates.push(candidate);
+        }
+        candidates.into_iter().find(|candidate| self.index.modules.contains(candidate))
+    }
+
+    fn written_path(&self, expr: &Expr) -> Option<Vec<String>> {
+        match expr {
+            Expr::Var(name) if self.local(&name.node).is_none() => Some(vec![name.node.clone()]),
+            Expr::Record(name, fields, _) if fields.is_empty() => Some(vec![name.node.clone()]),
+            Expr::FieldAccess(inner, name, _) => {
+                let mut path = self.written_path(inner)?;
+                path.push(name.node.clone());
+                Some(path)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolve a field name, given the type it belongs to if known, or else the receiver expression.
+    fn field(&self, name: &str, owner: Option<(Vec<String>, String)>, receiver: Option<Span>) -> Option<Symbol> {
+        let owners = self.index.fields.get(name)?;
+        let make = |(module, type_name): (Vec<String>, String)| Symbol::Field { module, type_name, name: name.to_string() };
+        if let Some(owner) = owner {
+            return owners.contains(&owner).then(|| make(owner));
+        }
+        if let [owner] = owners.as_slice() {
+            return Some(make(owner.clone()));
+        }
+        // Several types have this field: ask for the receiver's type
+        let ty = (self.receiver_type)(self.file, receiver?)?;
+        let type_name = ty.split('[').next().unwrap_or(&ty).trim();
+        let type_name = type_name.rsplit('.').next().unwrap_or(type_name);
+        owners.iter().find(|(_, name)| name == type_name).cloned().map(make)
+    }
+
+    // ---------------------------------------------------------------------
+    // Items
+    // ---------------------------------------------------------------------
+
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            self.item(item);
+        }
+    }
+
+    fn value_def(&mut self, name: &Ident) {
+        let symbol = Symbol::Value { module: self.module.clone(), name: name.node.clone() };
+        self.emit(name, symbol);
+    }
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::FnDef(def) => {
+                self.value_def(&def.name);
+                self.fn_def(def);
+            }
+            Item::TypeDef(def) => self.type_def(def),
+            Item::TraitDef(def) => {
+                self.emit(&def.name, Symbol::Trait { module: self.module.clone(), name: def.name.node.clone() });
+                for super_trait in &def.super_traits {
+                    self.type_name(super_trait);
+                }
+                for method in &def.methods {
+                    let symbol = Symbol::TraitMethod {
+                        module: self.module.clone(),
+                        trait_name: def.name.node.clone(),
+                        name: method.name.node.clone(),
+                    };
+                    self.emit(&method.name, symbol);
+                    self.scopes.push(HashMap::new());
+                    self.params(&method.params);
+                    if let Some(ty) = &method.return_type {
+                        self.type_expr(ty);
+                    }
+                    if let Some(body) = &method.default_impl {
+                        self.expr(body);
+                    }
+                    self.scopes.pop();
+                }
+            }
+            Item::TraitImpl(imp) => {
+                self.type_expr(&imp.ty);
+                let trait_symbol = self.global(&imp.trait_name.node, Namespace::Type);
+                if let Some(symbol) = &trait_symbol {
+                    self.emit(&imp.trait_name, symbol.clone());
+                }
+                for (_, bounds) in &imp.when_clause {
+                    for bound in bounds {
+                        self.type_name(bound);
+                    }
+                }
+                for method in &imp.methods {
+                    if let Some(Symbol::Trait { module, name }) = &trait_symbol {
+                        let symbol = Symbol::TraitMethod {
+                            module: module.clone(),
+                            trait_name: name.clone(),
+                            name: method.name.node.clone(),
+                        };
+                        self.emit(&method.name, symbol);
+                    }
+                    self.fn_def(method);
+                }
+            }
+            Item::ModuleDef(def) => {
+                self.module.push(def.name.node.clone());
+                self.items(&def.items);
+                self.module.pop();
+            }
+            Item::Use(stmt) => {
+                let path: Vec<String> = stmt.path.iter().map(|p| p.node.clone()).collect();
+                if let UseImports::Named(items) = &stmt.imports {
+                    for item in items {
+                        let name = &item.name.node;
+                        let symbol = self.index.find(&path, name, Namespace::Value)
+                            .or_else(|| self.index.find(&path, name, Namespace::Constructor))
+                            .or_else(|| self.index.find(&path, name, Namespace::Type));
+                        if let Some(symbol) = symbol {
+                            self.emit(&item.name, symbol);
+                        }
+                        if let Some(alias) = &item.alias {
+                            self.emit(alias, Symbol::ImportAlias { file: self.file, name: alias.node.clone() });
+                        }
+                    }
+                }
+            }
+            Item::Binding(binding) => {
+                if let Some(ty) = &binding.ty {
+                    self.type_expr(ty);
+                }
+                self.expr(&binding.value);
+                if let Pattern::Var(name) = &binding.pattern {
+                    self.value_def(name);
+                }
+            }
+            Item::MvarDef(def) => {
+                self.value_def(&def.name);
+                self.type_expr(&def.ty);
+                self.expr(&def.value);
+            }
+            Item::ConstDef(def) => {
+                self.value_def(&def.name);
+                self.expr(&def.value);
+            }
+            Item::Test(test) => {
+                self.scopes.push(HashMap::new());
+                self.expr(&test.body);
+                self.scopes.pop();
+            }
+            Item::Extern(decl) => match &decl.kind {
+                ExternKind::Function { name, params, return_type, .. } => {
+                    self.value_def(name);
+                    for (_, ty) in params {
+                        self.type_expr(ty);
+                    }
+                    self.type_expr(return_type);
+                }
+                ExternKind::Type { name } => {
+                    self.emit(name, Symbol::Type { module: self.module.clone(), name: name.node.clone() });
+                }
+            },
+        }
+    }
+
+    fn type_def(&mut self, def: &TypeDef) {
+        let module = self.module.clone();
+        self.emit(&def.name, Symbol::Type { module: module.clone(), name: def.name.node.clone() });
+        let field_def = |walker: &mut Self, field: &Field| {
+            let symbol = Symbol::Field { module: module.clone(), type_name: def.name.node.clone(), name: field.name.node.clone() };
+            walker.emit(&field.name, symbol);
+            walker.type_expr(&field.ty);
+        };
+        match &def.body {
+            TypeBody::Record(fields) => {
+                for field in fields {
+                    field_def(self, field);
+                }
+            }
+            TypeBody::Variant(variants) => {
+                for variant in variants {
+                    self.emit(&variant.name, Symbol::Constructor { module: module.clone(), name: variant.name.node.clone() });
+                    match &variant.fields {
+                        VariantFields::Unit => {}
+                        VariantFields::Positional(types) => {
+                            for ty in types {
+                                self.type_expr(ty);
+                            }
+                        }
+                        VariantFields::Named(fields) => {
+                            for field in fields {
+                                field_def(self, field);
+                            }
+                        }
+                    }
+                }
+            }
+            TypeBody::Alias(ty) => self.type_expr(ty),
+            TypeBody::Empty => {}
+        }
+    }
+
+    fn fn_def(&mut self, def: &FnDef) {
+        for param in &def.type_params {
+            for constraint in &param.constraints {
+                self.type_name(constraint);
+            }
+        }
+        for clause in &def.clauses {
+            self.scopes.push(HashMap::new());
+            self.params(&clause.params);
+            if let Some(guard) = &clause.guard {
+                self.expr(guard);
+            }
+            if let Some(ty) = &clause.return_type {
+                self.type_expr(ty);
+            }
+            self.expr(&clause.body);
+            self.scopes.pop();
+        }
+    }
+
+    fn params(&mut self, params: &[FnParam]) {
+        for param in params {
+            if let Some(ty) = &param.ty {
+                self.type_expr(ty);
+            }
+            if let Some(default) = &param.default {
+                self.expr(default);
+            }
+            self.pattern(&param.pattern, Binding::New);
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Types
+    // ---------------------------------------------------------------------
+
+    fn type_name(&mut self, name: &Ident) {
+        if let Some(symbol) = self.global(&name.node, Namespace::Type) {
+            self.emit(name, symbol);
+        }
+    }
+
+    fn type_expr(&mut self, ty: &TypeExpr) {
+        match ty {
+            TypeExpr::Name(name) => self.type_name(name),
+            TypeExpr::Generic(name, args) => {
+                self.type_name(name);
+                for arg in args {
+                    self.type_expr(arg);
+                }
+            }
+            TypeExpr::Function(params, ret) => {
+                for param in params {
+                    self.type_expr(param);
+                }
+                self.type_expr(ret);
+            }
+            TypeExpr::Record(fields) => {
+                for (_, ty) in fields {
+                    self.type_expr(ty);
+                }
+            }
+            TypeExpr::Tuple(types) => {
+                for ty in types {
+                    self.type_expr(ty);
+                }
+            }
+            TypeExpr::Unit => {}
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Patterns
+    // ---------------------------------------------------------------------
+
+    /// Bind a variable, returning its symbol.
+    fn bind(&mut self, name: &Ident, binding: Binding) -> Symbol {
+        let existing = match binding {
+            Binding::New => None,
+            Binding::Rebind => self.local(&name.node),
+            Binding::SameScope => self.scopes.last().and_then(|scope| scope.get(&name.node).cloned()),
+        };
+        let symbol = existing.unwrap_or(Symbol::Local { file: self.file, binding: name.span });
+        if self.scopes.is_empty() {
+            self.scopes.push(HashMap::new());
+        }
+        self.scopes.last_mut().unwrap().insert(name.node.clone(), symbol.clone());
+        symbol
+    }
+
+    fn pattern(&mut self, pattern: &Pattern, binding: Binding) {
+        match pattern {
+            Pattern::Var(name) => {
+                let symbol = self.bind(name, binding);
+                self.emit(name, symbol);
+            }
+            Pattern::Tuple(patterns, _) | Pattern::Set(patterns, _) => {
+                for pattern in patterns {
+                    self.pattern(pattern, binding);
+                }
+            }
+            Pattern::List(ListPattern::Cons(patterns, tail), _) => {
+                for pattern in patterns {
+                    self.pattern(pattern, binding);
+                }
+                if let Some(tail) = tail {
+                    self.pattern(tail, binding);
+                }
+            }
+            Pattern::StringCons(StringPattern::Cons(_, rest), _) => self.pattern(rest, binding),
+            Pattern::Record(fields, _) => self.record_pattern(fields, None, binding),
+            Pattern::Map(entries, _) => {
+                for (key, pattern) in entries {
+                    self.expr(key);
+                    self.pattern(pattern, binding);
+                }
+            }
+            Pattern::Variant(name, fields, _) => {
+                let constructor = self.global(&name.node, Namespace::Constructor);
+                if let Some(symbol) = &constructor {
+                    self.emit(name, symbol.clone());
+                }
+                match fields {
+                    VariantPatternFields::Unit => {}
+                    VariantPatternFields::Positional(patterns) => {
+                        for pattern in patterns {
+                            self.pattern(pattern, binding);
+                        }
+                    }
+                    VariantPatternFields::Named(fields) => {
+                        let owner = constructor.as_ref().and_then(|symbol| self.index.constructed_type(symbol));
+                        self.record_pattern(fields, owner, binding);
+                    }
+                }
+            }
+            Pattern::Pin(expr, _) => self.expr(expr),
+            Pattern::Or(alternatives, _) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    self.pattern(alternative, if i == 0 { binding } else { Binding::SameScope });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn record_pattern(&mut self, fields: &[RecordPatternField], owner: Option<(Vec<String>, String)>, binding: Binding) {
+        for field in fields {
+            match field {
+                RecordPatternField::Punned(name) => {
+                    if let Some(symbol) = self.field(&name.node, owner.clone(), None) {
+                        self.emit_kind(name, symbol, OccurrenceKind::PunnedField);
+                    }
+                    let symbol = self.bind(name, binding);
+                    self.emit_kind(name, symbol, OccurrenceKind::PunnedBinding);
+                }
+                RecordPatternField::Named(name, pattern) => {
+                    if let Some(symbol) = self.field(&name.node, owner.clone(), None) {
+                        self.emit(name, symbol);
+                    }
+                    self.pattern(pattern, binding);
+                }
+                RecordPatternField::Rest(_) => {}
+            }
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Expressions
+    // ---------------------------------------------------------------------
+
+    fn exprs<'e>(&mut self, exprs: impl IntoIterator<Item = &'e Expr>) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn call_args(&mut self, args: &[CallArg]) {
+        for arg in args {
+            match arg {
+                CallArg::Positional(expr) | CallArg::Named(_, expr) => self.expr(expr),
+            }
+        }
+    }
+
+    /// Match arms, each in its own scope.
+    fn arms(&mut self, arms: &[MatchArm]) {
+        for arm in arms {
+            self.scopes.push(HashMap::new());
+            self.pattern(&arm.pattern, Binding::New);
+            if let Some(guard) = &arm.guard {
+                self.expr(guard);
+            }
+            self.expr(&arm.body);
+            self.scopes.pop();
+        }
+    }
+
+    fn record_fields(&mut self, constructor: &Ident, fields: &[RecordField]) {
+        let symbol = self.global(&constructor.node, Namespace::Constructor);
+        if let Some(symbol) = &symbol {
+            self.emit(constructor, symbol.clone());
+        }
+        let owner = symbol.as_ref().and_then(|symbol| self.index.constructed_type(symbol));
+        for field in fields {
+            match field {
+                RecordField::Positional(expr) => self.expr(expr),
+                RecordField::Named(name, expr) => {
+                    if let Some(owner) = &owner {
+                        if let Some(symbol) = self.field(&name.node, Some(owner.clone()), None) {
+                            self.emit(name, symbol);
+                        }
+                    }
+                    self.expr(expr);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Var(name) => {
+                let symbol = match self.local(&name.node) {
+                    Some(local) => Some(local),
+                    None if name.node.starts_with(char::is_uppercase) => self.global(&name.node, Namespace::Constructor),
+                    None => self.callable(&name.node),
+                };
+                if let Some(symbol) = symbol {
+                    self.emit(name, symbol);
+                }
+            }
+            Expr::Record(name, fields, _) => self.record_fields(name, fields),
+            Expr::RecordUpdate(name, base, fields, _) => {
+                self.expr(base);
+                self.record_fields(name, fields);
+            }
+            Expr::FieldAccess(receiver, name, _) => {
+                if let Some(module) = self.module_path(receiver) {
+                    let ns = if name.node.starts_with(char::is_uppercase) { Namespace::Constructor } else { Namespace::Value };
+                    if let Some(symbol) = self.index.find(&module, &name.node, ns) {
+                        self.emit(name, symbol);
+                    }
+                    return;
+                }
+                self.expr(receiver);
+                if let Some(symbol) = self.field(&name.node, None, Some(receiver.span())) {
+                    self.emit(name, symbol);
+                }
+            }
+            Expr::MethodCall(receiver, name, args, _) => {
+                let symbol = match self.module_path(receiver) {
+                    Some(module) => self.index.find(&module, &name.node, Namespace::Value),
+                    None => {
+                        self.expr(receiver);
+                        self.callable(&name.node)
+                    }
+                };
+                if let Some(symbol) = symbol {
+                    self.emit(name, symbol);
+                }
+                self.call_args(args);
+            }
+            Expr::Call(callee, type_args, args, _) => {
+                self.expr(callee);
+                for ty in type_args {
+                    self.type_expr(ty);
+                }
+                self.call_args(args);
+            }
+            Expr::Lambda(params, body, _) => {
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    self.pattern(param, Binding::New);
+                }
+                self.expr(body);
+                self.scopes.pop();
+            }
+            Expr::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                self.arms(arms);
+            }
+            Expr::Block(stmts, _) => {
+                self.scopes.push(HashMap::new());
+                for stmt in stmts {
+                    self.stmt(stmt);
+                }
+                self.scopes.pop();
+            }
+            Expr::Do(stmts, _) => {
+                self.scopes.push(HashMap::new());
+                for stmt in stmts {
+                    match stmt {
+                        DoStmt::Bind(pattern, expr) => {
+                            self.expr(expr);
+                            self.pattern(pattern, Binding::Rebind);
+                        }
+                        DoStmt::Expr(expr) => self.expr(expr),
+                    }
+                }
+                self.scopes.pop();
+            }
+            Expr::Try(body, arms, finally, _) => {
+                self.expr(body);
+                self.arms(arms);
+                if let Some(finally) = finally {
+                    self.expr(finally);
+                }
+            }
+            Expr::Receive(arms, after, _) => {
+                self.arms(arms);
+                if let Some((timeout, body)) = after {
+                    self.expr(timeout);
+                    self.expr(body);
+                }
+            }
+            Expr::For(var, start, end, body, _) => {
+                self.expr(start);
+                self.expr(end);
+                self.scopes.push(HashMap::new());
+                let symbol = self.bind(var, Binding::New);
+                self.emit(var, symbol);
+                self.expr(body);
+                self.scopes.pop();
+            }
+            Expr::If(cond, then, otherwise, _) => self.exprs([&**cond, then, otherwise]),
+            Expr::BinOp(left, _, right, _) | Expr::Index(left, right, _) | Expr::Send(left, right, _)
+            | Expr::While(left, right, _) => self.exprs([&**left, right]),
+            Expr::UnaryOp(_, inner, _) | Expr::Try_(inner, _) | Expr::Quote(inner, _) | Expr::Splice(inner, _) => {
+                self.expr(inner)
+            }
+            Expr::Break(value, _) | Expr::Return(value, _) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Expr::Tuple(items, _) | Expr::Set(items, _) => self.exprs(items),
+            Expr::List(items, tail, _) => {
+                self.exprs(items);
+                if let Some(tail) = tail {
+                    self.expr(tail);
+                }
+            }
+            Expr::Map(entries, _) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::Spawn(_, func, args, _) => {
+                self.expr(func);
+                self.exprs(args);
+            }
+            Expr::String(StringLit::Interpolated(parts), _) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) => self.expr(expr),
+            Stmt::Let(binding) => {
+                if let Some(ty) = &binding.ty {
+                    self.type_expr(ty);
+                }
+                self.expr(&binding.value);
+                self.pattern(&binding.pattern, Binding::Rebind);
+            }
+            Stmt::Assign(target, value, _) => {
+                self.expr(value);
+                match target {
+                    AssignTarget::Var(name) => {
+                        let symbol = self.local(&name.node).or_else(|| self.global(&name.node, Namespace::Value));
+                        if let Some(symbol) = symbol {
+                            self.emit(name, symbol);
+                        }
+                    }
+                    AssignTarget::Field(receiver, name) => {
+                        self.expr(receiver);
+                        if let Some(symbol) = self.field(&name.node, None, Some(receiver.span())) {
+                            self.emit(name, symbol);
+                        }
+                    }
+                    AssignTarget::Index(receiver, index) => self.exprs([&**receiver, index]),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(files: &[(&str, &str)]) -> Vec<SourceModule> {
+        files.iter().map(|(path, source)| {
+            let (module, errors) = nostos_syntax::parse(source);
+            assert!(errors.is_empty(), "parse errors in {}: {:?}", path, errors);
+            SourceModule { path: path.split('.').map(String::from).collect(), module: module.unwrap() }
+        }).collect()
+    }
+
+    /// Rename the symbol at the first occurrence of `at` in `files[file]` and return the new sources.
+    fn rename_in(files: &[(&str, &str)], file: usize, at: &str, new_name: &str) -> Vec<String> {
+        let parsed = modules(files);
+        let offset = files[file].1.find(at).expect("marker not found");
+        let mut edits = rename(&parsed, file, offset, new_name, &|_, _| None).unwrap();
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.span.start));
+        files.iter().enumerate().map(|(i, (_, source))| {
+            let mut source = source.to_string();
+            for edit in edits.iter().filter(|edit| edit.file == i) {
+                source.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+            }
+            source
+        }).collect()
+    }
+
+    #[test]
+    fn test_rename_function_across_modules() {
+        let files = [
+            ("util", "pub double(x: Int) = x * 2\npub double(x: String) = x ++ x\nquad(x) = double(double(x))\n"),
+            ("main", "use util.{double}\nmain() = double(util.double(1)) + 2.double()\n"),
+        ];
+        let result = rename_in(&files, 0, "double", "twice");
+        assert_eq!(result[0], "pub twice(x: Int) = x * 2\npub twice(x: String) = x ++ x\nquad(x) = twice(twice(x))\n");
+        assert_eq!(result[1], "use util.{twice}\nmain() = twice(util.twice(1)) + 2.twice()\n");
+    }
+
+    #[test]
+    fn test_rename_respects_locals_and_aliases() {
+        let files = [
+            ("util", "pub size(xs) = xs.length()\n"),
+            ("main", "use util.{size as count}\nf(size) = size + 1\nmain() = count([1])\n"),
+        ];
+        let result = rename_in(&files, 0, "size", "len");
+        assert_eq!(result[0], "pub len(xs) = xs.length()\n");
+        // The parameter shadows the function and the alias keeps its name
+        assert_eq!(result[1], "use util.{len as count}\nf(size) = size + 1\nmain() = count([1])\n");
+
+        // Renaming the alias only touches this module
+        let result = rename_in(&files, 1, "count([1])", "total");
+        assert_eq!(result[1], "use util.{size as total}\nf(size) = size + 1\nmain() = total([1])\n");
+    }
+
+    #[test]
+    fn test_rename_local() {
+        let files = [("main", "f(x) = {\n    y = x + 1\n    y = y * 2\n    g = y => y + 1\n    g(y)\n}\n")];
+        let result = rename_in(&files, 0, "y = x", "z");
+        // The lambda parameter is a different variable
+        assert_eq!(result[0], "f(x) = {\n    z = x + 1\n    z = z * 2\n    g = y => y + 1\n    g(z)\n}\n");
+    }
+
+    #[test]
+    fn test_rename_type_and_constructor() {
+        let files = [(
+            "main",
+            "type Shape = Circle(Float) | Square(Float)\narea(s: Shape) -> Float = match s {\n    Circle(r) -> r * r\n    Square(a) -> a * a\n}\nmain() = area(Circle(1.0))\n",
+        )];
+        let result = rename_in(&files, 0, "Circle", "Round");
+        assert_eq!(result[0], files[0].1.replace("Circle", "Round"));
+        let result = rename_in(&files, 0, "Shape", "Figure");
+        assert_eq!(result[0], files[0].1.replace("Shape", "Figure"));
+    }
+
+    #[test]
+    fn test_rename_field() {
+        let files = [(
+            "main",
+            "type Person = { name: String, age: Int }\ntype Pet = { name: String }\ngreet(p: Person) = p.age\nshow(p) = match p {\n    Person{name, age} -> name\n}\nmain() = Person(name: \"a\", age: 1)\n",
+        )];
+        let result = rename_in(&files, 0, "age", "years");
+        assert_eq!(
+            result[0],
+            "type Person = { name: String, years: Int }\ntype Pet = { name: String }\ngreet(p: Person) = p.years\nshow(p) = match p {\n    Person{name, years: age} -> name\n}\nmain() = Person(name: \"a\", years: 1)\n",
+        );
+        // `name` is shared with Pet; only Person's is renamed
+        let result = rename_in(&files, 0, "name", "fullName");
+        assert_eq!(
+            result[0],
+            "type Person = { fullName: String, age: Int }\ntype Pet = { name: String }\ngreet(p: Person) = p.age\nshow(p) = match p {\n    Person{fullName: name, age} -> name\n}\nmain() = Person(fullName: \"a\", age: 1)\n",
+        );
+    }
+
+    #[test]
+    fn test_rename_trait_method() {
+        let files = [(
+            "main",
+            "trait Describe\n    describe(self) -> String\nend\ntype Dog = { name: String }\nDog: Describe\n    describe(self) = self.name\nend\nmain() = Dog(\"rex\").describe()\n",
+        )];
+        let result = rename_in(&files, 0, "describe", "explain");
+        assert_eq!(result[0], files[0].1.replace("describe", "explain"));
+    }
+
+    #[test]
+    fn test_nested_module_qualification() {
+        let files = [("main", "module Outer\n    module Inner\n        pub value() = 21\n    end\nend\nmain() = Outer.Inner.value() * 2\n")];
+        let result = rename_in(&files, 0, "value", "answer");
+        assert_eq!(result[0], files[0].1.replace("value", "answer"));
+    }
+
+    #[test]
+    fn test_rename_rejects_builtins_and_bad_names() {
+        let parsed = modules(&[("main", "main() = println(\"hi\")\n")]);
+        assert!(rename(&parsed, 0, 10, "say", &|_, _| None).is_err());
+        assert!(rename(&parsed, 0, 1, "Main", &|_, _| None).is_err());
+        assert!(rename(&parsed, 0, 1, "if", &|_, _| None).is_err());
+        assert!(rename(&parsed, 0, 1, "start", &|_, _| None).is_ok());
+    }
+}
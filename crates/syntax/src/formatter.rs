@@ -0,0 +1,623 @@
+//! Source code formatter for the Nostos programming language.
+//!
+//! The formatter works on the lexer's token stream, so comments are kept as
+//! they are. It keeps the author's line breaks and normalizes everything else:
+//!
+//! - indentation: four spaces per open bracket, `do` block or `trait`/`module`
+//!   block, and one extra level (at least) for continuation lines such as
+//!   `|> step`, `then ...`/`else ...` or the line after a trailing `=`/`->`
+//! - spacing between tokens: binary operators, `->`, `=>` and `=` get spaces,
+//!   `,` and `:` are followed by one, brackets and `.` get none
+//! - blank lines: at most one in a row, none after an opening bracket or
+//!   before a closing one, and none at the start or end of the file
+//!
+//! Since only whitespace changes, the result parses to the same AST. That is
+//! checked on every run: the formatted source is parsed again and compared to
+//! the original (ignoring spans), and the formatter refuses to return output
+//! that doesn't match.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::ast::{Item, Module};
+use crate::errors::{parse_errors_to_source_errors, SourceError};
+use crate::lexer::{lex, Token};
+use crate::parser::parse;
+
+/// Columns per indentation level.
+const INDENT: usize = 4;
+
+/// Why a source couldn't be formatted.
+#[derive(Debug, Clone)]
+pub enum FormatError {
+    /// The source has syntax errors
+    Parse(Vec<SourceError>),
+    /// The formatted source would parse differently (a formatter bug)
+    Changed,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Parse(errors) => match errors.first() {
+                Some(error) => write!(f, "cannot format a file with syntax errors: {}", error.message),
+                None => write!(f, "cannot format a file with syntax errors"),
+            },
+            FormatError::Changed => write!(f, "formatting would change the meaning of the program"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Format a whole source file.
+pub fn format_source(source: &str) -> Result<String, FormatError> {
+    let lines = format_lines(source)?;
+    Ok(join_lines(&lines))
+}
+
+/// Format the lines of `source` that overlap the byte range `range`.
+///
+/// Returns the byte range of the lines to replace (whole lines, including
+/// their newline) and the formatted text for them.
+pub fn format_range(source: &str, range: Range<usize>) -> Result<(Range<usize>, String), FormatError> {
+    let lines = format_lines(source)?;
+    let selected: Vec<&FormattedLine> = lines.iter()
+        .filter(|line| line.source.start < range.end.max(range.start + 1) && range.start < line.source.end)
+        .collect();
+    let (Some(first), Some(last)) = (selected.first(), selected.last()) else {
+        return Ok((range.start..range.start, String::new()));
+    };
+    let text = join_lines(selected.iter().copied());
+    Ok((first.source.start..last.source.end, text))
+}
+
+/// One source line and what it formats to.
+#[derive(Debug, Clone)]
+struct FormattedLine {
+    /// Byte range of the line in the source, including its newline
+    source: Range<usize>,
+    /// Formatted text without newline; `None` if the line is dropped
+    text: Option<String>,
+}
+
+/// The text of the lines that are kept, each ending in a newline.
+fn join_lines<'a>(lines: impl IntoIterator<Item = &'a FormattedLine>) -> String {
+    let mut text = String::new();
+    for line in lines {
+        if let Some(line) = &line.text {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// A token or other text on a line.
+#[derive(Debug, Clone)]
+struct Piece {
+    /// `None` for text the lexer skipped (e.g. a lone `#`), kept verbatim
+    token: Option<Token>,
+    span: Range<usize>,
+}
+
+impl Piece {
+    fn is(&self, token: &Token) -> bool {
+        self.token.as_ref() == Some(token)
+    }
+
+    fn is_comment(&self) -> bool {
+        matches!(self.token, None | Some(Token::Comment) | Some(Token::MultiLineComment))
+    }
+}
+
+/// A source line: the text up to and including a newline that is not part
+/// of a string or comment.
+struct Line {
+    source: Range<usize>,
+    pieces: Vec<Piece>,
+    /// Indentation in the source, in columns
+    indent: usize,
+}
+
+impl Line {
+    /// Pieces without comments.
+    fn code(&self) -> impl DoubleEndedIterator<Item = &Piece> {
+        self.pieces.iter().filter(|piece| !piece.is_comment())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameKind {
+    Root,
+    Paren,
+    Bracket,
+    /// `{ }`; map and set literals (`%{`, `#{`) and import lists get no inner spaces
+    Brace { literal: bool },
+    Do,
+    /// A `trait`, trait impl or `module` spanning several lines, up to its last line
+    Block { last_line: usize },
+}
+
+/// An open bracket or block.
+struct Frame {
+    kind: FrameKind,
+    /// Indentation of the line that opened the frame
+    outer: usize,
+    /// (formatted, source) indentation of the last line in this frame that
+    /// started a statement; continuation lines are indented relative to it
+    anchor: Option<(usize, usize)>,
+}
+
+impl Frame {
+    fn inner(&self) -> usize {
+        match self.kind {
+            FrameKind::Root => 0,
+            _ => self.outer + INDENT,
+        }
+    }
+
+    fn closed_by(&self, token: &Token) -> bool {
+        matches!(
+            (self.kind, token),
+            (FrameKind::Paren, Token::RParen)
+                | (FrameKind::Bracket, Token::RBracket)
+                | (FrameKind::Brace { .. }, Token::RBrace)
+                | (FrameKind::Do | FrameKind::Block { .. }, Token::End)
+        )
+    }
+}
+
+/// Format every line of `source`, checking that the result parses to the same AST.
+fn format_lines(source: &str) -> Result<Vec<FormattedLine>, FormatError> {
+    let (module, errors) = parse(source);
+    let module = match module {
+        Some(module) if errors.is_empty() => module,
+        _ => return Err(FormatError::Parse(parse_errors_to_source_errors(&errors))),
+    };
+
+    let lines = split_lines(source);
+    let blocks = block_lines(&module, &lines);
+    let formatted = layout(source, &lines, &blocks);
+
+    let output = join_lines(&formatted);
+    match parse(&output) {
+        (Some(reparsed), errors) if errors.is_empty() && same_ast(&module, &reparsed) => Ok(formatted),
+        _ => Err(FormatError::Changed),
+    }
+}
+
+/// Split the source into lines of pieces.
+fn split_lines(source: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut pieces = Vec::new();
+    let mut line_start = 0;
+    let mut cursor = 0;
+
+    let mut finish = |pieces: &mut Vec<Piece>, start: usize, end: usize| {
+        let indent = source[start..end].chars()
+            .take_while(|c| matches!(c, ' ' | '\t'))
+            .map(|c| if c == '\t' { INDENT } else { 1 })
+            .sum();
+        lines.push(Line { source: start..end, pieces: std::mem::take(pieces), indent });
+    };
+
+    for (token, span) in lex(source) {
+        // Text the lexer skipped that isn't whitespace
+        let gap = &source[cursor..span.start];
+        if !gap.trim().is_empty() {
+            let offset = cursor + (gap.len() - gap.trim_start().len());
+            pieces.push(Piece { token: None, span: offset..offset + gap.trim().len() });
+        }
+        cursor = span.end;
+
+        if token == Token::Newline {
+            for (i, _) in source[span.clone()].match_indices('\n') {
+                let end = span.start + i + 1;
+                finish(&mut pieces, line_start, end);
+                line_start = end;
+            }
+        } else {
+            pieces.push(Piece { token: Some(token), span });
+        }
+    }
+    let gap = &source[cursor..];
+    if !gap.trim().is_empty() {
+        let offset = cursor + (gap.len() - gap.trim_start().len());
+        pieces.push(Piece { token: None, span: offset..offset + gap.trim().len() });
+    }
+    if line_start < source.len() || !pieces.is_empty() {
+        finish(&mut pieces, line_start, source.len());
+    }
+    lines
+}
+
+/// First and last line of every `trait`, trait impl and `module` that spans
+/// several lines, so their bodies can be indented.
+fn block_lines(module: &Module, lines: &[Line]) -> Vec<(usize, usize)> {
+    fn collect(items: &[Item], lines: &[Line], blocks: &mut Vec<(usize, usize)>) {
+        let line_of = |offset: usize| lines.partition_point(|line| line.source.end <= offset);
+        for item in items {
+            let span = match item {
+                Item::TraitDef(def) => def.span,
+                Item::TraitImpl(impl_) => impl_.span,
+                Item::ModuleDef(def) => {
+                    collect(&def.items, lines, blocks);
+                    def.span
+                }
+                _ => continue,
+            };
+            let (first, last) = (line_of(span.start), line_of(span.end.saturating_sub(1)));
+            if first < last {
+                blocks.push((first, last));
+            }
+        }
+    }
+
+    let mut blocks = Vec::new();
+    collect(&module.items, lines, &mut blocks);
+    blocks.sort_unstable();
+    blocks
+}
+
+/// Tokens that end a value, after which `(`/`[` is a call or index and `-` is binary.
+fn ends_value(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::LowerIdent(_) | Token::UpperIdent(_) | Token::SelfKw | Token::SelfType | Token::Underscore
+            | Token::Int(_) | Token::HexInt(_) | Token::BinInt(_) | Token::BigInt(_)
+            | Token::Int8(_) | Token::Int16(_) | Token::Int32(_)
+            | Token::UInt8(_) | Token::UInt16(_) | Token::UInt32(_) | Token::UInt64(_)
+            | Token::Float(_) | Token::Float32(_) | Token::Decimal(_)
+            | Token::String(_) | Token::SingleQuoteString(_) | Token::Char(_)
+            | Token::True | Token::False
+            | Token::RParen | Token::RBracket | Token::RBrace | Token::End | Token::Question
+    )
+}
+
+/// Binary operators that may end a line whose expression continues on the next.
+fn is_binary_operator(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Percent | Token::StarStar
+            | Token::EqEq | Token::NotEq | Token::Lt | Token::Gt | Token::LtEq | Token::GtEq
+            | Token::AndAnd | Token::OrOr | Token::PlusPlus | Token::PipeRight | Token::Pipe
+    )
+}
+
+/// Does the expression on `line` continue on the next line, because it ends
+/// with `=`, `->`, `then`, a binary operator and the like?
+fn continues_after(line: &Line) -> bool {
+    let mut code = line.code().rev().filter_map(|p| p.token.as_ref());
+    match (code.next(), code.next()) {
+        // Not `.*` in imports or a unary operator
+        (Some(last), before) if is_binary_operator(last) => before.is_some_and(ends_value),
+        (Some(last), _) => matches!(
+            last,
+            Token::Eq | Token::RightArrow | Token::FatArrow | Token::LeftArrow | Token::Then | Token::Else
+                | Token::PlusEq | Token::MinusEq | Token::StarEq | Token::SlashEq
+        ),
+        _ => false,
+    }
+}
+
+/// Does a line starting with `token` continue the previous line?
+fn continues_before(token: &Token) -> bool {
+    // `-` is left out: a line can start with a negative literal
+    (is_binary_operator(token) && *token != Token::Minus) || *token == Token::Dot
+}
+
+/// Lay out all lines: compute indentation and spacing and drop extra blank lines.
+fn layout(source: &str, lines: &[Line], blocks: &[(usize, usize)]) -> Vec<FormattedLine> {
+    let mut stack = vec![Frame { kind: FrameKind::Root, outer: 0, anchor: None }];
+    let mut next_block = 0;
+    // Whether the previous code line continues on the next one, and whether it started with a closer
+    let mut previous: Option<(bool, bool)> = None;
+    let mut texts: Vec<Option<String>> = Vec::with_capacity(lines.len());
+    // Indentation for comment lines, taken from the next code line
+    let mut pending_comments: Vec<usize> = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        // Blocks without `end` close after their last line
+        while matches!(stack.last().map(|f| f.kind), Some(FrameKind::Block { last_line }) if last_line < index) {
+            stack.pop();
+        }
+
+        if line.pieces.is_empty() {
+            texts.push(None);
+            continue;
+        }
+        if line.pieces.iter().all(Piece::is_comment) {
+            pending_comments.push(index);
+            texts.push(None);
+            continue;
+        }
+
+        // Leading closers set the indentation to that of the line that opened them
+        let mut indent = None;
+        let mut closed = Vec::new();
+        for piece in line.pieces.iter().take_while(|p| p.token.as_ref().is_some_and(|t| matches!(t, Token::RParen | Token::RBracket | Token::RBrace | Token::End))) {
+            let token = piece.token.as_ref().unwrap();
+            if stack.len() > 1 && stack.last().unwrap().closed_by(token) {
+                let frame = stack.pop().unwrap();
+                indent = Some(frame.outer);
+                closed.push(frame.kind);
+            } else {
+                break;
+            }
+        }
+        let starts_with_closer = indent.is_some();
+        let comment_indent;
+
+        let indent = match indent {
+            Some(indent) => {
+                comment_indent = indent + INDENT;
+                indent
+            }
+            None => {
+                let first = line.code().next().and_then(|p| p.token.clone());
+                let frame = stack.last_mut().unwrap();
+                let indent = match (&first, previous, frame.anchor) {
+                    // `then`/`else` keep their position relative to the line with the `if`,
+                    // unless the `else` follows a closing bracket
+                    (Some(Token::Then | Token::Else), Some((_, false)) | None, Some((anchor, anchor_source))) => {
+                        anchor + line.indent.saturating_sub(anchor_source)
+                    }
+                    (Some(first), _, anchor) if continues_before(first) || previous.is_some_and(|(continues, _)| continues) => {
+                        let indent = match anchor {
+                            Some((anchor, anchor_source)) => anchor + line.indent.saturating_sub(anchor_source).max(INDENT),
+                            None => frame.inner(),
+                        };
+                        // An `if` on a continuation line is what its `then`/`else` line up with
+                        if *first == Token::If {
+                            frame.anchor = Some((indent, line.indent));
+                        }
+                        indent
+                    }
+                    _ => {
+                        frame.anchor = Some((frame.inner(), line.indent));
+                        frame.inner()
+                    }
+                };
+                comment_indent = indent;
+                indent
+            }
+        };
+        for comment in pending_comments.drain(..) {
+            texts[comment] = Some(comment_line(source, &lines[comment], comment_indent));
+        }
+
+        texts.push(Some(format_line(source, line, indent, closed.len(), &closed, &mut stack)));
+
+        if let Some(&(_, last_line)) = blocks.get(next_block).filter(|&&(first, _)| first == index) {
+            stack.push(Frame { kind: FrameKind::Block { last_line }, outer: indent, anchor: None });
+            next_block += 1;
+        }
+        while blocks.get(next_block).is_some_and(|&(first, _)| first <= index) {
+            next_block += 1;
+        }
+        previous = Some((continues_after(line), starts_with_closer));
+    }
+    let indent = stack.last().map(Frame::inner).unwrap_or(0);
+    for comment in pending_comments.drain(..) {
+        texts[comment] = Some(comment_line(source, &lines[comment], indent));
+    }
+
+    drop_blank_lines(lines, &mut texts);
+    lines.iter().zip(texts)
+        .map(|(line, text)| FormattedLine { source: line.source.clone(), text })
+        .collect()
+}
+
+/// A line holding only comments.
+fn comment_line(source: &str, line: &Line, indent: usize) -> String {
+    let mut text = " ".repeat(indent);
+    for (i, piece) in line.pieces.iter().enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        text.push_str(source[piece.span.clone()].trim_end());
+    }
+    text
+}
+
+/// Format one code line. The first `skip` pieces are closers already popped from `stack`.
+fn format_line(source: &str, line: &Line, indent: usize, skip: usize, closed: &[FrameKind], stack: &mut Vec<Frame>) -> String {
+    let mut text = " ".repeat(indent);
+    let mut prev: Option<&Piece> = None;
+    // Whether the previous piece was a prefix operator (unary `-`, `!`, `%{`, ...)
+    let mut prev_prefix = false;
+
+    for (i, piece) in line.pieces.iter().enumerate() {
+        let token = piece.token.as_ref();
+        let matching = if i < skip {
+            closed.get(i).copied()
+        } else {
+            token.and_then(|t| stack.last().filter(|f| f.closed_by(t)).map(|f| f.kind))
+        };
+
+        if let Some(prev) = prev {
+            let had_space = piece.span.start > prev.span.end;
+            let in_literal = matches!(stack.last().map(|f| f.kind), Some(FrameKind::Brace { literal: true }));
+            if space_between(prev, piece, had_space, prev_prefix, matching, in_literal) {
+                text.push(' ');
+            }
+        }
+        text.push_str(source[piece.span.clone()].trim_end_matches([' ', '\t', '\r']));
+
+        prev_prefix = match token {
+            Some(Token::Minus) => !prev.and_then(|p| p.token.as_ref()).is_some_and(ends_value),
+            Some(Token::Percent) => line.pieces.get(i + 1).is_some_and(|next| next.is(&Token::LBrace) && next.span.start == piece.span.end),
+            Some(Token::Bang | Token::At | Token::Tilde | Token::Dollar | Token::Hash) => true,
+            _ => false,
+        };
+
+        // Track brackets and blocks opened and closed on this line
+        if i >= skip {
+            match token {
+                Some(Token::LParen) => stack.push(Frame { kind: FrameKind::Paren, outer: indent, anchor: None }),
+                Some(Token::LBracket) => stack.push(Frame { kind: FrameKind::Bracket, outer: indent, anchor: None }),
+                Some(Token::LBrace) => {
+                    // `%{` and `#{` start map and set literals, `.{` an import list
+                    let literal = prev.is_some_and(|p| {
+                        p.is(&Token::Dot) || ((p.is(&Token::Hash) || p.is(&Token::Percent)) && p.span.end == piece.span.start)
+                    });
+                    stack.push(Frame { kind: FrameKind::Brace { literal }, outer: indent, anchor: None });
+                }
+                Some(Token::Do) => stack.push(Frame { kind: FrameKind::Do, outer: indent, anchor: None }),
+                Some(t) if matching.is_some() && stack.len() > 1 && stack.last().unwrap().closed_by(t) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+        prev = Some(piece);
+    }
+    text
+}
+
+/// Should there be a space between two pieces on a line?
+///
+/// `matching` is the frame closed by `next`, if it is a closer, and
+/// `in_literal` tells whether the innermost open frame is a map or set literal.
+fn space_between(prev: &Piece, next: &Piece, had_space: bool, prev_prefix: bool, matching: Option<FrameKind>, in_literal: bool) -> bool {
+    let (Some(p), Some(n)) = (&prev.token, &next.token) else {
+        return had_space;
+    };
+    match (p, n) {
+        (_, Token::Comment) => true,
+        (Token::MultiLineComment, _) | (_, Token::MultiLineComment) => had_space,
+        (_, Token::Comma | Token::Semicolon | Token::RParen | Token::RBracket | Token::Colon) => false,
+        (Token::LBrace, Token::RBrace) => false,
+        (_, Token::RBrace) => !matches!(matching, Some(FrameKind::Brace { literal: true })),
+        (Token::LParen | Token::LBracket, _) => false,
+        (Token::LBrace, _) => !in_literal,
+        (Token::Dot, _) | (_, Token::Dot) => false,
+        (Token::DotDot | Token::DotDotEq | Token::ColonColon | Token::Question | Token::Caret, _)
+        | (_, Token::DotDot | Token::DotDotEq | Token::ColonColon | Token::Question | Token::Caret) => had_space,
+        _ if prev_prefix => false,
+        (Token::Spawn | Token::SpawnLink | Token::SpawnMonitor | Token::Panic | Token::Quote, Token::LParen) => had_space,
+        (p, Token::LParen | Token::LBracket) if ends_value(p) => had_space,
+        _ => true,
+    }
+}
+
+/// Drop blank lines at the start and end, runs of more than one, and blank
+/// lines right after an opening bracket or before a closing one.
+fn drop_blank_lines(lines: &[Line], texts: &mut [Option<String>]) {
+    let opens = |line: &Line| {
+        line.code().last().and_then(|p| p.token.as_ref())
+            .is_some_and(|t| matches!(t, Token::LParen | Token::LBracket | Token::LBrace | Token::Do))
+    };
+    let closes = |line: &Line| {
+        line.code().next().and_then(|p| p.token.as_ref())
+            .is_some_and(|t| matches!(t, Token::RParen | Token::RBracket | Token::RBrace | Token::End))
+    };
+
+    let mut previous: Option<usize> = None;
+    let mut index = 0;
+    while index < lines.len() {
+        if !lines[index].pieces.is_empty() {
+            previous = Some(index);
+            index += 1;
+            continue;
+        }
+        let run_end = (index..lines.len()).find(|&i| !lines[i].pieces.is_empty());
+        let keep = match (previous, run_end) {
+            (Some(prev), Some(next)) => !opens(&lines[prev]) && !closes(&lines[next]),
+            _ => false,
+        };
+        if keep {
+            texts[index] = Some(String::new());
+        }
+        index = run_end.unwrap_or(lines.len());
+    }
+}
+
+/// Compare two ASTs, ignoring source spans.
+fn same_ast(a: &Module, b: &Module) -> bool {
+    strip_spans(&format!("{:?}", a)) == strip_spans(&format!("{:?}", b))
+}
+
+/// Remove `Span { .. }` from a Debug representation.
+fn strip_spans(debug: &str) -> String {
+    const MARKER: &str = "Span { file_id: ";
+    let mut out = String::with_capacity(debug.len());
+    let mut rest = debug;
+    while let Some(start) = rest.find(MARKER) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest.find('}') {
+            Some(end) => rest = &rest[end + 1..],
+            None => break,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spacing_and_indentation() {
+        let source = "main()={\n  x=[1,2 ,3]\n  y = x.map( n=>n*2 )\n  y\n}\n";
+        assert_eq!(format_source(source).unwrap(), "main() = {\n    x = [1, 2, 3]\n    y = x.map(n => n * 2)\n    y\n}\n");
+    }
+
+    #[test]
+    fn test_continuation_lines() {
+        let source = "f(x) =\nif x > 0\nthen 1\nelse 2\n\ng(xs) = {\n  ys = xs\n  .filter(x => x > 1)\n  ys\n}\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "f(x) =\n    if x > 0\n    then 1\n    else 2\n\ng(xs) = {\n    ys = xs\n        .filter(x => x > 1)\n    ys\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let source = "\n\n# answer\nanswer() = {\n\n  42   # the answer\n\n\n}\n\n\nmain() = answer()\n\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "# answer\nanswer() = {\n    42 # the answer\n}\n\nmain() = answer()\n",
+        );
+    }
+
+    #[test]
+    fn test_literals_and_imports() {
+        let source = "use stdlib.json.{ jsonParse , jsonStringify }\nm() = %{ \"a\": 1 }\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "use stdlib.json.{jsonParse, jsonStringify}\nm() = %{\"a\": 1}\n",
+        );
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let source = "type Shape = Circle(Float) | Rect(Float, Float)\n\narea(s) = match s {\n    Circle(r) -> 3.14 * r * r\n    Rect(w, h) -> w * h\n}\n";
+        let once = format_source(source).unwrap();
+        assert_eq!(once, source);
+        assert_eq!(format_source(&once).unwrap(), once);
+    }
+
+    #[test]
+    fn test_format_range() {
+        let source = "a()=1\nb()=2\nc()=3\n";
+        let start = source.find("b()").unwrap();
+        let (range, text) = format_range(source, start..start + 1).unwrap();
+        assert_eq!(&source[range], "b()=2\n");
+        assert_eq!(text, "b() = 2\n");
+
+        // A selection ending at the start of a line doesn't include that line
+        let end = source.find("c()").unwrap();
+        let (range, _) = format_range(source, start..end).unwrap();
+        assert_eq!(&source[range], "b()=2\n");
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        assert!(matches!(format_source("main() = (1 +\n"), Err(FormatError::Parse(_))));
+    }
+}
@@ -4,11 +4,13 @@
 
 pub mod ast;
 pub mod errors;
+pub mod formatter;
 pub mod lexer;
 pub mod parser;
 
 pub use ast::*;
 pub use errors::{ErrorKind, SourceError, format_errors, eprint_errors, offset_to_line_col, parse_error_to_source_error, parse_errors_to_source_errors};
+pub use formatter::{format_range, format_source, FormatError};
 pub use lexer::{lex, Token};
 pub use parser::{parse, parse_expr};
 
@@ -11,13 +11,13 @@
 //! - yield_now() is called every N instructions for fairness
 
 use std::collections::{HashMap, HashSet};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
 use imbl::{HashMap as ImblHashMap, HashSet as ImblHashSet};
 
-use tokio::sync::{mpsc, RwLock as TokioRwLock, OwnedRwLockReadGuard, OwnedRwLockWriteGuard};
+use tokio::sync::{mpsc, Notify, RwLock as TokioRwLock, OwnedRwLockReadGuard, OwnedRwLockWriteGuard};
 // LocalSet removed - now using multi-threaded runtime with tokio::spawn
 
 use std::pin::Pin;
@@ -135,7 +135,7 @@ pub struct ReactiveRenderContext {
     /// Nesting depth for RHtml calls. Only clear context on depth=0.
     pub nesting_depth: usize,
 }
-use crate::gc::{GcConfig, GcInt64List, GcList, GcMapKey, GcValue, Heap, GcNativeFn};
+use crate::gc::{GcConfig, GcInt64List, GcList, GcMapKey, GcValue, Heap, GcNativeFn, RawGcPtr};
 
 /// Held mvar lock guard (owned so it can be stored).
 pub enum HeldMvarLock {
@@ -148,19 +148,58 @@ pub enum HeldMvarLock {
 // - OwnedRwLockWriteGuard<T> is Send if T: Send
 // - ThreadSafeValue is designed to be Send + Sync (only contains primitives, String, Arc)
 unsafe impl Send for HeldMvarLock {}
-use crate::process::{CallFrame, ExceptionHandler, ProcessState, ThreadSafeValue, ProfileData};
+use crate::process::{CallFrame, ExceptionHandler, ExitReason, ProcessLimit, ProcessState, ThreadSafeValue, ProfileData};
+use crate::mailbox::{MailboxBound, MailboxPolicy, SendError};
+use crate::priority::{Priority, PriorityCell, PriorityTask};
+use crate::sampler::{Sampler, SamplerConfig};
+use crate::trace::{TraceCell, TraceEvent, TraceKind};
+use crate::tls::{ClientOptions, TlsFiles};
 use crate::value::{FunctionValue, Pid, TypeValue, RefId, RuntimeError, Value, ReactiveRecordValue, ReactiveVariantValue, VariantValue};
-use crate::shared_types::{SendableValue, JIT_YIELD_SENTINEL};
+use crate::shared_types::{SendableValue, SharedMapKey, SharedMapValue, JIT_YIELD_SENTINEL};
+use crate::jit_runtime::JitExit;
+use crate::supervisor::{AsyncChildSpec, AsyncSupervisor, RestartStrategy, RestartType, SupervisorConfig};
 use crate::io_runtime::{IoRequest, IoRuntime};
 use crate::process::IoResponseValue;
 
 /// Reductions per yield (how often we call yield_now for fairness).
-const REDUCTIONS_PER_YIELD: usize = 1000; // Balanced: good throughput for CPU-bound code, fair for concurrent
+pub(crate) const REDUCTIONS_PER_YIELD: usize = 1000; // Balanced: good throughput for CPU-bound code, fair for concurrent
+
+pub use crate::mailbox::{MailboxReceiver, MailboxSender};
+
+/// How a newly spawned process is tied to an existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnLinkage {
+    /// Plain `spawn`: nobody is notified when the child exits.
+    None,
+    /// `spawn_link`: the child and the given process are linked.
+    Link(Pid),
+    /// `spawn_monitor`: the given process monitors the child.
+    Monitor(Pid),
+}
+
+/// Per-process settings chosen at spawn time. Unset limits fall back to the
+/// VM-wide defaults in `AsyncSharedState::gc_config`.
+#[derive(Debug, Clone, Default)]
+pub struct SpawnOptions {
+    /// Live heap size in bytes at which the process is terminated.
+    pub max_heap_bytes: Option<usize>,
+    /// Number of queued messages at which the process is terminated.
+    pub max_mailbox_len: Option<usize>,
+    /// Scheduling priority.
+    pub priority: Priority,
+    /// Mailbox capacity and policy (unbounded when unset).
+    pub mailbox: Option<MailboxBound>,
+}
 
-/// Type for process mailbox sender.
-pub type MailboxSender = mpsc::UnboundedSender<ThreadSafeValue>;
-/// Type for process mailbox receiver.
-pub type MailboxReceiver = mpsc::UnboundedReceiver<ThreadSafeValue>;
+/// How long `Gc.snapshot` waits for another process to write its heap.
+const HEAP_SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A `Gc.snapshot` of another process, answered by that process with the
+/// number of objects written.
+pub struct HeapSnapshotRequest {
+    pub path: String,
+    pub reply: tokio::sync::oneshot::Sender<Result<usize, String>>,
+}
 
 /// Handle for a threaded evaluation, allowing independent cancellation.
 pub struct ThreadedEvalHandle {
@@ -227,6 +266,8 @@ pub struct AsyncSharedState {
     pub jit_string_match_functions: RwLock<HashMap<u16, crate::shared_types::JitStringMatchFn>>,
     /// Set of JIT function indices that return Bool (0/1) rather than Int64
     pub jit_bool_returning: RwLock<HashSet<u16>>,
+    /// JIT compile queue, set when the baseline and optimizing tiers are enabled.
+    pub jit_queue: std::sync::OnceLock<crate::jit_runtime::JitQueue>,
 
     /// Shutdown signal (permanent).
     pub shutdown: AtomicBool,
@@ -244,8 +285,8 @@ pub struct AsyncSharedState {
     pub spawn_runtime_handle: Option<tokio::runtime::Handle>,
 
     /// Process registry: Pid -> mailbox sender.
-    /// Protected by tokio RwLock for async access.
-    pub process_registry: TokioRwLock<HashMap<Pid, MailboxSender>>,
+    /// Protected by tokio RwLock for async access; shared with the TUI inspector.
+    pub process_registry: Arc<TokioRwLock<HashMap<Pid, MailboxSender>>>,
 
     /// Process abort handles: Pid -> tokio AbortHandle.
     /// Used to actually stop tasks when Process.kill is called.
@@ -255,6 +296,46 @@ pub struct AsyncSharedState {
     /// Used to close servers when a process is killed.
     pub process_servers: TokioRwLock<HashMap<Pid, Vec<u64>>>,
 
+    /// Process links (bidirectional): Pid -> linked Pids.
+    /// Linked processes receive ("EXIT", pid, reason) when the other side exits.
+    pub process_links: parking_lot::Mutex<HashMap<Pid, Vec<Pid>>>,
+
+    /// Process monitors: watched Pid -> watcher Pids.
+    /// Watchers receive ("DOWN", pid, reason) when the watched process exits.
+    pub process_monitors: parking_lot::Mutex<HashMap<Pid, Vec<Pid>>>,
+
+    /// Registered process names: name -> Pid (Process.register / Process.whereis).
+    /// A name is dropped automatically when its process exits.
+    pub process_names: parking_lot::Mutex<HashMap<String, Pid>>,
+
+    /// Timers created by Timer.sendAfter / Timer.interval.
+    pub timers: crate::timers::TimerService,
+
+    /// Other nodes and the connections to them (see `crate::node`).
+    pub nodes: crate::node::Nodes,
+
+    /// Shared key-value tables (Table.new / Table.insert / ...).
+    pub tables: crate::tables::Tables,
+
+    /// Recompiles modules for Code.reload (see `crate::code`).
+    pub code_loader: RwLock<Option<crate::code::CodeLoader>>,
+
+    /// Reload counts by module.
+    pub code_versions: crate::code::CodeVersions,
+
+    /// Running supervisors: supervisor Pid -> supervisor state.
+    pub supervisors: parking_lot::Mutex<HashMap<Pid, Arc<tokio::sync::Mutex<crate::supervisor::AsyncSupervisor>>>>,
+
+    /// Pending `Gc.snapshot` requests by target process. A process answers
+    /// them at its next reduction check, or right away while it waits in receive.
+    pub heap_snapshot_requests: parking_lot::Mutex<HashMap<Pid, Vec<HeapSnapshotRequest>>>,
+
+    /// Number of pending snapshot requests, so processes can poll cheaply.
+    pub heap_snapshot_pending: AtomicUsize,
+
+    /// Wakeups that interrupt a process's receive to serve requests (created on demand).
+    pub process_wakeups: parking_lot::Mutex<HashMap<Pid, Arc<Notify>>>,
+
     /// Debug counters for process lifecycle tracking
     pub spawned_count: AtomicU64,
     pub exited_count: AtomicU64,
@@ -300,12 +381,32 @@ pub struct AsyncSharedState {
     /// Panel command sender (for Panel.* calls from Nostos code).
     pub panel_command_sender: Option<crate::shared_types::PanelCommandSender>,
 
+    /// Debug hub of the running debug session, if any.
+    /// Processes spawned while it is set are debugged too.
+    pub debug_hub: parking_lot::RwLock<Option<Arc<crate::shared_types::DebugHub>>>,
+
     /// PID counter for generating unique PIDs.
     pub next_pid: AtomicU64,
 
     /// Whether profiling is enabled.
     pub profiling_enabled: bool,
 
+    /// Sampling profiler, when enabled.
+    pub sampler: Option<Arc<Sampler>>,
+
+    /// Priority of each live process (see `crate::priority`).
+    pub process_priorities: parking_lot::Mutex<HashMap<Pid, Arc<PriorityCell>>>,
+
+    /// Trace settings of each live process (see `crate::trace`).
+    pub traces: crate::trace::Traces,
+
+    /// Number of runnable (running or ready) high-priority processes.
+    pub runnable_high_priority: Arc<AtomicUsize>,
+
+    /// Heap configuration for new processes; its limits are the defaults
+    /// that `SpawnOptions` can override.
+    pub gc_config: GcConfig,
+
     /// Extension manager for native library functions.
     pub extensions: RwLock<Option<Arc<ExtensionManager>>>,
 }
@@ -316,6 +417,12 @@ impl AsyncSharedState {
         Pid(self.next_pid.fetch_add(1, Ordering::SeqCst))
     }
 
+    /// When a new process takes its first stack sample, if it is sampled.
+    fn first_sample(&self, pid: Pid) -> Option<Instant> {
+        let sampler = self.sampler.as_ref()?;
+        sampler.config.includes(pid).then(|| Instant::now() + sampler.config.interval)
+    }
+
     /// Register a process in the registry.
     pub async fn register_process(&self, pid: Pid, sender: MailboxSender) {
         self.spawned_count.fetch_add(1, Ordering::Relaxed);
@@ -323,9 +430,387 @@ impl AsyncSharedState {
     }
 
     /// Unregister a process from the registry.
-    pub async fn unregister_process(&self, pid: Pid) {
+    /// Returns false if the process was already gone (e.g. killed).
+    pub async fn unregister_process(&self, pid: Pid) -> bool {
         self.exited_count.fetch_add(1, Ordering::Relaxed);
-        self.process_registry.write().await.remove(&pid);
+        let removed = self.process_registry.write().await.remove(&pid).is_some();
+        self.forget_process_requests(pid);
+        removed
+    }
+
+    /// Drop the snapshot requests, wakeup and priority of a process that left
+    /// the registry. Dropping a request tells its requester that the process exited.
+    fn forget_process_requests(&self, pid: Pid) {
+        self.take_heap_snapshot_requests(pid);
+        self.process_wakeups.lock().remove(&pid);
+        self.process_priorities.lock().remove(&pid);
+    }
+
+    /// The priority of a process, created (normal) on first use.
+    pub fn priority_cell(&self, pid: Pid) -> Arc<PriorityCell> {
+        self.process_priorities.lock().entry(pid).or_default().clone()
+    }
+
+    /// Change the priority of a live process. Returns false if it isn't alive.
+    pub fn set_priority(&self, pid: Pid, priority: Priority) -> bool {
+        match self.process_priorities.lock().get(&pid) {
+            Some(cell) => {
+                cell.set(priority);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take the pending snapshot requests for `pid`.
+    pub fn take_heap_snapshot_requests(&self, pid: Pid) -> Vec<HeapSnapshotRequest> {
+        if self.heap_snapshot_pending.load(Ordering::Relaxed) == 0 {
+            return Vec::new();
+        }
+        let requests = self.heap_snapshot_requests.lock().remove(&pid).unwrap_or_default();
+        self.heap_snapshot_pending.fetch_sub(requests.len(), Ordering::Relaxed);
+        requests
+    }
+
+    /// The wakeup that interrupts `pid` while it waits in receive.
+    pub fn process_wakeup(&self, pid: Pid) -> Arc<Notify> {
+        self.process_wakeups.lock().entry(pid).or_default().clone()
+    }
+
+    /// Ask another process to write a snapshot of its heap to `path`, and wait
+    /// for the number of objects written.
+    pub async fn request_heap_snapshot(&self, pid: Pid, path: String) -> Result<usize, String> {
+        let (reply, answer) = tokio::sync::oneshot::channel();
+        {
+            // Hold the registry lock so the process can't exit (and miss the
+            // request cleanup) between the liveness check and the insert
+            let registry = self.process_registry.read().await;
+            if !registry.contains_key(&pid) {
+                return Err(format!("process {:?} is not running", pid));
+            }
+            self.heap_snapshot_requests.lock().entry(pid).or_default().push(HeapSnapshotRequest { path, reply });
+            self.heap_snapshot_pending.fetch_add(1, Ordering::Relaxed);
+            self.process_wakeup(pid).notify_one();
+        }
+        match tokio::time::timeout(HEAP_SNAPSHOT_TIMEOUT, answer).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!("process {:?} exited before writing the snapshot", pid)),
+            Err(_) => Err(format!("process {:?} did not respond", pid)),
+        }
+    }
+
+    /// Register `name` for a running process.
+    /// Fails if the name is taken or the process already has a name.
+    pub async fn register_name(&self, name: String, pid: Pid) -> bool {
+        // Hold the registry lock so the process can't exit (and miss the
+        // name cleanup) between the liveness check and the insert
+        let registry = self.process_registry.read().await;
+        if !registry.contains_key(&pid) {
+            return false;
+        }
+        let mut names = self.process_names.lock();
+        if names.contains_key(&name) || names.values().any(|p| *p == pid) {
+            return false;
+        }
+        names.insert(name, pid);
+        true
+    }
+
+    /// Look up the process registered under `name`.
+    pub fn whereis(&self, name: &str) -> Option<Pid> {
+        self.process_names.lock().get(name).copied()
+    }
+
+    /// Remove a registered name. Returns false if the name wasn't registered.
+    pub fn unregister_name(&self, name: &str) -> bool {
+        self.process_names.lock().remove(name).is_some()
+    }
+
+    /// Start a timer owned by `owner` that sends `message` to `target` after `delay`
+    /// (and then every `interval`). Returns None if the target isn't running.
+    pub async fn start_timer(
+        &self,
+        owner: Pid,
+        target: Pid,
+        message: ThreadSafeValue,
+        delay: Duration,
+        interval: Option<Duration>,
+    ) -> Option<u64> {
+        let sender = self.process_registry.read().await.get(&target)?.clone();
+        Some(self.timers.schedule(owner, sender, message, delay, interval, |task| self.spawn_task(task)))
+    }
+
+    /// Link two processes so each one is notified when the other exits.
+    pub fn link(&self, a: Pid, b: Pid) {
+        let mut links = self.process_links.lock();
+        links.entry(a).or_default().push(b);
+        links.entry(b).or_default().push(a);
+    }
+
+    /// Make `watcher` receive a "DOWN" message when `target` exits.
+    pub fn monitor(&self, watcher: Pid, target: Pid) {
+        self.process_monitors.lock().entry(target).or_default().push(watcher);
+    }
+
+    /// Exit bookkeeping for `pid`: drop its registered name, timers and tables, and
+    /// deliver exit notifications to its links and monitors.
+    /// Must be called exactly once per process, after it left the registry.
+    pub async fn process_exited(&self, pid: Pid, reason: &ExitReason) {
+        if let Some(trace) = self.traces.remove(pid) {
+            if trace.traces(TraceKind::Exit) {
+                trace.emit(TraceEvent { pid, kind: TraceKind::Exit, peer: pid, detail: reason.to_message() });
+            }
+        }
+        self.process_names.lock().retain(|_, p| *p != pid);
+        self.timers.cancel_owned_by(pid);
+        self.tables.drop_owned_by(pid);
+
+        let linked = {
+            let mut links = self.process_links.lock();
+            let linked = links.remove(&pid).unwrap_or_default();
+            for other in &linked {
+                if let Some(back) = links.get_mut(other) {
+                    back.retain(|p| *p != pid);
+                }
+            }
+            linked
+        };
+        let watchers = self.process_monitors.lock().remove(&pid).unwrap_or_default();
+
+        let reason = reason.to_message();
+        for other in linked {
+            if !other.is_local() {
+                crate::node::send_exit(self, pid, other, crate::node::ExitKind::Link, &reason);
+                continue;
+            }
+            let msg = ThreadSafeValue::Tuple(vec![
+                ThreadSafeValue::String("EXIT".to_string()),
+                ThreadSafeValue::Pid(pid.0),
+                ThreadSafeValue::String(reason.clone()),
+            ]);
+            self.send_message(other, msg).await;
+        }
+        for watcher in watchers {
+            if !watcher.is_local() {
+                crate::node::send_exit(self, pid, watcher, crate::node::ExitKind::Monitor, &reason);
+                continue;
+            }
+            let msg = ThreadSafeValue::Tuple(vec![
+                ThreadSafeValue::String("DOWN".to_string()),
+                ThreadSafeValue::Pid(pid.0),
+                ThreadSafeValue::String(reason.clone()),
+            ]);
+            self.send_message(watcher, msg).await;
+        }
+    }
+
+    /// Deliver the exit of `from`, a process on another node, to the local
+    /// process `to` it was linked to or monitored by.
+    pub async fn remote_exited(&self, from: Pid, to: Pid, kind: crate::node::ExitKind, reason: String) {
+        let known = match kind {
+            crate::node::ExitKind::Link => {
+                let mut links = self.process_links.lock();
+                let known = remove_pid(&mut links, from, to);
+                remove_pid(&mut links, to, from);
+                known
+            }
+            crate::node::ExitKind::Monitor => remove_pid(&mut self.process_monitors.lock(), from, to),
+        };
+        if !known {
+            return;
+        }
+        let msg = ThreadSafeValue::Tuple(vec![
+            ThreadSafeValue::String(kind.tag().to_string()),
+            ThreadSafeValue::Pid(from.0),
+            ThreadSafeValue::String(reason),
+        ]);
+        self.send_message(to, msg).await;
+    }
+
+    /// The connection to node `node` was lost: every link to and monitor of
+    /// its processes fires with `reason`, and its processes stop watching ours.
+    pub async fn node_down(&self, node: u16, reason: &ExitReason) {
+        let on_node = |pid: &Pid| pid.node() == node && node != 0;
+        let mut exits = Vec::new();
+        {
+            let links = self.process_links.lock();
+            for (pid, linked) in links.iter() {
+                if on_node(pid) {
+                    exits.extend(linked.iter().map(|other| (*pid, *other, crate::node::ExitKind::Link)));
+                }
+            }
+        }
+        {
+            let mut monitors = self.process_monitors.lock();
+            monitors.retain(|pid, watchers| {
+                if on_node(pid) {
+                    exits.extend(watchers.iter().map(|watcher| (*pid, *watcher, crate::node::ExitKind::Monitor)));
+                    return true;
+                }
+                watchers.retain(|watcher| !on_node(watcher));
+                !watchers.is_empty()
+            });
+        }
+        let reason = reason.to_message();
+        for (from, to, kind) in exits {
+            self.remote_exited(from, to, kind, reason.clone()).await;
+        }
+    }
+
+    /// Stop a process: close its servers, abort its task, unregister it and
+    /// notify its links and monitors. Stopping a supervisor also stops its children.
+    /// Returns false if the process was not running.
+    pub async fn kill_process(&self, pid: Pid, reason: ExitReason) -> bool {
+        // First close any servers owned by this process to release ports
+        let servers = self.process_servers.write().await.remove(&pid);
+        if let Some(server_handles) = servers {
+            if let Some(sender) = &self.io_sender {
+                for handle in server_handles {
+                    let (tx, _rx) = tokio::sync::oneshot::channel();
+                    let request = crate::io_runtime::IoRequest::ServerClose { handle, response: tx };
+                    let _ = sender.send(request);
+                }
+            }
+        }
+
+        // Then abort the task so it actually stops running
+        let abort_handle = self.process_abort_handles.write().await.remove(&pid);
+        if let Some(handle) = abort_handle {
+            handle.abort();
+        }
+
+        // Then unregister the process (its mailbox will be dropped, messages will fail)
+        let removed = self.process_registry.write().await.remove(&pid).is_some();
+        self.forget_process_requests(pid);
+
+        let supervisor = self.supervisors.lock().remove(&pid);
+        if let Some(supervisor) = supervisor {
+            let children = supervisor.lock().await.take_children();
+            for child in children.into_iter().rev() {
+                Box::pin(self.kill_process(child, ExitReason::Shutdown)).await;
+            }
+        }
+
+        if removed {
+            self.process_exited(pid, &reason).await;
+        }
+        removed
+    }
+
+    /// Spawn a task on the runtime that owns processes.
+    /// In interactive mode this is the long-lived IO runtime, so processes
+    /// survive past individual eval calls. Otherwise it is the current runtime.
+    pub fn spawn_task<F>(&self, task: F) -> tokio::task::JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let is_interactive = self.interactive_mode.load(Ordering::SeqCst);
+        match self.spawn_runtime_handle {
+            Some(ref handle) if is_interactive => handle.spawn(task),
+            _ => tokio::spawn(task),
+        }
+    }
+
+    /// Spawn a new process running `function` with the given arguments and captures.
+    /// The process is registered (and linked or monitored) before it starts running,
+    /// so messages and exit notifications can't be lost.
+    pub async fn spawn_function(
+        self: &Arc<Self>,
+        function: Arc<FunctionValue>,
+        args: Vec<ThreadSafeValue>,
+        captures: Vec<ThreadSafeValue>,
+        linkage: SpawnLinkage,
+        options: SpawnOptions,
+    ) -> Pid {
+        // Allocate new PID
+        let child_pid = self.alloc_pid();
+
+        // Create mailbox channel BEFORE spawning to avoid race condition
+        // The child process will receive messages through this channel
+        let (mailbox_sender, mailbox_receiver) = crate::mailbox::channel(options.mailbox);
+
+        // Register the process BEFORE spawning - this ensures messages can be
+        // delivered immediately after spawn returns, and it can be traced
+        self.priority_cell(child_pid).set(options.priority);
+        self.traces.cell(child_pid);
+        self.register_process(child_pid, mailbox_sender.clone()).await;
+        match linkage {
+            SpawnLinkage::None => {}
+            SpawnLinkage::Link(other) => self.link(child_pid, other),
+            SpawnLinkage::Monitor(watcher) => self.monitor(watcher, child_pid),
+        }
+
+        // Spawn as tokio task (can run on any thread)
+        // Wrap with AssertSend because all underlying types are Send but the compiler
+        // can't prove it through async fn's opaque future types.
+        let shared_clone = self.clone();
+        let spawn_task = AssertSend(async move {
+            // Create new process with pre-created mailbox
+            let mut process = AsyncProcess::new_with_mailbox(child_pid, shared_clone.clone(), mailbox_sender, mailbox_receiver);
+            let gc_config = process.heap.config_mut();
+            if options.max_heap_bytes.is_some() {
+                gc_config.max_heap_bytes = options.max_heap_bytes;
+            }
+            if options.max_mailbox_len.is_some() {
+                gc_config.max_mailbox_len = options.max_mailbox_len;
+            }
+
+            // Convert thread-safe values back to GcValues in new heap
+            let gc_args: Vec<GcValue> = args.iter()
+                .map(|v| v.to_gc_value(&mut process.heap))
+                .collect();
+            let gc_captures: Vec<GcValue> = captures.iter()
+                .map(|v| v.to_gc_value(&mut process.heap))
+                .collect();
+
+            // Set up initial call frame
+            let reg_count = function.code.register_count;
+            let mut registers = vec![GcValue::Unit; reg_count];
+            for (i, arg) in gc_args.into_iter().enumerate() {
+                if i < reg_count {
+                    registers[i] = arg;
+                }
+            }
+
+            process.frames.push(CallFrame {
+                function,
+                ip: 0,
+                registers,
+                captures: gc_captures.into(),
+                return_reg: None,
+            });
+            process.debug_attach();
+
+            // Run the process
+            let reason = match process.run_scheduled().await {
+                Ok(value) => {
+                    process.debug_detach(Some(process.heap.display_value(&value)));
+                    ExitReason::Normal
+                }
+                Err(RuntimeError::LimitExceeded(limit)) => {
+                    process.debug_detach(None);
+                    ExitReason::LimitExceeded(limit)
+                }
+                Err(e) => {
+                    process.debug_detach(None);
+                    ExitReason::Error(e.to_string().lines().next().unwrap_or_default().to_string())
+                }
+            };
+            drop(process);
+
+            // Unregister on exit (cleanup abort handle too)
+            if shared_clone.unregister_process(child_pid).await {
+                shared_clone.process_exited(child_pid, &reason).await;
+            }
+            shared_clone.process_abort_handles.write().await.remove(&child_pid);
+        });
+
+        let join_handle = self.spawn_task(spawn_task);
+
+        // Store the abort handle so Process.kill can actually stop the task
+        self.process_abort_handles.write().await.insert(child_pid, join_handle.abort_handle());
+
+        child_pid
     }
 
     /// Get process statistics for debugging.
@@ -337,14 +822,64 @@ impl AsyncSharedState {
         (spawned, exited, active)
     }
 
-    /// Send a message to a process by PID.
+    /// Send a message to a process by PID, ignoring its mailbox capacity
+    /// (exit notifications).
     pub async fn send_message(&self, target_pid: Pid, message: ThreadSafeValue) -> bool {
+        if !target_pid.is_local() {
+            return crate::node::send(self, target_pid, &message);
+        }
         if let Some(sender) = self.process_registry.read().await.get(&target_pid) {
             sender.send(message).is_ok()
         } else {
             false // Process not found or dead
         }
     }
+
+    /// Send a message from a process (`send`, `<-`): a full bounded mailbox
+    /// applies its policy, which may wait for the receiver to make room.
+    pub async fn send_bounded(&self, target_pid: Pid, message: ThreadSafeValue) -> bool {
+        // Remote mailboxes apply their policy on their own node
+        if !target_pid.is_local() {
+            return crate::node::send(self, target_pid, &message);
+        }
+        // Don't hold the registry lock while waiting
+        let sender = self.process_registry.read().await.get(&target_pid).cloned();
+        match sender {
+            Some(sender) => sender.send_bounded(message).await.is_ok(),
+            None => false,
+        }
+    }
+
+    /// Send a message unless the target's bounded mailbox is full (`trySend`).
+    pub async fn try_send_message(&self, target_pid: Pid, message: ThreadSafeValue) -> Result<(), SendError> {
+        if !target_pid.is_local() {
+            return if crate::node::send(self, target_pid, &message) { Ok(()) } else { Err(SendError::Closed) };
+        }
+        match self.process_registry.read().await.get(&target_pid) {
+            Some(sender) => sender.try_send(message),
+            None => Err(SendError::Closed),
+        }
+    }
+}
+
+/// Turn a failed table operation into a runtime error.
+fn table_error(what: &str, error: crate::tables::TableError) -> RuntimeError {
+    RuntimeError::Panic(format!("{}: {}", what, error))
+}
+
+/// Remove `pid` from the entry of `key`, dropping the entry once it's empty.
+/// Returns false if it wasn't there.
+fn remove_pid(map: &mut HashMap<Pid, Vec<Pid>>, key: Pid, pid: Pid) -> bool {
+    let Some(pids) = map.get_mut(&key) else {
+        return false;
+    };
+    let before = pids.len();
+    pids.retain(|p| *p != pid);
+    let removed = pids.len() != before;
+    if pids.is_empty() {
+        map.remove(&key);
+    }
+    removed
 }
 
 /// An async process (runs as a tokio task).
@@ -373,6 +908,12 @@ pub struct AsyncProcess {
     /// Instruction count since last yield.
     pub instructions_since_yield: usize,
 
+    /// Scheduling priority (shared with `Process.setPriority` callers).
+    pub priority: Arc<PriorityCell>,
+
+    /// What this process is traced for (shared with `Trace.start` callers).
+    pub trace: Arc<TraceCell>,
+
     /// Linked processes.
     pub links: Vec<Pid>,
 
@@ -409,6 +950,9 @@ pub struct AsyncProcess {
     /// Profiling data (only populated when profiling is enabled).
     pub profile: Option<ProfileData>,
 
+    /// When to take the next stack sample (`None` when this process isn't sampled).
+    pub next_sample: Option<Instant>,
+
     /// Local interrupt flag for this process (used for independent eval cancellation).
     /// If Some, this takes precedence over the shared interrupt flag.
     pub local_interrupt: Option<Arc<AtomicBool>>,
@@ -439,6 +983,18 @@ pub struct AsyncProcess {
     /// Reactive render context for RHtml dependency tracking.
     /// Tracks which components depend on which reactive records.
     pub reactive_context: ReactiveRenderContext,
+
+    // === Baseline JIT state (see `jit_runtime`) ===
+
+    /// Make `step` return after a single instruction
+    pub jit_single_step: bool,
+
+    /// Step result produced by a JIT helper, handed back to `run`
+    pub jit_step_result: Option<Result<StepResult, RuntimeError>>,
+
+    /// Wakeup that interrupts receive to serve requests from other processes
+    /// (fetched from the shared state on first receive)
+    pub wakeup: Option<Arc<Notify>>,
 }
 
 /// Helper trait to convert register/constant indices (u8, u16, etc.) to usize.
@@ -472,17 +1028,20 @@ unsafe impl Send for AsyncProcess {}
 impl AsyncProcess {
     /// Create a new async process.
     pub fn new(pid: Pid, shared: Arc<AsyncSharedState>) -> Self {
-        let (sender, receiver) = mpsc::unbounded_channel();
+        let (sender, receiver) = crate::mailbox::unbounded();
         let profiling_enabled = shared.profiling_enabled;
+        let next_sample = shared.first_sample(pid);
         Self {
             pid,
-            heap: Heap::with_config(GcConfig::default()),
+            heap: Heap::with_config(shared.gc_config.clone()),
             frames: Vec::new(),
             register_pool: Vec::new(),
             mailbox: receiver,
             mailbox_sender: sender,
             state: ProcessState::Running,
             instructions_since_yield: 0,
+            priority: shared.priority_cell(pid),
+            trace: shared.traces.cell(pid),
             links: Vec::new(),
             monitors: HashMap::new(),
             monitored_by: HashMap::new(),
@@ -495,6 +1054,7 @@ impl AsyncProcess {
             held_mvar_locks: HashMap::new(),
             mvar_lock_depths: HashMap::new(),
             profile: if profiling_enabled { Some(ProfileData::new()) } else { None },
+            next_sample,
             local_interrupt: None,
             // Debugger state
             breakpoints: std::collections::HashSet::new(),
@@ -505,6 +1065,9 @@ impl AsyncProcess {
             debug_event_sender: None,
             skip_next_breakpoint_check: false,
             reactive_context: ReactiveRenderContext::default(),
+            jit_single_step: false,
+            jit_step_result: None,
+            wakeup: None,
         }
     }
 
@@ -512,15 +1075,18 @@ impl AsyncProcess {
     /// Used when the mailbox must be registered BEFORE the process starts (to avoid race conditions).
     pub fn new_with_mailbox(pid: Pid, shared: Arc<AsyncSharedState>, sender: MailboxSender, receiver: MailboxReceiver) -> Self {
         let profiling_enabled = shared.profiling_enabled;
+        let next_sample = shared.first_sample(pid);
         Self {
             pid,
-            heap: Heap::with_config(GcConfig::default()),
+            heap: Heap::with_config(shared.gc_config.clone()),
             frames: Vec::new(),
             register_pool: Vec::new(),
             mailbox: receiver,
             mailbox_sender: sender,
             state: ProcessState::Running,
             instructions_since_yield: 0,
+            priority: shared.priority_cell(pid),
+            trace: shared.traces.cell(pid),
             links: Vec::new(),
             monitors: HashMap::new(),
             monitored_by: HashMap::new(),
@@ -533,6 +1099,7 @@ impl AsyncProcess {
             held_mvar_locks: HashMap::new(),
             mvar_lock_depths: HashMap::new(),
             profile: if profiling_enabled { Some(ProfileData::new()) } else { None },
+            next_sample,
             local_interrupt: None,
             // Debugger state
             breakpoints: std::collections::HashSet::new(),
@@ -543,16 +1110,27 @@ impl AsyncProcess {
             debug_event_sender: None,
             skip_next_breakpoint_check: false,
             reactive_context: ReactiveRenderContext::default(),
+            jit_single_step: false,
+            jit_step_result: None,
+            wakeup: None,
         }
     }
 
+    /// Value of `instructions_since_yield` at the start of a time slice:
+    /// the process yields again after its priority's reduction budget.
+    #[inline]
+    fn slice_start(&self) -> usize {
+        let high_runnable = self.shared.runnable_high_priority.load(Ordering::Relaxed) > 0;
+        REDUCTIONS_PER_YIELD - self.priority.get().reduction_budget(high_runnable)
+    }
+
     /// Maybe yield to other tasks for fairness.
     /// Called periodically during instruction execution.
     #[inline]
     pub async fn maybe_yield(&mut self) {
         self.instructions_since_yield += 1;
         if self.instructions_since_yield >= REDUCTIONS_PER_YIELD {
-            self.instructions_since_yield = 0;
+            self.instructions_since_yield = self.slice_start();
             tokio::task::yield_now().await;
         }
     }
@@ -584,12 +1162,93 @@ impl AsyncProcess {
                 .or_insert_with(crate::process::FunctionStats::new)
                 .record_call(duration.as_nanos() as u64);
         }
+        self.sample_native_call(func_name);
     }
 
-    /// Check if profiling is enabled.
+    /// Record the call stack for the sampling profiler when a sample is due.
+    /// Called where the process checks for interrupts.
+    #[inline]
+    fn maybe_sample(&mut self) {
+        if let Some(next) = self.next_sample {
+            let now = Instant::now();
+            if now >= next {
+                if let Some(sampler) = &self.shared.sampler {
+                    sampler.record(self.pid, &self.frames, None, 1);
+                    self.next_sample = Some(now + sampler.config.interval);
+                }
+            }
+        }
+    }
+
+    /// Credit a just-finished call into frameless native code (the pure
+    /// numeric JIT functions) with the samples that fell due while it ran.
+    fn sample_native_call(&mut self, func_name: &str) {
+        if let (Some(next), Some(sampler)) = (self.next_sample, &self.shared.sampler) {
+            let now = Instant::now();
+            if now >= next {
+                let interval = sampler.config.interval;
+                let due = 1 + ((now - next).as_nanos() / interval.as_nanos().max(1)) as u64;
+                sampler.record(self.pid, &self.frames, Some(func_name), due);
+                self.next_sample = Some(now + interval);
+            }
+        }
+    }
+
+    /// Check if profiling is enabled (call timing or stack sampling).
     #[inline]
     pub fn is_profiling(&self) -> bool {
-        self.profile.is_some()
+        self.profile.is_some() || self.next_sample.is_some()
+    }
+
+    // === Tracing (see `crate::trace`) ===
+
+    /// Report an event of this process to its tracer.
+    fn trace_event(&self, kind: TraceKind, peer: Pid, detail: String) {
+        self.trace.emit(TraceEvent { pid: self.pid, kind, peer, detail });
+    }
+
+    /// Trace a message sent to `target`, if sends are traced.
+    #[inline]
+    fn trace_send(&self, target: Pid, message: &GcValue) {
+        if self.trace.traces(TraceKind::Send) {
+            self.trace_event(TraceKind::Send, target, self.heap.display_value(message));
+        }
+    }
+
+    /// Trace a received message, if receives are traced.
+    #[inline]
+    fn trace_receive(&self, message: &GcValue) {
+        if self.trace.traces(TraceKind::Receive) {
+            self.trace_event(TraceKind::Receive, self.pid, self.heap.display_value(message));
+        }
+    }
+
+    /// Trace the call that just set up the current frame, if calls are traced.
+    #[inline]
+    fn trace_call(&self) {
+        if !self.trace.traces(TraceKind::Call) {
+            return;
+        }
+        if let Some(frame) = self.frames.last() {
+            let args: Vec<String> = frame.registers.iter()
+                .take(frame.function.arity)
+                .map(|arg| self.heap.display_value(arg))
+                .collect();
+            let call = format!("{}({})", crate::trace::function_name(&frame.function.name), args.join(", "));
+            self.trace_event(TraceKind::Call, self.pid, call);
+        }
+    }
+
+    /// Trace the current frame returning `value`, if returns are traced.
+    #[inline]
+    fn trace_return(&self, value: &GcValue) {
+        if !self.trace.traces(TraceKind::Return) {
+            return;
+        }
+        if let Some(frame) = self.frames.last() {
+            let detail = format!("{} -> {}", crate::trace::function_name(&frame.function.name), self.heap.display_value(value));
+            self.trace_event(TraceKind::Return, self.pid, detail);
+        }
     }
 
     // === Debugger Methods ===
@@ -604,6 +1263,16 @@ impl AsyncProcess {
         }
     }
 
+    /// Whether the last executed instruction is the first of its source line.
+    fn debug_at_line_start(&self) -> bool {
+        let Some(frame) = self.frames.last() else { return false };
+        let lines = &frame.function.code.lines;
+        match frame.ip {
+            0 | 1 => true,
+            ip => lines.get(ip - 2) != lines.get(ip - 1),
+        }
+    }
+
     /// Get current function name.
     fn debug_current_function(&self) -> String {
         self.frames.last()
@@ -713,6 +1382,10 @@ impl AsyncProcess {
             return false;
         }
 
+        if self.step_mode != StepMode::Paused {
+            self.debug_poll_commands();
+        }
+
         let fn_name = self.debug_current_function();
         Self::debug_log(&format!("debug_should_pause: fn={}, step_mode={:?}, breakpoints={:?}",
             fn_name, self.step_mode, self.breakpoints));
@@ -768,7 +1441,10 @@ impl AsyncProcess {
                     }
                 }
 
-                // Check line breakpoints
+                // Check line breakpoints, once per visit of the line
+                if !self.debug_at_line_start() {
+                    return false;
+                }
                 let file = self.debug_current_file();
                 let bp = crate::shared_types::Breakpoint::Line { file, line: current_line };
                 if self.breakpoints.contains(&bp) {
@@ -844,6 +1520,48 @@ impl AsyncProcess {
         }
     }
 
+    /// Attach a newly spawned process to the running debug session, if any.
+    /// Call after the initial frame is pushed.
+    fn debug_attach(&mut self) {
+        let Some(hub) = self.shared.debug_hub.read().clone() else { return };
+        self.debug_command_receiver = Some(hub.attach(self.pid.0));
+        self.debug_event_sender = Some(hub.event_sender());
+        self.breakpoints = hub.breakpoints();
+        let function = self.debug_current_function();
+        self.debug_send_event(crate::shared_types::DebugEvent::ProcessStarted { pid: self.pid.0, function });
+    }
+
+    /// Report the exit of a process attached by `debug_attach`.
+    fn debug_detach(&self, value: Option<String>) {
+        if self.debug_event_sender.is_none() {
+            return;
+        }
+        if let Some(hub) = self.shared.debug_hub.read().as_ref() {
+            hub.detach(self.pid.0);
+        }
+        self.debug_send_event(crate::shared_types::DebugEvent::Exited { pid: self.pid.0, value });
+    }
+
+    /// Apply commands sent while the process is running: breakpoint changes
+    /// and pause requests. Everything else waits until the process pauses.
+    fn debug_poll_commands(&mut self) {
+        use crate::shared_types::{DebugCommand, StepMode};
+
+        let Some(receiver) = self.debug_command_receiver.clone() else { return };
+        while let Ok(cmd) = receiver.try_recv() {
+            match cmd {
+                DebugCommand::AddBreakpoint(bp) => {
+                    self.breakpoints.insert(bp);
+                }
+                DebugCommand::RemoveBreakpoint(bp) => {
+                    self.breakpoints.remove(&bp);
+                }
+                DebugCommand::Pause => self.step_mode = StepMode::Paused,
+                _ => {}
+            }
+        }
+    }
+
     /// Send a debug event.
     fn debug_send_event(&self, event: crate::shared_types::DebugEvent) {
         if let Some(ref sender) = self.debug_event_sender {
@@ -879,9 +1597,18 @@ impl AsyncProcess {
 
         self.debug_send_event(DebugEvent::Paused { pid: self.pid.0, file: file.clone(), line, function: function.clone(), source, source_start_line });
 
+        // Other processes keep running on this runtime while we wait
+        let multi_thread = tokio::runtime::Handle::try_current()
+            .is_ok_and(|h| h.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread);
+        let next_command = || if multi_thread {
+            tokio::task::block_in_place(|| receiver.recv())
+        } else {
+            receiver.recv()
+        };
+
         // Process commands until we get a continue/step command
         while self.step_mode == StepMode::Paused {
-            match receiver.recv() {
+            match next_command() {
                 Ok(cmd) => match cmd {
                     DebugCommand::Continue => {
                         Self::debug_log(&format!("[Continue cmd] breakpoints={:?}", self.breakpoints));
@@ -913,6 +1640,7 @@ impl AsyncProcess {
                     DebugCommand::RemoveBreakpoint(bp) => {
                         self.breakpoints.remove(&bp);
                     }
+                    DebugCommand::Pause => {}
                     DebugCommand::ListBreakpoints => {
                         self.debug_send_event(DebugEvent::Breakpoints {
                             breakpoints: self.breakpoints.iter().cloned().collect(),
@@ -1149,10 +1877,7 @@ impl AsyncProcess {
 
     /// Try to receive without blocking (for non-blocking checks).
     pub fn try_receive(&mut self) -> Option<GcValue> {
-        match self.mailbox.try_recv() {
-            Ok(msg) => Some(msg.to_gc_value(&mut self.heap)),
-            Err(_) => None,
-        }
+        self.mailbox.try_recv().map(|msg| msg.to_gc_value(&mut self.heap))
     }
 
     /// Send a message to another process.
@@ -1161,7 +1886,7 @@ impl AsyncProcess {
             Some(v) => v,
             None => return false,
         };
-        self.shared.send_message(target_pid, safe_value).await
+        self.shared.send_bounded(target_pid, safe_value).await
     }
 
     /// Spawn a new process running the given function.
@@ -1211,9 +1936,12 @@ impl AsyncProcess {
                 captures: Arc::from([] as [GcValue; 0]),
                 return_reg: None,
             });
+            process.debug_attach();
 
             // Run the process
-            let result = process.run().await;
+            let result = process.run_scheduled().await;
+            let value = result.as_ref().ok().map(|v| process.heap.display_value(v));
+            process.debug_detach(value);
 
             // Unregister on exit
             shared_clone.unregister_process(pid).await;
@@ -1224,13 +1952,158 @@ impl AsyncProcess {
         pid
     }
 
+    /// Split a function or closure value into its function and thread-safe captures.
+    pub fn thread_safe_callable(&self, value: GcValue, what: &str) -> Result<(Arc<FunctionValue>, Vec<ThreadSafeValue>), RuntimeError> {
+        let (func, captures): (Arc<FunctionValue>, Arc<[GcValue]>) = match value {
+            GcValue::Function(f) => (f, Arc::from([] as [GcValue; 0])),
+            GcValue::Closure(ptr, _) => {
+                let closure = self.heap.get_closure(ptr)
+                    .ok_or_else(|| RuntimeError::Panic("Invalid closure pointer".into()))?;
+                (closure.function.clone(), closure.captures.clone())
+            }
+            _ => return Err(RuntimeError::Panic(format!("{}: expected function or closure", what))),
+        };
+        let safe_captures = captures.iter()
+            .filter_map(|v| ThreadSafeValue::from_gc_value(v, &self.heap))
+            .collect();
+        Ok((func, safe_captures))
+    }
+
+    /// Look up the running supervisor for a `Supervisor.*` pid argument.
+    fn supervisor_arg(&self, value: GcValue) -> Result<Arc<tokio::sync::Mutex<AsyncSupervisor>>, RuntimeError> {
+        let pid = match value {
+            GcValue::Pid(p) => Pid(p),
+            _ => return Err(RuntimeError::Panic("Supervisor: expected Pid".into())),
+        };
+        self.shared.supervisors.lock().get(&pid).cloned()
+            .ok_or_else(|| RuntimeError::Panic(format!("Supervisor: {:?} is not a running supervisor", pid)))
+    }
+
+    /// Read a string argument of a process builtin (`what` names it in errors).
+    fn string_arg(&self, value: GcValue, what: &str) -> Result<String, RuntimeError> {
+        match value {
+            GcValue::String(ptr) => self.heap.get_string(ptr).map(|s| s.data.clone())
+                .ok_or_else(|| RuntimeError::Panic("Invalid string pointer".into())),
+            _ => Err(RuntimeError::Panic(format!("{} must be a String", what))),
+        }
+    }
+
+    /// Read a `[(String, String)]` argument, such as an options list.
+    fn string_pairs_arg(&self, value: GcValue, what: &str) -> Result<Vec<(String, String)>, RuntimeError> {
+        let expected = || RuntimeError::Panic(format!("{} must be a [(String, String)]", what));
+        let GcValue::List(list) = value else {
+            return Err(expected());
+        };
+        let mut pairs = Vec::new();
+        for item in list.iter() {
+            let GcValue::Tuple(ptr) = item else {
+                return Err(expected());
+            };
+            let tuple = self.heap.get_tuple(*ptr).ok_or_else(expected)?;
+            if tuple.items.len() != 2 {
+                return Err(expected());
+            }
+            pairs.push((
+                self.string_arg(tuple.items[0].clone(), what)?,
+                self.string_arg(tuple.items[1].clone(), what)?,
+            ));
+        }
+        Ok(pairs)
+    }
+
+    /// A table key argument.
+    fn table_key(&self, value: GcValue, what: &str) -> Result<SharedMapKey, RuntimeError> {
+        value.to_gc_map_key(&self.heap)
+            .map(|key| key.to_shared_key())
+            .ok_or_else(|| RuntimeError::Panic(format!("{}: key must be hashable", what)))
+    }
+
+    /// A value to store in a table.
+    fn table_value(&self, value: GcValue, what: &str) -> Result<SharedMapValue, RuntimeError> {
+        self.heap.gc_value_to_shared(&value)
+            .ok_or_else(|| RuntimeError::Panic(format!("{}: value can't be stored in a table", what)))
+    }
+
+    /// A priority name argument ("low", "normal" or "high").
+    fn priority_arg(&self, value: GcValue, what: &str) -> Result<Priority, RuntimeError> {
+        let name = self.string_arg(value, &format!("{}: priority", what))?;
+        Priority::from_name(&name).ok_or_else(|| RuntimeError::Panic(format!(
+            "{}: unknown priority '{}' (expected low, normal or high)", what, name
+        )))
+    }
+
+        /// Start a timer for Timer.sendAfter / Timer.interval and return its TimerRef.
+    /// A timer for a process that isn't running gets id 0 and never fires.
+    async fn start_timer(&mut self, ms: GcValue, target: GcValue, message: GcValue, repeat: bool) -> Result<GcValue, RuntimeError> {
+        let what = if repeat { "Timer.interval" } else { "Timer.sendAfter" };
+        let ms = match ms {
+            GcValue::Int64(ms) if ms > 0 || (ms == 0 && !repeat) => ms as u64,
+            _ => return Err(RuntimeError::Panic(format!("{}: expected a positive number of milliseconds", what))),
+        };
+        let target = match target {
+            GcValue::Pid(p) => Pid(p),
+            _ => return Err(RuntimeError::Panic(format!("{}: expected Pid", what))),
+        };
+        let message = ThreadSafeValue::from_gc_value(&message, &self.heap)
+            .ok_or_else(|| RuntimeError::Panic(format!("{}: cannot convert message", what)))?;
+
+        let delay = Duration::from_millis(ms);
+        let interval = if repeat { Some(delay) } else { None };
+        let id = self.shared.start_timer(self.pid, target, message, delay, interval).await.unwrap_or(0);
+
+        let record = self.heap.alloc_record(
+            "TimerRef".to_string(),
+            vec!["id".to_string()],
+            vec![GcValue::Int64(id as i64)],
+            vec![false],
+        );
+        Ok(GcValue::Record(record))
+    }
+
+    /// Spawn a process running a function or closure value (spawn, spawn_link, spawn_monitor).
+    async fn spawn_value(
+        &self,
+        func_val: GcValue,
+        args: &[GcValue],
+        linkage: SpawnLinkage,
+        options: SpawnOptions,
+    ) -> Result<Pid, RuntimeError> {
+        let (func, safe_captures) = self.thread_safe_callable(func_val, "Spawn")?;
+        let traced_name = self.trace.traces(TraceKind::Spawn)
+            .then(|| crate::trace::function_name(&func.name).to_string());
+
+        // Convert args to thread-safe values (deep copy)
+        let safe_args: Vec<ThreadSafeValue> = args.iter()
+            .filter_map(|v| ThreadSafeValue::from_gc_value(v, &self.heap))
+            .collect();
+
+        let child_pid = self.shared.spawn_function(func, safe_args, safe_captures, linkage, options).await;
+        if let Some(name) = traced_name {
+            self.trace_event(TraceKind::Spawn, child_pid, name);
+        }
+
+        // Yield to allow the spawned task to start running
+        // This is critical for recursive spawns where parent immediately waits
+        tokio::task::yield_now().await;
+
+        Ok(child_pid)
+    }
+
+    /// Run the process, tracking its wakeups for priority scheduling
+    /// (see `crate::priority`).
+    pub async fn run_scheduled(&mut self) -> Result<GcValue, RuntimeError> {
+        let priority = self.priority.clone();
+        let runnable_high = self.shared.runnable_high_priority.clone();
+        PriorityTask::new(self.run(), priority, runnable_high).await
+    }
+
     /// Main execution loop for this process.
     pub async fn run(&mut self) -> Result<GcValue, RuntimeError> {
         loop {
             // Only check shutdown periodically to avoid atomic load overhead
             self.instructions_since_yield += 1;
             if self.instructions_since_yield >= REDUCTIONS_PER_YIELD {
-                self.instructions_since_yield = 0;
+                self.instructions_since_yield = self.slice_start();
 
                 // Check shutdown
                 if self.shared.shutdown.load(Ordering::SeqCst) {
@@ -1246,6 +2119,8 @@ impl AsyncProcess {
                     return Err(RuntimeError::Interrupted);
                 }
 
+                self.maybe_sample();
+
                 // Run garbage collection if threshold exceeded
                 self.maybe_gc();
 
@@ -1258,14 +2133,30 @@ impl AsyncProcess {
                 return Ok(self.exit_value.take().unwrap_or(GcValue::Unit));
             }
 
-            // Execute one instruction
-            match self.step().await {
+            // Run native code when the baseline JIT compiled this frame's function
+            let result = match self.jit_run() {
+                JitExit::NotCompiled => self.step().await,
+                JitExit::Interpret => {
+                    self.jit_single_step = true;
+                    let result = self.step().await;
+                    self.jit_single_step = false;
+                    result
+                }
+                JitExit::Resume => continue,
+                JitExit::Step(result) => result,
+            };
+
+            match result {
                 Ok(StepResult::Continue) => {
                     // Continue to next instruction (yield check is at top of loop)
                 }
                 Ok(StepResult::Finished(value)) => {
                     return Ok(value);
                 }
+                Err(e @ RuntimeError::LimitExceeded(_)) => {
+                    // Limit violations end the process; try/catch can't intercept them
+                    return Err(e);
+                }
                 Err(e) => {
                     // Try to handle exception
                     if !self.handle_exception(&e) {
@@ -1279,16 +2170,27 @@ impl AsyncProcess {
     }
 
     /// Execute instructions in a tight loop until we need to yield or change frames.
-    async fn step(&mut self) -> Result<StepResult, RuntimeError> {
+    pub(crate) async fn step(&mut self) -> Result<StepResult, RuntimeError> {
         use crate::value::Instruction::{self, *};
 
+        let mut executed = false;
+
         // Execute multiple instructions without returning to reduce async overhead
         'instruction_loop: loop {
 
+        // Hand control back to baseline JIT code at its next inline instruction
+        if self.jit_single_step {
+            if executed && !self.jit_batch_continues() {
+                return Ok(StepResult::Continue);
+            }
+            executed = true;
+        }
+
         // Periodically check for interrupts and yield for fairness
+        // (baseline JIT code charges its reductions itself)
         self.instructions_since_yield += 1;
-        if self.instructions_since_yield >= REDUCTIONS_PER_YIELD {
-            self.instructions_since_yield = 0;
+        if self.instructions_since_yield >= REDUCTIONS_PER_YIELD && !self.jit_single_step {
+            self.instructions_since_yield = self.slice_start();
 
             // Check interrupt (Ctrl+C) - local flag takes precedence
             let interrupted = self.local_interrupt
@@ -1299,6 +2201,10 @@ impl AsyncProcess {
                 return Err(RuntimeError::Interrupted);
             }
 
+            self.check_limits()?;
+            self.serve_heap_snapshots();
+            self.maybe_sample();
+
             // Yield for fairness (allow other tasks to run)
             tokio::task::yield_now().await;
         }
@@ -1996,6 +2902,11 @@ impl AsyncProcess {
                 // frame.ip was already incremented, so current ip = original_ip + 1
                 // target = original_ip + 1 + offset = current_ip + offset
                 frame.ip = (frame.ip as isize + *offset as isize) as usize;
+                // Loops close with a backward jump: move the frame into native
+                // code at the loop head once the JIT compiled it
+                if *offset < 0 && self.jit_backedge() {
+                    return Ok(StepResult::Continue);
+                }
             }
             JumpIfTrue(cond, offset) => {
                 if matches!(reg_ref!(cond), GcValue::Bool(true)) {
@@ -2057,6 +2968,8 @@ impl AsyncProcess {
                 let return_reg = cur.return_reg;
                 // Take ownership of return value (avoid clone) - frame is about to be destroyed
                 let value = std::mem::take(unsafe { cur.registers.get_unchecked_mut(src.as_idx()) });
+                cur.function.type_profile.record_return(crate::jit_runtime::value_kind(&value));
+                self.trace_return(&value);
                 // Pop frame and recycle its registers
                 if let Some(frame) = self.frames.pop() {
                     self.free_registers(frame.registers);
@@ -2077,8 +2990,8 @@ impl AsyncProcess {
 
             // === Function calls ===
             CallDirect(dst, func_idx, ref args) => {
-                // Skip JIT when debugging - must use interpreted execution for breakpoints
-                let use_jit = self.debug_event_sender.is_none();
+                // Skip JIT when debugging or tracing calls - must use interpreted execution for breakpoints and trace hooks
+                let use_jit = self.debug_event_sender.is_none() && !self.trace.traces_calls();
 
                 // Check for JIT-compiled version first based on arity
                 let func_idx_u16 = *func_idx;
@@ -2424,6 +3337,7 @@ impl AsyncProcess {
                     captures: Arc::from([] as [GcValue; 0]),
                     return_reg: Some(*dst),
                 });
+                self.trace_call();
                 // Must return to recompute cur_frame (frame was pushed)
                 return Ok(StepResult::Continue);
             }
@@ -2530,6 +3444,7 @@ impl AsyncProcess {
                             captures: Arc::from([] as [GcValue; 0]),
                             return_reg: Some(*dst),
                         });
+                        self.trace_call();
                         // Must return to recompute cur_frame (frame was pushed)
                         return Ok(StepResult::Continue);
                     }
@@ -2566,6 +3481,7 @@ impl AsyncProcess {
                             captures,
                             return_reg: Some(*dst),
                         });
+                        self.trace_call();
                         // Must return to recompute cur_frame (frame was pushed)
                         return Ok(StepResult::Continue);
                     }
@@ -2580,22 +3496,7 @@ impl AsyncProcess {
 
             // === GC operations ===
             GcCollect(dst) => {
-                // Gather roots from current execution state (same as maybe_gc)
-                let mut roots = Vec::new();
-                for frame in &self.frames {
-                    for val in &frame.registers {
-                        roots.extend(val.gc_pointers());
-                    }
-                    for val in frame.captures.iter() {
-                        roots.extend(val.gc_pointers());
-                    }
-                }
-                if let Some(ref exc) = self.current_exception {
-                    roots.extend(exc.gc_pointers());
-                }
-                if let Some(ref val) = self.exit_value {
-                    roots.extend(val.gc_pointers());
-                }
+                let roots = self.gc_roots();
                 self.heap.set_roots(roots);
                 let live_before = self.heap.live_objects();
                 self.heap.collect();
@@ -2625,18 +3526,37 @@ impl AsyncProcess {
                         "totalAllocated".to_string(),
                         "totalFreed".to_string(),
                         "collections".to_string(),
+                        "minorCollections".to_string(),
+                        "majorCollections".to_string(),
                     ],
                     vec![
                         GcValue::Int64(live),
                         GcValue::Int64(stats.total_allocated as i64),
                         GcValue::Int64(stats.total_freed as i64),
                         GcValue::Int64(stats.collections as i64),
+                        GcValue::Int64(stats.minor_collections as i64),
+                        GcValue::Int64(stats.major_collections as i64),
                     ],
-                    vec![false, false, false, false],
+                    vec![false; 6],
                 );
                 set_reg!(dst, GcValue::Record(record));
             }
 
+            GcSnapshot(dst, pid_reg, path_reg) => {
+                let target_pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("Gc.snapshot: expected Pid".into())),
+                };
+                let path = self.string_arg(reg!(path_reg), "Gc.snapshot: path")?;
+                let written = if target_pid == self.pid {
+                    self.write_heap_snapshot(&path)
+                } else {
+                    self.shared.request_heap_snapshot(target_pid, path).await
+                };
+                let count = written.map_err(|e| RuntimeError::IOError(format!("Gc.snapshot: {}", e)))?;
+                set_reg!(dst, GcValue::Int64(count as i64));
+            }
+
             ProcessAll(dst) => {
                 // Get all registered process PIDs
                 let registry = self.shared.process_registry.read().await;
@@ -2664,94 +3584,452 @@ impl AsyncProcess {
                     // Return -1 for processes we can't query (matches parallel VM behavior)
                     -1
                 };
-                set_reg!(dst, GcValue::Int64(uptime_ms));
+                set_reg!(dst, GcValue::Int64(uptime_ms));
+            }
+
+            ProcessAlive(dst, pid_reg) => {
+                // Check if a process is registered (alive)
+                let target_pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("ProcessAlive: expected Pid".into())),
+                };
+
+                // Check if process is registered in the registry
+                let registry = self.shared.process_registry.read().await;
+                let alive = registry.contains_key(&target_pid);
+                drop(registry);
+                set_reg!(dst, GcValue::Bool(alive));
+            }
+
+            ProcessInfo(dst, pid_reg) => {
+                let target_pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("ProcessInfo: expected Pid".into())),
+                };
+
+                let info = if target_pid == self.pid {
+                    // Own process - return full info
+                    let status = match self.state {
+                        ProcessState::Running => "running",
+                        ProcessState::Waiting => "waiting",
+                        ProcessState::WaitingTimeout => "waiting",
+                        ProcessState::WaitingIO => "io",
+                        ProcessState::WaitingForMvar(_, _) => "waiting",
+                        ProcessState::Sleeping => "sleeping",
+                        ProcessState::Suspended => "suspended",
+                        ProcessState::Exited(_) => "exited",
+                    };
+                    let uptime_ms = self.started_at.elapsed().as_millis() as i64;
+                    Some((status, self.mailbox.len(), self.mailbox.bound(), uptime_ms, self.priority.get()))
+                } else {
+                    // Other process - its state and start time aren't shared
+                    let registry = self.shared.process_registry.read().await;
+                    registry.get(&target_pid).map(|sender| {
+                        let priority = self.shared.priority_cell(target_pid).get();
+                        ("alive", sender.len(), sender.bound(), -1, priority)
+                    })
+                };
+
+                let result = match info {
+                    Some((status, mailbox_len, bound, uptime_ms, priority)) => {
+                        let status_str = self.heap.alloc_string(status.to_string());
+                        let priority_str = self.heap.alloc_string(priority.name().to_string());
+                        let capacity = bound.map_or(0, |b| b.capacity as i64);
+                        let record = self.heap.alloc_record(
+                            "ProcessInfo".to_string(),
+                            vec!["status".to_string(), "mailbox".to_string(), "uptime".to_string(), "priority".to_string(), "mailboxCapacity".to_string()],
+                            vec![GcValue::String(status_str), GcValue::Int64(mailbox_len as i64), GcValue::Int64(uptime_ms), GcValue::String(priority_str), GcValue::Int64(capacity)],
+                            vec![false; 5],
+                        );
+                        GcValue::Record(record)
+                    }
+                    None => GcValue::Unit,
+                };
+                set_reg!(dst, result);
+            }
+
+            ProcessKill(dst, pid_reg) => {
+                // Kill a process by unregistering it AND aborting its task
+                let target_pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("ProcessKill: expected Pid".into())),
+                };
+
+                // Can't kill self
+                let killed = if target_pid == self.pid {
+                    false
+                } else {
+                    self.shared.kill_process(target_pid, ExitReason::Killed).await
+                };
+                set_reg!(dst, GcValue::Bool(killed));
+            }
+
+            ProcessSetPriority(dst, pid_reg, priority_reg) => {
+                let target_pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("Process.setPriority: expected Pid".into())),
+                };
+                let priority = self.priority_arg(reg!(priority_reg), "Process.setPriority")?;
+                set_reg!(dst, GcValue::Bool(self.shared.set_priority(target_pid, priority)));
+            }
+
+            ProcessRegister(dst, name_reg, pid_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Process.register: name")?;
+                let pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("Process.register: expected Pid".into())),
+                };
+                let registered = self.shared.register_name(name, pid).await;
+                set_reg!(dst, GcValue::Bool(registered));
+            }
+
+            ProcessWhereis(dst, name_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Process.whereis: name")?;
+                let (constructor, fields) = match self.shared.whereis(&name) {
+                    Some(pid) => ("Some", vec![GcValue::Pid(pid.0)]),
+                    None => ("None", vec![]),
+                };
+                let option = self.heap.alloc_variant(
+                    Arc::new("Option".to_string()),
+                    Arc::new(constructor.to_string()),
+                    fields,
+                );
+                set_reg!(dst, GcValue::Variant(option));
+            }
+
+            ProcessUnregister(dst, name_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Process.unregister: name")?;
+                set_reg!(dst, GcValue::Bool(self.shared.unregister_name(&name)));
+            }
+
+            ProcessRegistered(dst) => {
+                let mut names: Vec<String> = self.shared.process_names.lock().keys().cloned().collect();
+                names.sort();
+                let items: Vec<GcValue> = names.into_iter()
+                    .map(|name| GcValue::String(self.heap.alloc_string(name)))
+                    .collect();
+                set_reg!(dst, GcValue::List(GcList::from_vec(items)));
+            }
+
+            ProcessSendNamed(dst, name_reg, msg_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Process.send: name")?;
+                let sent = match self.shared.whereis(&name) {
+                    Some(pid) => {
+                        let message = reg!(msg_reg);
+                        let safe_msg = ThreadSafeValue::from_gc_value(&message, &self.heap)
+                            .ok_or_else(|| RuntimeError::Panic("Process.send: cannot convert message".into()))?;
+                        self.trace_send(pid, &message);
+                        self.shared.send_bounded(pid, safe_msg).await
+                    }
+                    None => false,
+                };
+                set_reg!(dst, GcValue::Bool(sent));
+            }
+
+            // === Distributed nodes ===
+            NodeSelf(dst) => {
+                let name = self.shared.nodes.name().to_string();
+                set_reg!(dst, GcValue::String(self.heap.alloc_string(name)));
+            }
+
+            NodeConnect(dst, name_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Node.connect: node")?;
+                let result = match crate::node::connect(&self.shared, &name).await {
+                    Ok(()) => self.make_ok_variant(GcValue::Unit),
+                    Err(e) => self.make_err_variant(&e),
+                };
+                set_reg!(dst, result);
+            }
+
+            NodeDisconnect(dst, name_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Node.disconnect: node")?;
+                let disconnected = crate::node::disconnect(&self.shared, &name).await;
+                set_reg!(dst, GcValue::Bool(disconnected));
+            }
+
+            NodeList(dst) => {
+                let items: Vec<GcValue> = self.shared.nodes.connected().into_iter()
+                    .map(|name| GcValue::String(self.heap.alloc_string(name)))
+                    .collect();
+                set_reg!(dst, GcValue::List(GcList::from_vec(items)));
+            }
+
+            NodeOf(dst, pid_reg) => {
+                let pid = match reg!(pid_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("Node.of: expected Pid".into())),
+                };
+                let name = self.shared.nodes.node_name(pid.node());
+                set_reg!(dst, GcValue::String(self.heap.alloc_string(name)));
+            }
+
+            NodeSpawn(dst, node_reg, func_reg, linkage_reg) => {
+                let node = self.string_arg(reg!(node_reg), "Node.spawnOn: node")?;
+                let linkage_name = self.string_arg(reg!(linkage_reg), "Node.spawnOn: linkage")?;
+                let linkage = match linkage_name.as_str() {
+                    "none" => crate::node::RemoteLinkage::None,
+                    "link" => crate::node::RemoteLinkage::Link,
+                    "monitor" => crate::node::RemoteLinkage::Monitor,
+                    other => return Err(RuntimeError::Panic(format!(
+                        "Node.spawnOn: unknown linkage '{}' (expected none, link or monitor)", other
+                    ))),
+                };
+                let callable = match reg!(func_reg) {
+                    value @ (GcValue::Function(_) | GcValue::Closure(..)) => ThreadSafeValue::from_gc_value(&value, &self.heap)
+                        .ok_or_else(|| RuntimeError::Panic("Node.spawnOn: cannot convert function".into()))?,
+                    _ => return Err(RuntimeError::Panic("Node.spawnOn: expected function or closure".into())),
+                };
+                let child_pid = crate::node::spawn(&self.shared, &node, &callable, self.pid, linkage).await
+                    .map_err(|e| RuntimeError::Panic(format!("Node.spawnOn: {}", e)))?;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+            }
+
+            NodeSend(dst, node_reg, name_reg, msg_reg) => {
+                let node = self.string_arg(reg!(node_reg), "Node.send: node")?;
+                let name = self.string_arg(reg!(name_reg), "Node.send: name")?;
+                let safe_msg = ThreadSafeValue::from_gc_value(&reg!(msg_reg), &self.heap)
+                    .ok_or_else(|| RuntimeError::Panic("Node.send: cannot convert message".into()))?;
+                let sent = crate::node::send_named(&self.shared, &node, &name, safe_msg).await
+                    .map_err(|e| RuntimeError::Panic(format!("Node.send: {}", e)))?;
+                set_reg!(dst, GcValue::Bool(sent));
+            }
+
+            // === Shared tables ===
+            TableNew(dst, name_reg, options_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.new: name")?;
+                let option_names = match reg!(options_reg) {
+                    GcValue::List(list) => list.iter()
+                        .map(|v| self.string_arg(v.clone(), "Table.new: option"))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    _ => return Err(RuntimeError::Panic("Table.new: expected a list of options".into())),
+                };
+                let options = crate::tables::TableOptions::from_names(option_names.iter().map(String::as_str))
+                    .map_err(|e| RuntimeError::Panic(format!("Table.new: {}", e)))?;
+                let created = self.shared.tables.create(&name, self.pid, options);
+                set_reg!(dst, GcValue::Bool(created));
+            }
+
+            TableInsert(dst, name_reg, key_reg, value_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.insert: name")?;
+                let key = self.table_key(reg!(key_reg), "Table.insert")?;
+                let value = self.table_value(reg!(value_reg), "Table.insert")?;
+                self.shared.tables.insert(&name, self.pid, key, value).map_err(|e| table_error("Table.insert", e))?;
+                set_reg!(dst, GcValue::Unit);
+            }
+
+            TableLookup(dst, name_reg, key_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.lookup: name")?;
+                let key = self.table_key(reg!(key_reg), "Table.lookup")?;
+                let values = self.shared.tables.lookup(&name, &key).map_err(|e| table_error("Table.lookup", e))?;
+                let items: Vec<GcValue> = values.iter().map(|v| self.heap.shared_to_gc_value(v)).collect();
+                set_reg!(dst, GcValue::List(GcList::from_vec(items)));
+            }
+
+            TableDelete(dst, name_reg, key_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.delete: name")?;
+                let key = self.table_key(reg!(key_reg), "Table.delete")?;
+                let removed = self.shared.tables.delete(&name, self.pid, &key).map_err(|e| table_error("Table.delete", e))?;
+                set_reg!(dst, GcValue::Bool(removed));
+            }
+
+            TableMatch(dst, name_reg, pattern_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.matchKeys: name")?;
+                let pattern = self.table_key(reg!(pattern_reg), "Table.matchKeys")?;
+                let entries = self.shared.tables.matching(&name, &pattern).map_err(|e| table_error("Table.matchKeys", e))?;
+                let items: Vec<GcValue> = entries.iter().map(|(key, value)| {
+                    let key = GcMapKey::from_shared_key(key).to_gc_value(&mut self.heap);
+                    let value = self.heap.shared_to_gc_value(value);
+                    GcValue::Tuple(self.heap.alloc_tuple(vec![key, value]))
+                }).collect();
+                set_reg!(dst, GcValue::List(GcList::from_vec(items)));
+            }
+
+            TableSize(dst, name_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.size: name")?;
+                let size = self.shared.tables.size(&name).map_err(|e| table_error("Table.size", e))?;
+                set_reg!(dst, GcValue::Int64(size as i64));
+            }
+
+            TableDrop(dst, name_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.drop: name")?;
+                let dropped = self.shared.tables.drop_table(&name, self.pid).map_err(|e| table_error("Table.drop", e))?;
+                set_reg!(dst, GcValue::Bool(dropped));
+            }
+
+            TableRead(value_dst, version_dst, name_reg, key_reg, default_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.update: name")?;
+                let key = self.table_key(reg!(key_reg), "Table.update")?;
+                let (value, version) = self.shared.tables.read_for_update(&name, self.pid, &key)
+                    .map_err(|e| table_error("Table.update", e))?;
+                let value = match value {
+                    Some(value) => self.heap.shared_to_gc_value(&value),
+                    None => reg!(default_reg),
+                };
+                set_reg!(value_dst, value);
+                set_reg!(version_dst, GcValue::Int64(version as i64));
+            }
+
+            TableCommit(dst, name_reg, key_reg, version_reg, value_reg) => {
+                let name = self.string_arg(reg!(name_reg), "Table.update: name")?;
+                let key = self.table_key(reg!(key_reg), "Table.update")?;
+                let version = match reg!(version_reg) {
+                    GcValue::Int64(v) => v as u64,
+                    _ => return Err(RuntimeError::Panic("Table.update: expected Int version".into())),
+                };
+                let value = self.table_value(reg!(value_reg), "Table.update")?;
+                let stored = self.shared.tables.commit(&name, self.pid, key, version, value)
+                    .map_err(|e| table_error("Table.update", e))?;
+                set_reg!(dst, GcValue::Bool(stored));
             }
 
-            ProcessAlive(dst, pid_reg) => {
-                // Check if a process is registered (alive)
-                let target_pid = match reg!(pid_reg) {
-                    GcValue::Pid(p) => Pid(p),
-                    _ => return Err(RuntimeError::Panic("ProcessAlive: expected Pid".into())),
+            // === Hot code upgrade ===
+            CodeReload(dst, module_reg) => {
+                let module = self.string_arg(reg!(module_reg), "Code.reload: module")?;
+                let result = match crate::code::reload(&self.shared, &module).await {
+                    Ok(version) => self.make_ok_variant(GcValue::Int64(version as i64)),
+                    Err(e) => self.make_err_variant(&e),
                 };
+                set_reg!(dst, result);
+            }
 
-                // Check if process is registered in the registry
-                let registry = self.shared.process_registry.read().await;
-                let alive = registry.contains_key(&target_pid);
-                drop(registry);
-                set_reg!(dst, GcValue::Bool(alive));
+            CodeVersion(dst, module_reg) => {
+                let module = self.string_arg(reg!(module_reg), "Code.version: module")?;
+                let version = self.shared.code_versions.version(&module);
+                set_reg!(dst, GcValue::Int64(version as i64));
             }
 
-            ProcessInfo(dst, pid_reg) => {
-                // Get process info - only works for own process in async VM
-                let target_pid = match reg!(pid_reg) {
+            CodeChange(state_reg, func_idx) => {
+                // SAFETY: cur_frame is valid
+                let running = unsafe { self.frames.get_unchecked(cur_frame).function.clone() };
+                if let Some(hook) = crate::code::upgrade_hook(&self.shared, &running, *func_idx) {
+                    let mut registers = self.alloc_registers(hook.code.register_count);
+                    if let Some(first) = registers.first_mut() {
+                        *first = reg!(state_reg);
+                    }
+                    self.profile_enter(&hook.name);
+                    // The migrated state replaces the old one before the call is made
+                    self.frames.push(CallFrame {
+                        function: hook,
+                        ip: 0,
+                        registers,
+                        captures: Arc::from([] as [GcValue; 0]),
+                        return_reg: Some(*state_reg),
+                    });
+                    self.trace_call();
+                    return Ok(StepResult::Continue);
+                }
+            }
+
+            // === Process tracing ===
+            TraceStart(dst, pid_reg, flags_reg) => {
+                let pid = match reg!(pid_reg) {
                     GcValue::Pid(p) => Pid(p),
-                    _ => return Err(RuntimeError::Panic("ProcessInfo: expected Pid".into())),
+                    _ => return Err(RuntimeError::Panic("Trace.start: expected Pid".into())),
                 };
-
-                let result = if target_pid == self.pid {
-                    // Own process - return full info
-                    let status = match self.state {
-                        ProcessState::Running => "running",
-                        ProcessState::Waiting => "waiting",
-                        ProcessState::WaitingTimeout => "waiting",
-                        ProcessState::WaitingIO => "io",
-                        ProcessState::WaitingForMvar(_, _) => "waiting",
-                        ProcessState::Sleeping => "sleeping",
-                        ProcessState::Suspended => "suspended",
-                        ProcessState::Exited(_) => "exited",
-                    };
-                    let mailbox_len = 0i64; // Can't easily query own mailbox length
-                    let uptime_ms = self.started_at.elapsed().as_millis() as i64;
-
-                    let status_str = self.heap.alloc_string(status.to_string());
-                    let record = self.heap.alloc_record(
-                        "ProcessInfo".to_string(),
-                        vec!["status".to_string(), "mailbox".to_string(), "uptime".to_string()],
-                        vec![GcValue::String(status_str), GcValue::Int64(mailbox_len), GcValue::Int64(uptime_ms)],
-                        vec![false, false, false],
-                    );
-                    GcValue::Record(record)
-                } else {
-                    // Other process - can't access directly
-                    GcValue::Unit
+                let flag_names = match reg!(flags_reg) {
+                    GcValue::List(list) => list.iter()
+                        .map(|v| self.string_arg(v.clone(), "Trace.start: flag"))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    _ => return Err(RuntimeError::Panic("Trace.start: expected a list of flags".into())),
                 };
-                set_reg!(dst, result);
+                let flags = crate::trace::TraceFlags::from_names(flag_names.iter().map(String::as_str))
+                    .map_err(|e| RuntimeError::Panic(format!("Trace.start: {}", e)))?;
+                if pid == self.pid {
+                    return Err(RuntimeError::Panic("Trace.start: a process can't trace itself".into()));
+                }
+                let tracer = crate::trace::Tracer::Process(self.pid, self.mailbox_sender.clone());
+                set_reg!(dst, GcValue::Bool(self.shared.traces.start(pid, tracer, flags)));
             }
 
-            ProcessKill(dst, pid_reg) => {
-                // Kill a process by unregistering it AND aborting its task
-                let target_pid = match reg!(pid_reg) {
+            TraceStop(dst, pid_reg) => {
+                let pid = match reg!(pid_reg) {
                     GcValue::Pid(p) => Pid(p),
-                    _ => return Err(RuntimeError::Panic("ProcessKill: expected Pid".into())),
+                    _ => return Err(RuntimeError::Panic("Trace.stop: expected Pid".into())),
                 };
+                set_reg!(dst, GcValue::Bool(self.shared.traces.stop(pid)));
+            }
 
-                // Can't kill self
-                let killed = if target_pid == self.pid {
-                    false
-                } else {
-                    // First close any servers owned by this process to release ports
-                    let servers = self.shared.process_servers.write().await.remove(&target_pid);
-                    if let Some(server_handles) = servers {
-                        if let Some(sender) = &self.shared.io_sender {
-                            for handle in server_handles {
-                                let (tx, _rx) = tokio::sync::oneshot::channel();
-                                let request = crate::io_runtime::IoRequest::ServerClose { handle, response: tx };
-                                let _ = sender.send(request);
-                            }
-                        }
-                    }
+            // === Timers ===
+            TimerSendAfter(dst, ms_reg, pid_reg, msg_reg) => {
+                let timer_ref = self.start_timer(reg!(ms_reg), reg!(pid_reg), reg!(msg_reg), false).await?;
+                set_reg!(dst, timer_ref);
+            }
 
-                    // Then abort the task so it actually stops running
-                    let abort_handle = self.shared.process_abort_handles.write().await.remove(&target_pid);
-                    if let Some(handle) = abort_handle {
-                        handle.abort();
-                    }
+            TimerInterval(dst, ms_reg, pid_reg, msg_reg) => {
+                let timer_ref = self.start_timer(reg!(ms_reg), reg!(pid_reg), reg!(msg_reg), true).await?;
+                set_reg!(dst, timer_ref);
+            }
+
+            TimerCancel(dst, ref_reg) => {
+                let id = match reg!(ref_reg) {
+                    GcValue::Record(ptr) => match self.heap.get_record(ptr).map(|r| r.fields.first()) {
+                        Some(Some(GcValue::Int64(id))) => *id as u64,
+                        _ => return Err(RuntimeError::Panic("Timer.cancel: expected TimerRef".into())),
+                    },
+                    _ => return Err(RuntimeError::Panic("Timer.cancel: expected TimerRef".into())),
+                };
+                set_reg!(dst, GcValue::Bool(self.shared.timers.cancel(id)));
+            }
+
+            // === Supervision ===
+            SupervisorStart(dst, strategy_reg, max_restarts_reg, max_seconds_reg) => {
+                let strategy_name = self.string_arg(reg!(strategy_reg), "Supervisor: strategy")?;
+                let strategy = RestartStrategy::from_name(&strategy_name).ok_or_else(|| RuntimeError::Panic(format!(
+                    "Supervisor.start: unknown strategy '{}' (expected oneForOne, oneForAll or restForOne)", strategy_name
+                )))?;
+                let (max_restarts, max_seconds) = match (reg!(max_restarts_reg), reg!(max_seconds_reg)) {
+                    (GcValue::Int64(r), GcValue::Int64(s)) if r >= 0 && s > 0 => (r as u32, s as u32),
+                    _ => return Err(RuntimeError::Panic(
+                        "Supervisor.start: maxRestarts must be >= 0 and maxSeconds > 0".into()
+                    )),
+                };
+                let config = SupervisorConfig { strategy, max_restarts, max_seconds };
+                let sup_pid = AsyncSupervisor::start(&self.shared, self.pid, config).await;
+                set_reg!(dst, GcValue::Pid(sup_pid.0));
+            }
+
+            SupervisorStartChild(dst, sup_reg, id_reg, func_reg, restart_reg) => {
+                let supervisor = self.supervisor_arg(reg!(sup_reg))?;
+                let id = self.string_arg(reg!(id_reg), "Supervisor: child id")?;
+                let restart_name = self.string_arg(reg!(restart_reg), "Supervisor: restart type")?;
+                let restart = RestartType::from_name(&restart_name).ok_or_else(|| RuntimeError::Panic(format!(
+                    "Supervisor.startChild: unknown restart type '{}' (expected permanent, transient or temporary)", restart_name
+                )))?;
+                let (function, captures) = self.thread_safe_callable(reg!(func_reg), "Supervisor.startChild")?;
+                let spec = AsyncChildSpec { id, function, captures, restart };
+                let child_pid = supervisor.lock().await.start_child(&self.shared, spec).await
+                    .map_err(|e| RuntimeError::Panic(format!("Supervisor.startChild: {}", e)))?;
+                tokio::task::yield_now().await;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+            }
 
-                    // Then unregister the process (its mailbox will be dropped, messages will fail)
-                    let mut registry = self.shared.process_registry.write().await;
-                    registry.remove(&target_pid).is_some()
+            SupervisorTerminateChild(dst, sup_reg, id_reg) => {
+                let supervisor = self.supervisor_arg(reg!(sup_reg))?;
+                let id = self.string_arg(reg!(id_reg), "Supervisor: child id")?;
+                let terminated = supervisor.lock().await.terminate_child(&self.shared, &id).await;
+                set_reg!(dst, GcValue::Bool(terminated));
+            }
+
+            SupervisorWhichChildren(dst, sup_reg) => {
+                let supervisor = self.supervisor_arg(reg!(sup_reg))?;
+                let children = supervisor.lock().await.which_children();
+                let items: Vec<GcValue> = children.into_iter()
+                    .map(|(id, pid)| {
+                        let id_ptr = self.heap.alloc_string(id);
+                        GcValue::Tuple(self.heap.alloc_tuple(vec![GcValue::String(id_ptr), GcValue::Pid(pid.0)]))
+                    })
+                    .collect();
+                set_reg!(dst, GcValue::List(GcList::from_vec(items)));
+            }
+
+            SupervisorStop(dst, sup_reg) => {
+                let sup_pid = match reg!(sup_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("Supervisor.stop: expected Pid".into())),
                 };
-                set_reg!(dst, GcValue::Bool(killed));
+                let is_supervisor = self.shared.supervisors.lock().contains_key(&sup_pid);
+                let stopped = is_supervisor && self.shared.kill_process(sup_pid, ExitReason::Shutdown).await;
+                set_reg!(dst, GcValue::Bool(stopped));
             }
 
             // === MVar operations (async!) ===
@@ -2878,8 +4156,8 @@ impl AsyncProcess {
 
             // === Tail call (replaces current frame) ===
             TailCallDirect(func_idx, ref args) => {
-                // Skip JIT when debugging - must use interpreted execution for breakpoints
-                let use_jit = self.debug_event_sender.is_none();
+                // Skip JIT when debugging or tracing calls - must use interpreted execution for breakpoints and trace hooks
+                let use_jit = self.debug_event_sender.is_none() && !self.trace.traces_calls();
 
                 // Check for JIT-compiled version first
                 let func_idx_u16 = *func_idx;
@@ -3219,6 +4497,7 @@ impl AsyncProcess {
                     frame.captures = Arc::from([] as [GcValue; 0]);
                 }
                 // Note: return_reg stays the same since we're replacing this call
+                self.trace_call();
             }
 
             GeInt(dst, a, b) => {
@@ -3347,6 +4626,7 @@ impl AsyncProcess {
                     captures: Arc::from([] as [GcValue; 0]),
                     return_reg: Some(*dst),
                 });
+                self.trace_call();
                 // Must return to recompute cur_frame (frame was pushed)
                 return Ok(StepResult::Continue);
             }
@@ -3399,6 +4679,7 @@ impl AsyncProcess {
                     frame.ip = 0;
                     // NOTE: Do NOT clear captures - closures need them!
                 }
+                self.trace_call();
             }
 
             // === Assertions ===
@@ -3543,6 +4824,30 @@ impl AsyncProcess {
                 }
             }
 
+            // C function from an `extern` declaration
+            CallExtern(dst, desc_idx, ref args) => {
+                let descriptor = match get_const!(desc_idx) {
+                    Value::String(s) => s,
+                    _ => return Err(RuntimeError::Panic("CallExtern: expected string constant".into())),
+                };
+                let extern_fn = crate::ffi::resolve(&descriptor)
+                    .map_err(|e| RuntimeError::Panic(format!("Extern call failed: {}", e)))?;
+
+                let mut ffi_args = Vec::with_capacity(args.len());
+                for (r, ty) in args.iter().zip(extern_fn.signature.params.iter()) {
+                    let gc_val = reg!(*r);
+                    let arg = crate::ffi::to_ffi_arg(&mut self.heap, &gc_val, *ty)
+                        .map_err(|e| RuntimeError::Panic(format!("Extern call to `{}` failed: {}", extern_fn.signature.symbol, e)))?;
+                    ffi_args.push(arg);
+                }
+
+                // SAFETY: the extern declaration is the user's promise about the C signature.
+                let result = unsafe { extern_fn.call(&ffi_args) }
+                    .map_err(|e| RuntimeError::Panic(format!("Extern call failed: {}", e)))?;
+                let gc_val = crate::ffi::from_ffi_return(&mut self.heap, result);
+                set_reg!(dst, gc_val);
+            }
+
             // === Closures ===
             MakeClosure(dst, func_idx, ref captures) => {
                 let function = match get_const!(func_idx) {
@@ -3857,6 +5162,7 @@ impl AsyncProcess {
                 let rec_val = reg!(record_reg);
                 match rec_val {
                     GcValue::Record(ptr) => {
+                        self.heap.write_barrier(ptr.as_raw(), &value);
                         let rec = self.heap.get_record_mut(ptr)
                             .ok_or_else(|| RuntimeError::Panic("Invalid record reference".into()))?;
                         let idx = rec.field_names.iter().position(|n| n == &field_name)
@@ -3953,6 +5259,7 @@ impl AsyncProcess {
                 match rec_val {
                     GcValue::Record(ptr) => {
                         let idx = *field_idx as usize;
+                        self.heap.write_barrier(ptr.as_raw(), &value);
                         let rec = self.heap.get_record_mut(ptr)
                             .ok_or_else(|| RuntimeError::Panic("Invalid record reference".into()))?;
                         if idx >= rec.fields.len() {
@@ -4732,6 +6039,7 @@ impl AsyncProcess {
                 match coll_val {
                     GcValue::Array(ptr) => {
                         let new_value = reg!(val);
+                        self.heap.write_barrier(ptr.as_raw(), &new_value);
                         let array = self.heap.get_array_mut(ptr)
                             .ok_or_else(|| RuntimeError::Panic("Invalid array reference".into()))?;
                         if idx_val >= array.items.len() {
@@ -5042,98 +6350,68 @@ impl AsyncProcess {
             Spawn(dst, func_reg, ref args) => {
                 let func_val = reg!(func_reg);
                 let arg_values: Vec<GcValue> = args.iter().map(|r| reg!(*r)).collect();
+                let child_pid = self.spawn_value(func_val, &arg_values, SpawnLinkage::None, SpawnOptions::default()).await?;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+            }
 
-                let (func, captures): (Arc<FunctionValue>, Arc<[GcValue]>) = match func_val {
-                    GcValue::Function(f) => (f, Arc::from([] as [GcValue; 0])),
-                    GcValue::Closure(ptr, _) => {
-                        let closure = self.heap.get_closure(ptr)
-                            .ok_or_else(|| RuntimeError::Panic("Invalid closure pointer".into()))?;
-                        (closure.function.clone(), closure.captures.clone())
-                    }
-                    _ => return Err(RuntimeError::Panic("Spawn: expected function or closure".into())),
-                };
-
-                // Convert args and captures to thread-safe values (deep copy)
-                let safe_args: Vec<ThreadSafeValue> = arg_values.iter()
-                    .filter_map(|v| ThreadSafeValue::from_gc_value(v, &self.heap))
-                    .collect();
-                let safe_captures: Vec<ThreadSafeValue> = captures.iter()
-                    .filter_map(|v| ThreadSafeValue::from_gc_value(v, &self.heap))
-                    .collect();
-
-                // Allocate new PID
-                let child_pid = self.shared.alloc_pid();
-                let shared_clone = self.shared.clone();
-
-                // Create mailbox channel BEFORE spawning to avoid race condition
-                // The child process will receive messages through this channel
-                let (mailbox_sender, mailbox_receiver) = tokio::sync::mpsc::unbounded_channel();
-
-                // Register the process BEFORE spawning - this ensures messages can be
-                // delivered immediately after spawn returns
-                self.shared.register_process(child_pid, mailbox_sender.clone()).await;
-
-                // Spawn as tokio task (can run on any thread)
-                // Wrap with AssertSend because all underlying types are Send but the compiler
-                // can't prove it through async fn's opaque future types.
-                let shared_for_cleanup = self.shared.clone();
-                let spawn_task = AssertSend(async move {
-                    // Create new process with pre-created mailbox
-                    let mut process = AsyncProcess::new_with_mailbox(child_pid, shared_clone.clone(), mailbox_sender, mailbox_receiver);
-
-                    // Convert thread-safe values back to GcValues in new heap
-                    let gc_args: Vec<GcValue> = safe_args.iter()
-                        .map(|v| v.to_gc_value(&mut process.heap))
-                        .collect();
-                    let gc_captures: Vec<GcValue> = safe_captures.iter()
-                        .map(|v| v.to_gc_value(&mut process.heap))
-                        .collect();
-
-                    // Set up initial call frame
-                    let reg_count = func.code.register_count;
-                    let mut registers = vec![GcValue::Unit; reg_count];
-                    for (i, arg) in gc_args.into_iter().enumerate() {
-                        if i < reg_count {
-                            registers[i] = arg;
-                        }
-                    }
-
-                    process.frames.push(CallFrame {
-                        function: func.clone(),
-                        ip: 0,
-                        registers,
-                        captures: gc_captures.into(),
-                        return_reg: None,
-                    });
-
-                    // Run the process
-                    let _result = process.run().await;
+            SpawnLink(dst, func_reg, ref args) => {
+                let func_val = reg!(func_reg);
+                let arg_values: Vec<GcValue> = args.iter().map(|r| reg!(*r)).collect();
+                let child_pid = self.spawn_value(func_val, &arg_values, SpawnLinkage::Link(self.pid), SpawnOptions::default()).await?;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+            }
 
-                    // Unregister on exit (cleanup abort handle too)
-                    shared_clone.unregister_process(child_pid).await;
-                    shared_clone.process_abort_handles.write().await.remove(&child_pid);
-                });
+            SpawnMonitor(dst, ref_dst, func_reg, ref args) => {
+                let func_val = reg!(func_reg);
+                let arg_values: Vec<GcValue> = args.iter().map(|r| reg!(*r)).collect();
+                let child_pid = self.spawn_value(func_val, &arg_values, SpawnLinkage::Monitor(self.pid), SpawnOptions::default()).await?;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+                set_reg!(ref_dst, GcValue::Pid(child_pid.0));
+            }
 
-                // In interactive mode, use the long-lived IO runtime for spawned processes
-                // so they survive past individual eval calls. Otherwise use the current runtime.
-                let is_interactive = self.shared.interactive_mode.load(Ordering::SeqCst);
-                let join_handle = if let Some(ref handle) = self.shared.spawn_runtime_handle {
-                    if is_interactive {
-                        handle.spawn(spawn_task)
-                    } else {
-                        tokio::spawn(spawn_task)
+            SpawnLimited(dst, func_reg, heap_reg, mailbox_reg, linkage_reg) => {
+                // A limit <= 0 keeps the VM-wide default
+                let (max_heap_bytes, max_mailbox_len) = match (reg!(heap_reg), reg!(mailbox_reg)) {
+                    (GcValue::Int64(h), GcValue::Int64(m)) => {
+                        ((h > 0).then_some(h as usize), (m > 0).then_some(m as usize))
                     }
-                } else {
-                    tokio::spawn(spawn_task)
+                    _ => return Err(RuntimeError::Panic("Process.spawnLimited: expected Int limits".into())),
                 };
+                let linkage_name = self.string_arg(reg!(linkage_reg), "Process.spawnLimited: linkage")?;
+                let linkage = match linkage_name.as_str() {
+                    "none" => SpawnLinkage::None,
+                    "link" => SpawnLinkage::Link(self.pid),
+                    "monitor" => SpawnLinkage::Monitor(self.pid),
+                    other => return Err(RuntimeError::Panic(format!(
+                        "Process.spawnLimited: unknown linkage '{}' (expected none, link or monitor)", other
+                    ))),
+                };
+                let options = SpawnOptions { max_heap_bytes, max_mailbox_len, ..Default::default() };
+                let child_pid = self.spawn_value(reg!(func_reg), &[], linkage, options).await?;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+            }
 
-                // Store the abort handle so Process.kill can actually stop the task
-                shared_for_cleanup.process_abort_handles.write().await.insert(child_pid, join_handle.abort_handle());
-
-                // Yield to allow the spawned task to start running
-                // This is critical for recursive spawns where parent immediately waits
-                tokio::task::yield_now().await;
+            SpawnPriority(dst, func_reg, priority_reg) => {
+                let priority = self.priority_arg(reg!(priority_reg), "Process.spawnPriority")?;
+                let options = SpawnOptions { priority, ..Default::default() };
+                let child_pid = self.spawn_value(reg!(func_reg), &[], SpawnLinkage::None, options).await?;
+                set_reg!(dst, GcValue::Pid(child_pid.0));
+            }
 
+            SpawnBounded(dst, func_reg, capacity_reg, policy_reg) => {
+                let capacity = match reg!(capacity_reg) {
+                    GcValue::Int64(n) if n > 0 => n as usize,
+                    GcValue::Int64(n) => return Err(RuntimeError::Panic(format!(
+                        "Process.spawnBounded: capacity must be positive, got {}", n
+                    ))),
+                    _ => return Err(RuntimeError::Panic("Process.spawnBounded: expected Int capacity".into())),
+                };
+                let policy_name = self.string_arg(reg!(policy_reg), "Process.spawnBounded: policy")?;
+                let policy = MailboxPolicy::from_name(&policy_name).ok_or_else(|| RuntimeError::Panic(format!(
+                    "Process.spawnBounded: unknown policy '{}' (expected block, dropNewest or dropOldest)", policy_name
+                )))?;
+                let options = SpawnOptions { mailbox: Some(MailboxBound { capacity, policy }), ..Default::default() };
+                let child_pid = self.spawn_value(reg!(func_reg), &[], SpawnLinkage::None, options).await?;
                 set_reg!(dst, GcValue::Pid(child_pid.0));
             }
 
@@ -5151,17 +6429,39 @@ impl AsyncProcess {
                 let safe_msg = ThreadSafeValue::from_gc_value(&message, &self.heap)
                     .ok_or_else(|| RuntimeError::Panic("Send: cannot convert message".into()))?;
 
-                // Send via async channel (non-blocking)
-                self.shared.send_message(target_pid, safe_msg).await;
+                self.trace_send(target_pid, &message);
+                // Waits only if the target's bounded mailbox is full and blocks
+                self.shared.send_bounded(target_pid, safe_msg).await;
+            }
+
+            TrySend(dst, target_reg, msg_reg) => {
+                let target_pid = match reg!(target_reg) {
+                    GcValue::Pid(p) => Pid(p),
+                    _ => return Err(RuntimeError::Panic("trySend: expected Pid".into())),
+                };
+                let message = reg!(msg_reg);
+                let safe_msg = ThreadSafeValue::from_gc_value(&message, &self.heap)
+                    .ok_or_else(|| RuntimeError::Panic("trySend: cannot convert message".into()))?;
+                let result = match self.shared.try_send_message(target_pid, safe_msg).await {
+                    Ok(()) => {
+                        self.trace_send(target_pid, &message);
+                        self.make_ok_variant(GcValue::Unit)
+                    }
+                    Err(e) => self.make_err_variant(&e.to_string()),
+                };
+                set_reg!(dst, result);
             }
 
             // === Concurrency: Receive (async!) ===
             Receive(dst) => {
+                // A consumer that fell behind hits its mailbox limit here
+                self.check_limits()?;
                 // This is where async shines - we await the message!
                 // Unlike the parallel VM which polls, we yield to tokio scheduler
-                match self.mailbox.recv().await {
+                match self.receive_serving_requests().await {
                     Some(msg) => {
                         let gc_msg = msg.to_gc_value(&mut self.heap);
+                        self.trace_receive(&gc_msg);
                         set_reg!(dst, gc_msg);
                     }
                     None => {
@@ -5173,6 +6473,7 @@ impl AsyncProcess {
 
             // === Concurrency: Receive with Timeout ===
             ReceiveTimeout(dst, timeout_reg) => {
+                self.check_limits()?;
                 let timeout_ms = match reg!(timeout_reg) {
                     GcValue::Int64(n) => n as u64,
                     _ => return Err(RuntimeError::Panic("ReceiveTimeout: expected Int64 for timeout".into())),
@@ -5181,10 +6482,11 @@ impl AsyncProcess {
                 let timeout = Duration::from_millis(timeout_ms);
 
                 // Use tokio timeout - properly yields to scheduler
-                match tokio::time::timeout(timeout, self.mailbox.recv()).await {
+                match tokio::time::timeout(timeout, self.receive_serving_requests()).await {
                     Ok(Some(msg)) => {
                         // Message received before timeout
                         let gc_msg = msg.to_gc_value(&mut self.heap);
+                        self.trace_receive(&gc_msg);
                         set_reg!(dst, gc_msg);
                     }
                     Ok(None) => {
@@ -5546,6 +6848,7 @@ impl AsyncProcess {
                             captures: Arc::from([] as [GcValue; 0]),
                             return_reg,
                         });
+                        self.trace_call();
                         // Must return to recompute cur_frame (frame was replaced)
                         return Ok(StepResult::Continue);
                     }
@@ -5567,6 +6870,7 @@ impl AsyncProcess {
                             captures,
                             return_reg,
                         });
+                        self.trace_call();
                         // Must return to recompute cur_frame (frame was replaced)
                         return Ok(StepResult::Continue);
                     }
@@ -7057,7 +8361,39 @@ impl AsyncProcess {
                 };
                 let (tx, rx) = tokio::sync::oneshot::channel();
                 if let Some(sender) = &self.shared.io_sender {
-                    let request = IoRequest::ServerBind { port, response: tx };
+                    let request = IoRequest::ServerBind { port, tls: None, response: tx };
+                    if sender.send(request).is_err() {
+                        return Err(RuntimeError::IOError("IO runtime shutdown".to_string()));
+                    }
+                    let result = rx.await.map_err(|_| RuntimeError::IOError("IO response channel closed".to_string()))?;
+                    if let Some(gc_value) = self.handle_io_result(result, "io_error")? {
+                        // Track this server handle as owned by this process
+                        if let GcValue::Int64(handle) = gc_value {
+                            let mut servers = self.shared.process_servers.write().await;
+                            servers.entry(self.pid).or_insert_with(Vec::new).push(handle as u64);
+                        }
+                        set_reg!(dst, gc_value);
+                    }
+                } else {
+                    return Err(RuntimeError::IOError("IO runtime not available".to_string()));
+                }
+            }
+
+            ServerBindTls(dst, port_reg, cert_reg, key_reg) => {
+                let port = match reg!(port_reg) {
+                    GcValue::Int64(n) => n as u16,
+                    _ => return Err(RuntimeError::TypeError {
+                        expected: "Int".to_string(),
+                        found: "non-int".to_string(),
+                    }),
+                };
+                let tls = TlsFiles {
+                    cert_path: self.string_arg(reg!(cert_reg), "Server.bindTls: certPath")?,
+                    key_path: self.string_arg(reg!(key_reg), "Server.bindTls: keyPath")?,
+                };
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                if let Some(sender) = &self.shared.io_sender {
+                    let request = IoRequest::ServerBind { port, tls: Some(tls), response: tx };
                     if sender.send(request).is_err() {
                         return Err(RuntimeError::IOError("IO runtime shutdown".to_string()));
                     }
@@ -7465,7 +8801,7 @@ impl AsyncProcess {
                 };
                 let (tx, rx) = tokio::sync::oneshot::channel();
                 if let Some(sender) = &self.shared.io_sender {
-                    let request = IoRequest::TcpConnect { host, port, response: tx };
+                    let request = IoRequest::TcpConnect { host, port, tls: None, response: tx };
                     if sender.send(request).is_err() {
                         return Err(RuntimeError::IOError("IO runtime shutdown".to_string()));
                     }
@@ -7488,7 +8824,61 @@ impl AsyncProcess {
                 };
                 let (tx, rx) = tokio::sync::oneshot::channel();
                 if let Some(sender) = &self.shared.io_sender {
-                    let request = IoRequest::TcpListen { port, response: tx };
+                    let request = IoRequest::TcpListen { port, tls: None, response: tx };
+                    if sender.send(request).is_err() {
+                        return Err(RuntimeError::IOError("IO runtime shutdown".to_string()));
+                    }
+                    let result = rx.await.map_err(|_| RuntimeError::IOError("IO response channel closed".to_string()))?;
+                    if let Some(gc_value) = self.handle_io_result(result, "tcp_error")? {
+                        set_reg!(dst, gc_value);
+                    }
+                } else {
+                    return Err(RuntimeError::IOError("IO runtime not available".to_string()));
+                }
+            }
+
+            TcpConnectTls(dst, host_reg, port_reg, opts_reg) => {
+                let host = self.string_arg(reg!(host_reg), "Tcp.connectTls: host")?;
+                let port = match reg!(port_reg) {
+                    GcValue::Int64(n) => n as u16,
+                    _ => return Err(RuntimeError::TypeError {
+                        expected: "Int".to_string(),
+                        found: "non-int".to_string(),
+                    }),
+                };
+                let opts = self.string_pairs_arg(reg!(opts_reg), "Tcp.connectTls: opts")?;
+                let tls = ClientOptions::from_pairs(opts)
+                    .map_err(|e| RuntimeError::Panic(format!("Tcp.connectTls: {}", e)))?;
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                if let Some(sender) = &self.shared.io_sender {
+                    let request = IoRequest::TcpConnect { host, port, tls: Some(tls), response: tx };
+                    if sender.send(request).is_err() {
+                        return Err(RuntimeError::IOError("IO runtime shutdown".to_string()));
+                    }
+                    let result = rx.await.map_err(|_| RuntimeError::IOError("IO response channel closed".to_string()))?;
+                    if let Some(gc_value) = self.handle_io_result(result, "tcp_error")? {
+                        set_reg!(dst, gc_value);
+                    }
+                } else {
+                    return Err(RuntimeError::IOError("IO runtime not available".to_string()));
+                }
+            }
+
+            TcpListenTls(dst, port_reg, cert_reg, key_reg) => {
+                let port = match reg!(port_reg) {
+                    GcValue::Int64(n) => n as u16,
+                    _ => return Err(RuntimeError::TypeError {
+                        expected: "Int".to_string(),
+                        found: "non-int".to_string(),
+                    }),
+                };
+                let tls = TlsFiles {
+                    cert_path: self.string_arg(reg!(cert_reg), "Tcp.listenTls: certPath")?,
+                    key_path: self.string_arg(reg!(key_reg), "Tcp.listenTls: keyPath")?,
+                };
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                if let Some(sender) = &self.shared.io_sender {
+                    let request = IoRequest::TcpListen { port, tls: Some(tls), response: tx };
                     if sender.send(request).is_err() {
                         return Err(RuntimeError::IOError("IO runtime shutdown".to_string()));
                     }
@@ -9399,6 +10789,7 @@ impl AsyncProcess {
                     captures: Arc::from([] as [GcValue; 0]),
                     return_reg: Some(*dst),
                 });
+                self.trace_call();
                 // Must return to recompute cur_frame (frame was pushed)
                 return Ok(StepResult::Continue);
             }
@@ -9499,6 +10890,7 @@ impl AsyncProcess {
                         return_reg,
                     });
                 }
+                self.trace_call();
                 // Must return to recompute cur_frame
                 return Ok(StepResult::Continue);
             }
@@ -9944,13 +11336,19 @@ impl AsyncProcess {
     }
 
     /// Run garbage collection if threshold exceeded.
-    /// Collects roots from all stack frames and runs mark-sweep.
+    /// Collects roots from all stack frames and runs a minor or major collection.
     fn maybe_gc(&mut self) {
         if !self.heap.should_collect() {
             return;
         }
+        let roots = self.gc_roots();
+        self.heap.set_roots(roots);
+        self.heap.collect_auto();
+    }
 
-        // Collect all GC pointers from registers in all frames as roots
+    /// GC roots of this process: registers and captures of all frames, plus
+    /// the current exception and exit value.
+    fn gc_roots(&self) -> Vec<RawGcPtr> {
         let mut roots = Vec::new();
         for frame in &self.frames {
             for val in &frame.registers {
@@ -9960,19 +11358,70 @@ impl AsyncProcess {
                 roots.extend(val.gc_pointers());
             }
         }
-
-        // Also include current exception if any
         if let Some(ref exc) = self.current_exception {
             roots.extend(exc.gc_pointers());
         }
-
-        // Also include exit value if any
         if let Some(ref val) = self.exit_value {
             roots.extend(val.gc_pointers());
         }
+        roots
+    }
+
+    /// Fail with `LimitExceeded` when the mailbox or the live heap is over its
+    /// `GcConfig` limit. Runs periodically and before each receive. The heap is
+    /// collected before it is judged, so only reachable data counts against the limit.
+    fn check_limits(&mut self) -> Result<(), RuntimeError> {
+        if let Some(max) = self.heap.config().max_mailbox_len {
+            if self.mailbox.len() > max {
+                return Err(RuntimeError::LimitExceeded(ProcessLimit::Mailbox));
+            }
+        }
+        if self.heap.over_heap_limit() {
+            let roots = self.gc_roots();
+            self.heap.set_roots(roots);
+            self.heap.collect();
+            if self.heap.over_heap_limit() {
+                return Err(RuntimeError::LimitExceeded(ProcessLimit::Heap));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the next message, serving heap snapshot requests that arrive
+    /// in the meantime.
+    async fn receive_serving_requests(&mut self) -> Option<ThreadSafeValue> {
+        let wakeup = match &self.wakeup {
+            Some(wakeup) => wakeup.clone(),
+            None => {
+                let wakeup = self.shared.process_wakeup(self.pid);
+                self.wakeup = Some(wakeup.clone());
+                wakeup
+            }
+        };
+        loop {
+            tokio::select! {
+                msg = self.mailbox.recv() => return msg,
+                _ = wakeup.notified() => self.serve_heap_snapshots(),
+            }
+        }
+    }
+
+    /// Answer pending `Gc.snapshot` requests for this process.
+    fn serve_heap_snapshots(&mut self) {
+        for request in self.shared.take_heap_snapshot_requests(self.pid) {
+            let _ = request.reply.send(self.write_heap_snapshot(&request.path));
+        }
+    }
 
+    /// Collect the heap and write a snapshot of it to `path`.
+    /// Returns the number of objects written.
+    fn write_heap_snapshot(&mut self, path: &str) -> Result<usize, String> {
+        let roots = self.gc_roots();
         self.heap.set_roots(roots);
         self.heap.collect();
+        let snapshot = crate::heap_snapshot::HeapSnapshot::capture(&self.heap, self.heap.roots(), self.pid.0);
+        snapshot.write(std::path::Path::new(path)).map_err(|e| format!("cannot write {}: {}", path, e))?;
+        Ok(snapshot.objects.len())
     }
 
     /// Try to handle an exception with registered handlers.
@@ -10293,7 +11742,7 @@ impl AsyncProcess {
                 Ok(GcValue::Variant(ptr))
             }
             GcValue::Pid(pid) => {
-                let str_ptr = self.heap.alloc_string(format!("<pid:{}>", pid));
+                let str_ptr = self.heap.alloc_string(format!("<pid:{}>", Pid(pid)));
                 let ptr = self.heap.alloc_variant(json_type, Arc::new("String".to_string()), vec![GcValue::String(str_ptr)]);
                 Ok(GcValue::Variant(ptr))
             }
@@ -11140,6 +12589,11 @@ pub struct AsyncConfig {
     pub reductions_per_yield: usize,
     /// Enable function call profiling.
     pub profiling_enabled: bool,
+    /// Sample the call stacks of running processes.
+    pub sampler: Option<SamplerConfig>,
+    /// Heap configuration of every process, including the default
+    /// per-process heap and mailbox limits.
+    pub gc_config: GcConfig,
 }
 
 impl Default for AsyncConfig {
@@ -11148,6 +12602,8 @@ impl Default for AsyncConfig {
             num_threads: 0, // Auto-detect
             reductions_per_yield: REDUCTIONS_PER_YIELD,
             profiling_enabled: false,
+            sampler: None,
+            gc_config: GcConfig::default(),
         }
     }
 }
@@ -11194,13 +12650,26 @@ impl AsyncVM {
             jit_tuple_triple_functions_1: RwLock::new(HashMap::new()),
             jit_string_match_functions: RwLock::new(HashMap::new()),
             jit_bool_returning: RwLock::new(HashSet::new()),
+            jit_queue: std::sync::OnceLock::new(),
             shutdown: AtomicBool::new(false),
             interrupt: AtomicBool::new(false),
             interactive_mode: AtomicBool::new(false),
             spawn_runtime_handle: Some(spawn_runtime_handle),
-            process_registry: TokioRwLock::new(HashMap::new()),
+            process_registry: Arc::new(TokioRwLock::new(HashMap::new())),
             process_abort_handles: TokioRwLock::new(HashMap::new()),
             process_servers: TokioRwLock::new(HashMap::new()),
+            process_links: parking_lot::Mutex::new(HashMap::new()),
+            process_monitors: parking_lot::Mutex::new(HashMap::new()),
+            process_names: parking_lot::Mutex::new(HashMap::new()),
+            timers: crate::timers::TimerService::new(),
+            nodes: crate::node::Nodes::new(),
+            tables: crate::tables::Tables::new(),
+            code_loader: RwLock::new(None),
+            code_versions: crate::code::CodeVersions::default(),
+            supervisors: parking_lot::Mutex::new(HashMap::new()),
+            heap_snapshot_requests: parking_lot::Mutex::new(HashMap::new()),
+            heap_snapshot_pending: AtomicUsize::new(0),
+            process_wakeups: parking_lot::Mutex::new(HashMap::new()),
             spawned_count: AtomicU64::new(0),
             exited_count: AtomicU64::new(0),
             mvars: HashMap::new(),
@@ -11216,8 +12685,14 @@ impl AsyncVM {
             inspect_sender: None,
             output_sender: None,
             panel_command_sender: None,
+            debug_hub: parking_lot::RwLock::new(None),
             next_pid: AtomicU64::new(1),
             profiling_enabled: config.profiling_enabled,
+            sampler: config.sampler.clone().map(|c| Arc::new(Sampler::new(c))),
+            process_priorities: parking_lot::Mutex::new(HashMap::new()),
+            traces: crate::trace::Traces::default(),
+            runnable_high_priority: Arc::new(AtomicUsize::new(0)),
+            gc_config: config.gc_config.clone(),
             extensions: RwLock::new(None),
         });
 
@@ -11291,6 +12766,83 @@ impl AsyncVM {
             .insert(func_index, jit_fn);
     }
 
+    /// Enable the baseline and optimizing JIT tiers: functions called
+    /// `hot_threshold` times are sent to `sender`, whose compiler installs
+    /// their baseline code, and again after `OPTIMIZE_AFTER` times as many
+    /// calls for the optimizing tier. Functions that loop are sent after
+    /// `OSR_AFTER` times as many loop iterations, and running frames move
+    /// into the new code at their next loop head.
+    /// Returns false if the JIT tiers were already enabled.
+    pub fn enable_tiered_jit(&self, sender: std::sync::mpsc::Sender<crate::jit_runtime::JitRequest>, hot_threshold: u32) -> bool {
+        let hot_threshold = hot_threshold.max(1);
+        self.shared.jit_queue
+            .set(crate::jit_runtime::JitQueue {
+                sender,
+                hot_threshold,
+                optimize_threshold: hot_threshold.saturating_mul(crate::jit_runtime::OPTIMIZE_AFTER),
+                osr_threshold: hot_threshold.saturating_mul(crate::jit_runtime::OSR_AFTER),
+            })
+            .is_ok()
+    }
+
+    /// Queue the functions that were JIT-compiled in earlier runs, as recorded
+    /// in `cache`, without waiting for them to get hot again. Their type
+    /// profiles are seeded from the cache so the optimizing tier specializes
+    /// them the same way. Requires `enable_tiered_jit`; returns the number of
+    /// functions queued.
+    pub fn jit_warm_start(&self, cache: &crate::cache::JitCache) -> usize {
+        use crate::jit_runtime::JitRequest;
+
+        let queue = match self.shared.jit_queue.get() {
+            Some(queue) => queue,
+            None => return 0,
+        };
+        let functions = self.shared.function_list.read().unwrap();
+        let mut queued = 0;
+        for function in functions.iter() {
+            let entry = match cache.get(function) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            for (i, kind) in entry.arg_kinds.iter().enumerate() {
+                function.type_profile.record_arg(i, *kind);
+            }
+            function.type_profile.record_return(entry.return_kinds);
+            if queue.sender.send(JitRequest::new(function, &functions, false)).is_err() {
+                break;
+            }
+            if entry.optimized {
+                let _ = queue.sender.send(JitRequest::new(function, &functions, true));
+            }
+            queued += 1;
+        }
+        queued
+    }
+
+    /// Make this VM a distributed node: listen for other nodes on the
+    /// address in its name (see `crate::node`).
+    pub fn start_node(&self, config: crate::node::NodeConfig) -> Result<(), String> {
+        let handle = self.shared.spawn_runtime_handle.clone()
+            .ok_or_else(|| "no IO runtime to run the node on".to_string())?;
+        handle.block_on(crate::node::start(&self.shared, config))
+    }
+
+    /// Stack samples taken so far, when the sampling profiler is enabled.
+    pub fn stack_samples(&self) -> Option<crate::sampler::StackSamples> {
+        self.shared.sampler.as_ref().map(|sampler| sampler.samples())
+    }
+
+    /// Record in `cache` which functions were JIT-compiled in this run, for
+    /// `jit_warm_start` in the next one. Returns true if the cache changed.
+    pub fn record_jit_decisions(&self, cache: &mut crate::cache::JitCache) -> bool {
+        let functions = self.shared.function_list.read().unwrap();
+        let mut changed = false;
+        for function in functions.iter() {
+            changed |= cache.record(function);
+        }
+        changed
+    }
+
     /// Register a JIT-compiled function (arity 2) - safe during concurrent evals.
     pub fn register_jit_int_function_2(&mut self, func_index: u16, jit_fn: crate::shared_types::JitIntFn2) {
         self.shared.jit_int_functions_2.write().unwrap()
@@ -11466,6 +13018,7 @@ impl AsyncVM {
         Arc::get_mut(&mut self.shared)
             .expect("Cannot setup inspect after execution started")
             .inspect_sender = Some(sender.clone());
+        let registry = self.shared.process_registry.clone();
 
         // Register the inspect native function
         self.register_native("inspect", Arc::new(GcNativeFn {
@@ -11478,8 +13031,12 @@ impl AsyncVM {
                     }
                     _ => return Err(RuntimeError::Panic("inspect: second argument must be a string".to_string())),
                 };
-                let value = crate::process::ThreadSafeValue::from_gc_value(&args[0], heap)
-                    .unwrap_or(crate::process::ThreadSafeValue::Unit);
+                let value = match crate::process::ThreadSafeValue::from_gc_value(&args[0], heap) {
+                    // Show a live process with its mailbox
+                    Some(ThreadSafeValue::Pid(pid)) => inspect_process(&registry, pid),
+                    Some(value) => value,
+                    None => ThreadSafeValue::Unit,
+                };
                 let entry = crate::shared_types::InspectEntry { name, value };
                 let _ = sender.send(entry);
                 Ok(GcValue::Unit)
@@ -11645,6 +13202,27 @@ impl AsyncVM {
         // Empty for now - async VM doesn't need special setup
     }
 
+    /// Set the loader used by Code.reload to recompile a module.
+    pub fn set_code_loader(&mut self, loader: crate::code::CodeLoader) {
+        *self.shared.code_loader.write().unwrap() = Some(loader);
+    }
+
+    /// Install recompiled code for `module` (see `crate::code`), returning its new version.
+    pub fn install_code(&self, module: &str, code: crate::code::ReloadedCode) -> u64 {
+        crate::code::install(&self.shared, module, code)
+    }
+
+    /// Trace a process to a channel (the TUI trace panel, `nostos connect`).
+    /// Returns false if the process isn't alive.
+    pub fn trace_process(&self, pid: Pid, flags: crate::trace::TraceFlags, sender: crate::trace::TraceSender) -> bool {
+        self.shared.traces.start(pid, crate::trace::Tracer::Channel(sender), flags)
+    }
+
+    /// Stop tracing a process. Returns false if it isn't alive.
+    pub fn stop_trace(&self, pid: Pid) -> bool {
+        self.shared.traces.stop(pid)
+    }
+
     /// Set eval callback.
     pub fn set_eval_callback<F>(&mut self, callback: F)
     where
@@ -15591,7 +17169,7 @@ impl AsyncVM {
         };
 
         // Run main process (blocks until complete)
-        let result = process.run().await;
+        let result = process.run_scheduled().await;
 
         // Abort extension handler task when main completes
         if let Some(handle) = ext_handler {
@@ -15657,7 +17235,7 @@ impl AsyncVM {
         };
 
         // Run main process (blocks until complete)
-        let result = process.run().await;
+        let result = process.run_scheduled().await;
 
         // Abort extension handler task when main completes
         if let Some(handle) = ext_handler {
@@ -15679,7 +17257,14 @@ impl AsyncVM {
     /// Run the main function with debug support.
     /// Returns a DebugSession that can be used to control execution.
     pub fn run_debug(&self, main_fn_name: &str) -> Result<DebugSession, String> {
-        use crate::shared_types::{DebugCommand, DebugEvent, StepMode};
+        self.run_debug_with(main_fn_name, false)
+    }
+
+    /// Run the main function with debug support. With `debug_spawned`,
+    /// processes spawned by the program attach to the session's `DebugHub`
+    /// and can be stepped like the main process.
+    pub fn run_debug_with(&self, main_fn_name: &str, debug_spawned: bool) -> Result<DebugSession, String> {
+        use crate::shared_types::{DebugEvent, DebugHub, StepMode};
 
         // Log shared state info
         let shared_ptr = Arc::as_ptr(&self.shared) as usize;
@@ -15691,12 +17276,19 @@ impl AsyncVM {
         }
 
         // Create debug channels
-        let (cmd_tx, cmd_rx) = crossbeam::channel::unbounded::<DebugCommand>();
         let (event_tx, event_rx) = crossbeam::channel::unbounded::<DebugEvent>();
+        let hub = Arc::new(DebugHub::new(event_tx.clone()));
+        let pid = self.shared.alloc_pid();
+        let cmd_rx = hub.attach(pid.0);
+        let cmd_tx = hub.sender(pid.0).expect("main process is attached");
+        if debug_spawned {
+            *self.shared.debug_hub.write() = Some(Arc::clone(&hub));
+        }
 
         let shared = Arc::clone(&self.shared);
         let fn_name = main_fn_name.to_string();
         let (result_tx, result_rx) = std::sync::mpsc::channel();
+        let session_hub = Arc::clone(&hub);
 
         // Spawn execution thread
         let handle = std::thread::spawn(move || {
@@ -15712,7 +17304,6 @@ impl AsyncVM {
                     .clone();
 
                 // Create main process with debug channels
-                let pid = shared.alloc_pid();
                 let mut process = AsyncProcess::new(pid, Arc::clone(&shared));
 
                 // Log output_sender status after process creation
@@ -15742,7 +17333,13 @@ impl AsyncVM {
                 });
 
                 // Run main process
-                let result = process.run().await;
+                let result = process.run_scheduled().await;
+
+                // Processes spawned from now on aren't debugged
+                if debug_spawned {
+                    *shared.debug_hub.write() = None;
+                }
+                hub.detach(pid.0);
 
                 // Send exit event
                 match &result {
@@ -15768,6 +17365,8 @@ impl AsyncVM {
         });
 
         Ok(DebugSession {
+            pid: pid.0,
+            hub: session_hub,
             command_sender: cmd_tx,
             event_receiver: event_rx,
             result_receiver: result_rx,
@@ -15776,8 +17375,37 @@ impl AsyncVM {
     }
 }
 
+/// What the TUI inspector shows for a pid: the process and its mailbox if it
+/// is alive, the bare pid otherwise.
+fn inspect_process(registry: &TokioRwLock<HashMap<Pid, MailboxSender>>, pid: u64) -> ThreadSafeValue {
+    // Never wait here: inspect runs inside a process
+    let Ok(registry) = registry.try_read() else {
+        return ThreadSafeValue::Pid(pid);
+    };
+    let Some(sender) = registry.get(&Pid(pid)) else {
+        return ThreadSafeValue::Pid(pid);
+    };
+    let bound = sender.bound();
+    ThreadSafeValue::Record {
+        type_name: "Process".to_string(),
+        field_names: vec!["pid".to_string(), "mailbox".to_string(), "mailboxCapacity".to_string(), "mailboxPolicy".to_string()],
+        fields: vec![
+            ThreadSafeValue::Pid(pid),
+            ThreadSafeValue::Int64(sender.len() as i64),
+            ThreadSafeValue::Int64(bound.map_or(0, |b| b.capacity as i64)),
+            ThreadSafeValue::String(bound.map_or("unbounded", |b| b.policy.name()).to_string()),
+        ],
+        mutable_fields: vec![false; 4],
+    }
+}
+
 /// A debug session for controlling program execution.
 pub struct DebugSession {
+    /// Pid of the main process.
+    pub pid: u64,
+    /// Debug channels of the main process and, with `run_debug_with`, of
+    /// the processes it spawns.
+    pub hub: Arc<crate::shared_types::DebugHub>,
     /// Send commands to the running process.
     pub command_sender: crate::shared_types::DebugCommandSender,
     /// Receive events from the running process.
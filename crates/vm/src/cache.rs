@@ -549,6 +549,47 @@ pub fn load_manifest(path: &Path) -> Result<CacheManifest, String> {
         .map_err(|e| format!("Failed to deserialize manifest: {}", e))
 }
 
+// ============================================================================
+// Program Images
+// ============================================================================
+
+/// Format version of program images; bump when the layout changes
+pub const PROGRAM_IMAGE_FORMAT_VERSION: u32 = 1;
+
+/// A whole compiled program (project, packages and stdlib), as embedded in a
+/// standalone executable by `nostos build`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProgramImage {
+    pub format_version: u32,
+    pub compiler_version: String,
+    /// Function to run, e.g. "server.main/"
+    pub entry_point: String,
+    /// Function names in function-index order, so CallDirect indices stay valid
+    pub function_list: Vec<String>,
+    /// Module init functions, run before the entry point
+    pub module_init_functions: Vec<String>,
+    pub modules: Vec<CachedModule>,
+}
+
+/// Serialize a program image
+pub fn encode_program_image(image: &ProgramImage) -> Result<Vec<u8>, String> {
+    bincode::serialize(image)
+        .map_err(|e| format!("Failed to serialize program image: {}", e))
+}
+
+/// Deserialize a program image, rejecting images from another format version
+pub fn decode_program_image(bytes: &[u8]) -> Result<ProgramImage, String> {
+    let image: ProgramImage = bincode::deserialize(bytes)
+        .map_err(|e| format!("Failed to deserialize program image: {}", e))?;
+    if image.format_version != PROGRAM_IMAGE_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported program image format {} (expected {})",
+            image.format_version, PROGRAM_IMAGE_FORMAT_VERSION
+        ));
+    }
+    Ok(image)
+}
+
 // ============================================================================
 // Cache Invalidation
 // ============================================================================
@@ -1084,6 +1125,9 @@ pub fn cached_to_function(cached: &CachedFunction) -> FunctionValue {
         module: cached.module.clone(),
         source_span: cached.source_span,
         jit_code: None,
+        jit_entry: Default::default(),
+        opt_entry: Default::default(),
+        type_profile: Default::default(),
         call_count: std::sync::atomic::AtomicU32::new(0),
         debug_symbols,
         source_code: None, // Source code not cached
@@ -1118,6 +1162,9 @@ where
         module: cached.module.clone(),
         source_span: cached.source_span,
         jit_code: None,
+        jit_entry: Default::default(),
+        opt_entry: Default::default(),
+        type_profile: Default::default(),
         call_count: std::sync::atomic::AtomicU32::new(0),
         debug_symbols,
         source_code: None, // Source code not cached
@@ -1154,6 +1201,9 @@ pub fn cached_to_function_with_boxed_resolver(
         module: cached.module.clone(),
         source_span: cached.source_span,
         jit_code: None,
+        jit_entry: Default::default(),
+        opt_entry: Default::default(),
+        type_profile: Default::default(),
         call_count: std::sync::atomic::AtomicU32::new(0),
         debug_symbols,
         source_code: None, // Source code not cached
@@ -1166,6 +1216,129 @@ pub fn cached_to_function_with_boxed_resolver(
     }
 }
 
+// ============================================================================
+// JIT Cache
+// ============================================================================
+
+/// Format version of the JIT cache; bump when the layout changes
+pub const JIT_CACHE_FORMAT_VERSION: u32 = 1;
+
+/// JIT decisions carried over between runs: which functions got hot enough
+/// to be compiled, by which tier, and with which argument and return kinds.
+/// Entries are keyed by `function_bytecode_hash`, so changing a function
+/// drops its entry, and the whole cache is dropped when the compiler version
+/// changes.
+///
+/// Machine code is not stored: compiled code calls the runtime helpers and
+/// other functions by absolute address, so it cannot be loaded into another
+/// process without relocating it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JitCache {
+    pub format_version: u32,
+    pub compiler_version: String,
+    pub functions: HashMap<String, JitCacheEntry>,
+}
+
+/// JIT decisions for one function
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JitCacheEntry {
+    /// Function name (informational; lookups use the bytecode hash)
+    pub name: String,
+    /// Compiled by the optimizing tier rather than only the baseline tier
+    pub optimized: bool,
+    /// `TypeProfile` kinds of each argument
+    pub arg_kinds: Vec<u8>,
+    /// `TypeProfile` kinds of the returned values
+    pub return_kinds: u8,
+}
+
+impl JitCache {
+    pub fn new(compiler_version: &str) -> Self {
+        Self {
+            format_version: JIT_CACHE_FORMAT_VERSION,
+            compiler_version: compiler_version.to_string(),
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Load the JIT cache at `path`. Starts empty if the file is missing,
+    /// unreadable, or written by another format or compiler version.
+    pub fn load(path: &Path, compiler_version: &str) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<JitCache>(&bytes).ok())
+            .filter(|cache| {
+                cache.format_version == JIT_CACHE_FORMAT_VERSION
+                    && cache.compiler_version == compiler_version
+            })
+            .unwrap_or_else(|| Self::new(compiler_version))
+    }
+
+    /// Save the JIT cache to `path`
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = bincode::serialize(self)
+            .map_err(|e| format!("Failed to serialize JIT cache: {}", e))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+
+        fs::write(path, bytes)
+            .map_err(|e| format!("Failed to write JIT cache: {}", e))
+    }
+
+    /// Decisions recorded for `func`, if its bytecode is unchanged
+    pub fn get(&self, func: &FunctionValue) -> Option<&JitCacheEntry> {
+        self.functions.get(&function_bytecode_hash(func)?)
+    }
+
+    /// Record the tier `func` reached in this run and its profile.
+    /// Functions that were not compiled keep their earlier entry.
+    /// Returns true if the cache changed.
+    pub fn record(&mut self, func: &FunctionValue) -> bool {
+        let optimized = func.opt_entry.is_set();
+        if !optimized && !func.jit_entry.is_set() {
+            return false;
+        }
+        let hash = match function_bytecode_hash(func) {
+            Some(hash) => hash,
+            None => return false,
+        };
+        let profile = &func.type_profile;
+        let entry = JitCacheEntry {
+            name: func.name.clone(),
+            optimized,
+            arg_kinds: (0..func.arity.min(crate::value::TypeProfile::MAX_ARGS))
+                .map(|i| profile.arg(i))
+                .collect(),
+            return_kinds: profile.returns(),
+        };
+        if self.functions.get(&hash) == Some(&entry) {
+            return false;
+        }
+        self.functions.insert(hash, entry);
+        true
+    }
+}
+
+/// SHA256 hash of a function's name, arity and bytecode, identifying it
+/// across runs. None if its constants cannot be serialized.
+pub fn function_bytecode_hash(func: &FunctionValue) -> Option<String> {
+    use sha2::{Sha256, Digest};
+
+    let constants: Vec<CachedValue> = func.code.constants.iter()
+        .map(CachedValue::from_value)
+        .collect::<Option<_>>()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(func.name.as_bytes());
+    hasher.update((func.arity as u64).to_le_bytes());
+    hasher.update(bincode::serialize(&func.code.code).ok()?);
+    hasher.update(bincode::serialize(&constants).ok()?);
+    Some(format!("{:x}", hasher.finalize()))
+}
+
 
 // ============================================================================
 // Tests
@@ -1256,6 +1429,60 @@ mod tests {
         assert_eq!(module.exports, restored.exports);
     }
 
+    #[test]
+    fn test_program_image_roundtrip() {
+        let mut chunk = Chunk::new();
+        chunk.emit(Instruction::LoadConst(0, 0), 1);
+        chunk.emit(Instruction::Return(0), 1);
+        chunk.add_constant(Value::Int64(42));
+        chunk.register_count = 1;
+        let func = CachedFunction {
+            name: "main.main/".to_string(),
+            arity: 0,
+            param_names: vec![],
+            code: CachedChunk::from_chunk(&chunk).expect("Failed to convert chunk"),
+            module: Some("main".to_string()),
+            source_span: None,
+            debug_symbols: vec![],
+            source_file: None,
+            doc: None,
+            signature: None,
+            param_types: vec![],
+            return_type: None,
+            required_params: None,
+            default_values: vec![],
+            is_public: true,
+        };
+        let image = ProgramImage {
+            format_version: PROGRAM_IMAGE_FORMAT_VERSION,
+            compiler_version: "test".to_string(),
+            entry_point: "main.main/".to_string(),
+            function_list: vec!["main.main/".to_string()],
+            module_init_functions: vec![],
+            modules: vec![CachedModule {
+                module_path: vec!["main".to_string()],
+                source_hash: String::new(),
+                functions: vec![func],
+                function_signatures: HashMap::new(),
+                exports: vec![],
+                prelude_imports: vec![],
+                types: vec![],
+                mvars: vec![],
+                dependency_signatures: HashMap::new(),
+            }],
+        };
+
+        let bytes = encode_program_image(&image).expect("Failed to encode image");
+        let restored = decode_program_image(&bytes).expect("Failed to decode image");
+        assert_eq!(restored.entry_point, "main.main/");
+        assert_eq!(restored.modules[0].functions[0].code.code.len(), 2);
+
+        let mut old = image.clone();
+        old.format_version = 0;
+        let bytes = encode_program_image(&old).expect("Failed to encode image");
+        assert!(decode_program_image(&bytes).is_err());
+    }
+
     #[test]
     fn test_cache_manifest() {
         let mut manifest = CacheManifest::new("0.1.0");
@@ -1280,6 +1507,39 @@ mod tests {
         assert_eq!(manifest.dependency_graph.len(), restored.dependency_graph.len());
     }
 
+    #[test]
+    fn test_jit_cache_invalidation() {
+        let mut chunk = Chunk::new();
+        chunk.emit(Instruction::AddInt(0, 0, 1), 1);
+        chunk.emit(Instruction::Return(0), 1);
+        let func = FunctionValue::new_simple("add".to_string(), 2, vec![], Arc::new(chunk));
+        let mut cache = JitCache::new("0.1.0");
+
+        // Not compiled in this run: nothing to remember
+        assert!(!cache.record(&func));
+        cache.functions.insert(function_bytecode_hash(&func).unwrap(), JitCacheEntry {
+            name: "add".to_string(),
+            optimized: true,
+            arg_kinds: vec![1, 1],
+            return_kinds: 1,
+        });
+        assert!(cache.get(&func).is_some());
+
+        // Changed bytecode misses the entry
+        let mut chunk = Chunk::new();
+        chunk.emit(Instruction::SubInt(0, 0, 1), 1);
+        chunk.emit(Instruction::Return(0), 1);
+        let changed = FunctionValue::new_simple("add".to_string(), 2, vec![], Arc::new(chunk));
+        assert!(cache.get(&changed).is_none());
+
+        // Another compiler version starts over
+        let path = std::env::temp_dir().join(format!("nostos-jit-cache-test-{}.bin", std::process::id()));
+        cache.save(&path).expect("Failed to save JIT cache");
+        assert_eq!(JitCache::load(&path, "0.1.0").functions.len(), 1);
+        assert!(JitCache::load(&path, "0.2.0").functions.is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn test_transitive_invalidation() {
         let mut manifest = CacheManifest::new("0.1.0");
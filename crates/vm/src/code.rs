@@ -0,0 +1,173 @@
+//! Hot code upgrade: loading a new version of a module into a running VM.
+//!
+//! The VM can't compile source itself, so the embedder (the `nostos` CLI, the
+//! REPL engine) installs a [`CodeLoader`] that recompiles a module and returns
+//! the compiler's function table. Function indices are stable across
+//! recompiles, so [`install`] swaps the changed entries of the VM's table in
+//! place:
+//!
+//! - Frames that are already running keep the `FunctionValue` they started
+//!   with and finish on the old code.
+//! - Every call that goes through the table runs the new version: calls to
+//!   other functions and fully-qualified calls (`Counter.loop(state)`).
+//! - A function's unqualified call to itself (`CallSelf`) doesn't look at the
+//!   table, so a receive loop keeps running its version until it makes a
+//!   qualified call to itself.
+//!
+//! That qualified self-call is where the loop state can be migrated: the
+//! compiler emits `CodeChange` before it, and if the calling code has been
+//! replaced and the module now defines `codeChange(state)`, the first argument
+//! of the call is passed through it (see [`upgrade_hook`]).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::async_vm::AsyncSharedState;
+use crate::value::{FunctionValue, Instruction, TypeValue};
+
+/// Recompiles a module by name. Runs on a blocking thread.
+pub type CodeLoader = Arc<dyn Fn(&str) -> Result<ReloadedCode, String> + Send + Sync>;
+
+/// The compiler's tables after recompiling a module.
+pub struct ReloadedCode {
+    /// Function names by index (the compiler's function list)
+    pub function_names: Vec<String>,
+    /// All compiled functions by name
+    pub functions: HashMap<String, Arc<FunctionValue>>,
+    /// All type definitions by name
+    pub types: HashMap<String, Arc<TypeValue>>,
+}
+
+/// How many times each module has been reloaded.
+#[derive(Default)]
+pub struct CodeVersions {
+    versions: Mutex<HashMap<String, u64>>,
+}
+
+impl CodeVersions {
+    /// Current version of a module: 0 until it is reloaded for the first time.
+    pub fn version(&self, module: &str) -> u64 {
+        self.versions.lock().get(module).copied().unwrap_or(0)
+    }
+
+    fn bump(&self, module: &str) -> u64 {
+        let mut versions = self.versions.lock();
+        let version = versions.entry(module.to_string()).or_insert(0);
+        *version += 1;
+        *version
+    }
+}
+
+/// Recompile `module` with the VM's loader and install the result.
+/// Returns the module's new version.
+pub async fn reload(shared: &AsyncSharedState, module: &str) -> Result<u64, String> {
+    let loader = shared.code_loader.read().unwrap().clone()
+        .ok_or_else(|| "code reloading is not available in this VM".to_string())?;
+    let name = module.to_string();
+    let code = tokio::task::spawn_blocking(move || loader(&name)).await
+        .map_err(|e| format!("reloading {} failed: {}", module, e))??;
+    Ok(install(shared, module, code))
+}
+
+/// Install recompiled code. Returns the module's new version.
+pub fn install(shared: &AsyncSharedState, module: &str, code: ReloadedCode) -> u64 {
+    let mut changed = HashSet::new();
+    {
+        let mut list = shared.function_list.write().unwrap();
+        for (idx, name) in code.function_names.iter().enumerate() {
+            let Some(function) = code.functions.get(name) else { continue };
+            match list.get_mut(idx) {
+                Some(slot) if Arc::ptr_eq(slot, function) => {}
+                Some(slot) => {
+                    *slot = function.clone();
+                    changed.insert(idx as u16);
+                }
+                None => list.push(function.clone()),
+            }
+        }
+        forget_native_code(shared, &list, &changed);
+    }
+    shared.functions.write().unwrap().extend(code.functions);
+    shared.types.write().unwrap().extend(code.types);
+    shared.code_versions.bump(module)
+}
+
+/// Drop native code that would still run replaced functions: the JIT entries
+/// registered by index, and optimized code that may have inlined them.
+fn forget_native_code(shared: &AsyncSharedState, list: &[Arc<FunctionValue>], changed: &HashSet<u16>) {
+    if changed.is_empty() {
+        return;
+    }
+    for idx in changed {
+        shared.jit_int_functions.write().unwrap().remove(idx);
+        shared.jit_int_functions_0.write().unwrap().remove(idx);
+        shared.jit_int_functions_2.write().unwrap().remove(idx);
+        shared.jit_int_functions_3.write().unwrap().remove(idx);
+        shared.jit_int_functions_4.write().unwrap().remove(idx);
+        shared.jit_loop_array_functions.write().unwrap().remove(idx);
+        shared.jit_array_fill_functions.write().unwrap().remove(idx);
+        shared.jit_array_sum_functions.write().unwrap().remove(idx);
+        shared.jit_list_sum_functions.write().unwrap().remove(idx);
+        shared.jit_list_sum_tr_functions.write().unwrap().remove(idx);
+        shared.jit_tuple_pair_functions_1.write().unwrap().remove(idx);
+        shared.jit_tuple_pair_functions_2.write().unwrap().remove(idx);
+        shared.jit_tuple_triple_functions_1.write().unwrap().remove(idx);
+        shared.jit_string_match_functions.write().unwrap().remove(idx);
+        shared.jit_bool_returning.write().unwrap().remove(idx);
+    }
+    for function in list {
+        let calls_changed = function.code.code.iter().any(|instruction| {
+            matches!(instruction, Instruction::CallDirect(_, idx, _) if changed.contains(idx))
+        });
+        if calls_changed {
+            function.opt_entry.clear();
+        }
+    }
+}
+
+/// The `codeChange` function to run before a qualified self-call to the
+/// function at `func_idx` made by `running`, if `running` has been replaced
+/// since it started and its module defines `codeChange` with one parameter.
+pub fn upgrade_hook(shared: &AsyncSharedState, running: &Arc<FunctionValue>, func_idx: u16) -> Option<Arc<FunctionValue>> {
+    let current = shared.function_list.read().unwrap().get(func_idx as usize).cloned()?;
+    if Arc::ptr_eq(&current, running) {
+        return None;
+    }
+    let prefix = match module_of(&running.name) {
+        "" => "codeChange/".to_string(),
+        module => format!("{}.codeChange/", module),
+    };
+    shared.functions.read().unwrap().iter()
+        .find(|(name, function)| name.starts_with(&prefix) && function.arity == 1)
+        .map(|(_, function)| function.clone())
+}
+
+/// Module part of a function name: "app.counter" for "app.counter.loop/Int".
+fn module_of(name: &str) -> &str {
+    let base = name.split('/').next().unwrap_or(name);
+    base.rsplit_once('.').map(|(module, _)| module).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_of_strips_function_and_signature() {
+        assert_eq!("app.counter", module_of("app.counter.loop/Int"));
+        assert_eq!("counter", module_of("counter.loop/_"));
+        assert_eq!("", module_of("main/"));
+    }
+
+    #[test]
+    fn versions_start_at_zero_and_count_reloads() {
+        let versions = CodeVersions::default();
+        assert_eq!(0, versions.version("counter"));
+        assert_eq!(1, versions.bump("counter"));
+        assert_eq!(2, versions.bump("counter"));
+        assert_eq!(0, versions.version("other"));
+        assert_eq!(2, versions.version("counter"));
+    }
+}
@@ -0,0 +1,441 @@
+//! C foreign function interface for `extern` declarations.
+//!
+//! An `extern` declaration such as
+//!
+//! ```text
+//! extern cos(x: Float) -> Float from "m"
+//! ```
+//!
+//! is compiled into a wrapper function whose body is a single `CallExtern`
+//! instruction. The instruction's constant holds an [`ExternSignature`]
+//! serialized as a descriptor string, so compiled code stays cache-friendly
+//! and the library is only opened the first time the function is called.
+//!
+//! Calls are dispatched without a libffi dependency: every argument is
+//! classified as either an integer/pointer or a floating point value, and the
+//! target is invoked through a fixed signature that fills all argument
+//! registers of the platform C ABI. This covers every non-variadic C function
+//! whose arguments fit in registers (6 integer + 8 float on x86-64 System V,
+//! 8 + 8 on AArch64).
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::sync::{Arc, OnceLock};
+
+use libloading::Library;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::gc::{GcValue, Heap};
+
+/// C-level type of an extern parameter or return value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FfiType {
+    Unit,
+    Bool,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float32,
+    Float64,
+    /// NUL-terminated `const char*`
+    String,
+    /// Opaque pointer (`extern type` values)
+    Pointer,
+    /// `int64_t*` to the array's contents
+    Int64Array,
+    /// `double*` to the array's contents
+    Float64Array,
+    /// `float*` to the array's contents
+    Float32Array,
+}
+
+impl FfiType {
+    /// Map a Nostos type name to its C representation.
+    ///
+    /// Names that are not builtin scalars, `String` or typed arrays are treated
+    /// as opaque pointers, which is how `extern type` declarations are passed.
+    pub fn from_type_name(name: &str) -> FfiType {
+        match name {
+            "()" | "Unit" => FfiType::Unit,
+            "Bool" => FfiType::Bool,
+            "Int8" => FfiType::Int8,
+            "Int16" => FfiType::Int16,
+            "Int32" => FfiType::Int32,
+            "Int" | "Int64" => FfiType::Int64,
+            "UInt8" => FfiType::UInt8,
+            "UInt16" => FfiType::UInt16,
+            "UInt32" => FfiType::UInt32,
+            "UInt64" => FfiType::UInt64,
+            "Float32" => FfiType::Float32,
+            "Float" | "Float64" => FfiType::Float64,
+            "String" => FfiType::String,
+            "Int64Array" => FfiType::Int64Array,
+            "Float64Array" => FfiType::Float64Array,
+            "Float32Array" => FfiType::Float32Array,
+            _ => FfiType::Pointer,
+        }
+    }
+
+    /// Whether the value travels in a floating point register.
+    pub fn is_float(self) -> bool {
+        matches!(self, FfiType::Float32 | FfiType::Float64)
+    }
+}
+
+/// Everything needed to resolve and call a C function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExternSignature {
+    /// Library name as written in `from "..."`
+    pub library: String,
+    /// Exported symbol name
+    pub symbol: String,
+    pub params: Vec<FfiType>,
+    pub ret: FfiType,
+}
+
+impl ExternSignature {
+    /// Encode as the descriptor string stored in the `CallExtern` constant.
+    pub fn to_descriptor(&self) -> String {
+        serde_json::to_string(self).expect("extern signature is always serializable")
+    }
+
+    /// Decode a descriptor produced by [`ExternSignature::to_descriptor`].
+    pub fn from_descriptor(descriptor: &str) -> Result<Self, String> {
+        serde_json::from_str(descriptor).map_err(|e| format!("invalid extern descriptor: {}", e))
+    }
+}
+
+/// A resolved C function.
+#[derive(Debug)]
+pub struct ExternFn {
+    pub signature: ExternSignature,
+    /// Address of the symbol in the loaded library
+    pub address: usize,
+}
+
+/// An argument after conversion from a VM value.
+///
+/// Strings own their `CString` so the pointer stays valid until the call returns.
+pub enum FfiArg {
+    Int(i64),
+    Float(f64),
+    Float32(f32),
+    Str(CString),
+}
+
+/// The value returned by a C function, already narrowed to its declared type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FfiReturn {
+    Unit,
+    Bool(bool),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Float32(f32),
+    Float64(f64),
+    String(String),
+    Pointer(usize),
+}
+
+/// Process-wide cache of opened libraries and resolved symbols.
+struct FfiRegistry {
+    libraries: RwLock<HashMap<String, Arc<Library>>>,
+    functions: RwLock<HashMap<String, Arc<ExternFn>>>,
+}
+
+fn registry() -> &'static FfiRegistry {
+    static REGISTRY: OnceLock<FfiRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| FfiRegistry {
+        libraries: RwLock::new(HashMap::new()),
+        functions: RwLock::new(HashMap::new()),
+    })
+}
+
+/// File names to try, in order, when opening the library `name`.
+fn library_candidates(name: &str) -> Vec<String> {
+    let is_path = name.contains('/') || name.contains('\\')
+        || name.ends_with(".so") || name.contains(".so.")
+        || name.ends_with(".dylib") || name.ends_with(".dll");
+    if is_path {
+        return vec![name.to_string()];
+    }
+
+    let base = name.strip_prefix("lib").unwrap_or(name);
+    let mut candidates = vec![
+        libloading::library_filename(base).to_string_lossy().into_owned(),
+        name.to_string(),
+    ];
+    if cfg!(target_os = "linux") {
+        // Development symlinks like libm.so are often linker scripts or missing,
+        // so also try the runtime sonames.
+        candidates.push(format!("lib{}.so.6", base));
+        candidates.push(format!("lib{}.so.1", base));
+    }
+    candidates
+}
+
+fn open_library(name: &str) -> Result<Arc<Library>, String> {
+    let reg = registry();
+    if let Some(lib) = reg.libraries.read().get(name) {
+        return Ok(lib.clone());
+    }
+
+    let mut last_err = String::new();
+    let mut opened = None;
+    for candidate in library_candidates(name) {
+        // SAFETY: loading a library runs its initializers; extern declarations
+        // explicitly opt into trusting the named library.
+        match unsafe { Library::new(&candidate) } {
+            Ok(lib) => {
+                opened = Some(lib);
+                break;
+            }
+            Err(e) => last_err = e.to_string(),
+        }
+    }
+
+    // libc is already mapped into the process; fall back to the global namespace.
+    #[cfg(unix)]
+    if opened.is_none() && matches!(name, "" | "c" | "libc") {
+        opened = Some(libloading::os::unix::Library::this().into());
+    }
+
+    let lib = Arc::new(opened.ok_or_else(|| format!("cannot load library \"{}\": {}", name, last_err))?);
+    reg.libraries.write().insert(name.to_string(), lib.clone());
+    Ok(lib)
+}
+
+/// Resolve the function described by `descriptor`, loading its library if needed.
+///
+/// Results are cached, so repeated calls only pay for a hash lookup.
+pub fn resolve(descriptor: &str) -> Result<Arc<ExternFn>, String> {
+    let reg = registry();
+    if let Some(f) = reg.functions.read().get(descriptor) {
+        return Ok(f.clone());
+    }
+
+    let signature = ExternSignature::from_descriptor(descriptor)?;
+    check_arity(&signature)?;
+    let lib = open_library(&signature.library)?;
+    // SAFETY: we only take the address here; the caller's declared signature is
+    // trusted when the function is invoked.
+    let address = unsafe {
+        let sym: libloading::Symbol<*const std::ffi::c_void> = lib
+            .get(signature.symbol.as_bytes())
+            .map_err(|e| format!("symbol `{}` not found in \"{}\": {}", signature.symbol, signature.library, e))?;
+        *sym as usize
+    };
+
+    let f = Arc::new(ExternFn { signature, address });
+    reg.functions.write().insert(descriptor.to_string(), f.clone());
+    Ok(f)
+}
+
+#[cfg(target_arch = "x86_64")]
+const MAX_INT_ARGS: usize = 6;
+#[cfg(not(target_arch = "x86_64"))]
+const MAX_INT_ARGS: usize = 8;
+const MAX_FLOAT_ARGS: usize = 8;
+
+fn check_arity(sig: &ExternSignature) -> Result<(), String> {
+    let floats = sig.params.iter().filter(|t| t.is_float()).count();
+    let ints = sig.params.len() - floats;
+    if ints > MAX_INT_ARGS || floats > MAX_FLOAT_ARGS {
+        return Err(format!(
+            "extern `{}` takes too many arguments (at most {} integer/pointer and {} float arguments are supported)",
+            sig.symbol, MAX_INT_ARGS, MAX_FLOAT_ARGS
+        ));
+    }
+    Ok(())
+}
+
+type IntFn = unsafe extern "C" fn(u64, u64, u64, u64, u64, u64, u64, u64, f64, f64, f64, f64, f64, f64, f64, f64) -> u64;
+type FloatFn = unsafe extern "C" fn(u64, u64, u64, u64, u64, u64, u64, u64, f64, f64, f64, f64, f64, f64, f64, f64) -> f64;
+
+impl ExternFn {
+    /// Call the function with already-converted arguments.
+    ///
+    /// # Safety
+    /// The declared signature must match the real C function, and any pointers
+    /// passed (arrays, opaque handles) must be valid for the duration of the call.
+    pub unsafe fn call(&self, args: &[FfiArg]) -> Result<FfiReturn, String> {
+        if args.len() != self.signature.params.len() {
+            return Err(format!(
+                "extern `{}` expects {} arguments, got {}",
+                self.signature.symbol, self.signature.params.len(), args.len()
+            ));
+        }
+        if !cfg!(all(unix, any(target_arch = "x86_64", target_arch = "aarch64"))) {
+            return Err("extern functions are not supported on this platform".to_string());
+        }
+
+        let mut ints = [0u64; 8];
+        let mut floats = [0f64; 8];
+        let (mut ni, mut nf) = (0, 0);
+        for arg in args {
+            match arg {
+                FfiArg::Int(v) => { ints[ni] = *v as u64; ni += 1; }
+                FfiArg::Str(s) => { ints[ni] = s.as_ptr() as u64; ni += 1; }
+                FfiArg::Float(v) => { floats[nf] = *v; nf += 1; }
+                // Only the low 32 bits of the register are read for a float argument.
+                FfiArg::Float32(v) => { floats[nf] = f64::from_bits(v.to_bits() as u64); nf += 1; }
+            }
+        }
+
+        let [i0, i1, i2, i3, i4, i5, i6, i7] = ints;
+        let [f0, f1, f2, f3, f4, f5, f6, f7] = floats;
+        let ret = self.signature.ret;
+        if ret.is_float() {
+            let f: FloatFn = std::mem::transmute(self.address);
+            let r = f(i0, i1, i2, i3, i4, i5, i6, i7, f0, f1, f2, f3, f4, f5, f6, f7);
+            return Ok(match ret {
+                FfiType::Float32 => FfiReturn::Float32(f32::from_bits(r.to_bits() as u32)),
+                _ => FfiReturn::Float64(r),
+            });
+        }
+
+        let f: IntFn = std::mem::transmute(self.address);
+        let r = f(i0, i1, i2, i3, i4, i5, i6, i7, f0, f1, f2, f3, f4, f5, f6, f7);
+        // Narrow explicitly: callees only define the low bits of small return types.
+        Ok(match ret {
+            FfiType::Unit => FfiReturn::Unit,
+            FfiType::Bool => FfiReturn::Bool(r as u8 != 0),
+            FfiType::Int8 => FfiReturn::Int8(r as i8),
+            FfiType::Int16 => FfiReturn::Int16(r as i16),
+            FfiType::Int32 => FfiReturn::Int32(r as i32),
+            FfiType::Int64 => FfiReturn::Int64(r as i64),
+            FfiType::UInt8 => FfiReturn::UInt8(r as u8),
+            FfiType::UInt16 => FfiReturn::UInt16(r as u16),
+            FfiType::UInt32 => FfiReturn::UInt32(r as u32),
+            FfiType::UInt64 => FfiReturn::UInt64(r),
+            FfiType::String => {
+                let p = r as usize as *const std::ffi::c_char;
+                if p.is_null() {
+                    FfiReturn::String(String::new())
+                } else {
+                    FfiReturn::String(CStr::from_ptr(p).to_string_lossy().into_owned())
+                }
+            }
+            FfiType::Pointer | FfiType::Int64Array | FfiType::Float64Array | FfiType::Float32Array => {
+                FfiReturn::Pointer(r as usize)
+            }
+            FfiType::Float32 | FfiType::Float64 => unreachable!("float returns handled above"),
+        })
+    }
+}
+
+/// Convert a VM value into a C argument of type `ty`.
+///
+/// Typed arrays are passed by pointer to their storage, so C code can fill
+/// them in place. The pointer must not be retained after the call returns.
+pub fn to_ffi_arg(heap: &mut Heap, value: &GcValue, ty: FfiType) -> Result<FfiArg, String> {
+    let arg = match (ty, value) {
+        (FfiType::Unit, _) => FfiArg::Int(0),
+        (FfiType::Bool, GcValue::Bool(b)) => FfiArg::Int(*b as i64),
+        (FfiType::Float64, GcValue::Float64(f)) => FfiArg::Float(*f),
+        (FfiType::Float32, GcValue::Float32(f)) => FfiArg::Float32(*f),
+        (FfiType::String, GcValue::String(ptr)) => {
+            let s = heap.get_string(*ptr).map(|s| s.data.as_str()).unwrap_or("");
+            FfiArg::Str(CString::new(s).map_err(|_| "string passed to extern contains a NUL byte".to_string())?)
+        }
+        (FfiType::Pointer, GcValue::Pointer(p)) => FfiArg::Int(*p as i64),
+        (FfiType::Int64Array, GcValue::Int64Array(ptr)) => {
+            let arr = heap.get_int64_array_mut(*ptr).ok_or("invalid Int64Array")?;
+            FfiArg::Int(arr.items.as_mut_ptr() as i64)
+        }
+        (FfiType::Float64Array, GcValue::Float64Array(ptr)) => {
+            let arr = heap.get_float64_array_mut(*ptr).ok_or("invalid Float64Array")?;
+            FfiArg::Int(arr.items.as_mut_ptr() as i64)
+        }
+        (FfiType::Float32Array, GcValue::Float32Array(ptr)) => {
+            let arr = heap.get_float32_array_mut(*ptr).ok_or("invalid Float32Array")?;
+            FfiArg::Int(arr.items.as_mut_ptr() as i64)
+        }
+        (_, GcValue::Int8(v)) => FfiArg::Int(*v as i64),
+        (_, GcValue::Int16(v)) => FfiArg::Int(*v as i64),
+        (_, GcValue::Int32(v)) => FfiArg::Int(*v as i64),
+        (_, GcValue::Int64(v)) => FfiArg::Int(*v),
+        (_, GcValue::UInt8(v)) => FfiArg::Int(*v as i64),
+        (_, GcValue::UInt16(v)) => FfiArg::Int(*v as i64),
+        (_, GcValue::UInt32(v)) => FfiArg::Int(*v as i64),
+        (_, GcValue::UInt64(v)) => FfiArg::Int(*v as i64),
+        (ty, other) => {
+            return Err(format!("cannot pass {} to extern parameter of type {:?}", other.type_name(heap), ty));
+        }
+    };
+    Ok(arg)
+}
+
+/// Convert a C return value into a VM value.
+pub fn from_ffi_return(heap: &mut Heap, ret: FfiReturn) -> GcValue {
+    match ret {
+        FfiReturn::Unit => GcValue::Unit,
+        FfiReturn::Bool(b) => GcValue::Bool(b),
+        FfiReturn::Int8(v) => GcValue::Int8(v),
+        FfiReturn::Int16(v) => GcValue::Int16(v),
+        FfiReturn::Int32(v) => GcValue::Int32(v),
+        FfiReturn::Int64(v) => GcValue::Int64(v),
+        FfiReturn::UInt8(v) => GcValue::UInt8(v),
+        FfiReturn::UInt16(v) => GcValue::UInt16(v),
+        FfiReturn::UInt32(v) => GcValue::UInt32(v),
+        FfiReturn::UInt64(v) => GcValue::UInt64(v),
+        FfiReturn::Float32(v) => GcValue::Float32(v),
+        FfiReturn::Float64(v) => GcValue::Float64(v),
+        FfiReturn::String(s) => GcValue::String(heap.alloc_string(s)),
+        FfiReturn::Pointer(p) => GcValue::Pointer(p),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(library: &str, symbol: &str, params: Vec<FfiType>, ret: FfiType) -> String {
+        ExternSignature { library: library.into(), symbol: symbol.into(), params, ret }.to_descriptor()
+    }
+
+    #[test]
+    fn test_descriptor_roundtrip() {
+        let s = ExternSignature {
+            library: "m".into(),
+            symbol: "pow".into(),
+            params: vec![FfiType::Float64, FfiType::Float64],
+            ret: FfiType::Float64,
+        };
+        assert_eq!(ExternSignature::from_descriptor(&s.to_descriptor()).unwrap(), s);
+    }
+
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    #[test]
+    fn test_call_libc_and_libm() {
+        let labs = resolve(&sig("c", "labs", vec![FfiType::Int64], FfiType::Int64)).unwrap();
+        assert_eq!(unsafe { labs.call(&[FfiArg::Int(-42)]) }.unwrap(), FfiReturn::Int64(42));
+
+        let strlen = resolve(&sig("c", "strlen", vec![FfiType::String], FfiType::UInt64)).unwrap();
+        let s = CString::new("hello").unwrap();
+        assert_eq!(unsafe { strlen.call(&[FfiArg::Str(s)]) }.unwrap(), FfiReturn::UInt64(5));
+
+        let pow = resolve(&sig("m", "pow", vec![FfiType::Float64, FfiType::Float64], FfiType::Float64)).unwrap();
+        assert_eq!(unsafe { pow.call(&[FfiArg::Float(2.0), FfiArg::Float(10.0)]) }.unwrap(), FfiReturn::Float64(1024.0));
+
+        let sqrtf = resolve(&sig("m", "sqrtf", vec![FfiType::Float32], FfiType::Float32)).unwrap();
+        assert_eq!(unsafe { sqrtf.call(&[FfiArg::Float32(9.0)]) }.unwrap(), FfiReturn::Float32(3.0));
+    }
+
+    #[test]
+    fn test_missing_symbol() {
+        let err = resolve(&sig("c", "nostos_no_such_symbol", vec![], FfiType::Unit)).unwrap_err();
+        assert!(err.contains("nostos_no_such_symbol"), "{}", err);
+    }
+}
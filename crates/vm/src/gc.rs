@@ -1,6 +1,6 @@
 //! Garbage Collector for Nostos
 //!
-//! A simple mark-and-sweep garbage collector designed for:
+//! A generational mark-and-sweep garbage collector designed for:
 //! - Per-process heaps (Erlang-style isolation)
 //! - JIT-friendly safepoints
 //! - Predictable collection behavior
@@ -12,6 +12,15 @@
 //! Collection uses mark-and-sweep: mark all reachable objects from roots,
 //! then sweep unmarked objects back to the free list.
 //!
+//! Objects are not moved between generations; each one carries an `old`
+//! flag. Minor collections mark and sweep only young objects, promoting
+//! those that survive `GcConfig::promotion_age` of them. Old objects that
+//! may point at young ones are kept in a remembered set, filled by
+//! `Heap::write_barrier` on mutation (record fields, array elements) and
+//! by promotion. Major collections (`Heap::collect`) trace the whole heap
+//! and promote every survivor; `Heap::collect_auto` runs one once the old
+//! generation has grown by `GcConfig::old_growth_factor`.
+//!
 //! # Usage
 //!
 //! ```ignore
@@ -1260,6 +1269,12 @@ pub struct GcObject {
     pub marked: bool,
     /// Size estimate in bytes (for memory pressure tracking)
     pub size: usize,
+    /// Promoted to the old generation (only traced by major collections)
+    pub old: bool,
+    /// Minor collections survived while young
+    pub age: u8,
+    /// In the remembered set: an old object that may point at young objects
+    pub remembered: bool,
 }
 
 /// The data stored in a heap object.
@@ -1424,6 +1439,7 @@ impl fmt::Debug for GcObject {
             .field("data", &self.data)
             .field("marked", &self.marked)
             .field("size", &self.size)
+            .field("old", &self.old)
             .finish()
     }
 }
@@ -1431,8 +1447,14 @@ impl fmt::Debug for GcObject {
 /// Statistics about GC activity.
 #[derive(Clone, Debug, Default)]
 pub struct GcStats {
-    /// Number of collections performed
+    /// Number of collections performed (minor and major)
     pub collections: u64,
+    /// Collections of the young generation only
+    pub minor_collections: u64,
+    /// Collections of the whole heap
+    pub major_collections: u64,
+    /// Objects promoted from the young to the old generation
+    pub total_promoted: u64,
     /// Total objects allocated
     pub total_allocated: u64,
     /// Total objects freed
@@ -1456,6 +1478,15 @@ pub struct GcConfig {
     pub gc_threshold: usize,
     /// Growth factor when heap needs to expand
     pub growth_factor: f64,
+    /// Minor collections a young object survives before it is promoted
+    pub promotion_age: u8,
+    /// Growth of the old generation since the last major collection that
+    /// makes the next automatic collection a major one
+    pub old_growth_factor: f64,
+    /// Live heap size at which the owning process is terminated (None = unlimited)
+    pub max_heap_bytes: Option<usize>,
+    /// Mailbox length at which the owning process is terminated (None = unlimited)
+    pub max_mailbox_len: Option<usize>,
     /// Whether to print debug info during collection
     pub debug: bool,
 }
@@ -1466,6 +1497,10 @@ impl Default for GcConfig {
             initial_capacity: 1024,
             gc_threshold: 1024 * 1024, // 1MB
             growth_factor: 2.0,
+            promotion_age: 2,
+            old_growth_factor: 2.0,
+            max_heap_bytes: None,
+            max_mailbox_len: None,
             debug: false,
         }
     }
@@ -1479,6 +1514,10 @@ impl GcConfig {
             initial_capacity: 8, // Minimal pre-allocation
             gc_threshold: 64 * 1024, // 64KB - trigger GC earlier for small heaps
             growth_factor: 2.0,
+            promotion_age: 2,
+            old_growth_factor: 2.0,
+            max_heap_bytes: None,
+            max_mailbox_len: None,
             debug: false,
         }
     }
@@ -1502,6 +1541,17 @@ pub struct Heap {
     config: GcConfig,
     /// Statistics
     stats: GcStats,
+    /// Young objects, swept by minor collections
+    young: Vec<RawGcPtr>,
+    /// Old objects that may point at young objects, traced as roots by
+    /// minor collections (see `write_barrier`)
+    remembered: Vec<RawGcPtr>,
+    /// Bytes in the old generation
+    old_bytes: usize,
+    /// Bytes in live (not yet swept) objects of both generations
+    live_bytes: usize,
+    /// Old generation size that triggers a major collection
+    major_threshold: usize,
     /// Cache for unit variants (0-field variants): discriminant → pre-allocated GcPtr.
     /// Avoids repeated heap allocation for common variants like None, Red, Green, etc.
     unit_variant_cache: HashMap<u16, GcPtr<GcVariant>>,
@@ -1528,8 +1578,13 @@ impl Heap {
             roots: Vec::new(),
             bytes_since_gc: 0,
             live_count: 0,
+            major_threshold: config.gc_threshold,
             config,
             stats: GcStats::default(),
+            young: Vec::new(),
+            remembered: Vec::new(),
+            old_bytes: 0,
+            live_bytes: 0,
             unit_variant_cache: HashMap::new(),
         }
     }
@@ -1539,6 +1594,11 @@ impl Heap {
         &self.config
     }
 
+    /// Get the configuration for changes, e.g. a process's limits.
+    pub fn config_mut(&mut self) -> &mut GcConfig {
+        &mut self.config
+    }
+
     /// Get GC statistics.
     pub fn stats(&self) -> &GcStats {
         &self.stats
@@ -1549,6 +1609,15 @@ impl Heap {
         self.live_count
     }
 
+    /// Iterate over the objects on the heap with their pointers, including
+    /// garbage that hasn't been swept yet.
+    pub fn objects(&self) -> impl Iterator<Item = (RawGcPtr, &GcObject)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, obj)| obj.as_ref().map(|obj| (i as RawGcPtr, obj)))
+    }
+
     /// Get the total heap capacity.
     pub fn capacity(&self) -> usize {
         self.objects.len()
@@ -1574,6 +1643,9 @@ impl Heap {
             data,
             marked: false,
             size,
+            old: false,
+            age: 0,
+            remembered: false,
         };
 
         // Update stats
@@ -1581,6 +1653,7 @@ impl Heap {
         self.stats.total_bytes_allocated += size as u64;
         self.bytes_since_gc += size;
         self.live_count += 1;
+        self.live_bytes += size;
 
         // Try to reuse a free slot
         let index = if let Some(free_idx) = self.free_list.pop() {
@@ -1592,6 +1665,7 @@ impl Heap {
             self.objects.push(Some(obj));
             idx
         };
+        self.young.push(index);
 
         // Update peak stats (now O(1) since live_count is tracked)
         if self.live_count > self.stats.peak_objects {
@@ -2427,7 +2501,7 @@ impl Heap {
             }
             GcValue::Function(f) => format!("<function {}>", f.name),
             GcValue::NativeFunction(n) => format!("<native {}>", n.name),
-            GcValue::Pid(p) => format!("<pid {}>", p),
+            GcValue::Pid(p) => format!("<pid {}>", Pid(*p)),
             GcValue::Ref(r) => format!("<ref {}>", r),
             GcValue::Type(t) => format!("<type {}>", t.name),
             GcValue::Pointer(p) => format!("<ptr 0x{:x}>", p),
@@ -2670,12 +2744,14 @@ impl Heap {
         self.bytes_since_gc >= self.config.gc_threshold
     }
 
-    /// Force a garbage collection.
+    /// Force a full (major) garbage collection of both generations.
+    /// Survivors are promoted, leaving the young generation empty.
     pub fn collect(&mut self) {
         self.stats.collections += 1;
+        self.stats.major_collections += 1;
         if self.config.debug {
             eprintln!(
-                "[GC] Starting collection #{}, {} live objects, {} bytes since last GC",
+                "[GC] Starting major collection #{}, {} live objects, {} bytes since last GC",
                 self.stats.collections,
                 self.live_objects(),
                 self.bytes_since_gc
@@ -2699,6 +2775,155 @@ impl Heap {
         self.bytes_since_gc = 0;
     }
 
+    /// Collect the young generation only.
+    ///
+    /// Old objects are assumed live and are not traced; young objects they
+    /// point at are found through the remembered set. Young survivors age
+    /// and are promoted after `GcConfig::promotion_age` minor collections.
+    pub fn collect_minor(&mut self) {
+        self.stats.collections += 1;
+        self.stats.minor_collections += 1;
+        if self.config.debug {
+            eprintln!(
+                "[GC] Starting minor collection #{}, {} young of {} live objects",
+                self.stats.collections,
+                self.young.len(),
+                self.live_objects()
+            );
+        }
+
+        // Mark young objects reachable from the roots and remembered old objects
+        let mut worklist: Vec<RawGcPtr> = self.roots.clone();
+        for &ptr in &self.remembered {
+            if let Some(obj) = self.objects.get(ptr as usize).and_then(|o| o.as_ref()) {
+                worklist.extend(obj.data.gc_pointers());
+            }
+        }
+        while let Some(ptr) = worklist.pop() {
+            if let Some(obj) = self.objects.get_mut(ptr as usize).and_then(|o| o.as_mut()) {
+                if !obj.old && !obj.marked {
+                    obj.marked = true;
+                    worklist.extend(obj.data.gc_pointers());
+                }
+            }
+        }
+
+        // Sweep the young generation, aging and promoting survivors
+        let mut freed = 0;
+        let mut bytes_freed = 0;
+        let mut promoted = Vec::new();
+        for ptr in std::mem::take(&mut self.young) {
+            let slot = &mut self.objects[ptr as usize];
+            let obj = match slot {
+                Some(obj) => obj,
+                None => continue,
+            };
+            if !obj.marked {
+                bytes_freed += obj.size;
+                freed += 1;
+                *slot = None;
+                self.free_list.push(ptr);
+                continue;
+            }
+            obj.marked = false;
+            obj.age = obj.age.saturating_add(1);
+            if obj.age >= self.config.promotion_age {
+                obj.old = true;
+                self.old_bytes += obj.size;
+                promoted.push(ptr);
+            } else {
+                self.young.push(ptr);
+            }
+        }
+
+        // Keep remembered (and newly promoted) objects that still point at young ones
+        let candidates: Vec<RawGcPtr> = std::mem::take(&mut self.remembered)
+            .into_iter()
+            .chain(promoted.iter().copied())
+            .collect();
+        for ptr in candidates {
+            let points_young = match self.objects.get(ptr as usize).and_then(|o| o.as_ref()) {
+                Some(obj) => obj.data.gc_pointers().into_iter().any(|p| self.is_young(p)),
+                None => continue,
+            };
+            if let Some(obj) = self.objects[ptr as usize].as_mut() {
+                obj.remembered = points_young;
+            }
+            if points_young {
+                self.remembered.push(ptr);
+            }
+        }
+
+        self.stats.total_freed += freed;
+        self.stats.total_bytes_freed += bytes_freed as u64;
+        self.stats.total_promoted += promoted.len() as u64;
+        self.live_count -= freed as usize;
+        self.live_bytes -= bytes_freed;
+
+        if self.config.debug {
+            eprintln!(
+                "[GC] Minor collection complete, freed {} objects, promoted {}, {} now live",
+                freed,
+                promoted.len(),
+                self.live_objects()
+            );
+        }
+
+        self.bytes_since_gc = 0;
+    }
+
+    /// Run the collection that is due: a minor collection, or a major one
+    /// once the old generation has grown by `GcConfig::old_growth_factor`
+    /// since the last major collection. Roots must be set, as for `collect`.
+    pub fn collect_auto(&mut self) {
+        if self.old_bytes >= self.major_threshold {
+            self.collect();
+        } else {
+            self.collect_minor();
+        }
+    }
+
+    /// Write barrier: call before storing `value` into the heap object
+    /// `target` (record fields, array elements). An old object that gets a
+    /// pointer to a young object joins the remembered set, so minor
+    /// collections keep that young object alive.
+    #[inline]
+    pub fn write_barrier(&mut self, target: RawGcPtr, value: &GcValue) {
+        match self.objects.get(target as usize) {
+            Some(Some(obj)) if obj.old && !obj.remembered => {}
+            _ => return,
+        }
+        if value.gc_pointers().into_iter().any(|p| self.is_young(p)) {
+            if let Some(obj) = self.objects[target as usize].as_mut() {
+                obj.remembered = true;
+            }
+            self.remembered.push(target);
+        }
+    }
+
+    /// Whether `ptr` is a live object in the young generation.
+    #[inline]
+    pub fn is_young(&self, ptr: RawGcPtr) -> bool {
+        matches!(self.objects.get(ptr as usize), Some(Some(obj)) if !obj.old)
+    }
+
+    /// Get the number of bytes in the old generation.
+    pub fn old_bytes(&self) -> usize {
+        self.old_bytes
+    }
+
+    /// Get the number of bytes in live objects. Objects that became garbage
+    /// still count until a collection sweeps them.
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes
+    }
+
+    /// Whether the live heap is larger than `GcConfig::max_heap_bytes`.
+    /// Callers should collect (with roots set) before acting on a true result.
+    pub fn over_heap_limit(&self) -> bool {
+        matches!(self.config.max_heap_bytes, Some(max) if self.live_bytes > max)
+    }
+
     /// Mark phase: mark all reachable objects starting from roots.
     fn mark_phase(&mut self) {
         // Unmark all objects first
@@ -2720,22 +2945,38 @@ impl Heap {
         }
     }
 
-    /// Sweep phase: free unmarked objects.
+    /// Sweep phase: free unmarked objects and promote the survivors.
     fn sweep_phase(&mut self) -> usize {
         let mut freed = 0;
         let mut bytes_freed = 0;
+        let mut live_bytes = 0;
 
         for i in 0..self.objects.len() {
-            if let Some(ref obj) = self.objects[i] {
+            if let Some(ref mut obj) = self.objects[i] {
                 if !obj.marked {
                     bytes_freed += obj.size;
                     freed += 1;
                     self.objects[i] = None;
                     self.free_list.push(i as RawGcPtr);
+                } else {
+                    if !obj.old {
+                        obj.old = true;
+                        self.stats.total_promoted += 1;
+                    }
+                    obj.remembered = false;
+                    live_bytes += obj.size;
                 }
             }
         }
 
+        // Everything is old now: nothing to remember
+        self.young.clear();
+        self.remembered.clear();
+        self.old_bytes = live_bytes;
+        self.live_bytes = live_bytes;
+        self.major_threshold = ((live_bytes as f64 * self.config.old_growth_factor) as usize)
+            .max(self.config.gc_threshold);
+
         self.stats.total_freed += freed;
         self.stats.total_bytes_freed += bytes_freed as u64;
         self.live_count -= freed as usize;
@@ -2746,7 +2987,7 @@ impl Heap {
     /// Collect if threshold exceeded, otherwise do nothing.
     pub fn maybe_collect(&mut self) {
         if self.should_collect() {
-            self.collect();
+            self.collect_auto();
         }
     }
 
@@ -3998,6 +4239,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: std::sync::atomic::AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -4319,6 +4563,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: std::sync::atomic::AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -4576,6 +4823,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: std::sync::atomic::AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -4653,6 +4903,142 @@ mod tests {
         assert_eq!(after, before + 1);
     }
 
+    // ============================================================
+    // Generational Collection Tests
+    // ============================================================
+
+    #[test]
+    fn test_minor_collection_skips_old_generation() {
+        let mut heap = Heap::new();
+
+        // Promoted by a major collection, then unreachable
+        let old = heap.alloc_string("old".to_string());
+        heap.add_root(old.as_raw());
+        heap.collect();
+        heap.clear_roots();
+        assert!(!heap.is_young(old.as_raw()));
+
+        let young = heap.alloc_string("young".to_string());
+        let _garbage = heap.alloc_string("garbage".to_string());
+        heap.add_root(young.as_raw());
+        heap.collect_minor();
+
+        // Young garbage is freed; the old object waits for a major collection
+        assert_eq!(heap.live_objects(), 2);
+        assert!(heap.get_string(old).is_some());
+        assert!(heap.get_string(young).is_some());
+
+        heap.clear_roots();
+        heap.collect();
+        assert_eq!(heap.live_objects(), 0);
+        assert_eq!(heap.stats.minor_collections, 1);
+        assert_eq!(heap.stats.major_collections, 2);
+        assert_eq!(heap.stats.collections, 3);
+    }
+
+    #[test]
+    fn test_promotion_after_minor_collections() {
+        let config = GcConfig {
+            promotion_age: 2,
+            ..Default::default()
+        };
+        let mut heap = Heap::with_config(config);
+        let ptr = heap.alloc_string("survivor".to_string());
+        heap.add_root(ptr.as_raw());
+
+        heap.collect_minor();
+        assert!(heap.is_young(ptr.as_raw()));
+        heap.collect_minor();
+        assert!(!heap.is_young(ptr.as_raw()));
+        assert_eq!(heap.stats.total_promoted, 1);
+        assert!(heap.old_bytes() > 0);
+    }
+
+    #[test]
+    fn test_write_barrier_keeps_young_objects_alive() {
+        let mut heap = Heap::new();
+        let array = heap.alloc_array(vec![GcValue::Unit]);
+        heap.add_root(array.as_raw());
+        heap.collect();
+
+        // Only the (old) array points at the new string
+        let s = heap.alloc_string("stored".to_string());
+        let value = GcValue::String(s);
+        heap.write_barrier(array.as_raw(), &value);
+        heap.get_array_mut(array).unwrap().items[0] = value;
+        heap.collect_minor();
+        assert_eq!(heap.get_string(s).unwrap().data, "stored");
+
+        // Once promoted, the array no longer needs remembering
+        heap.collect_minor();
+        assert!(!heap.is_young(s.as_raw()));
+        assert!(heap.remembered.is_empty());
+    }
+
+    #[test]
+    fn test_promoted_object_remembers_young_children() {
+        let mut heap = Heap::new();
+        let parent = heap.alloc_array(vec![GcValue::Unit]);
+        heap.add_root(parent.as_raw());
+        heap.collect_minor();
+
+        // Stored while the parent is young, so no barrier applies
+        let child = heap.alloc_string("child".to_string());
+        heap.get_array_mut(parent).unwrap().items[0] = GcValue::String(child);
+
+        // The parent is promoted while the child stays young
+        heap.collect_minor();
+        assert!(!heap.is_young(parent.as_raw()));
+        assert!(heap.is_young(child.as_raw()));
+        heap.collect_minor();
+        assert_eq!(heap.get_string(child).unwrap().data, "child");
+    }
+
+    #[test]
+    fn test_collect_auto_runs_major_when_old_generation_grows() {
+        let config = GcConfig {
+            gc_threshold: 100,
+            promotion_age: 1,
+            ..Default::default()
+        };
+        let mut heap = Heap::with_config(config);
+        let ptrs: Vec<_> = (0..20).map(|i| heap.alloc_string(format!("live{}", i))).collect();
+        for ptr in &ptrs {
+            heap.add_root(ptr.as_raw());
+        }
+
+        // Survivors are promoted until the old generation passes the threshold
+        heap.collect_auto();
+        assert_eq!(heap.stats.minor_collections, 1);
+        assert!(heap.old_bytes() >= 100);
+        heap.collect_auto();
+        assert_eq!(heap.stats.major_collections, 1);
+        assert_eq!(heap.live_objects(), 20);
+    }
+
+    #[test]
+    fn test_heap_limit_counts_only_live_bytes() {
+        let config = GcConfig {
+            max_heap_bytes: Some(500),
+            ..Default::default()
+        };
+        let mut heap = Heap::with_config(config);
+        let keep = heap.alloc_string("keep".to_string());
+        heap.add_root(keep.as_raw());
+        for i in 0..20 {
+            heap.alloc_string(format!("garbage{}", i));
+        }
+        assert!(heap.over_heap_limit());
+
+        // Sweeping the garbage brings the heap back under the limit
+        heap.collect_minor();
+        assert!(!heap.over_heap_limit());
+        let live = heap.live_bytes();
+        heap.collect();
+        assert_eq!(heap.live_bytes(), live);
+        assert_eq!(heap.live_bytes(), heap.old_bytes());
+    }
+
     // ============================================================
     // Edge Cases
     // ============================================================
@@ -0,0 +1,445 @@
+//! Heap snapshots: a dump of every object in a process heap, and the offline
+//! analysis behind `nostos heap`.
+//!
+//! `Gc.snapshot(pid, path)` runs a major collection in the target process and
+//! writes its heap, so a snapshot holds only objects reachable from the
+//! process's registers, captures and exception state.
+//!
+//! # Format
+//!
+//! A snapshot is a JSON Lines file (one JSON object per line). The first line
+//! is a header:
+//!
+//! ```text
+//! {"format":"nostos-heap-snapshot","version":1,"pid":3,"roots":[0,5]}
+//! ```
+//!
+//! `roots` lists the objects the process references directly. Every further
+//! line is one heap object:
+//!
+//! ```text
+//! {"id":5,"kind":"Record","type":"Point","size":72,"refs":[6,7]}
+//! ```
+//!
+//! - `id`: heap slot of the object, unique within the snapshot
+//! - `kind`: heap data kind (`String`, `Record`, `Variant`, `Tuple`, `Closure`, `Map`, ...)
+//! - `type`: record or variant type name, function name for closures, otherwise the kind
+//! - `size`: estimated size in bytes, as counted by the collector
+//! - `refs`: ids of the objects this one points to (may repeat)
+//!
+//! Lists are stored inline in values rather than as heap objects, so the
+//! elements of a list held by an object appear directly in that object's refs.
+//!
+//! # Analysis
+//!
+//! [`HeapSnapshot::analyze`] computes the dominator tree of the object graph
+//! (an object dominates another when every path from the roots to the second
+//! goes through the first) and each object's retained size: the bytes that
+//! would be freed if it became unreachable.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gc::{HeapData, Heap, RawGcPtr};
+
+/// Value of the header's `format` field.
+pub const HEAP_SNAPSHOT_FORMAT: &str = "nostos-heap-snapshot";
+
+/// Current snapshot format version.
+pub const HEAP_SNAPSHOT_VERSION: u32 = 1;
+
+/// First line of a snapshot file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotHeader {
+    format: String,
+    version: u32,
+    pid: u64,
+    roots: Vec<RawGcPtr>,
+}
+
+/// One heap object in a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotObject {
+    pub id: RawGcPtr,
+    pub kind: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub size: usize,
+    pub refs: Vec<RawGcPtr>,
+}
+
+/// A process heap as written by `Gc.snapshot`.
+#[derive(Debug, Clone)]
+pub struct HeapSnapshot {
+    pub pid: u64,
+    pub roots: Vec<RawGcPtr>,
+    pub objects: Vec<SnapshotObject>,
+}
+
+impl HeapSnapshot {
+    /// Capture every object in `heap`.
+    pub fn capture(heap: &Heap, roots: &[RawGcPtr], pid: u64) -> Self {
+        let objects = heap
+            .objects()
+            .map(|(id, obj)| {
+                let kind = format!("{:?}", obj.data.object_type());
+                let type_name = match &obj.data {
+                    HeapData::Record(rec) => rec.type_name.to_string(),
+                    HeapData::Variant(var) => var.type_name.to_string(),
+                    HeapData::Closure(clo) => clo.function.name.clone(),
+                    _ => kind.clone(),
+                };
+                SnapshotObject { id, kind, type_name, size: obj.size, refs: obj.data.gc_pointers() }
+            })
+            .collect();
+        let mut roots = roots.to_vec();
+        roots.sort_unstable();
+        roots.dedup();
+        Self { pid, roots, objects }
+    }
+
+    /// Write the snapshot to `path`.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let header = SnapshotHeader {
+            format: HEAP_SNAPSHOT_FORMAT.to_string(),
+            version: HEAP_SNAPSHOT_VERSION,
+            pid: self.pid,
+            roots: self.roots.clone(),
+        };
+        serde_json::to_writer(&mut out, &header)?;
+        out.write_all(b"\n")?;
+        for obj in &self.objects {
+            serde_json::to_writer(&mut out, obj)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()
+    }
+
+    /// Read a snapshot written by [`HeapSnapshot::write`].
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+        let mut lines = BufReader::new(file).lines();
+        let header_line = lines
+            .next()
+            .ok_or("empty snapshot file")?
+            .map_err(|e| e.to_string())?;
+        let header: SnapshotHeader = serde_json::from_str(&header_line)
+            .map_err(|_| "not a heap snapshot (bad header)".to_string())?;
+        if header.format != HEAP_SNAPSHOT_FORMAT {
+            return Err("not a heap snapshot (bad header)".to_string());
+        }
+        if header.version != HEAP_SNAPSHOT_VERSION {
+            return Err(format!(
+                "unsupported snapshot version {} (expected {})",
+                header.version, HEAP_SNAPSHOT_VERSION
+            ));
+        }
+        let mut objects = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let obj = serde_json::from_str(&line).map_err(|e| format!("line {}: {}", i + 2, e))?;
+            objects.push(obj);
+        }
+        Ok(Self { pid: header.pid, roots: header.roots, objects })
+    }
+
+    /// Compute the dominator tree and retained sizes.
+    pub fn analyze(&self) -> HeapAnalysis {
+        HeapAnalysis::new(self)
+    }
+}
+
+/// Retained-size totals for one type name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeSummary {
+    pub type_name: String,
+    /// Reachable objects of this type
+    pub count: usize,
+    /// Sum of their own sizes
+    pub shallow_size: usize,
+    /// Bytes kept alive by objects of this type. Objects dominated by another
+    /// object of the same type are not counted twice.
+    pub retained_size: usize,
+}
+
+/// Dominator tree and retained sizes of a [`HeapSnapshot`].
+///
+/// Objects are referred to by their index in `HeapSnapshot::objects`.
+#[derive(Debug, Clone)]
+pub struct HeapAnalysis {
+    /// Immediate dominator of each object; None for objects dominated only by
+    /// the root set, and for unreachable objects
+    pub idom: Vec<Option<usize>>,
+    /// Retained size of each object (0 when unreachable)
+    pub retained: Vec<usize>,
+    /// Whether each object is reachable from the roots
+    pub reachable: Vec<bool>,
+    /// Children of each object in the dominator tree, largest retained size first
+    pub children: Vec<Vec<usize>>,
+    /// Objects directly below the root set in the dominator tree, largest first
+    pub top_level: Vec<usize>,
+}
+
+impl HeapAnalysis {
+    /// Cooper, Harvey and Kennedy's iterative dominator algorithm over the
+    /// object graph, with a virtual node 0 standing for the root set.
+    fn new(snapshot: &HeapSnapshot) -> Self {
+        let count = snapshot.objects.len();
+        let index: HashMap<RawGcPtr, usize> = snapshot
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, obj)| (obj.id, i + 1))
+            .collect();
+        let mut succs: Vec<Vec<usize>> = Vec::with_capacity(count + 1);
+        succs.push(snapshot.roots.iter().filter_map(|r| index.get(r).copied()).collect());
+        for obj in &snapshot.objects {
+            succs.push(obj.refs.iter().filter_map(|r| index.get(r).copied()).collect());
+        }
+
+        // Reverse postorder from the virtual root
+        let mut order = Vec::with_capacity(count + 1);
+        let mut visited = vec![false; count + 1];
+        let mut stack = vec![(0usize, 0usize)];
+        visited[0] = true;
+        while let Some((node, next)) = stack.last_mut() {
+            if let Some(&succ) = succs[*node].get(*next) {
+                *next += 1;
+                if !visited[succ] {
+                    visited[succ] = true;
+                    stack.push((succ, 0));
+                }
+            } else {
+                order.push(*node);
+                stack.pop();
+            }
+        }
+        order.reverse();
+        let mut rpo = vec![usize::MAX; count + 1];
+        for (pos, &node) in order.iter().enumerate() {
+            rpo[node] = pos;
+        }
+
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); count + 1];
+        for &node in &order {
+            for &succ in &succs[node] {
+                preds[succ].push(node);
+            }
+        }
+
+        const UNDEF: usize = usize::MAX;
+        let mut doms = vec![UNDEF; count + 1];
+        doms[0] = 0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in order.iter().skip(1) {
+                let mut new_idom = UNDEF;
+                for &pred in &preds[node] {
+                    if doms[pred] == UNDEF {
+                        continue;
+                    }
+                    new_idom = if new_idom == UNDEF {
+                        pred
+                    } else {
+                        let (mut a, mut b) = (pred, new_idom);
+                        while a != b {
+                            while rpo[a] > rpo[b] {
+                                a = doms[a];
+                            }
+                            while rpo[b] > rpo[a] {
+                                b = doms[b];
+                            }
+                        }
+                        a
+                    };
+                }
+                if doms[node] != new_idom {
+                    doms[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        // Retained sizes: children come after their dominator in reverse postorder
+        let mut retained = vec![0usize; count + 1];
+        for &node in order.iter().skip(1) {
+            retained[node] = snapshot.objects[node - 1].size;
+        }
+        for &node in order.iter().skip(1).rev() {
+            retained[doms[node]] += retained[node];
+        }
+
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut top_level = Vec::new();
+        for &node in order.iter().skip(1) {
+            match doms[node] {
+                0 => top_level.push(node - 1),
+                dom => children[dom - 1].push(node - 1),
+            }
+        }
+        let by_retained = |a: &usize, b: &usize| retained[b + 1].cmp(&retained[a + 1]).then(a.cmp(b));
+        top_level.sort_by(by_retained);
+        for list in &mut children {
+            list.sort_by(by_retained);
+        }
+
+        Self {
+            idom: (1..=count)
+                .map(|n| match doms[n] {
+                    UNDEF | 0 => None,
+                    dom => Some(dom - 1),
+                })
+                .collect(),
+            retained: retained[1..].to_vec(),
+            reachable: visited[1..].to_vec(),
+            children,
+            top_level,
+        }
+    }
+
+    /// Totals per type name, largest retained size first.
+    pub fn by_type(&self, snapshot: &HeapSnapshot) -> Vec<TypeSummary> {
+        let mut summaries: HashMap<&str, TypeSummary> = HashMap::new();
+        // Walk the dominator tree, tracking the types on the path from the
+        // roots so nested objects of one type are only counted at the top
+        let mut active: HashMap<&str, usize> = HashMap::new();
+        let mut stack: Vec<(usize, bool)> = self.top_level.iter().rev().map(|&n| (n, false)).collect();
+        while let Some((node, exiting)) = stack.pop() {
+            let type_name = snapshot.objects[node].type_name.as_str();
+            if exiting {
+                *active.get_mut(type_name).unwrap() -= 1;
+                continue;
+            }
+            let entry = summaries.entry(type_name).or_insert_with(|| TypeSummary {
+                type_name: type_name.to_string(),
+                count: 0,
+                shallow_size: 0,
+                retained_size: 0,
+            });
+            entry.count += 1;
+            entry.shallow_size += snapshot.objects[node].size;
+            let depth = active.entry(type_name).or_insert(0);
+            if *depth == 0 {
+                entry.retained_size += self.retained[node];
+            }
+            *depth += 1;
+            stack.push((node, true));
+            stack.extend(self.children[node].iter().rev().map(|&c| (c, false)));
+        }
+        let mut summaries: Vec<TypeSummary> = summaries.into_values().collect();
+        summaries.sort_by(|a, b| {
+            b.retained_size.cmp(&a.retained_size).then_with(|| a.type_name.cmp(&b.type_name))
+        });
+        summaries
+    }
+
+    /// Total size of the reachable objects.
+    pub fn reachable_size(&self, snapshot: &HeapSnapshot) -> usize {
+        snapshot
+            .objects
+            .iter()
+            .zip(&self.reachable)
+            .filter(|(_, reachable)| **reachable)
+            .map(|(obj, _)| obj.size)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gc::GcValue;
+
+    fn object(id: RawGcPtr, type_name: &str, size: usize, refs: Vec<RawGcPtr>) -> SnapshotObject {
+        SnapshotObject { id, kind: "Record".to_string(), type_name: type_name.to_string(), size, refs }
+    }
+
+    #[test]
+    fn test_dominators_and_retained_sizes() {
+        // root -> 1 -> {2, 3}, 2 -> 4, 3 -> 4, root -> 5 -> 3; 6 unreachable
+        let snapshot = HeapSnapshot {
+            pid: 1,
+            roots: vec![1, 5],
+            objects: vec![
+                object(1, "Tree", 10, vec![2, 3]),
+                object(2, "Node", 20, vec![4]),
+                object(3, "Node", 30, vec![4]),
+                object(4, "Leaf", 40, vec![]),
+                object(5, "Cache", 50, vec![3]),
+                object(6, "Leaf", 60, vec![]),
+            ],
+        };
+        let analysis = snapshot.analyze();
+
+        // 3 is reachable through 1 and 5, so only the roots dominate it;
+        // 4 is reachable through 2 and 3, so 1 doesn't dominate it either
+        assert_eq!(analysis.idom, vec![None, Some(0), None, None, None, None]);
+        assert_eq!(analysis.retained, vec![30, 20, 30, 40, 50, 0]);
+        assert_eq!(analysis.reachable, vec![true, true, true, true, true, false]);
+        assert_eq!(analysis.top_level, vec![4, 3, 0, 2]);
+        assert_eq!(analysis.reachable_size(&snapshot), 150);
+
+        let by_type = analysis.by_type(&snapshot);
+        let node = by_type.iter().find(|t| t.type_name == "Node").unwrap();
+        assert_eq!((node.count, node.shallow_size, node.retained_size), (2, 50, 50));
+        assert_eq!(by_type[0].type_name, "Cache");
+    }
+
+    #[test]
+    fn test_nested_objects_of_one_type_are_counted_once() {
+        // A chain Cons -> Cons -> Cons retains everything from its head
+        let snapshot = HeapSnapshot {
+            pid: 1,
+            roots: vec![1],
+            objects: vec![
+                object(1, "Cons", 10, vec![2]),
+                object(2, "Cons", 10, vec![3]),
+                object(3, "Cons", 10, vec![]),
+            ],
+        };
+        let analysis = snapshot.analyze();
+        assert_eq!(analysis.retained, vec![30, 20, 10]);
+        let by_type = analysis.by_type(&snapshot);
+        assert_eq!(by_type.len(), 1);
+        assert_eq!((by_type[0].count, by_type[0].retained_size), (3, 30));
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip_from_heap() {
+        let mut heap = Heap::new();
+        let name = heap.alloc_string("origin".to_string());
+        let point = heap.alloc_record(
+            "Point".to_string(),
+            vec!["name".to_string(), "x".to_string()],
+            vec![GcValue::String(name), GcValue::Int64(0)],
+            vec![false, false],
+        );
+        heap.alloc_string("garbage".to_string());
+
+        let snapshot = HeapSnapshot::capture(&heap, &[point.as_raw()], 7);
+        let path = std::env::temp_dir().join(format!("nostos_heap_snapshot_{}.jsonl", std::process::id()));
+        snapshot.write(&path).unwrap();
+        let read = HeapSnapshot::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(read.pid, 7);
+        assert_eq!(read.roots, vec![point.as_raw()]);
+        assert_eq!(read.objects, snapshot.objects);
+        let record = read.objects.iter().find(|o| o.id == point.as_raw()).unwrap();
+        assert_eq!((record.kind.as_str(), record.type_name.as_str()), ("Record", "Point"));
+        assert_eq!(record.refs, vec![name.as_raw()]);
+
+        let analysis = read.analyze();
+        let garbage = read.objects.iter().position(|o| o.type_name == "String" && o.id != name.as_raw()).unwrap();
+        assert!(!analysis.reachable[garbage]);
+    }
+}
@@ -27,6 +27,7 @@ use futures::{SinkExt, StreamExt};
 use thirtyfour::prelude::*;
 
 use crate::process::{IoResponseValue, PgValue};
+use crate::tls::{ClientOptions, TcpConn, TlsFiles};
 
 /// Wrapper for pgvector binary format
 /// Format: 2 bytes dim (u16 big-endian), 2 bytes unused, n*4 bytes f32 (big-endian)
@@ -118,6 +119,9 @@ type LockedPgConnection = Arc<TokioMutex<PgConnection>>;
 /// Map of connection handles to per-connection locked connections
 type PgConnections = Arc<tokio::sync::RwLock<HashMap<u64, LockedPgConnection>>>;
 
+/// Map of TCP listener handles to listeners, with the TLS acceptor of `Tcp.listenTls` listeners
+type TcpListeners = Arc<TokioMutex<HashMap<u64, (tokio::net::TcpListener, Option<tokio_rustls::TlsAcceptor>)>>>;
+
 /// Listener connection for LISTEN/NOTIFY
 /// Uses a dedicated non-pooled connection with a notification channel
 struct PgListenerConnection {
@@ -227,6 +231,7 @@ pub enum IoError {
     InvalidUrl(String),
     ConnectionFailed(String),
     PgError(String),
+    TlsError(String),
     Other(String),
 }
 
@@ -242,6 +247,7 @@ impl std::fmt::Display for IoError {
             IoError::InvalidUrl(u) => write!(f, "Invalid URL: {}", u),
             IoError::ConnectionFailed(e) => write!(f, "Connection failed: {}", e),
             IoError::PgError(e) => write!(f, "PostgreSQL error: {}", e),
+            IoError::TlsError(e) => write!(f, "TLS error: {}", e),
             IoError::Other(e) => write!(f, "{}", e),
         }
     }
@@ -376,8 +382,10 @@ pub enum IoRequest {
     },
 
     // HTTP Server operations
+    /// Start an HTTP server, or an HTTPS one (serving `wss://` too) with `tls`
     ServerBind {
         port: u16,
+        tls: Option<TlsFiles>,
         response: IoResponse,
     },
     ServerAccept {
@@ -644,15 +652,17 @@ pub enum IoRequest {
     },
 
     // TCP Socket operations
-    /// Connect to a TCP server
+    /// Connect to a TCP server, over TLS with `tls`
     TcpConnect {
         host: String,
         port: u16,
+        tls: Option<ClientOptions>,
         response: IoResponse,
     },
-    /// Listen on a TCP port
+    /// Listen on a TCP port; with `tls`, accepted sockets are TLS
     TcpListen {
         port: u16,
+        tls: Option<TlsFiles>,
         response: IoResponse,
     },
     /// Accept a connection on a TCP listener
@@ -830,12 +840,12 @@ impl IoRuntime {
         use tokio::net::{TcpListener, TcpStream};
 
         // TCP listeners (server sockets)
-        let tcp_listeners: Arc<TokioMutex<HashMap<u64, TcpListener>>> = Arc::new(TokioMutex::new(HashMap::new()));
+        let tcp_listeners: TcpListeners = Arc::new(TokioMutex::new(HashMap::new()));
         let mut next_tcp_listener_handle: u64 = 1;
 
-        // TCP streams (client connections) - each stream has its own mutex to avoid deadlocks
+        // TCP streams (client connections, plain or TLS) - each stream has its own mutex to avoid deadlocks
         // when multiple processes read/write different streams concurrently
-        let tcp_streams: Arc<TokioMutex<HashMap<u64, Arc<TokioMutex<TcpStream>>>>> = Arc::new(TokioMutex::new(HashMap::new()));
+        let tcp_streams: Arc<TokioMutex<HashMap<u64, Arc<TokioMutex<TcpConn>>>>> = Arc::new(TokioMutex::new(HashMap::new()));
         let mut next_tcp_stream_handle: u64 = 1;
 
         while let Some(request) = request_rx.recv().await {
@@ -1126,13 +1136,22 @@ impl IoRuntime {
                 }
 
                 // HTTP Server operations
-                IoRequest::ServerBind { port, response } => {
+                IoRequest::ServerBind { port, tls, response } => {
                     use axum::{
                         extract::Request,
                         routing::any,
                         Router,
                     };
 
+                    // Load the certificate before binding, so a bad one doesn't hold the port
+                    let tls_acceptor = match tls.map(|files| crate::tls::server_config(&files, &[b"http/1.1"])).transpose() {
+                        Ok(config) => config.map(tokio_rustls::TlsAcceptor::from),
+                        Err(e) => {
+                            let _ = response.send(Err(IoError::TlsError(e)));
+                            continue;
+                        }
+                    };
+
                     let handle = next_server_handle;
                     next_server_handle += 1;
 
@@ -1352,7 +1371,12 @@ impl IoRuntime {
                         Ok(listener) => {
                             // Spawn the server and store its abort handle for cleanup
                             let server_task = tokio::spawn(async move {
-                                axum::serve(listener, app).await.ok();
+                                match tls_acceptor {
+                                    Some(acceptor) => crate::tls::serve_https(listener, acceptor, app).await,
+                                    None => {
+                                        axum::serve(listener, app).await.ok();
+                                    }
+                                }
                             });
                             server_abort_handles.lock().await.insert(handle, server_task.abort_handle());
                             let _ = response.send(Ok(IoResponseValue::ServerHandle(handle)));
@@ -2772,27 +2796,40 @@ impl IoRuntime {
                 }
 
                 // TCP Socket operations
-                IoRequest::TcpConnect { host, port, response } => {
+                IoRequest::TcpConnect { host, port, tls, response } => {
                     let streams = tcp_streams.clone();
                     let handle = next_tcp_stream_handle;
                     next_tcp_stream_handle += 1;
 
                     tokio::spawn(async move {
-                        let addr = format!("{}:{}", host, port);
-                        match TcpStream::connect(&addr).await {
+                        let connected: Result<TcpConn, IoError> = match tls {
+                            Some(options) => crate::tls::connect(&host, port, &options).await
+                                .map_err(IoError::TlsError),
+                            None => TcpStream::connect(format!("{}:{}", host, port)).await
+                                .map(|stream| Box::new(stream) as TcpConn)
+                                .map_err(|e| IoError::IoError(format!("TCP connect error: {}", e))),
+                        };
+                        match connected {
                             Ok(stream) => {
                                 // Wrap in Arc<Mutex> for per-stream locking
                                 streams.lock().await.insert(handle, Arc::new(TokioMutex::new(stream)));
                                 let _ = response.send(Ok(IoResponseValue::Int(handle as i64)));
                             }
                             Err(e) => {
-                                let _ = response.send(Err(IoError::IoError(format!("TCP connect error: {}", e))));
+                                let _ = response.send(Err(e));
                             }
                         }
                     });
                 }
 
-                IoRequest::TcpListen { port, response } => {
+                IoRequest::TcpListen { port, tls, response } => {
+                    let acceptor = match tls.as_ref().map(crate::tls::acceptor).transpose() {
+                        Ok(acceptor) => acceptor,
+                        Err(e) => {
+                            let _ = response.send(Err(IoError::TlsError(e)));
+                            continue;
+                        }
+                    };
                     let listeners = tcp_listeners.clone();
                     let handle = next_tcp_listener_handle;
                     next_tcp_listener_handle += 1;
@@ -2801,7 +2838,7 @@ impl IoRuntime {
                         let addr = format!("0.0.0.0:{}", port);
                         match TcpListener::bind(&addr).await {
                             Ok(listener) => {
-                                listeners.lock().await.insert(handle, listener);
+                                listeners.lock().await.insert(handle, (listener, acceptor));
                                 let _ = response.send(Ok(IoResponseValue::Int(handle as i64)));
                             }
                             Err(e) => {
@@ -2819,10 +2856,24 @@ impl IoRuntime {
 
                     tokio::spawn(async move {
                         let mut listeners_guard = listeners.lock().await;
-                        if let Some(listener) = listeners_guard.get_mut(&handle) {
-                            match listener.accept().await {
-                                Ok((stream, _addr)) => {
-                                    drop(listeners_guard); // Release listener lock before getting stream lock
+                        if let Some((listener, acceptor)) = listeners_guard.get_mut(&handle) {
+                            // On a TLS listener, a client whose handshake fails is dropped
+                            // and the next connection is accepted instead
+                            let accepted = loop {
+                                match listener.accept().await {
+                                    Ok((stream, _addr)) => match acceptor {
+                                        Some(acceptor) => match acceptor.accept(stream).await {
+                                            Ok(stream) => break Ok(Box::new(stream) as TcpConn),
+                                            Err(_) => continue,
+                                        },
+                                        None => break Ok(Box::new(stream) as TcpConn),
+                                    },
+                                    Err(e) => break Err(e),
+                                }
+                            };
+                            drop(listeners_guard); // Release listener lock before getting stream lock
+                            match accepted {
+                                Ok(stream) => {
                                     // Wrap in Arc<Mutex> for per-stream locking
                                     streams.lock().await.insert(stream_handle, Arc::new(TokioMutex::new(stream)));
                                     let _ = response.send(Ok(IoResponseValue::Int(stream_handle as i64)));
@@ -0,0 +1,506 @@
+//! Runtime support for the baseline and optimizing JIT tiers.
+//!
+//! Baseline-compiled functions (see `nostos_jit::baseline`) work directly on the
+//! interpreter's call frames: every value stays in the frame's registers.
+//! Control flow, moves and Int64 arithmetic are compiled inline (see
+//! `compiled_inline`); runs of other instructions are handed to the
+//! interpreter through `nos_jit_interpret`, which executes them up to the next
+//! inline instruction without going back through the scheduler loop.
+//!
+//! Native code returns an exit code to `AsyncProcess::run`:
+//! - `ip >= 0`: the instruction at `ip` must be executed by the async
+//!   interpreter (it may suspend the process, or the reduction budget ran out
+//!   at a back-edge).
+//! - `JIT_EXIT_RESUME`: frames changed (call, return, exception) or the process
+//!   must yield; continue wherever the current frame points.
+//! - `JIT_EXIT_STEP`: a helper produced a step result (error or process exit)
+//!   that is waiting in `AsyncProcess::jit_step_result`.
+//!
+//! Optimized code (see `nostos_jit::optimizing`) is specialized to the
+//! argument kinds in the function's `TypeProfile` and keeps values unboxed.
+//! It is only entered at the start of a call; if an argument guard fails it
+//! returns `JIT_EXIT_DEOPT` and the call runs in the baseline tier instead.
+//! Everywhere else it leaves (returns, back-edges that must yield, division
+//! by zero), it boxes its values back into the frame's registers and returns
+//! the ip to continue at, like baseline code.
+//!
+//! Loops move running frames into native code (on-stack replacement): the
+//! interpreter counts back-edges and queues functions that loop a lot even
+//! if they are called only once, and hands the frame to native code at the
+//! loop head once it exists. Baseline code can be entered at any ip;
+//! optimized code has entries at loop heads that guard and unbox the live
+//! registers. Native loops charge their reductions at each back-edge and,
+//! when the process must yield, leave with the frame at the loop head so the
+//! run loop checks for interrupts and yields before re-entering.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::task::{Context, Poll, Waker};
+
+use crate::async_vm::{AsyncProcess, StepResult, REDUCTIONS_PER_YIELD};
+use crate::gc::GcValue;
+use crate::value::{FunctionValue, Instruction, RuntimeError, TypeProfile};
+
+/// Signature of baseline-compiled code: `(process, entry_ip) -> exit code`.
+pub type JitEntryFn = unsafe extern "C" fn(*mut AsyncProcess, i64) -> i64;
+
+/// Continue at the current frame's ip.
+pub const JIT_EXIT_RESUME: i64 = -1;
+/// A step result is waiting in `AsyncProcess::jit_step_result`.
+pub const JIT_EXIT_STEP: i64 = -2;
+
+/// Optimized code was not entered because an argument guard failed;
+/// nothing has been changed.
+pub const JIT_EXIT_DEOPT: i64 = -3;
+
+/// Argument guard failures before optimized code is dropped for good.
+pub const MAX_DEOPTS: u32 = 4;
+
+/// A function is sent to the optimizing tier after this many times the
+/// baseline threshold of calls.
+pub const OPTIMIZE_AFTER: u32 = 10;
+
+/// A function is sent to the baseline tier after this many times the
+/// baseline threshold of loop back-edges, even if it was called only once.
+pub const OSR_AFTER: u32 = 10;
+
+/// Operations with an Int64 fast path in `nos_jit_int_op`.
+pub const JIT_OP_ADD: i64 = 0;
+pub const JIT_OP_SUB: i64 = 1;
+pub const JIT_OP_MUL: i64 = 2;
+pub const JIT_OP_EQ: i64 = 3;
+pub const JIT_OP_LT: i64 = 4;
+pub const JIT_OP_LE: i64 = 5;
+pub const JIT_OP_GT: i64 = 6;
+pub const JIT_OP_GE: i64 = 7;
+
+/// A hot function for the JIT compiler thread.
+pub struct JitRequest {
+    pub function: Arc<FunctionValue>,
+    /// For the optimizing tier rather than the baseline tier
+    pub optimize: bool,
+    /// Functions called with `CallDirect`, by index
+    pub callees: HashMap<u16, Arc<FunctionValue>>,
+}
+
+impl JitRequest {
+    /// Request compilation of `function`, resolving the `CallDirect` callees
+    /// the optimizing tier may inline from `functions`.
+    pub fn new(function: &Arc<FunctionValue>, functions: &[Arc<FunctionValue>], optimize: bool) -> Self {
+        let mut callees = HashMap::new();
+        if optimize {
+            for instruction in function.code.code.iter() {
+                if let Instruction::CallDirect(_, idx, _) = instruction {
+                    if let Some(callee) = functions.get(*idx as usize) {
+                        callees.insert(*idx, callee.clone());
+                    }
+                }
+            }
+        }
+        JitRequest { function: function.clone(), optimize, callees }
+    }
+}
+
+/// Queue of hot functions waiting for the JIT compiler.
+pub struct JitQueue {
+    /// Hot functions are sent here; the compiler installs
+    /// `FunctionValue::jit_entry` and `FunctionValue::opt_entry`.
+    pub sender: Sender<JitRequest>,
+    /// Calls before a function is queued for the baseline tier.
+    pub hot_threshold: u32,
+    /// Calls before a function is queued for the optimizing tier.
+    pub optimize_threshold: u32,
+    /// Loop back-edges before a function is queued for the baseline tier
+    /// (and `OPTIMIZE_AFTER` times as many for the optimizing tier).
+    pub osr_threshold: u32,
+}
+
+/// How `AsyncProcess::run` continues after trying native code.
+pub(crate) enum JitExit {
+    /// The current frame has no native code: interpret as usual.
+    NotCompiled,
+    /// Interpret the instruction at the current ip, then re-enter native code.
+    Interpret,
+    /// Re-enter at the current frame.
+    Resume,
+    /// A helper produced this step result.
+    Step(Result<StepResult, RuntimeError>),
+}
+
+impl AsyncProcess {
+    /// Run native code for the current frame if it has been compiled,
+    /// preferring optimized code. Counts calls (recording argument kinds)
+    /// until a function is hot enough for the optimizing tier, queueing it
+    /// for each tier on the way.
+    pub(crate) fn jit_run(&mut self) -> JitExit {
+        let Some(queue) = self.shared.jit_queue.get() else {
+            return JitExit::NotCompiled;
+        };
+        // The debugger needs to see every instruction, call tracing every call
+        if self.debug_event_sender.is_some() || self.trace.traces_calls() {
+            return JitExit::NotCompiled;
+        }
+        let Some(frame) = self.frames.last() else {
+            return JitExit::NotCompiled;
+        };
+        let function = &frame.function;
+        let ip = frame.ip;
+
+        if ip == 0 && function.call_count.load(Ordering::Relaxed) < queue.optimize_threshold {
+            let profile = &function.type_profile;
+            for (i, value) in frame.registers.iter().take(function.arity.min(TypeProfile::MAX_ARGS)).enumerate() {
+                profile.record_arg(i, value_kind(value));
+            }
+            let calls = function.call_count.fetch_add(1, Ordering::Relaxed) + 1;
+            if calls == queue.hot_threshold || calls == queue.optimize_threshold {
+                let _ = queue.sender.send(self.jit_request(function, calls == queue.optimize_threshold));
+            }
+        }
+        if let Some(optimized) = function.opt_entry.get() {
+            // SAFETY: installed by the optimizing compiler with the JitEntryFn signature
+            match unsafe { optimized(self as *mut AsyncProcess, ip as i64) } {
+                // Only argument guards count: the registers at loop heads may
+                // legitimately differ from the ones the code was compiled for
+                JIT_EXIT_DEOPT if ip == 0 => self.jit_deoptimized(),
+                JIT_EXIT_DEOPT => {}
+                exit => return self.jit_exit(exit),
+            }
+        }
+
+        let Some(frame) = self.frames.last() else {
+            return JitExit::NotCompiled;
+        };
+        let Some(entry) = frame.function.jit_entry.get() else {
+            return JitExit::NotCompiled;
+        };
+        // SAFETY: the entry was installed by the baseline compiler with the
+        // JitEntryFn signature, and only touches this process through the helpers.
+        let exit = unsafe { entry(self as *mut AsyncProcess, ip as i64) };
+        self.jit_exit(exit)
+    }
+
+    fn jit_exit(&mut self, exit: i64) -> JitExit {
+        match exit {
+            JIT_EXIT_RESUME => JitExit::Resume,
+            JIT_EXIT_STEP => JitExit::Step(
+                self.jit_step_result.take().unwrap_or(Ok(StepResult::Continue)),
+            ),
+            ip => {
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.ip = ip as usize;
+                }
+                JitExit::Interpret
+            }
+        }
+    }
+
+    /// An argument guard of the current frame's optimized code failed. After
+    /// `MAX_DEOPTS` failures the function stays with the baseline tier.
+    fn jit_deoptimized(&mut self) {
+        if let Some(frame) = self.frames.last() {
+            let profile = &frame.function.type_profile;
+            if profile.deopts.fetch_add(1, Ordering::Relaxed) + 1 >= MAX_DEOPTS {
+                frame.function.opt_entry.clear();
+            }
+        }
+    }
+
+    /// Count a loop back-edge taken by the interpreter. Returns true when the
+    /// current frame's function has native code by now, which the run loop
+    /// then enters at the loop head.
+    pub(crate) fn jit_backedge(&self) -> bool {
+        if self.shared.jit_queue.get().is_none() || self.debug_event_sender.is_some() || self.trace.traces_calls() {
+            return false;
+        }
+        let Some(frame) = self.frames.last() else {
+            return false;
+        };
+        if frame.function.jit_entry.is_set() || frame.function.opt_entry.is_set() {
+            return true;
+        }
+        self.jit_count_backedges(1);
+        false
+    }
+
+    /// Add `count` back-edges to the current frame's function, queueing it
+    /// for a tier when that tier's threshold is crossed.
+    fn jit_count_backedges(&self, count: u32) {
+        let (Some(queue), Some(frame)) = (self.shared.jit_queue.get(), self.frames.last()) else {
+            return;
+        };
+        let optimize_threshold = queue.osr_threshold.saturating_mul(OPTIMIZE_AFTER);
+        let backedges = &frame.function.type_profile.backedges;
+        let before = backedges.load(Ordering::Relaxed);
+        if before >= optimize_threshold {
+            return;
+        }
+        let after = backedges.fetch_add(count, Ordering::Relaxed).saturating_add(count);
+        for (threshold, optimize) in [(queue.osr_threshold, false), (optimize_threshold, true)] {
+            if before < threshold && after >= threshold {
+                let _ = queue.sender.send(self.jit_request(&frame.function, optimize));
+            }
+        }
+    }
+
+    /// Compilation request for `function`. The optimizing tier also gets the
+    /// functions it calls directly, as inlining candidates.
+    fn jit_request(&self, function: &Arc<FunctionValue>, optimize: bool) -> JitRequest {
+        let functions = self.shared.function_list.read().unwrap();
+        JitRequest::new(function, &functions, optimize)
+    }
+
+    /// Execute instructions of the current frame with the interpreter,
+    /// starting at `ip` and stopping before the next instruction compiled
+    /// inline. Returns the ip to continue at, or an exit code.
+    fn jit_interpret(&mut self, ip: usize) -> i64 {
+        let (function, len) = match self.frames.last_mut() {
+            Some(frame) => {
+                frame.ip = ip;
+                (Arc::as_ptr(&frame.function), self.frames.len())
+            }
+            None => return JIT_EXIT_RESUME,
+        };
+
+        self.jit_single_step = true;
+        let result = {
+            let mut step = std::pin::pin!(self.step());
+            let mut cx = Context::from_waker(Waker::noop());
+            match step.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => result,
+                // Suspending instructions are never handed to this helper
+                Poll::Pending => Err(RuntimeError::Panic(
+                    "baseline JIT: instruction suspended outside the interpreter".to_string(),
+                )),
+            }
+        };
+        self.jit_single_step = false;
+
+        match result {
+            Ok(StepResult::Continue) => match self.frames.last() {
+                // Tail self-calls jump back without a native back-edge, so the
+                // budget is checked here as well
+                Some(frame) if self.frames.len() == len
+                    && Arc::as_ptr(&frame.function) == function
+                    && self.instructions_since_yield + 1 < REDUCTIONS_PER_YIELD => frame.ip as i64,
+                _ => JIT_EXIT_RESUME,
+            },
+            result => {
+                self.jit_step_result = Some(result);
+                JIT_EXIT_STEP
+            }
+        }
+    }
+
+    /// Whether a batch started by `jit_interpret` (or a single step requested
+    /// by `run`) goes on with the current frame's next instruction.
+    pub(crate) fn jit_batch_continues(&self) -> bool {
+        if self.instructions_since_yield + 1 >= REDUCTIONS_PER_YIELD {
+            return false;
+        }
+        match self.frames.last() {
+            Some(frame) => frame.function.code.code.get(frame.ip)
+                .is_some_and(|i| !i.may_suspend() && !compiled_inline(i)),
+            None => false,
+        }
+    }
+
+    #[inline(always)]
+    fn jit_reg(&self, r: i64) -> &GcValue {
+        // SAFETY: native code only runs for the top frame, with registers taken
+        // from its own (validated) bytecode
+        unsafe { self.frames.last().unwrap_unchecked().registers.get_unchecked(r as usize) }
+    }
+
+    #[inline(always)]
+    fn jit_set_reg(&mut self, r: i64, value: GcValue) {
+        // SAFETY: see `jit_reg`
+        unsafe {
+            *self.frames.last_mut().unwrap_unchecked().registers.get_unchecked_mut(r as usize) = value;
+        }
+    }
+}
+
+/// Interpret instructions starting at `ip`. Returns the ip to continue at,
+/// or an exit code.
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_interpret(process: *mut AsyncProcess, ip: i64) -> i64 {
+    let process = unsafe { &mut *process };
+    process.jit_interpret(ip as usize)
+}
+
+/// Charge `count` reductions at a loop back-edge to `target`. Returns 1 when
+/// the process must return to the interpreter loop to yield and check for
+/// interrupts; the frame is then left at `target` and native code exits with
+/// `JIT_EXIT_RESUME` (after boxing its registers, for optimized code).
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_tick(process: *mut AsyncProcess, count: i64, target: i64) -> i64 {
+    let process = unsafe { &mut *process };
+    process.jit_count_backedges(1);
+    process.instructions_since_yield += count as usize;
+    if process.instructions_since_yield + 1 < REDUCTIONS_PER_YIELD {
+        return 0;
+    }
+    if let Some(frame) = process.frames.last_mut() {
+        frame.ip = target as usize;
+    }
+    1
+}
+
+/// `Move(dst, src)`
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_move(process: *mut AsyncProcess, dst: i64, src: i64) {
+    let process = unsafe { &mut *process };
+    let value = process.jit_reg(src).clone();
+    process.jit_set_reg(dst, value);
+}
+
+/// `LoadUnit`, `LoadTrue` and `LoadFalse`: `kind` is 0 for unit, 1 for true
+/// and 2 for false.
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_load_simple(process: *mut AsyncProcess, dst: i64, kind: i64) {
+    let process = unsafe { &mut *process };
+    let value = match kind {
+        0 => GcValue::Unit,
+        1 => GcValue::Bool(true),
+        _ => GcValue::Bool(false),
+    };
+    process.jit_set_reg(dst, value);
+}
+
+/// Branch condition of register `reg`: 1 for `true`, 0 for `false` and 2
+/// for anything else (which never branches).
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_test_bool(process: *mut AsyncProcess, reg: i64) -> i64 {
+    let process = unsafe { &*process };
+    match process.jit_reg(reg) {
+        GcValue::Bool(true) => 1,
+        GcValue::Bool(false) => 0,
+        _ => 2,
+    }
+}
+
+/// Typed integer arithmetic and comparisons with an Int64 fast path.
+/// Other operand types are handed to the interpreter at `ip`. Returns like
+/// `nos_jit_interpret`.
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_int_op(
+    process: *mut AsyncProcess,
+    ip: i64,
+    op: i64,
+    dst: i64,
+    a: i64,
+    b: i64,
+) -> i64 {
+    let process = unsafe { &mut *process };
+    let (x, y) = match (process.jit_reg(a), process.jit_reg(b)) {
+        (GcValue::Int64(x), GcValue::Int64(y)) => (*x, *y),
+        _ => return process.jit_interpret(ip as usize),
+    };
+    let value = match op {
+        JIT_OP_ADD => GcValue::Int64(x.wrapping_add(y)),
+        JIT_OP_SUB => GcValue::Int64(x.wrapping_sub(y)),
+        JIT_OP_MUL => GcValue::Int64(x.wrapping_mul(y)),
+        JIT_OP_EQ => GcValue::Bool(x == y),
+        JIT_OP_LT => GcValue::Bool(x < y),
+        JIT_OP_LE => GcValue::Bool(x <= y),
+        JIT_OP_GT => GcValue::Bool(x > y),
+        JIT_OP_GE => GcValue::Bool(x >= y),
+        _ => return process.jit_interpret(ip as usize),
+    };
+    process.jit_set_reg(dst, value);
+    ip + 1
+}
+
+/// Profile kind of a value (see `TypeProfile`).
+#[inline]
+pub fn value_kind(value: &GcValue) -> u8 {
+    match value {
+        GcValue::Int64(_) => TypeProfile::INT,
+        GcValue::Float64(_) => TypeProfile::FLOAT,
+        GcValue::Bool(_) => TypeProfile::BOOL,
+        _ => TypeProfile::OTHER,
+    }
+}
+
+/// Whether register `reg` holds a value of profile kind `kind`: the argument
+/// guards of optimized code.
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_guard(process: *mut AsyncProcess, reg: i64, kind: i64) -> i64 {
+    let process = unsafe { &*process };
+    (value_kind(process.jit_reg(reg)) as i64 == kind) as i64
+}
+
+/// Unboxed bits of register `reg`, which passed `nos_jit_guard`: the integer,
+/// the float's bits, or 0/1 for booleans.
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_unbox(process: *mut AsyncProcess, reg: i64) -> i64 {
+    let process = unsafe { &*process };
+    match process.jit_reg(reg) {
+        GcValue::Int64(n) => *n,
+        GcValue::Float64(f) => f.to_bits() as i64,
+        GcValue::Bool(b) => *b as i64,
+        _ => 0,
+    }
+}
+
+/// Store unboxed `bits` of profile kind `kind` into register `reg`, when
+/// optimized code hands the frame back to the interpreter.
+///
+/// # Safety
+/// `process` must be the process running the calling native code.
+pub unsafe extern "C" fn nos_jit_box(process: *mut AsyncProcess, reg: i64, kind: i64, bits: i64) {
+    let process = unsafe { &mut *process };
+    let value = match kind as u8 {
+        TypeProfile::FLOAT => GcValue::Float64(f64::from_bits(bits as u64)),
+        TypeProfile::BOOL => GcValue::Bool(bits != 0),
+        _ => GcValue::Int64(bits),
+    };
+    process.jit_set_reg(reg, value);
+}
+
+/// Instructions the baseline compiler emits inline code for, instead of
+/// handing them to `nos_jit_interpret`.
+pub fn compiled_inline(instruction: &Instruction) -> bool {
+    use Instruction::*;
+    matches!(
+        instruction,
+        Nop | Jump(..) | JumpIfTrue(..) | JumpIfFalse(..) |
+        Move(..) | LoadUnit(..) | LoadTrue(..) | LoadFalse(..) |
+        AddInt(..) | SubInt(..) | MulInt(..) |
+        EqInt(..) | LtInt(..) | LeInt(..) | GtInt(..) | GeInt(..)
+    )
+}
+
+/// Helper symbols for the JIT compilers to link against.
+pub fn helper_symbols() -> [(&'static str, *const u8); 9] {
+    [
+        ("nos_jit_interpret", nos_jit_interpret as *const u8),
+        ("nos_jit_tick", nos_jit_tick as *const u8),
+        ("nos_jit_move", nos_jit_move as *const u8),
+        ("nos_jit_load_simple", nos_jit_load_simple as *const u8),
+        ("nos_jit_test_bool", nos_jit_test_bool as *const u8),
+        ("nos_jit_int_op", nos_jit_int_op as *const u8),
+        ("nos_jit_guard", nos_jit_guard as *const u8),
+        ("nos_jit_unbox", nos_jit_unbox as *const u8),
+        ("nos_jit_box", nos_jit_box as *const u8),
+    ]
+}
@@ -13,27 +13,45 @@
 
 pub mod async_vm;
 pub mod cache;
+pub mod code;
 pub mod extensions;
+pub mod ffi;
 pub mod gc;
+pub mod heap_snapshot;
 pub mod inspect;
 pub mod io_runtime;
+pub mod jit_runtime;
+pub mod mailbox;
+pub mod node;
+pub mod priority;
 pub mod process;
+pub mod sampler;
 pub mod scheduler;
 pub mod shared_types;
 pub mod supervisor;
+pub mod tables;
+pub mod timers;
+pub mod tls;
+pub mod trace;
 pub mod value;
+pub mod wire;
 
 pub use gc::*;
+pub use heap_snapshot::{HeapSnapshot, HeapAnalysis, SnapshotObject, TypeSummary};
 pub use inspect::*;
 pub use io_runtime::*;
 pub use shared_types::*;
+pub use mailbox::{MailboxBound, MailboxPolicy};
+pub use node::NodeConfig;
+pub use priority::Priority;
 pub use process::*;
+pub use sampler::{Sampler, SamplerConfig, StackSamples};
 pub use scheduler::*;
 pub use supervisor::*;
 pub use value::*;
 pub use extensions::ExtensionManager;
 pub use async_vm::{ThreadedEvalHandle, DebugSession};
 pub use async_vm::{enable_output_capture, disable_output_capture, is_output_capture_enabled};
-pub use cache::{BytecodeCache, CachedModule, CachedFunction, CachedChunk, CachedValue, CacheManifest};
+pub use cache::{BytecodeCache, CachedModule, CachedFunction, CachedChunk, CachedValue, CacheManifest, JitCache};
 pub use cache::{function_to_cached, function_to_cached_with_fn_list, cached_to_function, cached_to_function_with_resolver, compute_file_hash};
 pub use cache::{ModuleCache, CompiledModuleData};
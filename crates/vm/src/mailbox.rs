@@ -0,0 +1,347 @@
+//! Process mailboxes.
+//!
+//! A mailbox is a FIFO queue of messages with one receiver (the process) and
+//! any number of senders (the process registry, timers, supervisors). It is
+//! unbounded unless a capacity is chosen at spawn time. Messages sent with
+//! `send`/`<-` or `Process.send` to a full bounded mailbox are handled by its
+//! [`MailboxPolicy`]; `trySend` fails instead. Exit notifications and timer
+//! messages are always queued, so links, monitors and timers keep working
+//! when a mailbox is full.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+use crate::process::ThreadSafeValue;
+
+/// What a bounded mailbox does with a message sent while it is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MailboxPolicy {
+    /// The sender waits until the receiver takes a message.
+    #[default]
+    Block,
+    /// The new message is dropped.
+    DropNewest,
+    /// The oldest queued message is dropped to make room.
+    DropOldest,
+}
+
+impl MailboxPolicy {
+    /// Name used by `Process.spawnBounded` and `Process.info`.
+    pub fn name(self) -> &'static str {
+        match self {
+            MailboxPolicy::Block => "block",
+            MailboxPolicy::DropNewest => "dropNewest",
+            MailboxPolicy::DropOldest => "dropOldest",
+        }
+    }
+
+    /// Parse a policy name.
+    pub fn from_name(name: &str) -> Option<MailboxPolicy> {
+        match name {
+            "block" => Some(MailboxPolicy::Block),
+            "dropNewest" => Some(MailboxPolicy::DropNewest),
+            "dropOldest" => Some(MailboxPolicy::DropOldest),
+            _ => None,
+        }
+    }
+}
+
+/// Capacity of a bounded mailbox and what happens when it is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MailboxBound {
+    pub capacity: usize,
+    pub policy: MailboxPolicy,
+}
+
+/// Why a message wasn't queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// The bounded mailbox is full (`try_send` only).
+    Full,
+    /// The receiving process exited.
+    Closed,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full => write!(f, "mailbox full"),
+            SendError::Closed => write!(f, "process not running"),
+        }
+    }
+}
+
+struct Shared {
+    queue: Mutex<VecDeque<ThreadSafeValue>>,
+    bound: Option<MailboxBound>,
+    /// Wakes the receiver when a message arrives or the last sender is dropped.
+    message_ready: Notify,
+    /// Wakes blocked senders when a message is taken or the receiver is dropped.
+    space_ready: Notify,
+    senders: AtomicUsize,
+    /// The receiver was dropped.
+    closed: AtomicBool,
+}
+
+impl Shared {
+    fn push(&self, queue: &mut VecDeque<ThreadSafeValue>, message: ThreadSafeValue) {
+        queue.push_back(message);
+        self.message_ready.notify_one();
+    }
+}
+
+/// Create a mailbox, bounded if `bound` is set.
+pub fn channel(bound: Option<MailboxBound>) -> (MailboxSender, MailboxReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        bound,
+        message_ready: Notify::new(),
+        space_ready: Notify::new(),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+    });
+    (MailboxSender { shared: shared.clone() }, MailboxReceiver { shared })
+}
+
+/// Create an unbounded mailbox.
+pub fn unbounded() -> (MailboxSender, MailboxReceiver) {
+    channel(None)
+}
+
+/// Sending half of a mailbox.
+pub struct MailboxSender {
+    shared: Arc<Shared>,
+}
+
+impl Clone for MailboxSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl Drop for MailboxSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Let the receiver see that no more messages can arrive
+            self.shared.message_ready.notify_one();
+        }
+    }
+}
+
+impl MailboxSender {
+    /// Queue a message regardless of the capacity (exit notifications, timers).
+    pub fn send(&self, message: ThreadSafeValue) -> Result<(), SendError> {
+        let mut queue = self.shared.queue.lock();
+        if self.shared.closed.load(Ordering::Acquire) {
+            return Err(SendError::Closed);
+        }
+        self.shared.push(&mut queue, message);
+        Ok(())
+    }
+
+    /// Queue a message, applying the mailbox policy when it is full. Only
+    /// waits when the policy is `Block`; a dropped message still counts as sent.
+    pub async fn send_bounded(&self, message: ThreadSafeValue) -> Result<(), SendError> {
+        let Some(bound) = self.shared.bound else {
+            return self.send(message);
+        };
+        let mut message = Some(message);
+        loop {
+            // Register for the wakeup before checking, so a receive in between isn't missed
+            let space = self.shared.space_ready.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+            {
+                let mut queue = self.shared.queue.lock();
+                if self.shared.closed.load(Ordering::Acquire) {
+                    return Err(SendError::Closed);
+                }
+                if queue.len() < bound.capacity {
+                    self.shared.push(&mut queue, message.take().unwrap());
+                    return Ok(());
+                }
+                match bound.policy {
+                    MailboxPolicy::Block => {}
+                    MailboxPolicy::DropNewest => return Ok(()),
+                    MailboxPolicy::DropOldest => {
+                        queue.pop_front();
+                        self.shared.push(&mut queue, message.take().unwrap());
+                        return Ok(());
+                    }
+                }
+            }
+            space.await;
+        }
+    }
+
+    /// Queue a message unless the mailbox is full.
+    pub fn try_send(&self, message: ThreadSafeValue) -> Result<(), SendError> {
+        let mut queue = self.shared.queue.lock();
+        if self.shared.closed.load(Ordering::Acquire) {
+            return Err(SendError::Closed);
+        }
+        if self.shared.bound.is_some_and(|bound| queue.len() >= bound.capacity) {
+            return Err(SendError::Full);
+        }
+        self.shared.push(&mut queue, message);
+        Ok(())
+    }
+
+    /// Number of queued messages.
+    pub fn len(&self) -> usize {
+        self.shared.queue.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Capacity and policy, if the mailbox is bounded.
+    pub fn bound(&self) -> Option<MailboxBound> {
+        self.shared.bound
+    }
+}
+
+/// Receiving half of a mailbox, owned by the process.
+pub struct MailboxReceiver {
+    shared: Arc<Shared>,
+}
+
+impl MailboxReceiver {
+    /// Wait for the next message. Returns `None` once every sender is gone and
+    /// the mailbox is empty. Cancel-safe: a message is only taken when returned.
+    pub async fn recv(&mut self) -> Option<ThreadSafeValue> {
+        loop {
+            if let Some(message) = self.try_recv() {
+                return Some(message);
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                // A last message may have arrived just before its sender was dropped
+                return self.try_recv();
+            }
+            self.shared.message_ready.notified().await;
+        }
+    }
+
+    /// Take the next message if there is one.
+    pub fn try_recv(&mut self) -> Option<ThreadSafeValue> {
+        let message = self.shared.queue.lock().pop_front()?;
+        if self.shared.bound.is_some() {
+            self.shared.space_ready.notify_waiters();
+        }
+        Some(message)
+    }
+
+    /// Number of queued messages.
+    pub fn len(&self) -> usize {
+        self.shared.queue.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Capacity and policy, if the mailbox is bounded.
+    pub fn bound(&self) -> Option<MailboxBound> {
+        self.shared.bound
+    }
+}
+
+impl Drop for MailboxReceiver {
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock();
+        self.shared.closed.store(true, Ordering::Release);
+        queue.clear();
+        drop(queue);
+        // Blocked senders give up
+        self.shared.space_ready.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+    }
+
+    fn bounded(capacity: usize, policy: MailboxPolicy) -> (MailboxSender, MailboxReceiver) {
+        channel(Some(MailboxBound { capacity, policy }))
+    }
+
+    fn ints(rx: &mut MailboxReceiver) -> Vec<i64> {
+        std::iter::from_fn(|| rx.try_recv())
+            .map(|msg| match msg {
+                ThreadSafeValue::Int64(n) => n,
+                _ => panic!("unexpected message"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_policies() {
+        runtime().block_on(async {
+            let (tx, mut rx) = bounded(2, MailboxPolicy::DropNewest);
+            for n in 1..=4 {
+                tx.send_bounded(ThreadSafeValue::Int64(n)).await.unwrap();
+            }
+            assert_eq!(ints(&mut rx), vec![1, 2]);
+
+            let (tx, mut rx) = bounded(2, MailboxPolicy::DropOldest);
+            for n in 1..=4 {
+                tx.send_bounded(ThreadSafeValue::Int64(n)).await.unwrap();
+            }
+            assert_eq!(ints(&mut rx), vec![3, 4]);
+        });
+    }
+
+    #[test]
+    fn test_try_send_fails_when_full_and_system_messages_always_fit() {
+        let (tx, mut rx) = bounded(1, MailboxPolicy::Block);
+        assert_eq!(tx.try_send(ThreadSafeValue::Int64(1)), Ok(()));
+        assert_eq!(tx.try_send(ThreadSafeValue::Int64(2)), Err(SendError::Full));
+        assert_eq!(tx.send(ThreadSafeValue::Int64(3)), Ok(()));
+        assert_eq!(tx.len(), 2);
+        assert_eq!(ints(&mut rx), vec![1, 3]);
+        drop(rx);
+        assert_eq!(tx.try_send(ThreadSafeValue::Int64(4)), Err(SendError::Closed));
+    }
+
+    #[test]
+    fn test_blocked_sender_waits_for_space() {
+        runtime().block_on(async {
+            let (tx, mut rx) = bounded(1, MailboxPolicy::Block);
+            tx.send_bounded(ThreadSafeValue::Int64(1)).await.unwrap();
+            let producer = tokio::spawn(async move {
+                tx.send_bounded(ThreadSafeValue::Int64(2)).await.unwrap();
+                tx.send_bounded(ThreadSafeValue::Int64(3)).await
+            });
+            let mut received = Vec::new();
+            while let Some(ThreadSafeValue::Int64(n)) = rx.recv().await {
+                assert!(rx.len() <= 1);
+                received.push(n);
+            }
+            assert_eq!(received, vec![1, 2, 3]);
+            assert_eq!(producer.await.unwrap(), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_blocked_sender_fails_when_receiver_exits() {
+        runtime().block_on(async {
+            let (tx, rx) = bounded(1, MailboxPolicy::Block);
+            tx.send_bounded(ThreadSafeValue::Int64(1)).await.unwrap();
+            let producer = tokio::spawn(async move { tx.send_bounded(ThreadSafeValue::Int64(2)).await });
+            tokio::task::yield_now().await;
+            drop(rx);
+            assert_eq!(producer.await.unwrap(), Err(SendError::Closed));
+        });
+    }
+}
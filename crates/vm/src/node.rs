@@ -0,0 +1,824 @@
+//! Distributed nodes.
+//!
+//! A VM started with a node name (`nostos --node app@127.0.0.1:9000`) listens
+//! on the address after the `@` and can connect to other nodes. Each pair of
+//! connected nodes shares one TCP connection, so messages between two
+//! processes arrive in the order they were sent.
+//!
+//! Connections start with a handshake: both sides prove that they know the
+//! shared cookie by hashing it with a random challenge chosen by the other
+//! side, so the cookie itself is never sent. After that, every frame is a
+//! `u32` length followed by a frame type and its fields, with messages in the
+//! encoding of [`crate::wire`].
+//!
+//! Processes on other nodes get pids with the index of their node in the high
+//! bits (see [`Pid`]). Sending to such a pid forwards the message over the
+//! node's connection. Links and monitors of remote processes are kept on both
+//! sides: exits are forwarded as `EXIT` frames, and when a connection is lost
+//! every link and monitor through it fires with reason `"noconnection"`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::async_vm::{AsyncSharedState, SpawnLinkage, SpawnOptions};
+use crate::process::{ExitReason, ThreadSafeValue};
+use crate::value::{Chunk, FunctionValue, Pid, Value};
+use crate::wire::{FunctionRef, Reader, WireContext, WireError, Writer, MAX_FRAME_LEN, WIRE_VERSION};
+
+/// Name of a VM that isn't a node.
+pub const NO_NODE: &str = "nonode@nohost";
+
+/// How long `Node.connect` waits for the other node.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a remote spawn waits for the new pid.
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+mod frame {
+    // Handshake
+    pub const HELLO: u8 = 1;
+    pub const CHALLENGE: u8 = 2;
+    pub const RESPONSE: u8 = 3;
+    pub const READY: u8 = 4;
+    pub const REFUSED: u8 = 5;
+
+    // Connected
+    pub const SEND: u8 = 10;
+    pub const SEND_NAMED: u8 = 11;
+    pub const SPAWN: u8 = 12;
+    pub const SPAWN_REPLY: u8 = 13;
+    pub const EXIT: u8 = 14;
+}
+
+/// Name and cookie of this node.
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    /// `name@host:port`; the node listens on `host:port`
+    pub name: String,
+    /// Shared secret of the nodes that may connect to each other
+    pub cookie: String,
+}
+
+impl NodeConfig {
+    /// Check the node name and cookie.
+    pub fn new(name: &str, cookie: &str) -> Result<Self, String> {
+        listen_addr(name)?;
+        if cookie.is_empty() {
+            return Err("the node cookie must not be empty".to_string());
+        }
+        Ok(Self { name: name.to_string(), cookie: cookie.to_string() })
+    }
+}
+
+/// The `host:port` part of a node name.
+fn listen_addr(name: &str) -> Result<&str, String> {
+    match name.split_once('@') {
+        Some((node, addr)) if !node.is_empty() && addr.rsplit_once(':').is_some_and(|(host, port)| {
+            !host.is_empty() && port.parse::<u16>().is_ok()
+        }) => Ok(addr),
+        _ => Err(format!("invalid node name '{}' (expected name@host:port)", name)),
+    }
+}
+
+/// How a remotely spawned process is tied to the process that spawned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteLinkage {
+    None,
+    Link,
+    Monitor,
+}
+
+impl RemoteLinkage {
+    fn to_byte(self) -> u8 {
+        match self {
+            RemoteLinkage::None => 0,
+            RemoteLinkage::Link => 1,
+            RemoteLinkage::Monitor => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(RemoteLinkage::None),
+            1 => Some(RemoteLinkage::Link),
+            2 => Some(RemoteLinkage::Monitor),
+            _ => None,
+        }
+    }
+}
+
+/// Which exit notification an `EXIT` frame carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitKind {
+    /// `("EXIT", pid, reason)` to a linked process
+    Link,
+    /// `("DOWN", pid, reason)` to a monitoring process
+    Monitor,
+}
+
+impl ExitKind {
+    /// The tag of the notification message.
+    pub fn tag(self) -> &'static str {
+        match self {
+            ExitKind::Link => "EXIT",
+            ExitKind::Monitor => "DOWN",
+        }
+    }
+}
+
+/// An open connection to another node.
+struct Connection {
+    /// Tells this connection apart from a later one to the same node
+    id: u64,
+    /// Frames for the writer task
+    frames: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+/// A remote spawn waiting for its pid.
+struct PendingSpawn {
+    node: u16,
+    requester: Pid,
+    linkage: RemoteLinkage,
+    reply: oneshot::Sender<Result<Pid, String>>,
+}
+
+#[derive(Default)]
+struct NodeTable {
+    /// Names of the nodes pids have referred to; node index `i` is `names[i - 1]`
+    names: Vec<String>,
+    indices: HashMap<String, u16>,
+    connections: HashMap<u16, Connection>,
+}
+
+/// The nodes known to one VM.
+#[derive(Default)]
+pub struct Nodes {
+    config: OnceLock<NodeConfig>,
+    table: Mutex<NodeTable>,
+    next_connection: AtomicU64,
+    next_request: AtomicU64,
+    pending_spawns: Mutex<HashMap<u64, PendingSpawn>>,
+}
+
+impl Nodes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of this node, or [`NO_NODE`] if distribution isn't started.
+    pub fn name(&self) -> &str {
+        self.config.get().map_or(NO_NODE, |config| config.name.as_str())
+    }
+
+    fn config(&self) -> Result<&NodeConfig, String> {
+        self.config.get().ok_or_else(|| "this VM is not a node (start nostos with --node name@host:port)".to_string())
+    }
+
+    /// The index pids of `name` carry, 0 for this node.
+    pub fn index_of(&self, name: &str) -> u16 {
+        if name == self.name() {
+            return 0;
+        }
+        let mut table = self.table.lock();
+        if let Some(&index) = table.indices.get(name) {
+            return index;
+        }
+        table.names.push(name.to_string());
+        let index = table.names.len() as u16;
+        table.indices.insert(name.to_string(), index);
+        index
+    }
+
+    /// Name of the node with `index`.
+    pub fn node_name(&self, index: u16) -> String {
+        if index == 0 {
+            return self.name().to_string();
+        }
+        self.table.lock().names.get(index as usize - 1).cloned().unwrap_or_else(|| NO_NODE.to_string())
+    }
+
+    /// Names of the connected nodes.
+    pub fn connected(&self) -> Vec<String> {
+        let table = self.table.lock();
+        let mut names: Vec<String> = table.connections.keys()
+            .map(|&index| table.names[index as usize - 1].clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn is_connected(&self, name: &str) -> bool {
+        let table = self.table.lock();
+        table.indices.get(name).is_some_and(|index| table.connections.contains_key(index))
+    }
+
+    /// Queue a frame for a connected node. Returns false if it isn't connected.
+    fn send_frame(&self, node: u16, frame: Vec<u8>) -> bool {
+        match self.table.lock().connections.get(&node) {
+            Some(connection) => connection.frames.send(frame).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Remember a new connection. Returns its id, or None if the node is
+    /// already connected.
+    fn add_connection(&self, name: &str, frames: mpsc::UnboundedSender<Vec<u8>>) -> Option<(u16, u64)> {
+        let index = self.index_of(name);
+        let mut table = self.table.lock();
+        if table.connections.contains_key(&index) {
+            return None;
+        }
+        let id = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        table.connections.insert(index, Connection { id, frames });
+        Some((index, id))
+    }
+
+    /// Forget a connection (`id` of None: whichever is open). Returns false if
+    /// it was already gone, so its node-down runs only once.
+    fn remove_connection(&self, node: u16, id: Option<u64>) -> bool {
+        let mut table = self.table.lock();
+        match table.connections.get(&node) {
+            Some(connection) if id.is_none_or(|id| connection.id == id) => {
+                table.connections.remove(&node);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Encodes values with the pids and functions of one VM.
+struct VmWire<'a> {
+    nodes: &'a Nodes,
+    functions: &'a [Arc<FunctionValue>],
+}
+
+impl WireContext for VmWire<'_> {
+    fn pid_to_wire(&self, pid: u64) -> (String, u64) {
+        let pid = Pid(pid);
+        (self.nodes.node_name(pid.node()), pid.id())
+    }
+
+    fn pid_from_wire(&self, node: &str, id: u64) -> u64 {
+        Pid::on_node(self.nodes.index_of(node), id).0
+    }
+
+    fn function_to_wire(&self, function: &Arc<FunctionValue>) -> Option<FunctionRef> {
+        for (index, candidate) in self.functions.iter().enumerate() {
+            let mut path = Vec::new();
+            if Arc::ptr_eq(candidate, function) || find_lambda(&candidate.code, function, &mut path) {
+                return Some(FunctionRef { index: index as u32, name: candidate.name.clone(), path });
+            }
+        }
+        None
+    }
+
+    fn function_from_wire(&self, function: &FunctionRef) -> Option<Arc<FunctionValue>> {
+        let mut current = match self.functions.get(function.index as usize) {
+            Some(f) if f.name == function.name => f.clone(),
+            _ => self.functions.iter().find(|f| f.name == function.name)?.clone(),
+        };
+        for &index in &function.path {
+            current = match current.code.constants.get(index as usize)? {
+                Value::Function(f) => f.clone(),
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+}
+
+/// Find the constant indices leading from `chunk` to the lambda `target`.
+fn find_lambda(chunk: &Chunk, target: &Arc<FunctionValue>, path: &mut Vec<u32>) -> bool {
+    for (index, constant) in chunk.constants.iter().enumerate() {
+        if let Value::Function(f) = constant {
+            path.push(index as u32);
+            if Arc::ptr_eq(f, target) || find_lambda(&f.code, target, path) {
+                return true;
+            }
+            path.pop();
+        }
+    }
+    false
+}
+
+/// Start a frame of type `kind`.
+fn frame_writer(kind: u8) -> Writer {
+    let mut writer = Writer::new();
+    writer.u8(kind);
+    writer
+}
+
+/// Encode `value` after the fields already in `writer`.
+fn encode_value(shared: &AsyncSharedState, mut writer: Writer, value: &ThreadSafeValue) -> Result<Vec<u8>, WireError> {
+    let functions = shared.function_list.read().unwrap();
+    writer.value(value, &VmWire { nodes: &shared.nodes, functions: &functions })?;
+    Ok(writer.into_bytes())
+}
+
+fn decode_value(shared: &AsyncSharedState, reader: &mut Reader) -> Result<ThreadSafeValue, WireError> {
+    let functions = shared.function_list.read().unwrap();
+    reader.value(&VmWire { nodes: &shared.nodes, functions: &functions })
+}
+
+/// Send a message to a process on another node. Returns false if its node
+/// isn't connected or the message can't be sent between nodes.
+pub fn send(shared: &AsyncSharedState, target: Pid, message: &ThreadSafeValue) -> bool {
+    let mut writer = frame_writer(frame::SEND);
+    writer.u64(target.id());
+    match encode_value(shared, writer, message) {
+        Ok(frame) => shared.nodes.send_frame(target.node(), frame),
+        Err(_) => false,
+    }
+}
+
+/// Send a message to the process registered under `name` on `node`.
+/// Returns false if the node isn't connected; whether the name is registered
+/// there is only known for this node.
+pub async fn send_named(shared: &AsyncSharedState, node: &str, name: &str, message: ThreadSafeValue) -> Result<bool, String> {
+    let index = shared.nodes.index_of(node);
+    if index == 0 {
+        return Ok(match shared.whereis(name) {
+            Some(pid) => shared.send_bounded(pid, message).await,
+            None => false,
+        });
+    }
+    let mut writer = frame_writer(frame::SEND_NAMED);
+    writer.str(name);
+    let frame = encode_value(shared, writer, &message).map_err(|e| e.to_string())?;
+    Ok(shared.nodes.send_frame(index, frame))
+}
+
+/// Forward the exit of local process `from` to `to` on another node.
+pub fn send_exit(shared: &AsyncSharedState, from: Pid, to: Pid, kind: ExitKind, reason: &str) {
+    let mut writer = frame_writer(frame::EXIT);
+    writer.u64(from.id());
+    writer.u64(to.id());
+    writer.u8(match kind {
+        ExitKind::Link => 0,
+        ExitKind::Monitor => 1,
+    });
+    writer.str(reason);
+    shared.nodes.send_frame(to.node(), writer.into_bytes());
+}
+
+/// Spawn `callable` (a function or closure) on `node`, linked to or monitored
+/// by `requester` depending on `linkage`, and return the new pid.
+pub async fn spawn(
+    shared: &AsyncSharedState,
+    node: &str,
+    callable: &ThreadSafeValue,
+    requester: Pid,
+    linkage: RemoteLinkage,
+) -> Result<Pid, String> {
+    let index = shared.nodes.index_of(node);
+    if !shared.nodes.is_connected(node) {
+        return Err(format!("node {} is not connected", node));
+    }
+    let request = shared.nodes.next_request.fetch_add(1, Ordering::Relaxed);
+    let mut writer = frame_writer(frame::SPAWN);
+    writer.u64(request);
+    writer.u64(requester.id());
+    writer.u8(linkage.to_byte());
+    let frame = encode_value(shared, writer, callable).map_err(|e| e.to_string())?;
+
+    let (reply, answer) = oneshot::channel();
+    shared.nodes.pending_spawns.lock().insert(request, PendingSpawn { node: index, requester, linkage, reply });
+    if !shared.nodes.send_frame(index, frame) {
+        shared.nodes.pending_spawns.lock().remove(&request);
+        return Err(format!("node {} is not connected", node));
+    }
+    match tokio::time::timeout(SPAWN_TIMEOUT, answer).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(format!("node {} went down", node)),
+        Err(_) => {
+            shared.nodes.pending_spawns.lock().remove(&request);
+            Err(format!("node {} did not answer", node))
+        }
+    }
+}
+
+/// Close the connection to `node`. Returns false if it wasn't connected.
+pub async fn disconnect(shared: &AsyncSharedState, node: &str) -> bool {
+    let index = shared.nodes.index_of(node);
+    if index == 0 || !shared.nodes.remove_connection(index, None) {
+        return false;
+    }
+    node_down(shared, index).await;
+    true
+}
+
+/// Links, monitors and spawns through a lost connection fail.
+async fn node_down(shared: &AsyncSharedState, node: u16) {
+    shared.nodes.pending_spawns.lock().retain(|_, pending| pending.node != node);
+    shared.node_down(node, &ExitReason::NodeDown).await;
+}
+
+/// Start listening for other nodes. Connections are served on the current
+/// tokio runtime.
+pub async fn start(shared: &Arc<AsyncSharedState>, config: NodeConfig) -> Result<(), String> {
+    let addr = listen_addr(&config.name)?.to_string();
+    let listener = TcpListener::bind(&addr).await
+        .map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+    shared.nodes.config.set(config).map_err(|_| "the node is already started".to_string())?;
+    let weak = Arc::downgrade(shared);
+    spawn_io(shared, accept_loop(listener, weak));
+    Ok(())
+}
+
+/// Run connection tasks on the IO runtime, which outlives evals and runs.
+fn spawn_io<F>(shared: &AsyncSharedState, task: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    match &shared.spawn_runtime_handle {
+        Some(handle) => drop(handle.spawn(task)),
+        None => drop(tokio::spawn(task)),
+    }
+}
+
+async fn accept_loop(listener: TcpListener, shared: Weak<AsyncSharedState>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        tokio::spawn(async move {
+            let _ = stream.set_nodelay(true);
+            let (mut reader, mut writer) = stream.into_split();
+            let Ok(config) = shared.nodes.config() else {
+                return;
+            };
+            let nodes = &shared.nodes;
+            let peer = handshake_accept(&mut reader, &mut writer, config, |name| nodes.is_connected(name)).await;
+            if let Ok(peer) = peer {
+                serve(shared.clone(), peer, reader, writer).await;
+            }
+        });
+    }
+}
+
+/// Connect to `node` (`name@host:port`). Succeeds if it already is connected.
+pub async fn connect(shared: &Arc<AsyncSharedState>, node: &str) -> Result<(), String> {
+    let config = shared.nodes.config()?;
+    let addr = listen_addr(node)?;
+    if node == config.name || shared.nodes.is_connected(node) {
+        return Ok(());
+    }
+    let result = tokio::time::timeout(CONNECT_TIMEOUT, async {
+        let stream = TcpStream::connect(addr).await.map_err(|e| format!("cannot connect to {}: {}", node, e))?;
+        let _ = stream.set_nodelay(true);
+        let (mut reader, mut writer) = stream.into_split();
+        handshake_connect(&mut reader, &mut writer, config, node).await?;
+        Ok((reader, writer))
+    }).await;
+    match result {
+        Ok(Ok((reader, writer))) => {
+            spawn_io(shared, serve(shared.clone(), node.to_string(), reader, writer));
+            // Wait until the connection is registered, so sends right after
+            // connect go through it
+            for _ in 0..100 {
+                if shared.nodes.is_connected(node) {
+                    return Ok(());
+                }
+                tokio::task::yield_now().await;
+            }
+            Ok(())
+        }
+        // Both nodes connected to each other at the same time
+        Ok(Err(_)) if shared.nodes.is_connected(node) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(format!("timed out connecting to {}", node)),
+    }
+}
+
+/// Run a connection after the handshake until it is closed.
+async fn serve<R, W>(shared: Arc<AsyncSharedState>, peer: String, mut reader: R, mut writer: W)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (frames, mut outgoing) = mpsc::unbounded_channel::<Vec<u8>>();
+    let Some((node, id)) = shared.nodes.add_connection(&peer, frames) else {
+        return;
+    };
+    tokio::spawn(async move {
+        while let Some(frame) = outgoing.recv().await {
+            if write_frame(&mut writer, &frame).await.is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown().await;
+    });
+    while let Ok(frame) = read_frame(&mut reader).await {
+        if handle_frame(&shared, node, &frame).await.is_err() {
+            break;
+        }
+    }
+    if shared.nodes.remove_connection(node, Some(id)) {
+        node_down(&shared, node).await;
+    }
+}
+
+/// Act on a frame from node `node`.
+async fn handle_frame(shared: &Arc<AsyncSharedState>, node: u16, frame: &[u8]) -> Result<(), WireError> {
+    let mut reader = Reader::new(frame);
+    match reader.u8()? {
+        frame::SEND => {
+            let target = Pid(reader.u64()?);
+            let message = decode_value(shared, &mut reader)?;
+            shared.send_bounded(target, message).await;
+        }
+        frame::SEND_NAMED => {
+            let name = reader.str()?;
+            let message = decode_value(shared, &mut reader)?;
+            if let Some(target) = shared.whereis(&name) {
+                shared.send_bounded(target, message).await;
+            }
+        }
+        frame::SPAWN => {
+            let request = reader.u64()?;
+            let requester = Pid::on_node(node, reader.u64()?);
+            let linkage = RemoteLinkage::from_byte(reader.u8()?).ok_or(WireError::Malformed("unknown linkage"))?;
+            let mut reply = frame_writer(frame::SPAWN_REPLY);
+            reply.u64(request);
+            match decode_value(shared, &mut reader) {
+                Ok(callable) => match spawn_local(shared, callable, requester, linkage).await {
+                    Ok(pid) => {
+                        reply.u8(0);
+                        reply.u64(pid.id());
+                    }
+                    Err(e) => {
+                        reply.u8(1);
+                        reply.str(&e);
+                    }
+                },
+                Err(e) => {
+                    reply.u8(1);
+                    reply.str(&e.to_string());
+                }
+            }
+            shared.nodes.send_frame(node, reply.into_bytes());
+        }
+        frame::SPAWN_REPLY => {
+            let request = reader.u64()?;
+            let result = match reader.u8()? {
+                0 => Ok(Pid::on_node(node, reader.u64()?)),
+                _ => Err(reader.str()?),
+            };
+            let pending = shared.nodes.pending_spawns.lock().remove(&request);
+            if let Some(pending) = pending {
+                // Record the link before later frames can carry the exit of the new process
+                if let Ok(child) = result {
+                    match pending.linkage {
+                        RemoteLinkage::None => {}
+                        RemoteLinkage::Link => shared.link(pending.requester, child),
+                        RemoteLinkage::Monitor => shared.monitor(pending.requester, child),
+                    }
+                }
+                let _ = pending.reply.send(result);
+            }
+        }
+        frame::EXIT => {
+            let from = Pid::on_node(node, reader.u64()?);
+            let to = Pid(reader.u64()?);
+            let kind = match reader.u8()? {
+                0 => ExitKind::Link,
+                _ => ExitKind::Monitor,
+            };
+            let reason = reader.str()?;
+            shared.remote_exited(from, to, kind, reason).await;
+        }
+        _ => return Err(WireError::Malformed("unknown frame")),
+    }
+    Ok(())
+}
+
+/// Spawn a process requested by `requester` on another node.
+async fn spawn_local(
+    shared: &Arc<AsyncSharedState>,
+    callable: ThreadSafeValue,
+    requester: Pid,
+    linkage: RemoteLinkage,
+) -> Result<Pid, String> {
+    let (function, captures) = match callable {
+        ThreadSafeValue::Function(function) => (function, Vec::new()),
+        ThreadSafeValue::Closure { function, captures, .. } => (function, captures),
+        _ => return Err("Node.spawnOn: expected a function".to_string()),
+    };
+    if function.arity != 0 {
+        return Err(format!("Node.spawnOn: {} takes arguments", function.name));
+    }
+    let linkage = match linkage {
+        RemoteLinkage::None => SpawnLinkage::None,
+        RemoteLinkage::Link => SpawnLinkage::Link(requester),
+        RemoteLinkage::Monitor => SpawnLinkage::Monitor(requester),
+    };
+    Ok(shared.spawn_function(function, Vec::new(), captures, linkage, SpawnOptions::default()).await)
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    let len = reader.read_u32_le().await? as usize;
+    if len == 0 || len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid frame length"));
+    }
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> std::io::Result<()> {
+    writer.write_u32_le(frame.len() as u32).await?;
+    writer.write_all(frame).await?;
+    writer.flush().await
+}
+
+/// Proof of knowing the cookie for `challenge`. Connecting and accepting
+/// nodes hash differently, so one can't be used to answer for the other.
+fn cookie_digest(cookie: &str, role: &str, challenge: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(cookie.as_bytes());
+    hasher.update(role.as_bytes());
+    hasher.update(challenge);
+    hasher.finalize().into()
+}
+
+fn new_challenge() -> [u8; 16] {
+    rand::random()
+}
+
+fn handshake_error(e: impl std::fmt::Display) -> String {
+    format!("handshake failed: {}", e)
+}
+
+/// Read a handshake frame of type `kind`, turning a refusal into its reason.
+async fn read_handshake<R: AsyncRead + Unpin>(reader: &mut R, kind: u8) -> Result<Vec<u8>, String> {
+    let frame = read_frame(reader).await.map_err(handshake_error)?;
+    match frame[0] {
+        k if k == kind => Ok(frame),
+        frame::REFUSED => Err(Reader::new(&frame[1..]).str().unwrap_or_else(|_| "connection refused".to_string())),
+        _ => Err(handshake_error("unexpected frame")),
+    }
+}
+
+async fn refuse<W: AsyncWrite + Unpin>(writer: &mut W, reason: &str) -> String {
+    let mut refusal = frame_writer(frame::REFUSED);
+    refusal.str(reason);
+    let _ = write_frame(writer, &refusal.into_bytes()).await;
+    reason.to_string()
+}
+
+/// Connecting side of the handshake with node `peer`.
+async fn handshake_connect<R, W>(reader: &mut R, writer: &mut W, config: &NodeConfig, peer: &str) -> Result<(), String>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let challenge = new_challenge();
+    let mut hello = frame_writer(frame::HELLO);
+    hello.u32(WIRE_VERSION);
+    hello.str(&config.name);
+    hello.bytes(&challenge);
+    write_frame(writer, &hello.into_bytes()).await.map_err(handshake_error)?;
+
+    let frame = read_handshake(reader, frame::CHALLENGE).await?;
+    let mut fields = Reader::new(&frame[1..]);
+    let name = fields.str().map_err(handshake_error)?;
+    let their_challenge = fields.bytes(16).map_err(handshake_error)?.to_vec();
+    let digest = fields.bytes(32).map_err(handshake_error)?;
+    if name != peer {
+        return Err(refuse(writer, &format!("expected node {}, found {}", peer, name)).await);
+    }
+    if digest != cookie_digest(&config.cookie, "accept", &challenge) {
+        return Err(refuse(writer, "cookie mismatch").await);
+    }
+
+    let mut response = frame_writer(frame::RESPONSE);
+    response.bytes(&cookie_digest(&config.cookie, "connect", &their_challenge));
+    write_frame(writer, &response.into_bytes()).await.map_err(handshake_error)?;
+    read_handshake(reader, frame::READY).await?;
+    Ok(())
+}
+
+/// Accepting side of the handshake. Returns the name of the connecting node.
+async fn handshake_accept<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    config: &NodeConfig,
+    is_connected: impl Fn(&str) -> bool,
+) -> Result<String, String>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let frame = read_handshake(reader, frame::HELLO).await?;
+    let mut fields = Reader::new(&frame[1..]);
+    let version = fields.u32().map_err(handshake_error)?;
+    let peer = fields.str().map_err(handshake_error)?;
+    let their_challenge = fields.bytes(16).map_err(handshake_error)?.to_vec();
+    if version != WIRE_VERSION {
+        return Err(refuse(writer, &format!("wire version {} is not supported (expected {})", version, WIRE_VERSION)).await);
+    }
+    if let Err(e) = listen_addr(&peer) {
+        return Err(refuse(writer, &e).await);
+    }
+    if peer == config.name || is_connected(&peer) {
+        return Err(refuse(writer, &format!("node {} is already connected", peer)).await);
+    }
+
+    let challenge = new_challenge();
+    let mut reply = frame_writer(frame::CHALLENGE);
+    reply.str(&config.name);
+    reply.bytes(&challenge);
+    reply.bytes(&cookie_digest(&config.cookie, "accept", &their_challenge));
+    write_frame(writer, &reply.into_bytes()).await.map_err(handshake_error)?;
+
+    let frame = read_handshake(reader, frame::RESPONSE).await?;
+    if frame[1..] != cookie_digest(&config.cookie, "connect", &challenge) {
+        return Err(refuse(writer, "cookie mismatch").await);
+    }
+    write_frame(writer, &[frame::READY]).await.map_err(handshake_error)?;
+    Ok(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+    }
+
+    fn config(name: &str, cookie: &str) -> NodeConfig {
+        NodeConfig::new(name, cookie).unwrap()
+    }
+
+    /// Run both sides of a handshake over an in-memory pipe.
+    async fn handshake(connecting: NodeConfig, accepting: NodeConfig, peer: &str) -> (Result<(), String>, Result<String, String>) {
+        let (client, server) = tokio::io::duplex(4096);
+        let (mut client_read, mut client_write) = tokio::io::split(client);
+        let (mut server_read, mut server_write) = tokio::io::split(server);
+        tokio::join!(
+            handshake_connect(&mut client_read, &mut client_write, &connecting, peer),
+            handshake_accept(&mut server_read, &mut server_write, &accepting, |_| false),
+        )
+    }
+
+    #[test]
+    fn test_node_names() {
+        assert_eq!(listen_addr("a@127.0.0.1:9000"), Ok("127.0.0.1:9000"));
+        assert!(listen_addr("a@127.0.0.1").is_err());
+        assert!(listen_addr("@127.0.0.1:9000").is_err());
+        assert!(listen_addr("127.0.0.1:9000").is_err());
+        assert!(NodeConfig::new("a@localhost:1", "").is_err());
+    }
+
+    #[test]
+    fn test_handshake_with_matching_cookies() {
+        runtime().block_on(async {
+            let (connected, accepted) = handshake(config("a@h:1", "secret"), config("b@h:2", "secret"), "b@h:2").await;
+            assert_eq!(connected, Ok(()));
+            assert_eq!(accepted, Ok("a@h:1".to_string()));
+        });
+    }
+
+    #[test]
+    fn test_handshake_rejects_wrong_cookie_and_name() {
+        runtime().block_on(async {
+            let (connected, accepted) = handshake(config("a@h:1", "secret"), config("b@h:2", "other"), "b@h:2").await;
+            assert_eq!(connected, Err("cookie mismatch".to_string()));
+            assert!(accepted.is_err());
+
+            let (connected, _) = handshake(config("a@h:1", "secret"), config("c@h:3", "secret"), "b@h:2").await;
+            assert_eq!(connected, Err("expected node b@h:2, found c@h:3".to_string()));
+        });
+    }
+
+    #[test]
+    fn test_node_indices() {
+        let nodes = Nodes::new();
+        nodes.config.set(config("a@h:1", "secret")).unwrap();
+        assert_eq!(nodes.index_of("a@h:1"), 0);
+        let b = nodes.index_of("b@h:2");
+        assert_eq!(b, 1);
+        assert_eq!(nodes.index_of("b@h:2"), b);
+        assert_eq!(nodes.node_name(b), "b@h:2");
+        assert!(nodes.connected().is_empty());
+
+        let (frames, _outgoing) = mpsc::unbounded_channel();
+        let (index, id) = nodes.add_connection("b@h:2", frames.clone()).unwrap();
+        assert_eq!(index, b);
+        assert!(nodes.add_connection("b@h:2", frames).is_none());
+        assert_eq!(nodes.connected(), vec!["b@h:2".to_string()]);
+        assert!(!nodes.remove_connection(b, Some(id + 1)));
+        assert!(nodes.remove_connection(b, Some(id)));
+        assert!(!nodes.remove_connection(b, None));
+    }
+}
@@ -0,0 +1,250 @@
+//! Process priorities.
+//!
+//! Processes are tokio tasks that yield after a budget of reductions
+//! (`REDUCTIONS_PER_YIELD`). Priorities change that budget:
+//!
+//! - `high` processes always get the full budget.
+//! - `normal` processes get the full budget, unless a high-priority process
+//!   is runnable; then they yield after a tenth of it.
+//! - `low` processes get a quarter of the budget (a quarter of the CPU of a
+//!   normal process when both are busy), and a tenth when a high-priority
+//!   process is runnable.
+//!
+//! Runnable high-priority processes are counted by [`PriorityTask`], which
+//! wraps every process future: a process is runnable from the wakeup that
+//! makes it ready (a message arrived, IO completed, a timer fired) until it
+//! blocks again, including while it yields between slices. A high-priority
+//! process blocked in `receive` or on IO therefore doesn't slow down anyone,
+//! and a busy one gets a full slice for every short slice of the others.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use crate::async_vm::REDUCTIONS_PER_YIELD;
+
+/// Scheduling priority of a process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Name used by `Process.setPriority` and `Process.info`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+
+    /// Parse a priority name.
+    pub fn from_name(name: &str) -> Option<Priority> {
+        match name {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            _ => None,
+        }
+    }
+
+    /// Reductions a process runs before it yields, depending on whether a
+    /// high-priority process is runnable.
+    pub fn reduction_budget(self, high_runnable: bool) -> usize {
+        match (self, high_runnable) {
+            (Priority::High, _) => REDUCTIONS_PER_YIELD,
+            (Priority::Normal, false) => REDUCTIONS_PER_YIELD,
+            (Priority::Low, false) => REDUCTIONS_PER_YIELD / 4,
+            (_, true) => REDUCTIONS_PER_YIELD / 10,
+        }
+    }
+}
+
+/// The priority of one process, shared by the process, its task wrapper and
+/// `Process.setPriority` calls from other processes.
+#[derive(Debug)]
+pub struct PriorityCell(AtomicU8);
+
+impl Default for PriorityCell {
+    fn default() -> Self {
+        Self::new(Priority::Normal)
+    }
+}
+
+impl PriorityCell {
+    pub fn new(priority: Priority) -> Self {
+        Self(AtomicU8::new(priority as u8))
+    }
+
+    pub fn get(&self) -> Priority {
+        match self.0.load(Ordering::Relaxed) {
+            0 => Priority::Low,
+            2 => Priority::High,
+            _ => Priority::Normal,
+        }
+    }
+
+    pub fn set(&self, priority: Priority) {
+        self.0.store(priority as u8, Ordering::Relaxed);
+    }
+}
+
+/// Per-task state shared with the task's wakers.
+struct TaskState {
+    priority: Arc<PriorityCell>,
+    /// Number of runnable high-priority tasks (VM-wide).
+    runnable_high: Arc<AtomicUsize>,
+    /// Whether this task is counted in `runnable_high`.
+    counted: AtomicBool,
+    /// Set by every wakeup; tells whether the task woke itself while polled.
+    woken: AtomicBool,
+}
+
+impl TaskState {
+    /// Count the task as runnable if it has high priority.
+    fn mark_runnable(&self) {
+        if self.priority.get() == Priority::High && !self.counted.swap(true, Ordering::AcqRel) {
+            self.runnable_high.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// The task blocked or finished.
+    fn mark_blocked(&self) {
+        if self.counted.swap(false, Ordering::AcqRel) {
+            self.runnable_high.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// Waker that counts a high-priority task as runnable before waking it.
+struct TrackingWaker {
+    inner: Waker,
+    state: Arc<TaskState>,
+}
+
+impl Wake for TrackingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.state.woken.store(true, Ordering::Release);
+        self.state.mark_runnable();
+        self.inner.wake_by_ref();
+    }
+}
+
+/// A process task whose wakeups are tracked for priority scheduling.
+pub struct PriorityTask<F> {
+    future: Pin<Box<F>>,
+    state: Arc<TaskState>,
+    /// Our waker, and the runtime waker it wraps.
+    waker: Option<(Waker, Waker)>,
+}
+
+impl<F: Future> PriorityTask<F> {
+    pub fn new(future: F, priority: Arc<PriorityCell>, runnable_high: Arc<AtomicUsize>) -> Self {
+        Self {
+            future: Box::pin(future),
+            state: Arc::new(TaskState {
+                priority,
+                runnable_high,
+                counted: AtomicBool::new(false),
+                woken: AtomicBool::new(false),
+            }),
+            waker: None,
+        }
+    }
+}
+
+impl<F: Future> Future for PriorityTask<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        let state = &this.state;
+        // Running counts as runnable (the priority may also have changed since the wakeup)
+        state.woken.store(false, Ordering::Release);
+        state.mark_runnable();
+        let waker = match &this.waker {
+            Some((waker, inner)) if inner.will_wake(cx.waker()) => waker.clone(),
+            _ => {
+                let waker = Waker::from(Arc::new(TrackingWaker {
+                    inner: cx.waker().clone(),
+                    state: state.clone(),
+                }));
+                this.waker = Some((waker.clone(), cx.waker().clone()));
+                waker
+            }
+        };
+        let result = this.future.as_mut().poll(&mut Context::from_waker(&waker));
+        state.mark_blocked();
+        // Still runnable if it yielded, or was woken from another thread meanwhile
+        if result.is_pending() && state.woken.load(Ordering::Acquire) {
+            state.mark_runnable();
+        }
+        result
+    }
+}
+
+impl<F> Drop for PriorityTask<F> {
+    fn drop(&mut self) {
+        // A killed process may be dropped while it is runnable
+        self.state.mark_blocked();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_names() {
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            assert_eq!(Priority::from_name(priority.name()), Some(priority));
+            let cell = PriorityCell::new(priority);
+            assert_eq!(cell.get(), priority);
+        }
+        assert_eq!(Priority::from_name("urgent"), None);
+        assert_eq!(PriorityCell::default().get(), Priority::Normal);
+    }
+
+    #[test]
+    fn test_runnable_high_priority_tasks_are_counted() {
+        let runnable_high = Arc::new(AtomicUsize::new(0));
+        let priority = Arc::new(PriorityCell::new(Priority::High));
+        let mut yielded = false;
+        let mut task = PriorityTask::new(
+            std::future::poll_fn(move |cx| {
+                if yielded {
+                    Poll::Ready(())
+                } else {
+                    yielded = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }),
+            priority.clone(),
+            runnable_high.clone(),
+        );
+        let mut cx = Context::from_waker(Waker::noop());
+
+        // The task woke itself while it was polled (it yielded): still runnable
+        assert!(Pin::new(&mut task).poll(&mut cx).is_pending());
+        assert_eq!(runnable_high.load(Ordering::Relaxed), 1);
+
+        assert!(Pin::new(&mut task).poll(&mut cx).is_ready());
+        assert_eq!(runnable_high.load(Ordering::Relaxed), 0);
+
+        // A task waiting for a wakeup isn't runnable
+        let mut blocked = PriorityTask::new(std::future::pending::<()>(), priority, runnable_high.clone());
+        assert!(Pin::new(&mut blocked).poll(&mut cx).is_pending());
+        assert_eq!(runnable_high.load(Ordering::Relaxed), 0);
+    }
+}
@@ -457,6 +457,59 @@ pub enum ExitReason {
     LinkedExit(Pid, String),
     /// Clean shutdown (e.g., by supervisor).
     Shutdown,
+    /// Terminated by the runtime for exceeding a per-process limit.
+    LimitExceeded(ProcessLimit),
+    /// The connection to the process's node was lost.
+    NodeDown,
+}
+
+/// A per-process resource limit (see `GcConfig::max_heap_bytes` and
+/// `GcConfig::max_mailbox_len`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessLimit {
+    /// The live heap grew past the maximum heap size.
+    Heap,
+    /// The mailbox held more messages than the maximum mailbox length.
+    Mailbox,
+}
+
+impl ProcessLimit {
+    /// Short name used in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProcessLimit::Heap => "heap",
+            ProcessLimit::Mailbox => "mailbox",
+        }
+    }
+}
+
+impl ExitReason {
+    /// Reason string carried by `("EXIT", pid, reason)` and `("DOWN", pid, reason)` messages.
+    pub fn to_message(&self) -> String {
+        match self {
+            ExitReason::Normal => "normal".to_string(),
+            ExitReason::Error(msg) => msg.clone(),
+            ExitReason::Killed => "killed".to_string(),
+            ExitReason::LinkedExit(_, msg) => msg.clone(),
+            ExitReason::Shutdown => "shutdown".to_string(),
+            ExitReason::LimitExceeded(ProcessLimit::Heap) => "heap_limit".to_string(),
+            ExitReason::LimitExceeded(ProcessLimit::Mailbox) => "mailbox_limit".to_string(),
+            ExitReason::NodeDown => "noconnection".to_string(),
+        }
+    }
+
+    /// Parse a reason string produced by [`ExitReason::to_message`].
+    pub fn from_message(msg: &str) -> Self {
+        match msg {
+            "normal" => ExitReason::Normal,
+            "killed" => ExitReason::Killed,
+            "shutdown" => ExitReason::Shutdown,
+            "heap_limit" => ExitReason::LimitExceeded(ProcessLimit::Heap),
+            "mailbox_limit" => ExitReason::LimitExceeded(ProcessLimit::Mailbox),
+            "noconnection" => ExitReason::NodeDown,
+            other => ExitReason::Error(other.to_string()),
+        }
+    }
 }
 
 /// Thread-safe map key for cross-thread communication.
@@ -1149,7 +1202,7 @@ impl Process {
 
             // Set roots and collect
             self.heap.set_roots(roots);
-            self.heap.collect();
+            self.heap.collect_auto();
 
             // Clear roots after collection (they're only valid during this GC)
             self.heap.clear_roots();
@@ -0,0 +1,242 @@
+//! Sampling profiler.
+//!
+//! Unlike `--profile`, which times every call, the sampler records the call
+//! stack of each running process about once per interval. Processes take
+//! their own samples at the points where they already check for interrupts
+//! (every `REDUCTIONS_PER_YIELD` instructions, and at the back-edges where
+//! native code yields), so the cost while sampling is one clock read per
+//! check plus one stack walk per sample, and nothing when it is off.
+//!
+//! A process is only sampled while it runs: a process waiting in `receive`,
+//! sleeping or blocked on IO adds no samples, so sample counts approximate
+//! CPU time. Frames whose function runs as baseline or optimized native code
+//! are named `[JIT] <function>`, like in the `--profile` summary. Functions
+//! compiled by the pure-numeric JIT run without frames or checks; when one
+//! returns, the samples that fell due during the call are added on top of
+//! the caller's stack.
+//!
+//! Samples are aggregated per process and stack, and can be written in
+//! Brendan Gregg's folded-stack format (input of `flamegraph.pl` and
+//! `inferno`) or as a speedscope JSON file with one profile per process.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use crate::process::CallFrame;
+use crate::value::Pid;
+
+/// What to sample.
+#[derive(Clone, Debug)]
+pub struct SamplerConfig {
+    /// Time between two samples of the same process.
+    pub interval: Duration,
+    /// Only sample these processes (all when `None`).
+    pub pids: Option<HashSet<u64>>,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(1),
+            pids: None,
+        }
+    }
+}
+
+impl SamplerConfig {
+    /// Whether the process is sampled.
+    pub fn includes(&self, pid: Pid) -> bool {
+        self.pids.as_ref().is_none_or(|pids| pids.contains(&pid.0))
+    }
+}
+
+/// Sampler shared by the processes of a VM.
+pub struct Sampler {
+    pub config: SamplerConfig,
+    samples: Mutex<StackSamples>,
+}
+
+impl Sampler {
+    pub fn new(config: SamplerConfig) -> Self {
+        let samples = StackSamples::new(config.interval);
+        Self {
+            config,
+            samples: Mutex::new(samples),
+        }
+    }
+
+    /// Record `count` samples of the current stack of a process. `native` is
+    /// a function running in frameless native code on top of the frames.
+    pub fn record(&self, pid: Pid, frames: &[CallFrame], native: Option<&str>, count: u64) {
+        let mut stack: Vec<String> = frames.iter().map(frame_name).collect();
+        if let Some(name) = native {
+            stack.push(format!("[JIT] {}", name));
+        }
+        self.samples.lock().add(pid, stack, count);
+    }
+
+    /// A copy of the samples taken so far.
+    pub fn samples(&self) -> StackSamples {
+        self.samples.lock().clone()
+    }
+}
+
+/// Name of a frame in a sampled stack.
+fn frame_name(frame: &CallFrame) -> String {
+    let function = &frame.function;
+    if function.jit_entry.is_set() || function.opt_entry.is_set() {
+        format!("[JIT] {}", function.name)
+    } else {
+        function.name.clone()
+    }
+}
+
+/// Sample counts per process and call stack.
+#[derive(Clone, Debug, Default)]
+pub struct StackSamples {
+    /// Time between two samples of the same process.
+    pub interval: Duration,
+    /// Number of samples per process and stack (outermost frame first).
+    counts: BTreeMap<u64, HashMap<Vec<String>, u64>>,
+}
+
+impl StackSamples {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Add `count` samples of a stack (outermost frame first).
+    pub fn add(&mut self, pid: Pid, stack: Vec<String>, count: u64) {
+        *self.counts.entry(pid.0).or_default().entry(stack).or_default() += count;
+    }
+
+    /// Total number of samples.
+    pub fn total(&self) -> u64 {
+        self.counts.values().flat_map(|stacks| stacks.values()).sum()
+    }
+
+    /// Stacks of one process with their counts, most samples first.
+    fn sorted_stacks(stacks: &HashMap<Vec<String>, u64>) -> Vec<(&Vec<String>, u64)> {
+        let mut sorted: Vec<_> = stacks.iter().map(|(stack, count)| (stack, *count)).collect();
+        sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        sorted
+    }
+
+    /// Folded stacks: one `pid N;outer;...;inner count` line per distinct
+    /// stack, for `flamegraph.pl` or `inferno-flamegraph`.
+    pub fn to_folded(&self) -> String {
+        let mut out = String::new();
+        for (pid, stacks) in &self.counts {
+            for (stack, count) in Self::sorted_stacks(stacks) {
+                out.push_str(&format!("pid {}", pid));
+                for frame in stack {
+                    out.push(';');
+                    // ';' separates frames
+                    out.push_str(&frame.replace(';', ":"));
+                }
+                out.push_str(&format!(" {}\n", count));
+            }
+        }
+        out
+    }
+
+    /// A speedscope file (https://www.speedscope.app) with one sampled
+    /// profile per process, weighted in microseconds.
+    pub fn to_speedscope(&self, name: &str) -> String {
+        let mut frames: Vec<&str> = Vec::new();
+        let mut frame_index: HashMap<&str, usize> = HashMap::new();
+        let weight = self.interval.as_micros() as u64;
+        let mut profiles = Vec::new();
+
+        for (pid, stacks) in &self.counts {
+            let mut samples = Vec::new();
+            let mut weights = Vec::new();
+            for (stack, count) in Self::sorted_stacks(stacks) {
+                let indices: Vec<usize> = stack
+                    .iter()
+                    .map(|frame| {
+                        *frame_index.entry(frame.as_str()).or_insert_with(|| {
+                            frames.push(frame.as_str());
+                            frames.len() - 1
+                        })
+                    })
+                    .collect();
+                samples.push(indices);
+                weights.push(count * weight);
+            }
+            let total: u64 = weights.iter().sum();
+            profiles.push(serde_json::json!({
+                "type": "sampled",
+                "name": format!("pid {}", pid),
+                "unit": "microseconds",
+                "startValue": 0,
+                "endValue": total,
+                "samples": samples,
+                "weights": weights,
+            }));
+        }
+
+        let frames: Vec<_> = frames.iter().map(|name| serde_json::json!({ "name": name })).collect();
+        serde_json::json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "name": name,
+            "exporter": "nostos",
+            "activeProfileIndex": 0,
+            "shared": { "frames": frames },
+            "profiles": profiles,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(frames: &[&str]) -> Vec<String> {
+        frames.iter().map(|f| f.to_string()).collect()
+    }
+
+    fn samples() -> StackSamples {
+        let mut samples = StackSamples::new(Duration::from_millis(2));
+        samples.add(Pid(1), stack(&["main", "loop"]), 3);
+        samples.add(Pid(1), stack(&["main", "[JIT] fib"]), 5);
+        samples.add(Pid(2), stack(&["worker"]), 1);
+        samples.add(Pid(1), stack(&["main", "loop"]), 1);
+        samples
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let samples = samples();
+        assert_eq!(samples.total(), 10);
+        assert_eq!(
+            samples.to_folded(),
+            "pid 1;main;[JIT] fib 5\npid 1;main;loop 4\npid 2;worker 1\n"
+        );
+    }
+
+    #[test]
+    fn test_speedscope_profiles() {
+        let json: serde_json::Value = serde_json::from_str(&samples().to_speedscope("test")).unwrap();
+        let frames: Vec<&str> = json["shared"]["frames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(frames, vec!["main", "[JIT] fib", "loop", "worker"]);
+
+        let first = &json["profiles"][0];
+        assert_eq!(first["name"], "pid 1");
+        assert_eq!(first["samples"], serde_json::json!([[0, 1], [0, 2]]));
+        assert_eq!(first["weights"], serde_json::json!([10000, 8000]));
+        assert_eq!(first["endValue"], 18000);
+        assert_eq!(json["profiles"][1]["samples"], serde_json::json!([[3]]));
+    }
+}
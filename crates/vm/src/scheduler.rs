@@ -1351,6 +1351,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: std::sync::atomic::AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -1390,6 +1393,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: std::sync::atomic::AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -10,6 +10,7 @@ use imbl::HashMap as ImblHashMap;
 
 use crate::gc::{GcMapKey, GcValue, Heap};
 use crate::process::ThreadSafeValue;
+use crate::value::Pid;
 
 // ============================================================================
 // Channel types for cross-thread communication
@@ -130,6 +131,8 @@ pub enum DebugCommand {
     PrintLocalsForFrame(usize),
     /// Print the call stack
     PrintStack,
+    /// Pause a running process at its next instruction
+    Pause,
 }
 
 /// Events sent from a process to the debugger
@@ -158,6 +161,11 @@ pub enum DebugEvent {
         pid: u64,
         value: Option<String>,
     },
+    /// A process was spawned while debugging (see `DebugHub`)
+    ProcessStarted {
+        pid: u64,
+        function: String,
+    },
     /// Variable value
     Variable {
         name: String,
@@ -213,6 +221,84 @@ pub type DebugEventSender = crossbeam::channel::Sender<DebugEvent>;
 /// Type alias for debug event receiver
 pub type DebugEventReceiver = crossbeam::channel::Receiver<DebugEvent>;
 
+/// The debug channels of all processes of a program being debugged.
+///
+/// Processes spawned during a debug session attach to the hub: they report
+/// on the session's event channel, start with the session's breakpoints and
+/// get their own command channel, so each one can be stepped on its own.
+pub struct DebugHub {
+    event_sender: DebugEventSender,
+    /// Command senders of the attached processes, by pid
+    processes: parking_lot::Mutex<std::collections::BTreeMap<u64, DebugCommandSender>>,
+    breakpoints: parking_lot::Mutex<StdHashSet<Breakpoint>>,
+}
+
+impl DebugHub {
+    pub fn new(event_sender: DebugEventSender) -> Self {
+        Self {
+            event_sender,
+            processes: parking_lot::Mutex::new(std::collections::BTreeMap::new()),
+            breakpoints: parking_lot::Mutex::new(StdHashSet::new()),
+        }
+    }
+
+    /// Attach a process, returning the receiver for its commands.
+    pub fn attach(&self, pid: u64) -> DebugCommandReceiver {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        self.processes.lock().insert(pid, sender);
+        receiver
+    }
+
+    /// Command sender of an attached process.
+    pub fn sender(&self, pid: u64) -> Option<DebugCommandSender> {
+        self.processes.lock().get(&pid).cloned()
+    }
+
+    /// Detach an exited process.
+    pub fn detach(&self, pid: u64) {
+        self.processes.lock().remove(&pid);
+    }
+
+    pub fn event_sender(&self) -> DebugEventSender {
+        self.event_sender.clone()
+    }
+
+    /// Pids of the attached processes, in spawn order.
+    pub fn pids(&self) -> Vec<u64> {
+        self.processes.lock().keys().copied().collect()
+    }
+
+    /// Breakpoints that newly attached processes start with.
+    pub fn breakpoints(&self) -> StdHashSet<Breakpoint> {
+        self.breakpoints.lock().clone()
+    }
+
+    /// Send a command to one process.
+    pub fn send(&self, pid: u64, cmd: DebugCommand) -> Result<(), String> {
+        let sender = self.sender(pid)
+            .ok_or_else(|| format!("Process {} is not being debugged", pid))?;
+        sender.send(cmd).map_err(|e| e.to_string())
+    }
+
+    /// Add a breakpoint to all processes, including ones spawned later.
+    pub fn add_breakpoint(&self, bp: Breakpoint) {
+        self.breakpoints.lock().insert(bp.clone());
+        self.broadcast(DebugCommand::AddBreakpoint(bp));
+    }
+
+    /// Remove a breakpoint from all processes.
+    pub fn remove_breakpoint(&self, bp: Breakpoint) {
+        self.breakpoints.lock().remove(&bp);
+        self.broadcast(DebugCommand::RemoveBreakpoint(bp));
+    }
+
+    fn broadcast(&self, cmd: DebugCommand) {
+        for sender in self.processes.lock().values() {
+            let _ = sender.send(cmd.clone());
+        }
+    }
+}
+
 /// Command for evaluating code from Nostos via the main thread (which has the compiler).
 /// Uses a request-response pattern with a reply channel.
 pub struct EvalCommand {
@@ -863,7 +949,7 @@ impl SendableValue {
             SendableValue::Float64(f) => f.to_string(),
             SendableValue::BigInt(bi) => bi.to_string(),
             SendableValue::Decimal(d) => d.to_string(),
-            SendableValue::Pid(p) => format!("<pid:{}>", p),
+            SendableValue::Pid(p) => format!("<pid:{}>", Pid(*p)),
             SendableValue::String(s) => format!("\"{}\"", s),
             SendableValue::List(items) => {
                 let items_str: Vec<String> = items.iter().map(|v| v.display()).collect();
@@ -1115,7 +1201,7 @@ impl SharedMapValue {
             SharedMapValue::Bool(b) => b.to_string(),
             SharedMapValue::Int64(i) => i.to_string(),
             SharedMapValue::Float64(f) => f.to_string(),
-            SharedMapValue::Pid(p) => format!("<pid:{}>", p),
+            SharedMapValue::Pid(p) => format!("<pid:{}>", Pid(*p)),
             SharedMapValue::String(s) => format!("\"{}\"", s),
             SharedMapValue::Char(c) => format!("'{}'", c),
             SharedMapValue::List(items) => {
@@ -14,6 +14,9 @@
 //! - `Permanent`: Always restart (default for long-running services)
 //! - `Transient`: Restart only if terminated abnormally
 //! - `Temporary`: Never restart (for one-off tasks)
+//!
+//! [`Supervisor`] drives the threaded scheduler; [`AsyncSupervisor`] runs as a
+//! process on the async VM and backs the `Supervisor.*` builtins.
 
 use std::collections::VecDeque;
 use std::sync::Arc;
@@ -21,8 +24,9 @@ use std::time::{Duration, Instant};
 
 use parking_lot::Mutex;
 
+use crate::async_vm::{AsyncSharedState, MailboxReceiver, SpawnLinkage, SpawnOptions};
 use crate::gc::GcValue;
-use crate::process::ExitReason;
+use crate::process::{ExitReason, ThreadSafeValue};
 use crate::scheduler::Scheduler;
 use crate::value::{FunctionValue, Pid};
 
@@ -38,6 +42,18 @@ pub enum RestartStrategy {
     RestForOne,
 }
 
+impl RestartStrategy {
+    /// Parse the strategy name used by `Supervisor.start`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "oneForOne" => Some(RestartStrategy::OneForOne),
+            "oneForAll" => Some(RestartStrategy::OneForAll),
+            "restForOne" => Some(RestartStrategy::RestForOne),
+            _ => None,
+        }
+    }
+}
+
 /// When to restart a child process.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum RestartType {
@@ -50,6 +66,27 @@ pub enum RestartType {
     Temporary,
 }
 
+impl RestartType {
+    /// Parse the restart type name used by `Supervisor.startChild`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "permanent" => Some(RestartType::Permanent),
+            "transient" => Some(RestartType::Transient),
+            "temporary" => Some(RestartType::Temporary),
+            _ => None,
+        }
+    }
+
+    /// Whether a child that exited for `reason` should be restarted.
+    pub fn should_restart(&self, reason: &ExitReason) -> bool {
+        match self {
+            RestartType::Permanent => true,
+            RestartType::Transient => !matches!(reason, ExitReason::Normal),
+            RestartType::Temporary => false,
+        }
+    }
+}
+
 /// Specification for a supervised child process.
 #[derive(Clone)]
 pub struct ChildSpec {
@@ -157,10 +194,49 @@ struct ChildInfo {
     pid: Pid,
 }
 
-/// Record of a restart event.
+/// Restart intensity tracking: at most `max_restarts` restarts within `max_seconds`.
 #[derive(Clone)]
-struct RestartRecord {
-    timestamp: Instant,
+pub struct RestartIntensity {
+    max_restarts: u32,
+    max_seconds: u32,
+    history: VecDeque<Instant>,
+}
+
+impl RestartIntensity {
+    /// Create a tracker allowing `max_restarts` restarts per `max_seconds`.
+    pub fn new(max_restarts: u32, max_seconds: u32) -> Self {
+        Self {
+            max_restarts,
+            max_seconds,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Record a restart, or fail if the intensity limit has been reached.
+    pub fn record_restart(&mut self) -> Result<(), SupervisorError> {
+        let now = Instant::now();
+        let window = Duration::from_secs(self.max_seconds as u64);
+
+        // Remove old restart records
+        while let Some(front) = self.history.front() {
+            if now.duration_since(*front) > window {
+                self.history.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        // Check if we've exceeded the limit
+        if self.history.len() >= self.max_restarts as usize {
+            return Err(SupervisorError::MaxRestartsExceeded {
+                restarts: self.history.len() as u32,
+                seconds: self.max_seconds,
+            });
+        }
+
+        self.history.push_back(now);
+        Ok(())
+    }
 }
 
 /// A supervisor that manages child processes.
@@ -172,7 +248,7 @@ pub struct Supervisor {
     /// Running children
     children: Vec<ChildInfo>,
     /// Restart history for intensity tracking
-    restart_history: VecDeque<RestartRecord>,
+    intensity: RestartIntensity,
     /// The scheduler
     scheduler: Arc<Scheduler>,
     /// Supervisor's own pid (if running as a process)
@@ -183,10 +259,10 @@ impl Supervisor {
     /// Create a new supervisor.
     pub fn new(scheduler: Arc<Scheduler>, config: SupervisorConfig) -> Self {
         Self {
+            intensity: RestartIntensity::new(config.max_restarts, config.max_seconds),
             config,
             specs: Vec::new(),
             children: Vec::new(),
-            restart_history: VecDeque::new(),
             scheduler,
             self_pid: None,
         }
@@ -258,25 +334,14 @@ impl Supervisor {
         let spec = child.spec.clone();
 
         // Check if we should restart
-        let should_restart = match spec.restart {
-            RestartType::Permanent => true,
-            RestartType::Transient => !matches!(reason, ExitReason::Normal),
-            RestartType::Temporary => false,
-        };
-
-        if !should_restart {
+        if !spec.restart.should_restart(reason) {
             // Remove from children list
             self.children.remove(child_idx);
             return Ok(false);
         }
 
-        // Check restart intensity
-        self.check_restart_intensity()?;
-
-        // Record this restart
-        self.restart_history.push_back(RestartRecord {
-            timestamp: Instant::now(),
-        });
+        // Check restart intensity and record this restart
+        self.intensity.record_restart()?;
 
         // Apply restart strategy
         match self.config.strategy {
@@ -310,31 +375,6 @@ impl Supervisor {
         Ok(true)
     }
 
-    /// Check if we've exceeded restart intensity.
-    fn check_restart_intensity(&mut self) -> Result<(), SupervisorError> {
-        let now = Instant::now();
-        let cutoff = now - Duration::from_secs(self.config.max_seconds as u64);
-
-        // Remove old restart records
-        while let Some(front) = self.restart_history.front() {
-            if front.timestamp < cutoff {
-                self.restart_history.pop_front();
-            } else {
-                break;
-            }
-        }
-
-        // Check if we've exceeded the limit
-        if self.restart_history.len() >= self.config.max_restarts as usize {
-            return Err(SupervisorError::MaxRestartsExceeded {
-                restarts: self.restart_history.len() as u32,
-                seconds: self.config.max_seconds,
-            });
-        }
-
-        Ok(())
-    }
-
     /// Stop all children.
     fn stop_all_children(&mut self) {
         for child in &self.children {
@@ -458,6 +498,206 @@ impl Clone for SupervisorHandle {
     }
 }
 
+// ============================================================================
+// Supervisors on the async VM
+// ============================================================================
+
+/// Child specification for a supervisor running on the async VM.
+#[derive(Clone)]
+pub struct AsyncChildSpec {
+    /// Unique identifier for this child
+    pub id: String,
+    /// Function the child process runs
+    pub function: Arc<FunctionValue>,
+    /// Captured values of the function (for closures)
+    pub captures: Vec<ThreadSafeValue>,
+    /// When to restart
+    pub restart: RestartType,
+}
+
+/// A running child of an [`AsyncSupervisor`].
+struct AsyncChild {
+    spec: AsyncChildSpec,
+    pid: Pid,
+}
+
+/// A supervisor running as its own process on the async VM.
+///
+/// The supervisor is linked to the process that started it and to each of its
+/// children, and acts on the `("EXIT", pid, reason)` messages those links deliver.
+/// If its parent exits, or restart intensity is exceeded, it stops its children
+/// and exits itself.
+pub struct AsyncSupervisor {
+    /// Supervisor's own pid
+    pid: Pid,
+    /// Process that started the supervisor
+    parent: Pid,
+    /// Restart strategy
+    strategy: RestartStrategy,
+    /// Restart intensity tracking
+    intensity: RestartIntensity,
+    /// Running children (in start order)
+    children: Vec<AsyncChild>,
+}
+
+impl AsyncSupervisor {
+    /// Start a supervisor process linked to `parent`.
+    pub async fn start(shared: &Arc<AsyncSharedState>, parent: Pid, config: SupervisorConfig) -> Pid {
+        let pid = shared.alloc_pid();
+        let (sender, mailbox) = crate::mailbox::unbounded();
+        shared.register_process(pid, sender).await;
+        shared.link(pid, parent);
+
+        let state = Arc::new(tokio::sync::Mutex::new(AsyncSupervisor {
+            pid,
+            parent,
+            strategy: config.strategy,
+            intensity: RestartIntensity::new(config.max_restarts, config.max_seconds),
+            children: Vec::new(),
+        }));
+        shared.supervisors.lock().insert(pid, state.clone());
+
+        let handle = shared.spawn_task(run_async_supervisor(shared.clone(), state, mailbox));
+        shared.process_abort_handles.write().await.insert(pid, handle.abort_handle());
+        pid
+    }
+
+    /// Start a new child and add it to the end of the child list.
+    pub async fn start_child(&mut self, shared: &Arc<AsyncSharedState>, spec: AsyncChildSpec) -> Result<Pid, SupervisorError> {
+        if self.children.iter().any(|c| c.spec.id == spec.id) {
+            return Err(SupervisorError::StartFailed(format!("child '{}' already exists", spec.id)));
+        }
+        let pid = self.spawn_child(shared, &spec).await;
+        self.children.push(AsyncChild { spec, pid });
+        Ok(pid)
+    }
+
+    /// Stop a child and remove it from the child list.
+    /// Returns false if there is no child with this id.
+    pub async fn terminate_child(&mut self, shared: &Arc<AsyncSharedState>, id: &str) -> bool {
+        match self.children.iter().position(|c| c.spec.id == id) {
+            Some(idx) => {
+                let child = self.children.remove(idx);
+                shared.kill_process(child.pid, ExitReason::Shutdown).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ids and pids of the running children, in start order.
+    pub fn which_children(&self) -> Vec<(String, Pid)> {
+        self.children.iter().map(|c| (c.spec.id.clone(), c.pid)).collect()
+    }
+
+    /// Forget all children, returning their pids in start order.
+    pub fn take_children(&mut self) -> Vec<Pid> {
+        self.children.drain(..).map(|c| c.pid).collect()
+    }
+
+    async fn spawn_child(&self, shared: &Arc<AsyncSharedState>, spec: &AsyncChildSpec) -> Pid {
+        shared
+            .spawn_function(
+                spec.function.clone(),
+                Vec::new(),
+                spec.captures.clone(),
+                SpawnLinkage::Link(self.pid),
+                SpawnOptions::default(),
+            )
+            .await
+    }
+
+    /// Handle a child exit according to its restart type and the strategy.
+    /// Exits of processes that are no longer children (e.g. siblings stopped
+    /// during a restart) are ignored.
+    async fn handle_child_exit(
+        &mut self,
+        shared: &Arc<AsyncSharedState>,
+        pid: Pid,
+        reason: &ExitReason,
+    ) -> Result<(), SupervisorError> {
+        let Some(child_idx) = self.children.iter().position(|c| c.pid == pid) else {
+            return Ok(());
+        };
+
+        if !self.children[child_idx].spec.restart.should_restart(reason) {
+            self.children.remove(child_idx);
+            return Ok(());
+        }
+
+        self.intensity.record_restart()?;
+
+        // Children to restart, as a range of the child list
+        let restart = match self.strategy {
+            RestartStrategy::OneForOne => child_idx..child_idx + 1,
+            RestartStrategy::OneForAll => 0..self.children.len(),
+            RestartStrategy::RestForOne => child_idx..self.children.len(),
+        };
+
+        // Stop the affected siblings in reverse start order, then restart in order
+        for idx in restart.clone().rev() {
+            if idx != child_idx {
+                shared.kill_process(self.children[idx].pid, ExitReason::Shutdown).await;
+            }
+        }
+        for idx in restart {
+            let spec = self.children[idx].spec.clone();
+            self.children[idx].pid = self.spawn_child(shared, &spec).await;
+        }
+        Ok(())
+    }
+}
+
+/// Parse an `("EXIT", pid, reason)` message.
+fn exit_signal(msg: &ThreadSafeValue) -> Option<(Pid, ExitReason)> {
+    match msg {
+        ThreadSafeValue::Tuple(items) => match items.as_slice() {
+            [ThreadSafeValue::String(tag), ThreadSafeValue::Pid(pid), ThreadSafeValue::String(reason)]
+                if tag == "EXIT" =>
+            {
+                Some((Pid(*pid), ExitReason::from_message(reason)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Main loop of a supervisor process.
+async fn run_async_supervisor(
+    shared: Arc<AsyncSharedState>,
+    state: Arc<tokio::sync::Mutex<AsyncSupervisor>>,
+    mut mailbox: MailboxReceiver,
+) {
+    let pid = state.lock().await.pid;
+    let exit_reason = loop {
+        let Some(msg) = mailbox.recv().await else {
+            break ExitReason::Shutdown;
+        };
+        // Anything but exit signals is ignored
+        let Some((from, reason)) = exit_signal(&msg) else {
+            continue;
+        };
+        let mut supervisor = state.lock().await;
+        if from == supervisor.parent {
+            break reason;
+        }
+        if let Err(e) = supervisor.handle_child_exit(&shared, from, &reason).await {
+            break ExitReason::Error(e.to_string());
+        }
+    };
+
+    shared.supervisors.lock().remove(&pid);
+    let children = state.lock().await.take_children();
+    for child in children.into_iter().rev() {
+        shared.kill_process(child, ExitReason::Shutdown).await;
+    }
+    shared.process_abort_handles.write().await.remove(&pid);
+    if shared.unregister_process(pid).await {
+        shared.process_exited(pid, &exit_reason).await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -482,6 +722,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             source_code: None,
@@ -757,4 +1000,28 @@ mod tests {
         assert!(supervisor.get_child_spec("worker1").is_some());
         assert!(supervisor.get_child_spec("nonexistent").is_none());
     }
+
+    #[test]
+    fn test_restart_intensity_limit() {
+        let mut intensity = RestartIntensity::new(2, 60);
+        assert!(intensity.record_restart().is_ok());
+        assert!(intensity.record_restart().is_ok());
+        assert!(matches!(
+            intensity.record_restart(),
+            Err(SupervisorError::MaxRestartsExceeded { restarts: 2, seconds: 60 })
+        ));
+    }
+
+    #[test]
+    fn test_restart_names_and_exit_reasons() {
+        assert_eq!(RestartStrategy::from_name("restForOne"), Some(RestartStrategy::RestForOne));
+        assert_eq!(RestartStrategy::from_name("one_for_one"), None);
+        assert_eq!(RestartType::from_name("transient"), Some(RestartType::Transient));
+
+        let normal = ExitReason::from_message(&ExitReason::Normal.to_message());
+        let crash = ExitReason::from_message("boom");
+        assert!(!RestartType::Transient.should_restart(&normal));
+        assert!(RestartType::Transient.should_restart(&crash));
+        assert!(!RestartType::Temporary.should_restart(&crash));
+    }
 }
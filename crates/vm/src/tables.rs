@@ -0,0 +1,401 @@
+//! Shared key-value tables (`Table.new`, `Table.insert`, ...).
+//!
+//! A table is a named hash table owned by the process that created it. Any
+//! process can read it concurrently; by default only the owner writes, unless
+//! the table was created with the `"public"` option. A `"set"` table holds one
+//! value per key, a `"bag"` table a list of distinct values. Keys and values
+//! are stored as [`SharedMapKey`]/[`SharedMapValue`], so reads copy them into
+//! the reading process's heap. Tables are deleted when their owner exits.
+//!
+//! `Table.update` is an optimistic read-modify-write: every entry carries the
+//! version of its last write, and [`Tables::commit`] only stores the new value
+//! if the entry wasn't written since it was read. The compiled code retries
+//! otherwise, so the update function runs in the caller without holding a lock.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::shared_types::{SharedMapKey, SharedMapValue};
+use crate::value::Pid;
+
+/// How many values a table keeps per key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    /// One value per key; inserting replaces it
+    Set,
+    /// Any number of distinct values per key
+    Bag,
+}
+
+/// Options of `Table.new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableOptions {
+    pub kind: TableKind,
+    /// Any process may write, not only the owner
+    pub public: bool,
+}
+
+impl TableOptions {
+    /// Parse option names (`"set"`, `"bag"`, `"public"`, `"protected"`).
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut options = TableOptions { kind: TableKind::Set, public: false };
+        for name in names {
+            match name {
+                "set" => options.kind = TableKind::Set,
+                "bag" => options.kind = TableKind::Bag,
+                "public" => options.public = true,
+                "protected" => options.public = false,
+                other => return Err(format!(
+                    "unknown table option '{}' (expected set, bag, public or protected)", other
+                )),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Why a table operation failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableError {
+    /// No table with this name
+    NoTable(String),
+    /// A process other than the owner wrote to a protected table
+    Protected(String),
+    /// `Table.update` on a bag table
+    NotASet(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::NoTable(name) => write!(f, "no table named '{}'", name),
+            TableError::Protected(name) => write!(f, "table '{}' is protected (only its owner may write)", name),
+            TableError::NotASet(name) => write!(f, "table '{}' is a bag (update needs a set)", name),
+        }
+    }
+}
+
+struct Entry {
+    values: Vec<SharedMapValue>,
+    /// Version of the last write (see [`Tables::commit`])
+    version: u64,
+}
+
+struct Table {
+    name: String,
+    owner: Pid,
+    options: TableOptions,
+    entries: RwLock<HashMap<SharedMapKey, Entry>>,
+}
+
+impl Table {
+    fn check_write(&self, caller: Pid) -> Result<(), TableError> {
+        if self.options.public || caller == self.owner {
+            Ok(())
+        } else {
+            Err(TableError::Protected(self.name.clone()))
+        }
+    }
+}
+
+/// The tables of one VM.
+#[derive(Default)]
+pub struct Tables {
+    tables: RwLock<HashMap<String, Arc<Table>>>,
+    /// Versions start at 1, so 0 stands for "no entry"
+    next_version: AtomicU64,
+}
+
+impl Tables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, name: &str) -> Result<Arc<Table>, TableError> {
+        self.tables.read().get(name).cloned().ok_or_else(|| TableError::NoTable(name.to_string()))
+    }
+
+    fn version(&self) -> u64 {
+        self.next_version.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Create a table owned by `owner`. Returns false if the name is taken.
+    pub fn create(&self, name: &str, owner: Pid, options: TableOptions) -> bool {
+        let mut tables = self.tables.write();
+        if tables.contains_key(name) {
+            return false;
+        }
+        tables.insert(name.to_string(), Arc::new(Table {
+            name: name.to_string(),
+            owner,
+            options,
+            entries: RwLock::new(HashMap::new()),
+        }));
+        true
+    }
+
+    /// Delete a table. Only its owner may delete a protected table.
+    /// Returns false if there was no such table.
+    pub fn drop_table(&self, name: &str, caller: Pid) -> Result<bool, TableError> {
+        let mut tables = self.tables.write();
+        match tables.get(name) {
+            Some(table) => {
+                table.check_write(caller)?;
+                tables.remove(name);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Delete the tables owned by a process that exited.
+    pub fn drop_owned_by(&self, owner: Pid) {
+        self.tables.write().retain(|_, table| table.owner != owner);
+    }
+
+    /// Names of all tables, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tables.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Store `value` under `key`: replace the value of a set, add it to a bag
+    /// unless an equal value is already there.
+    pub fn insert(&self, name: &str, caller: Pid, key: SharedMapKey, value: SharedMapValue) -> Result<(), TableError> {
+        let table = self.get(name)?;
+        table.check_write(caller)?;
+        let version = self.version();
+        let mut entries = table.entries.write();
+        match table.options.kind {
+            TableKind::Set => {
+                entries.insert(key, Entry { values: vec![value], version });
+            }
+            TableKind::Bag => {
+                let entry = entries.entry(key).or_insert_with(|| Entry { values: Vec::new(), version });
+                if !entry.values.iter().any(|v| same_value(v, &value)) {
+                    entry.values.push(value);
+                    entry.version = version;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The values stored under `key` (none, one for a set, any number for a bag).
+    pub fn lookup(&self, name: &str, key: &SharedMapKey) -> Result<Vec<SharedMapValue>, TableError> {
+        let table = self.get(name)?;
+        let entries = table.entries.read();
+        Ok(entries.get(key).map(|entry| entry.values.clone()).unwrap_or_default())
+    }
+
+    /// Remove `key`. Returns false if it wasn't there.
+    pub fn delete(&self, name: &str, caller: Pid, key: &SharedMapKey) -> Result<bool, TableError> {
+        let table = self.get(name)?;
+        table.check_write(caller)?;
+        let removed = table.entries.write().remove(key).is_some();
+        Ok(removed)
+    }
+
+    /// Number of keys.
+    pub fn size(&self, name: &str) -> Result<usize, TableError> {
+        Ok(self.get(name)?.entries.read().len())
+    }
+
+    /// The entries whose key matches `pattern`, one per value. `Unit` in the
+    /// pattern matches anything, so `()` matches every key and `("user", ())`
+    /// every pair starting with "user".
+    pub fn matching(&self, name: &str, pattern: &SharedMapKey) -> Result<Vec<(SharedMapKey, SharedMapValue)>, TableError> {
+        let table = self.get(name)?;
+        let entries = table.entries.read();
+        Ok(entries.iter()
+            .filter(|(key, _)| key_matches(pattern, key))
+            .flat_map(|(key, entry)| entry.values.iter().map(move |value| (key.clone(), value.clone())))
+            .collect())
+    }
+
+    /// Read the value of `key` in a set for an update, with the version to
+    /// pass to [`Tables::commit`]. Returns None (version 0) for a missing key.
+    pub fn read_for_update(&self, name: &str, caller: Pid, key: &SharedMapKey) -> Result<(Option<SharedMapValue>, u64), TableError> {
+        let table = self.get(name)?;
+        table.check_write(caller)?;
+        if table.options.kind != TableKind::Set {
+            return Err(TableError::NotASet(name.to_string()));
+        }
+        let entries = table.entries.read();
+        Ok(match entries.get(key) {
+            Some(entry) => (entry.values.first().cloned(), entry.version),
+            None => (None, 0),
+        })
+    }
+
+    /// Store the result of an update unless `key` was written since it was
+    /// read at `version`. Returns false if the update has to be retried.
+    pub fn commit(&self, name: &str, caller: Pid, key: SharedMapKey, version: u64, value: SharedMapValue) -> Result<bool, TableError> {
+        let table = self.get(name)?;
+        table.check_write(caller)?;
+        let mut entries = table.entries.write();
+        let current = entries.get(&key).map_or(0, |entry| entry.version);
+        if current != version {
+            return Ok(false);
+        }
+        entries.insert(key, Entry { values: vec![value], version: self.version() });
+        Ok(true)
+    }
+}
+
+/// Whether `key` matches `pattern`, where `Unit` is a wildcard.
+fn key_matches(pattern: &SharedMapKey, key: &SharedMapKey) -> bool {
+    let all = |patterns: &[SharedMapKey], keys: &[SharedMapKey]| {
+        patterns.len() == keys.len() && patterns.iter().zip(keys).all(|(p, k)| key_matches(p, k))
+    };
+    match (pattern, key) {
+        (SharedMapKey::Unit, _) => true,
+        (SharedMapKey::Tuple(ps), SharedMapKey::Tuple(ks)) => all(ps, ks),
+        (
+            SharedMapKey::Record { type_name: t1, field_names: n1, fields: ps },
+            SharedMapKey::Record { type_name: t2, field_names: n2, fields: ks },
+        ) => t1 == t2 && n1 == n2 && all(ps, ks),
+        (
+            SharedMapKey::Variant { type_name: t1, constructor: c1, fields: ps },
+            SharedMapKey::Variant { type_name: t2, constructor: c2, fields: ks },
+        ) => t1 == t2 && c1 == c2 && all(ps, ks),
+        _ => pattern == key,
+    }
+}
+
+/// Structural equality of stored values (for the distinct values of a bag).
+fn same_value(a: &SharedMapValue, b: &SharedMapValue) -> bool {
+    use SharedMapValue as V;
+    let all = |xs: &[V], ys: &[V]| xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_value(x, y));
+    match (a, b) {
+        (V::Unit, V::Unit) => true,
+        (V::Bool(x), V::Bool(y)) => x == y,
+        (V::Int64(x), V::Int64(y)) => x == y,
+        (V::Float64(x), V::Float64(y)) => x == y,
+        (V::Pid(x), V::Pid(y)) => x == y,
+        (V::String(x), V::String(y)) => x == y,
+        (V::Char(x), V::Char(y)) => x == y,
+        (V::List(xs), V::List(ys)) | (V::Tuple(xs), V::Tuple(ys)) => all(xs, ys),
+        (
+            V::Record { type_name: t1, field_names: n1, fields: xs },
+            V::Record { type_name: t2, field_names: n2, fields: ys },
+        ) => t1 == t2 && n1 == n2 && all(xs, ys),
+        (
+            V::Variant { type_name: t1, constructor: c1, fields: xs },
+            V::Variant { type_name: t2, constructor: c2, fields: ys },
+        ) => t1 == t2 && c1 == c2 && all(xs, ys),
+        (V::Map(x), V::Map(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| same_value(v, w)))
+        }
+        (V::Set(xs), V::Set(ys)) => xs.len() == ys.len() && xs.iter().all(|x| ys.contains(x)),
+        (V::Int64Array(x), V::Int64Array(y)) => x == y,
+        (V::Float64Array(x), V::Float64Array(y)) => x == y,
+        (V::Float32Array(x), V::Float32Array(y)) => x == y,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> SharedMapKey {
+        SharedMapKey::String(s.to_string())
+    }
+
+    fn int(n: i64) -> SharedMapValue {
+        SharedMapValue::Int64(n)
+    }
+
+    fn ints(values: Vec<SharedMapValue>) -> Vec<i64> {
+        values.into_iter().map(|v| match v {
+            SharedMapValue::Int64(n) => n,
+            other => panic!("not an Int: {:?}", other),
+        }).collect()
+    }
+
+    const OWNER: Pid = Pid(1);
+    const OTHER: Pid = Pid(2);
+
+    fn options(kind: TableKind, public: bool) -> TableOptions {
+        TableOptions { kind, public }
+    }
+
+    #[test]
+    fn test_set_and_bag_semantics() {
+        let tables = Tables::new();
+        assert!(tables.create("s", OWNER, options(TableKind::Set, false)));
+        assert!(!tables.create("s", OTHER, options(TableKind::Bag, false)));
+        tables.insert("s", OWNER, key("a"), int(1)).unwrap();
+        tables.insert("s", OWNER, key("a"), int(2)).unwrap();
+        assert_eq!(ints(tables.lookup("s", &key("a")).unwrap()), vec![2]);
+
+        assert!(tables.create("b", OWNER, options(TableKind::Bag, false)));
+        for n in [1, 2, 1] {
+            tables.insert("b", OWNER, key("a"), int(n)).unwrap();
+        }
+        assert_eq!(ints(tables.lookup("b", &key("a")).unwrap()), vec![1, 2]);
+        assert!(tables.lookup("b", &key("missing")).unwrap().is_empty());
+        assert!(tables.delete("b", OWNER, &key("a")).unwrap());
+        assert!(!tables.delete("b", OWNER, &key("a")).unwrap());
+        assert_eq!(tables.size("b").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_protected_tables_and_owner_exit() {
+        let tables = Tables::new();
+        tables.create("p", OWNER, options(TableKind::Set, false));
+        tables.create("q", OWNER, options(TableKind::Set, true));
+        tables.create("r", OTHER, options(TableKind::Set, false));
+        assert_eq!(tables.insert("p", OTHER, key("a"), int(1)), Err(TableError::Protected("p".to_string())));
+        assert!(tables.insert("q", OTHER, key("a"), int(1)).is_ok());
+
+        tables.drop_owned_by(OWNER);
+        assert_eq!(tables.names(), vec!["r".to_string()]);
+        assert_eq!(tables.lookup("p", &key("a")).unwrap_err(), TableError::NoTable("p".to_string()));
+    }
+
+    #[test]
+    fn test_match_with_wildcards() {
+        let tables = Tables::new();
+        tables.create("t", OWNER, options(TableKind::Set, false));
+        let pair = |a: &str, b: i64| SharedMapKey::Tuple(vec![key(a), SharedMapKey::Int64(b)]);
+        tables.insert("t", OWNER, pair("x", 1), int(10)).unwrap();
+        tables.insert("t", OWNER, pair("x", 2), int(20)).unwrap();
+        tables.insert("t", OWNER, pair("y", 1), int(30)).unwrap();
+
+        let pattern = SharedMapKey::Tuple(vec![key("x"), SharedMapKey::Unit]);
+        let mut found = ints(tables.matching("t", &pattern).unwrap().into_iter().map(|(_, v)| v).collect());
+        found.sort();
+        assert_eq!(found, vec![10, 20]);
+        assert_eq!(tables.matching("t", &SharedMapKey::Unit).unwrap().len(), 3);
+        assert_eq!(tables.matching("t", &pair("y", 2)).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_commit_fails_after_concurrent_write() {
+        let tables = Tables::new();
+        tables.create("c", OWNER, options(TableKind::Set, true));
+        let (value, version) = tables.read_for_update("c", OWNER, &key("n")).unwrap();
+        assert!(value.is_none());
+        assert_eq!(version, 0);
+
+        // Another process writes in between: the first commit must retry
+        let (_, other_version) = tables.read_for_update("c", OTHER, &key("n")).unwrap();
+        assert!(tables.commit("c", OTHER, key("n"), other_version, int(1)).unwrap());
+        assert!(!tables.commit("c", OWNER, key("n"), version, int(1)).unwrap());
+
+        let (value, version) = tables.read_for_update("c", OWNER, &key("n")).unwrap();
+        assert_eq!(ints(value.into_iter().collect()), vec![1]);
+        assert!(tables.commit("c", OWNER, key("n"), version, int(2)).unwrap());
+        assert_eq!(ints(tables.lookup("c", &key("n")).unwrap()), vec![2]);
+
+        tables.create("b", OWNER, options(TableKind::Bag, false));
+        assert!(matches!(tables.read_for_update("b", OWNER, &key("n")), Err(TableError::NotASet(_))));
+    }
+}
@@ -0,0 +1,254 @@
+//! Runtime-managed timers for the async VM.
+//!
+//! `Timer.sendAfter` and `Timer.interval` deliver a message to a process after a
+//! delay (once or repeatedly). All pending timers live in one ordered queue that
+//! is driven by a single tokio task, so the number of pending timers doesn't
+//! translate into tasks or threads.
+//!
+//! Every timer has an owner (the process that created it). Timers are cancelled
+//! when their owner exits, and one-shot or interval timers whose target process
+//! is gone are dropped the next time they fire.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::async_vm::MailboxSender;
+use crate::process::ThreadSafeValue;
+use crate::value::Pid;
+
+/// A pending timer.
+struct Timer {
+    /// Process that created the timer
+    owner: Pid,
+    /// When the timer fires next
+    deadline: Instant,
+    /// Repeat period for interval timers
+    interval: Option<Duration>,
+    /// Mailbox of the target process
+    target: MailboxSender,
+    /// Message to deliver
+    message: ThreadSafeValue,
+}
+
+/// Timer bookkeeping shared between the API and the driver task.
+#[derive(Default)]
+struct TimerQueue {
+    next_id: u64,
+    /// Pending timers ordered by deadline
+    queue: BTreeSet<(Instant, u64)>,
+    timers: HashMap<u64, Timer>,
+    /// Timer ids by owner, for cancellation on exit
+    by_owner: HashMap<Pid, HashSet<u64>>,
+}
+
+impl TimerQueue {
+    fn remove(&mut self, id: u64) -> Option<Timer> {
+        let timer = self.timers.remove(&id)?;
+        self.queue.remove(&(timer.deadline, id));
+        if let Some(ids) = self.by_owner.get_mut(&timer.owner) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.by_owner.remove(&timer.owner);
+            }
+        }
+        Some(timer)
+    }
+
+    /// Deliver every timer due at `now`, rescheduling intervals.
+    /// Returns the next deadline, if any timer is left.
+    fn fire_due(&mut self, now: Instant) -> Option<Instant> {
+        while let Some(&(deadline, id)) = self.queue.first() {
+            if deadline > now {
+                return Some(deadline);
+            }
+            self.queue.pop_first();
+            let Some(timer) = self.timers.get_mut(&id) else {
+                continue;
+            };
+            let delivered = timer.target.send(timer.message.clone()).is_ok();
+            match timer.interval {
+                Some(period) if delivered => {
+                    // Don't try to catch up on missed ticks
+                    timer.deadline = (deadline + period).max(now);
+                    self.queue.insert((timer.deadline, id));
+                }
+                _ => {
+                    self.remove(id);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The timer service of one VM.
+#[derive(Default)]
+pub struct TimerService {
+    state: Arc<Mutex<TimerQueue>>,
+    /// Wakes the driver when an earlier deadline was scheduled
+    wakeup: Arc<Notify>,
+    /// Driver task, restarted if its runtime went away
+    driver: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl TimerService {
+    /// Create an empty timer service. The driver starts with the first timer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule `message` to be sent to `target` after `delay`, and then every
+    /// `interval` if given. `spawn` starts the driver task on the process runtime.
+    /// Returns the timer id.
+    pub fn schedule<S>(
+        &self,
+        owner: Pid,
+        target: MailboxSender,
+        message: ThreadSafeValue,
+        delay: Duration,
+        interval: Option<Duration>,
+        spawn: S,
+    ) -> u64
+    where
+        S: FnOnce(std::pin::Pin<Box<dyn Future<Output = ()> + Send>>) -> JoinHandle<()>,
+    {
+        self.ensure_driver(spawn);
+
+        let deadline = Instant::now() + delay;
+        let (id, earliest) = {
+            let mut state = self.state.lock();
+            state.next_id += 1;
+            let id = state.next_id;
+            state.timers.insert(id, Timer { owner, deadline, interval, target, message });
+            state.queue.insert((deadline, id));
+            state.by_owner.entry(owner).or_default().insert(id);
+            let earliest = state.queue.first().map(|&(_, first)| first) == Some(id);
+            (id, earliest)
+        };
+        if earliest {
+            self.wakeup.notify_one();
+        }
+        id
+    }
+
+    /// Cancel a pending timer. Returns false if it already fired or was cancelled.
+    pub fn cancel(&self, id: u64) -> bool {
+        self.state.lock().remove(id).is_some()
+    }
+
+    /// Cancel all timers owned by `owner` (called when the owner exits).
+    pub fn cancel_owned_by(&self, owner: Pid) {
+        let mut state = self.state.lock();
+        if let Some(ids) = state.by_owner.remove(&owner) {
+            for id in ids {
+                if let Some(timer) = state.timers.remove(&id) {
+                    state.queue.remove(&(timer.deadline, id));
+                }
+            }
+        }
+    }
+
+    /// Number of pending timers.
+    pub fn pending(&self) -> usize {
+        self.state.lock().timers.len()
+    }
+
+    fn ensure_driver<S>(&self, spawn: S)
+    where
+        S: FnOnce(std::pin::Pin<Box<dyn Future<Output = ()> + Send>>) -> JoinHandle<()>,
+    {
+        let mut driver = self.driver.lock();
+        if driver.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return;
+        }
+        let state = self.state.clone();
+        let wakeup = self.wakeup.clone();
+        *driver = Some(spawn(Box::pin(run_driver(state, wakeup))));
+    }
+}
+
+/// Driver loop: sleep until the earliest deadline (or until woken by an
+/// earlier one), then deliver everything that is due.
+async fn run_driver(state: Arc<Mutex<TimerQueue>>, wakeup: Arc<Notify>) {
+    loop {
+        let next = state.lock().fire_due(Instant::now());
+        match next {
+            Some(deadline) => {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {}
+                    _ = wakeup.notified() => {}
+                }
+            }
+            None => wakeup.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    fn spawn_local(task: std::pin::Pin<Box<dyn Future<Output = ()> + Send>>) -> JoinHandle<()> {
+        tokio::spawn(task)
+    }
+
+    #[test]
+    fn test_timers_fire_in_deadline_order() {
+        runtime().block_on(async {
+            let service = TimerService::new();
+            let (tx, mut rx) = crate::mailbox::unbounded();
+            service.schedule(Pid(1), tx.clone(), ThreadSafeValue::Int64(2), Duration::from_millis(20), None, spawn_local);
+            service.schedule(Pid(1), tx, ThreadSafeValue::Int64(1), Duration::from_millis(5), None, spawn_local);
+
+            assert!(matches!(rx.recv().await, Some(ThreadSafeValue::Int64(1))));
+            assert!(matches!(rx.recv().await, Some(ThreadSafeValue::Int64(2))));
+            assert_eq!(service.pending(), 0);
+        });
+    }
+
+    #[test]
+    fn test_interval_repeats_until_cancelled() {
+        runtime().block_on(async {
+            let service = TimerService::new();
+            let (tx, mut rx) = crate::mailbox::unbounded();
+            let id = service.schedule(
+                Pid(1), tx, ThreadSafeValue::Unit,
+                Duration::from_millis(1), Some(Duration::from_millis(1)), spawn_local,
+            );
+            for _ in 0..3 {
+                assert!(rx.recv().await.is_some());
+            }
+            assert!(service.cancel(id));
+            assert!(!service.cancel(id));
+            assert_eq!(service.pending(), 0);
+        });
+    }
+
+    #[test]
+    fn test_owner_exit_cancels_timers() {
+        runtime().block_on(async {
+            let service = TimerService::new();
+            let (tx, _rx) = crate::mailbox::unbounded();
+            for _ in 0..1000 {
+                service.schedule(Pid(7), tx.clone(), ThreadSafeValue::Unit, Duration::from_secs(60), None, spawn_local);
+            }
+            service.schedule(Pid(8), tx, ThreadSafeValue::Unit, Duration::from_secs(60), None, spawn_local);
+            service.cancel_owned_by(Pid(7));
+            assert_eq!(service.pending(), 1);
+        });
+    }
+}
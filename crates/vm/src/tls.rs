@@ -0,0 +1,172 @@
+//! TLS for the HTTP server, TCP sockets and, through the server, WebSockets.
+//!
+//! `Server.bindTls`, `Tcp.listenTls` and `Tcp.connectTls` use rustls with the
+//! ring provider, so no system TLS library or reverse proxy is involved.
+//! Servers load a PEM certificate chain and private key. Clients verify the
+//! server against the bundled webpki roots, or only against the certificates
+//! of a `ca` PEM file, which is how a self-signed certificate is trusted.
+
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// Certificate chain and private key of a TLS server, as PEM file paths.
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+/// Options of `Tcp.connectTls`, given as `[(name, value)]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientOptions {
+    /// PEM file with the only certificates to trust (`("ca", path)`)
+    pub ca_path: Option<String>,
+    /// Name to verify the server certificate for, instead of the host
+    /// (`("serverName", name)`)
+    pub server_name: Option<String>,
+}
+
+impl ClientOptions {
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Result<Self, String> {
+        let mut options = ClientOptions::default();
+        for (name, value) in pairs {
+            match name.as_str() {
+                "ca" => options.ca_path = Some(value),
+                "serverName" => options.server_name = Some(value),
+                _ => return Err(format!("unknown TLS option '{}' (expected ca or serverName)", name)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// A TCP socket, plain or TLS.
+pub trait TcpIo: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> TcpIo for T {}
+
+pub type TcpConn = Box<dyn TcpIo>;
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("can't read certificates from {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("no certificates in {}", path));
+    }
+    Ok(certs)
+}
+
+/// Server configuration from PEM files. `alpn` lists the protocols offered
+/// to clients, most preferred first.
+pub fn server_config(files: &TlsFiles, alpn: &[&[u8]]) -> Result<Arc<ServerConfig>, String> {
+    let certs = load_certs(&files.cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(&files.key_path)
+        .map_err(|e| format!("can't read private key from {}: {}", files.key_path, e))?;
+    let mut config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("invalid certificate or key: {}", e))?;
+    config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+    Ok(Arc::new(config))
+}
+
+/// Acceptor for raw TLS sockets (`Tcp.listenTls`).
+pub fn acceptor(files: &TlsFiles) -> Result<TlsAcceptor, String> {
+    server_config(files, &[]).map(TlsAcceptor::from)
+}
+
+fn client_config(options: &ClientOptions) -> Result<Arc<ClientConfig>, String> {
+    let mut roots = RootCertStore::empty();
+    match &options.ca_path {
+        Some(path) => {
+            for cert in load_certs(path)? {
+                roots.add(cert).map_err(|e| format!("invalid certificate in {}: {}", path, e))?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Connect to `host:port` and complete the TLS handshake.
+pub async fn connect(host: &str, port: u16, options: &ClientOptions) -> Result<TcpConn, String> {
+    let config = client_config(options)?;
+    let name = options.server_name.as_deref().unwrap_or(host);
+    let server_name = ServerName::try_from(name.to_string())
+        .map_err(|_| format!("invalid server name '{}'", name))?;
+    let stream = TcpStream::connect((host, port)).await.map_err(|e| e.to_string())?;
+    let stream = TlsConnector::from(config)
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| format!("handshake with {}:{} failed: {}", host, port, e))?;
+    Ok(Box::new(stream))
+}
+
+/// Serve `app` over HTTPS (HTTP/1.1, with upgrades for `wss://`).
+/// A client whose handshake fails only loses its own connection.
+pub async fn serve_https(listener: TcpListener, acceptor: TlsAcceptor, app: axum::Router) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_) => {
+                // Out of file descriptors and the like: back off instead of spinning
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let Ok(stream) = acceptor.accept(stream).await else {
+                return;
+            };
+            let service = hyper_util::service::TowerToHyperService::new(app);
+            let _ = hyper::server::conn::http1::Builder::new()
+                .serve_connection(hyper_util::rt::TokioIo::new(stream), service)
+                .with_upgrades()
+                .await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_options_from_pairs() {
+        let options = ClientOptions::from_pairs(vec![
+            ("ca".to_string(), "ca.pem".to_string()),
+            ("serverName".to_string(), "example.com".to_string()),
+        ]).unwrap();
+        assert_eq!(Some("ca.pem".to_string()), options.ca_path);
+        assert_eq!(Some("example.com".to_string()), options.server_name);
+        assert_eq!(ClientOptions::default(), ClientOptions::from_pairs(vec![]).unwrap());
+        assert!(ClientOptions::from_pairs(vec![("verify".to_string(), "no".to_string())]).is_err());
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let files = TlsFiles { cert_path: "/nonexistent/cert.pem".to_string(), key_path: "/nonexistent/key.pem".to_string() };
+        let err = server_config(&files, &[]).err().unwrap();
+        assert!(err.contains("/nonexistent/cert.pem"), "{}", err);
+    }
+}
@@ -0,0 +1,329 @@
+//! Process tracing.
+//!
+//! `Trace.start(pid, flags)` makes the VM report what a process does to a
+//! tracer: the process that started the trace, or a channel read by the TUI
+//! and `nostos connect`. Every process owns a [`TraceCell`] holding the flags
+//! it is traced for; the hooks on the send, receive, call, return, spawn and
+//! exit paths check one flag with a relaxed load, so an untraced process pays
+//! nothing else. While calls or returns are traced, the process runs in the
+//! interpreter only, the same as under the debugger, so every call is seen.
+//!
+//! A tracer process receives each event as a message
+//! `("TRACE", pid, kind, peer, detail)`:
+//!
+//! | kind        | peer                | detail                    |
+//! |-------------|---------------------|---------------------------|
+//! | `"send"`    | receiver            | the message               |
+//! | `"receive"` | the traced process  | the message               |
+//! | `"call"`    | the traced process  | `name(arg, ...)`          |
+//! | `"return"`  | the traced process  | `name -> value`           |
+//! | `"spawn"`   | the new process     | the function it runs      |
+//! | `"exit"`    | the traced process  | the exit reason           |
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::mailbox::MailboxSender;
+use crate::process::ThreadSafeValue;
+use crate::value::Pid;
+
+/// Something a traced process did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceKind {
+    Send,
+    Receive,
+    Call,
+    Return,
+    Spawn,
+    Exit,
+}
+
+impl TraceKind {
+    const ALL: [TraceKind; 6] = [
+        TraceKind::Send,
+        TraceKind::Receive,
+        TraceKind::Call,
+        TraceKind::Return,
+        TraceKind::Spawn,
+        TraceKind::Exit,
+    ];
+
+    /// Flag name, also the `kind` of trace messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            TraceKind::Send => "send",
+            TraceKind::Receive => "receive",
+            TraceKind::Call => "call",
+            TraceKind::Return => "return",
+            TraceKind::Spawn => "spawn",
+            TraceKind::Exit => "exit",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The set of events a process is traced for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TraceFlags(u8);
+
+impl TraceFlags {
+    pub const NONE: TraceFlags = TraceFlags(0);
+
+    /// Parse flag names (`"send"`, `"receive"`, `"call"`, `"return"`,
+    /// `"spawn"`, `"exit"`, or `"all"`).
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut flags = TraceFlags::NONE;
+        for name in names {
+            if name == "all" {
+                flags = TraceKind::ALL.iter().fold(flags, |flags, kind| flags.with(*kind));
+                continue;
+            }
+            let kind = TraceKind::ALL.iter().find(|kind| kind.name() == name).ok_or_else(|| format!(
+                "unknown trace flag '{}' (expected send, receive, call, return, spawn, exit or all)", name
+            ))?;
+            flags = flags.with(*kind);
+        }
+        Ok(flags)
+    }
+
+    pub fn with(self, kind: TraceKind) -> TraceFlags {
+        TraceFlags(self.0 | kind.bit())
+    }
+
+    pub fn contains(self, kind: TraceKind) -> bool {
+        self.0 & kind.bit() != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Flag names, for display.
+    pub fn names(self) -> Vec<&'static str> {
+        TraceKind::ALL.iter().filter(|kind| self.contains(**kind)).map(|kind| kind.name()).collect()
+    }
+}
+
+/// One trace event.
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    /// The traced process
+    pub pid: Pid,
+    pub kind: TraceKind,
+    /// The other process involved (see the module docs)
+    pub peer: Pid,
+    /// The message, call, returned value, function or exit reason, formatted
+    pub detail: String,
+}
+
+impl TraceEvent {
+    /// The message a tracer process receives.
+    pub fn to_message(&self) -> ThreadSafeValue {
+        ThreadSafeValue::Tuple(vec![
+            ThreadSafeValue::String("TRACE".to_string()),
+            ThreadSafeValue::Pid(self.pid.0),
+            ThreadSafeValue::String(self.kind.name().to_string()),
+            ThreadSafeValue::Pid(self.peer.0),
+            ThreadSafeValue::String(self.detail.clone()),
+        ])
+    }
+
+    /// One line for trace views: `<5> send to <7>: "ping"`.
+    pub fn describe(&self) -> String {
+        match self.kind {
+            TraceKind::Send => format!("<{}> send to <{}>: {}", self.pid, self.peer, self.detail),
+            TraceKind::Spawn => format!("<{}> spawn <{}>: {}", self.pid, self.peer, self.detail),
+            kind => format!("<{}> {} {}", self.pid, kind.name(), self.detail),
+        }
+    }
+}
+
+/// Function name without its signature: `counter.loop` for `counter.loop/Int`.
+pub fn function_name(name: &str) -> &str {
+    name.split('/').next().unwrap_or(name)
+}
+
+/// Sending end of a channel tracer.
+pub type TraceSender = crossbeam::channel::Sender<TraceEvent>;
+
+/// Receiving end of a channel tracer.
+pub type TraceReceiver = crossbeam::channel::Receiver<TraceEvent>;
+
+/// A channel for [`Tracer::Channel`].
+pub fn channel() -> (TraceSender, TraceReceiver) {
+    crossbeam::channel::unbounded()
+}
+
+/// Where the events of a traced process go.
+#[derive(Clone)]
+pub enum Tracer {
+    /// The mailbox of a tracer process
+    Process(Pid, MailboxSender),
+    /// A channel read outside the VM (the TUI trace panel, `nostos connect`)
+    Channel(TraceSender),
+}
+
+impl Tracer {
+    /// Deliver an event. Returns false once the tracer is gone.
+    fn deliver(&self, event: TraceEvent) -> bool {
+        match self {
+            Tracer::Process(_, mailbox) => mailbox.send(event.to_message()).is_ok(),
+            Tracer::Channel(sender) => sender.send(event).is_ok(),
+        }
+    }
+}
+
+/// Trace settings of one process, shared between the process and the
+/// processes that start or stop tracing it.
+#[derive(Default)]
+pub struct TraceCell {
+    flags: AtomicU8,
+    tracer: Mutex<Option<Tracer>>,
+}
+
+impl TraceCell {
+    /// True if `kind` events are traced. This is the check on the hot paths.
+    #[inline]
+    pub fn traces(&self, kind: TraceKind) -> bool {
+        self.flags.load(Ordering::Relaxed) & kind.bit() != 0
+    }
+
+    /// True if calls or returns are traced: native code, which doesn't run
+    /// the hooks, is skipped meanwhile.
+    #[inline]
+    pub fn traces_calls(&self) -> bool {
+        self.flags.load(Ordering::Relaxed) & (TraceKind::Call.bit() | TraceKind::Return.bit()) != 0
+    }
+
+    pub fn flags(&self) -> TraceFlags {
+        TraceFlags(self.flags.load(Ordering::Relaxed))
+    }
+
+    /// Report an event to the tracer. Tracing stops if the tracer is gone.
+    pub fn emit(&self, event: TraceEvent) {
+        let mut tracer = self.tracer.lock();
+        let delivered = tracer.as_ref().is_some_and(|t| t.deliver(event));
+        if !delivered {
+            *tracer = None;
+            self.flags.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn start(&self, tracer: Tracer, flags: TraceFlags) {
+        let mut current = self.tracer.lock();
+        *current = Some(tracer);
+        self.flags.store(flags.0, Ordering::Relaxed);
+    }
+
+    fn stop(&self) {
+        let mut current = self.tracer.lock();
+        *current = None;
+        self.flags.store(0, Ordering::Relaxed);
+    }
+
+    fn traced_by(&self, pid: Pid) -> bool {
+        matches!(&*self.tracer.lock(), Some(Tracer::Process(tracer, _)) if *tracer == pid)
+    }
+}
+
+/// Trace cells of the live processes.
+#[derive(Default)]
+pub struct Traces {
+    cells: Mutex<HashMap<Pid, Arc<TraceCell>>>,
+}
+
+impl Traces {
+    /// The trace cell of a process, created (untraced) on first use.
+    pub fn cell(&self, pid: Pid) -> Arc<TraceCell> {
+        self.cells.lock().entry(pid).or_default().clone()
+    }
+
+    /// Trace `pid` for `flags`, replacing any previous tracer.
+    /// Returns false if the process isn't alive.
+    pub fn start(&self, pid: Pid, tracer: Tracer, flags: TraceFlags) -> bool {
+        match self.cells.lock().get(&pid) {
+            Some(cell) => {
+                cell.start(tracer, flags);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop tracing `pid`. Returns false if the process isn't alive.
+    pub fn stop(&self, pid: Pid) -> bool {
+        match self.cells.lock().get(&pid) {
+            Some(cell) => {
+                cell.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget an exited process. Processes it was tracing stop being traced.
+    pub fn remove(&self, pid: Pid) -> Option<Arc<TraceCell>> {
+        let mut cells = self.cells.lock();
+        for cell in cells.values() {
+            if cell.traced_by(pid) {
+                cell.stop();
+            }
+        }
+        cells.remove(&pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_parse_names_and_all() {
+        let flags = TraceFlags::from_names(["send", "return"]).unwrap();
+        assert!(flags.contains(TraceKind::Send));
+        assert!(flags.contains(TraceKind::Return));
+        assert!(!flags.contains(TraceKind::Call));
+        assert_eq!(vec!["send", "return"], flags.names());
+
+        let all = TraceFlags::from_names(["all"]).unwrap();
+        assert_eq!(6, all.names().len());
+        assert!(TraceFlags::from_names(["sned"]).is_err());
+        assert!(TraceFlags::from_names([]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn channel_tracer_gets_events_until_stopped() {
+        let traces = Traces::default();
+        let pid = Pid(7);
+        let cell = traces.cell(pid);
+        let (sender, receiver) = channel();
+        assert!(!traces.start(Pid(8), Tracer::Channel(sender.clone()), TraceFlags::NONE));
+        assert!(traces.start(pid, Tracer::Channel(sender), TraceFlags::from_names(["send"]).unwrap()));
+        assert!(cell.traces(TraceKind::Send));
+        assert!(!cell.traces(TraceKind::Receive));
+
+        cell.emit(TraceEvent { pid, kind: TraceKind::Send, peer: Pid(9), detail: "1".to_string() });
+        let event = receiver.try_recv().unwrap();
+        assert_eq!("<7> send to <9>: 1", event.describe());
+
+        assert!(traces.stop(pid));
+        assert!(!cell.traces(TraceKind::Send));
+    }
+
+    #[test]
+    fn tracing_stops_when_the_tracer_is_gone() {
+        let traces = Traces::default();
+        let cell = traces.cell(Pid(1));
+        let (sender, receiver) = channel();
+        traces.start(Pid(1), Tracer::Channel(sender), TraceFlags::from_names(["all"]).unwrap());
+        drop(receiver);
+        cell.emit(TraceEvent { pid: Pid(1), kind: TraceKind::Exit, peer: Pid(1), detail: "normal".to_string() });
+        assert!(cell.flags().is_empty());
+    }
+}
@@ -12,7 +12,7 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicPtr, AtomicU8, AtomicU32, Ordering};
 
 use nostos_extension::GcNativeHandle;
 use serde::{Serialize, Deserialize};
@@ -1013,6 +1013,13 @@ pub struct FunctionValue {
     pub jit_code: Option<JitFunction>,
     /// Call counter for JIT hot detection (thread-safe for multi-CPU execution)
     pub call_count: AtomicU32,
+    /// Baseline JIT entry point, installed once the function got hot
+    pub jit_entry: JitEntry,
+    /// Optimizing JIT entry point (entered at ip 0 only), installed once the
+    /// function's type profile is stable
+    pub opt_entry: JitEntry,
+    /// Argument and result kinds observed while running
+    pub type_profile: TypeProfile,
     /// Debug symbols: local variable names and their registers
     pub debug_symbols: Vec<LocalVarSymbol>,
 
@@ -1047,6 +1054,9 @@ impl FunctionValue {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
             call_count: AtomicU32::new(0),
             debug_symbols: vec![],
             // REPL introspection fields - default to None/empty for simple construction
@@ -1072,6 +1082,10 @@ impl Clone for FunctionValue {
             source_span: self.source_span,
             jit_code: self.jit_code.clone(),
             call_count: AtomicU32::new(self.call_count.load(std::sync::atomic::Ordering::Relaxed)),
+            // Clones may get new code, so they are compiled on their own
+            jit_entry: JitEntry::default(),
+            opt_entry: JitEntry::default(),
+            type_profile: TypeProfile::default(),
             debug_symbols: self.debug_symbols.clone(),
             // REPL introspection fields
             source_code: self.source_code.clone(),
@@ -1118,9 +1132,49 @@ pub struct NativeFn {
 }
 
 /// Process ID for concurrency.
+///
+/// The high 16 bits hold the node the process runs on: 0 for this VM, or the
+/// index of a connected node (see `crate::node`). The low bits are the number
+/// of the process on its node.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Pid(pub u64);
 
+impl Pid {
+    const NODE_SHIFT: u32 = 48;
+    const ID_MASK: u64 = (1 << Self::NODE_SHIFT) - 1;
+
+    /// The pid of process `id` on node `node`.
+    pub fn on_node(node: u16, id: u64) -> Pid {
+        Pid(((node as u64) << Self::NODE_SHIFT) | (id & Self::ID_MASK))
+    }
+
+    /// The node this process runs on (0 for this VM).
+    pub fn node(self) -> u16 {
+        (self.0 >> Self::NODE_SHIFT) as u16
+    }
+
+    /// The number of this process on its node.
+    pub fn id(self) -> u64 {
+        self.0 & Self::ID_MASK
+    }
+
+    /// True if the process runs on this VM.
+    pub fn is_local(self) -> bool {
+        self.node() == 0
+    }
+}
+
+impl fmt::Display for Pid {
+    /// `5` for local processes, `node.5` for processes on other nodes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_local() {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "{}.{}", self.node(), self.id())
+        }
+    }
+}
+
 /// Reference ID for monitors.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct RefId(pub u64);
@@ -1183,6 +1237,103 @@ pub struct JitFunction {
 unsafe impl Send for JitFunction {}
 unsafe impl Sync for JitFunction {}
 
+/// Native code of a function compiled by the baseline JIT tier.
+/// Null until the compiler thread installs it; see `jit_runtime`.
+#[derive(Default)]
+pub struct JitEntry(AtomicPtr<u8>);
+
+impl JitEntry {
+    /// The installed entry point, if any.
+    #[inline]
+    pub fn get(&self) -> Option<crate::jit_runtime::JitEntryFn> {
+        let ptr = self.0.load(Ordering::Acquire);
+        if ptr.is_null() {
+            None
+        } else {
+            // SAFETY: only `set` stores non-null pointers, which must be JitEntryFn code
+            Some(unsafe { std::mem::transmute::<*mut u8, crate::jit_runtime::JitEntryFn>(ptr) })
+        }
+    }
+
+    /// Whether native code has been installed.
+    pub fn is_set(&self) -> bool {
+        !self.0.load(Ordering::Acquire).is_null()
+    }
+
+    /// Install native code.
+    ///
+    /// # Safety
+    /// `code` must point to a function with the `JitEntryFn` signature that
+    /// stays valid for as long as this function can be called.
+    pub unsafe fn set(&self, code: *const u8) {
+        self.0.store(code as *mut u8, Ordering::Release);
+    }
+
+    /// Stop using the installed code (the code itself stays valid).
+    pub fn clear(&self) {
+        self.0.store(std::ptr::null_mut(), Ordering::Release);
+    }
+}
+
+/// Kinds of values a function was called with and returned, as bit sets of
+/// `TypeProfile::INT`, `FLOAT`, `BOOL` and `OTHER`. The optimizing JIT tier
+/// specializes functions whose arguments and results have a single kind.
+#[derive(Default)]
+pub struct TypeProfile {
+    args: [AtomicU8; TypeProfile::MAX_ARGS],
+    returns: AtomicU8,
+    /// Number of times optimized code was left because an argument guard failed
+    pub deopts: AtomicU32,
+    /// Loop back-edges taken, counted until the function is hot enough for
+    /// on-stack replacement into the optimizing tier
+    pub backedges: AtomicU32,
+}
+
+impl TypeProfile {
+    /// `Int64`
+    pub const INT: u8 = 1;
+    /// `Float64`
+    pub const FLOAT: u8 = 2;
+    /// `Bool`
+    pub const BOOL: u8 = 4;
+    /// Anything else
+    pub const OTHER: u8 = 8;
+    /// Arguments beyond this many are not profiled.
+    pub const MAX_ARGS: usize = 8;
+
+    /// Record the kind of argument `index`.
+    #[inline]
+    pub fn record_arg(&self, index: usize, kind: u8) {
+        if let Some(slot) = self.args.get(index) {
+            Self::record(slot, kind);
+        }
+    }
+
+    /// Record the kind of a returned value.
+    #[inline]
+    pub fn record_return(&self, kind: u8) {
+        Self::record(&self.returns, kind);
+    }
+
+    #[inline]
+    fn record(slot: &AtomicU8, kind: u8) {
+        // Only write when something new shows up, so the steady state is read-only
+        if slot.load(Ordering::Relaxed) & kind != kind {
+            slot.fetch_or(kind, Ordering::Relaxed);
+        }
+    }
+
+    /// Kinds seen for argument `index` (0 if never recorded).
+    pub fn arg(&self, index: usize) -> u8 {
+        self.args.get(index).map_or(0, |slot| slot.load(Ordering::Relaxed))
+    }
+
+    /// Kinds of the values returned so far.
+    pub fn returns(&self) -> u8 {
+        self.returns.load(Ordering::Relaxed)
+    }
+}
+
 /// Runtime errors.
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum RuntimeError {
@@ -1237,6 +1388,11 @@ pub enum RuntimeError {
     #[error("Interrupted (Ctrl+C)")]
     Interrupted,
 
+    /// Raised by the runtime when a process exceeds a limit; it ends the
+    /// process and is never delivered to try/catch.
+    #[error("Process exceeded its {} limit", .0.name())]
+    LimitExceeded(crate::process::ProcessLimit),
+
     #[error("{error}\n\nStack trace:\n{stack_trace}")]
     WithStackTrace {
         error: Box<RuntimeError>,
@@ -1308,6 +1464,9 @@ impl RuntimeError {
             RuntimeError::Interrupted => {
                 ("Interrupted", "execution interrupted (Ctrl+C)".to_string())
             }
+            RuntimeError::LimitExceeded(limit) => {
+                ("LimitExceeded", format!("process exceeded its {} limit", limit.name()))
+            }
             RuntimeError::WithStackTrace { error, .. } => {
                 // Delegate to the inner error
                 error.to_exception_info()
@@ -1377,6 +1536,9 @@ impl RuntimeError {
             RuntimeError::Interrupted => {
                 ("Interrupted", "execution interrupted (Ctrl+C)".to_string())
             }
+            RuntimeError::LimitExceeded(limit) => {
+                ("LimitExceeded", format!("process exceeded its {} limit", limit.name()))
+            }
         };
 
         // Create a record-like structure: Error{type: "...", message: "..."}
@@ -1623,6 +1785,9 @@ pub enum Instruction {
     CallExtension(Reg, ConstIdx, RegList),
     /// Call extension function by index (fast path - no string lookup)
     CallExtensionIdx(Reg, u16, RegList),
+    /// Call C function from an `extern` declaration: dst = extern_fn(args...)
+    /// The constant holds the serialized `ffi::ExternSignature` descriptor.
+    CallExtern(Reg, ConstIdx, RegList),
     /// Return value from function
     Return(Reg),
 
@@ -1684,14 +1849,24 @@ pub enum Instruction {
     SpawnLink(Reg, Reg, RegList),
     /// Spawn monitored: (pid, ref) = spawn_monitor(func, args)
     SpawnMonitor(Reg, Reg, Reg, RegList),
+    /// Spawn with limits: dst = Process.spawnLimited(func, maxHeapBytes, maxMailbox, linkage)
+    SpawnLimited(Reg, Reg, Reg, Reg, Reg),
+    /// Spawn with a priority: dst = Process.spawnPriority(func, priority)
+    SpawnPriority(Reg, Reg, Reg),
+    /// Spawn with a bounded mailbox: dst = Process.spawnBounded(func, capacity, policy)
+    SpawnBounded(Reg, Reg, Reg, Reg),
     /// Send message: pid <- msg
     Send(Reg, Reg),
+    /// Send unless the mailbox is full: dst = trySend(pid, msg)
+    TrySend(Reg, Reg, Reg),
     /// Get self PID: dst = self()
     SelfPid(Reg),
     /// Force garbage collection: Gc.collect() -> { collected: Int, live: Int }
     GcCollect(Reg),
-    /// Get GC statistics: Gc.stats() -> { live: Int, total_allocated: Int, total_freed: Int, collections: Int }
+    /// Get GC statistics: Gc.stats() -> { live: Int, total_allocated: Int, total_freed: Int, collections: Int, minor_collections: Int, major_collections: Int }
     GcStats(Reg),
+    /// Write a heap snapshot of a process: dst = Gc.snapshot(pid, path) -> objects written
+    GcSnapshot(Reg, Reg, Reg),
     /// Receive message into dst register (handled specially by VM - switches to receive mode)
     Receive(Reg),
     /// Receive with timeout: dst = receive or Unit if timeout (timeout_ms in timeout_reg)
@@ -1714,6 +1889,89 @@ pub enum Instruction {
     ProcessInfo(Reg, Reg),
     /// Kill a process: Process.kill(pid)
     ProcessKill(Reg, Reg),
+    /// Change the priority of a process: dst = Process.setPriority(pid, priority)
+    ProcessSetPriority(Reg, Reg, Reg),
+    /// Register a name for a process: dst = Process.register(name, pid)
+    ProcessRegister(Reg, Reg, Reg),
+    /// Look up a registered name: dst = Process.whereis(name) -> Option[Pid]
+    ProcessWhereis(Reg, Reg),
+    /// Remove a registered name: dst = Process.unregister(name)
+    ProcessUnregister(Reg, Reg),
+    /// List registered names: dst = Process.registered()
+    ProcessRegistered(Reg),
+    /// Send to a registered name: dst = Process.send(name, msg)
+    ProcessSendNamed(Reg, Reg, Reg),
+
+    // === Distributed nodes ===
+    /// Name of this node: dst = Node.self()
+    NodeSelf(Reg),
+    /// Connect to a node: dst = Node.connect(name) -> Result[(), String]
+    NodeConnect(Reg, Reg),
+    /// Close the connection to a node: dst = Node.disconnect(name)
+    NodeDisconnect(Reg, Reg),
+    /// Names of the connected nodes: dst = Node.list()
+    NodeList(Reg),
+    /// Node a process runs on: dst = Node.of(pid)
+    NodeOf(Reg, Reg),
+    /// Spawn on another node: dst = Node.spawnOn(node, func, linkage)
+    NodeSpawn(Reg, Reg, Reg, Reg),
+    /// Send to a registered name on another node: dst = Node.send(node, name, msg)
+    NodeSend(Reg, Reg, Reg, Reg),
+
+    // === Shared tables ===
+    /// Create a table owned by the caller: dst = Table.new(name, options)
+    TableNew(Reg, Reg, Reg),
+    /// Store a value: dst = Table.insert(name, key, value)
+    TableInsert(Reg, Reg, Reg, Reg),
+    /// Values under a key: dst = Table.lookup(name, key)
+    TableLookup(Reg, Reg, Reg),
+    /// Remove a key: dst = Table.delete(name, key)
+    TableDelete(Reg, Reg, Reg),
+    /// Entries whose key matches a pattern: dst = Table.matchKeys(name, pattern)
+    TableMatch(Reg, Reg, Reg),
+    /// Number of keys: dst = Table.size(name)
+    TableSize(Reg, Reg),
+    /// Delete a table: dst = Table.drop(name)
+    TableDrop(Reg, Reg),
+    /// Start of Table.update: (value, version) = value of key or default
+    TableRead(Reg, Reg, Reg, Reg, Reg),
+    /// End of Table.update: dst = stored (false if written since the read)
+    TableCommit(Reg, Reg, Reg, Reg, Reg),
+
+    // === Hot code upgrade ===
+    /// Recompile and install a module: dst = Code.reload(module)
+    CodeReload(Reg, Reg),
+    /// Times a module was reloaded: dst = Code.version(module)
+    CodeVersion(Reg, Reg),
+    /// Before a fully-qualified self-call to function func_idx: if the running code has
+    /// been replaced, pass the state register through the module's codeChange
+    CodeChange(Reg, u16),
+
+    // === Process tracing ===
+    /// Trace a process for flags (list of names) to the caller: dst = Trace.start(pid, flags)
+    TraceStart(Reg, Reg, Reg),
+    /// Stop tracing a process: dst = Trace.stop(pid)
+    TraceStop(Reg, Reg),
+
+    // === Timers ===
+    /// One-shot timer: dst = Timer.sendAfter(ms, pid, msg)
+    TimerSendAfter(Reg, Reg, Reg, Reg),
+    /// Periodic timer: dst = Timer.interval(ms, pid, msg)
+    TimerInterval(Reg, Reg, Reg, Reg),
+    /// Cancel a timer: dst = Timer.cancel(ref)
+    TimerCancel(Reg, Reg),
+
+    // === Supervision ===
+    /// Start a supervisor: dst = Supervisor.start(strategy, maxRestarts, maxSeconds)
+    SupervisorStart(Reg, Reg, Reg, Reg),
+    /// Start a supervised child: dst = Supervisor.startChild(sup, id, func, restart)
+    SupervisorStartChild(Reg, Reg, Reg, Reg, Reg),
+    /// Stop and remove a supervised child: dst = Supervisor.terminateChild(sup, id)
+    SupervisorTerminateChild(Reg, Reg, Reg),
+    /// List supervised children: dst = Supervisor.whichChildren(sup)
+    SupervisorWhichChildren(Reg, Reg),
+    /// Stop a supervisor and its children: dst = Supervisor.stop(sup)
+    SupervisorStop(Reg, Reg),
 
     // === External process execution ===
     /// Run command and wait: dst = Exec.run(cmd, args)
@@ -1918,6 +2176,8 @@ pub enum Instruction {
     /// Bind and start HTTP server: dst = Server.bind(port)
     /// Returns (status, server_handle)
     ServerBind(Reg, Reg),
+    /// Bind and start HTTPS server: dst = Server.bindTls(port, cert_path, key_path)
+    ServerBindTls(Reg, Reg, Reg, Reg),
     /// Accept next HTTP request: dst = Server.accept(server_handle)
     /// Returns (status, HttpRequest{id, method, path, headers, body})
     /// Suspends process until request arrives
@@ -1950,6 +2210,10 @@ pub enum Instruction {
     TcpConnect(Reg, Reg, Reg),
     /// Listen on TCP port: dst = Tcp.listen(port)
     TcpListen(Reg, Reg),
+    /// Connect to TLS server: dst = Tcp.connectTls(host, port, opts)
+    TcpConnectTls(Reg, Reg, Reg, Reg),
+    /// Listen on TCP port with TLS: dst = Tcp.listenTls(port, cert_path, key_path)
+    TcpListenTls(Reg, Reg, Reg, Reg),
     /// Accept connection: dst = Tcp.accept(listener_handle)
     TcpAccept(Reg, Reg),
     /// Read from socket: dst = Tcp.read(socket_handle, max_bytes)
@@ -2154,6 +2418,55 @@ pub enum Instruction {
 unsafe impl Send for Instruction {}
 unsafe impl Sync for Instruction {}
 
+impl Instruction {
+    /// Whether executing this instruction may suspend the process (message
+    /// receive, I/O, sleeping, locking an mvar, ...). Native code produced by
+    /// the baseline JIT hands these back to the async interpreter loop.
+    pub fn may_suspend(&self) -> bool {
+        use Instruction::*;
+        matches!(
+            self,
+            CodeReload(..) |
+            DirCreate(..) | DirCreateAll(..) | DirExists(..) | DirList(..) | DirRemove(..) |
+            DirRemoveAll(..) |
+            ExecKill(..) | ExecReadLine(..) | ExecReadStderr(..) | ExecRun(..) | ExecSpawn(..) |
+            ExecWait(..) | ExecWrite(..) |
+            FileAppend(..) | FileClose(..) | FileCopy(..) | FileExists(..) | FileFlush(..) |
+            FileOpen(..) | FileRead(..) | FileReadAll(..) | FileReadAllBytes(..) |
+            FileReadLine(..) | FileRemove(..) | FileRename(..) | FileSeek(..) | FileSize(..) |
+            FileWrite(..) | FileWriteAll(..) |
+            GcSnapshot(..) |
+            HttpDelete(..) | HttpGet(..) | HttpHead(..) | HttpPatch(..) | HttpPost(..) |
+            HttpPut(..) | HttpRequest(..) |
+            MvarLock(..) | MvarRead(..) | MvarUnlock(..) | MvarWrite(..) |
+            NodeConnect(..) | NodeDisconnect(..) | NodeSend(..) | NodeSpawn(..) |
+            PgAwaitNotification(..) | PgBegin(..) | PgClose(..) | PgCommit(..) | PgConnect(..) |
+            PgDeallocate(..) | PgExecute(..) | PgExecutePrepared(..) | PgListen(..) |
+            PgListenConnect(..) | PgNotify(..) | PgPrepare(..) | PgQuery(..) | PgQueryPrepared(..) |
+            PgRollback(..) | PgUnlisten(..) |
+            ProcessAlive(..) | ProcessAll(..) | ProcessInfo(..) | ProcessKill(..) | ProcessRegister(..) |
+            ProcessSendNamed(..) |
+            Receive(..) | ReceiveTimeout(..) |
+            SeleniumClick(..) | SeleniumClose(..) | SeleniumConnect(..) | SeleniumExecuteJs(..) |
+            SeleniumExecuteJsWithArgs(..) | SeleniumExists(..) | SeleniumGetAttribute(..) |
+            SeleniumGoto(..) | SeleniumSendKeys(..) | SeleniumText(..) | SeleniumWaitFor(..) |
+            Send(..) |
+            ServerAccept(..) | ServerBind(..) | ServerBindTls(..) | ServerClose(..) | ServerRespond(..) |
+            Sleep(..) |
+            Spawn(..) | SpawnBounded(..) | SpawnLimited(..) | SpawnLink(..) | SpawnMonitor(..) | SpawnPriority(..) |
+            SupervisorStart(..) | SupervisorStartChild(..) | SupervisorStop(..) |
+            SupervisorTerminateChild(..) | SupervisorWhichChildren(..) |
+            TcpAccept(..) | TcpClose(..) | TcpConnect(..) | TcpConnectTls(..) | TcpListen(..) | TcpListenTls(..) | TcpRead(..) |
+            TcpWrite(..) |
+            TimerInterval(..) | TimerSendAfter(..) |
+            TrySend(..) |
+            VmStats(..) |
+            WebSocketAccept(..) | WebSocketClose(..) | WebSocketConnect(..) | WebSocketReceive(..) |
+            WebSocketSend(..) | WebSocketSendShared(..) | WebSocketSplit(..)
+        )
+    }
+}
+
 impl Chunk {
     pub fn new() -> Self {
         Self::default()
@@ -2382,7 +2695,7 @@ impl fmt::Debug for Value {
             Value::Function(func) => write!(f, "<function {}>", func.name),
             Value::Closure(c) => write!(f, "<closure {}>", c.function.name),
             Value::NativeFunction(n) => write!(f, "<native {}>", n.name),
-            Value::Pid(p) => write!(f, "<pid {}>", p.0),
+            Value::Pid(p) => write!(f, "<pid {}>", p),
             Value::Ref(r) => write!(f, "<ref {}>", r.0),
             Value::Map(m) => write!(f, "%{{...{} entries}}", m.len()),
             Value::Set(s) => write!(f, "#{{...{} items}}", s.len()),
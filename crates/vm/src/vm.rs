@@ -1576,6 +1576,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1614,6 +1617,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1657,6 +1663,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1697,6 +1706,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1731,6 +1743,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1765,6 +1780,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1794,6 +1812,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1825,6 +1846,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1856,6 +1880,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1888,6 +1915,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1927,6 +1957,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1960,6 +1993,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -1995,6 +2031,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -2028,6 +2067,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -2056,6 +2098,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -2086,6 +2131,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
@@ -2117,6 +2165,9 @@ mod tests {
             module: None,
             source_span: None,
             jit_code: None,
+            jit_entry: Default::default(),
+            opt_entry: Default::default(),
+            type_profile: Default::default(),
         };
 
         vm.register_function(func);
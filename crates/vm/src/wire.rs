@@ -0,0 +1,856 @@
+//! Wire encoding of messages sent between nodes.
+//!
+//! A [`ThreadSafeValue`] is written as a tag byte followed by its contents.
+//! Integers are little-endian, strings and sequences are prefixed with their
+//! length as a `u32`. The encoding doesn't depend on the Rust layout of the
+//! values, so nodes built from different versions of the VM can talk to each
+//! other as long as [`WIRE_VERSION`] matches.
+//!
+//! Two kinds of values need help from the VM (a [`WireContext`]):
+//!
+//! - Pids are numbered per VM, so a pid travels as the name of its node and
+//!   its number on that node.
+//! - Functions and closures travel as the place their function is defined in
+//!   the program ([`FunctionRef`]); the receiving node must run the same program. Native functions can't be
+//!   sent.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::process::{ThreadSafeMapKey, ThreadSafeValue};
+use crate::shared_types::{SharedMap, SharedMapKey, SharedMapValue};
+use crate::value::FunctionValue;
+
+/// Version of the wire encoding, checked during the node handshake.
+pub const WIRE_VERSION: u32 = 1;
+
+/// Largest message accepted from another node.
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Where a function is defined: a function of the program's function list,
+/// or a lambda nested in it, found by following constant indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionRef {
+    /// Index in the function list
+    pub index: u32,
+    /// Name of that function, to detect a different program
+    pub name: String,
+    /// Constant indices leading from that function to a lambda
+    pub path: Vec<u32>,
+}
+
+/// Translates the VM-specific parts of a value to and from the wire.
+pub trait WireContext {
+    /// Node name and number on that node of a pid of this VM.
+    fn pid_to_wire(&self, pid: u64) -> (String, u64);
+    /// Pid of this VM for a process `id` on node `node`.
+    fn pid_from_wire(&self, node: &str, id: u64) -> u64;
+    /// Where `function` is defined in this program.
+    fn function_to_wire(&self, function: &Arc<FunctionValue>) -> Option<FunctionRef>;
+    /// The function a reference points to in this program.
+    fn function_from_wire(&self, function: &FunctionRef) -> Option<Arc<FunctionValue>>;
+}
+
+/// Why a value couldn't be encoded or decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    /// The value has no wire encoding (native functions).
+    Unsupported(&'static str),
+    /// A function of the message isn't part of this program.
+    UnknownFunction(String),
+    /// The input ended early or holds an invalid tag.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Unsupported(what) => write!(f, "cannot send {} to another node", what),
+            WireError::UnknownFunction(name) => write!(f, "function '{}' is not defined on this node", name),
+            WireError::Malformed(what) => write!(f, "malformed message: {}", what),
+        }
+    }
+}
+
+mod tag {
+    pub const UNIT: u8 = 0;
+    pub const BOOL: u8 = 1;
+    pub const INT64: u8 = 2;
+    pub const FLOAT64: u8 = 3;
+    pub const PID: u8 = 4;
+    pub const STRING: u8 = 5;
+    pub const CHAR: u8 = 6;
+    pub const LIST: u8 = 7;
+    pub const TUPLE: u8 = 8;
+    pub const RECORD: u8 = 9;
+    pub const CLOSURE: u8 = 10;
+    pub const VARIANT: u8 = 11;
+    pub const FUNCTION: u8 = 12;
+    pub const MAP: u8 = 13;
+    pub const SET: u8 = 14;
+    pub const INT64_ARRAY: u8 = 15;
+    pub const FLOAT64_ARRAY: u8 = 16;
+    pub const FLOAT32_ARRAY: u8 = 17;
+
+    // Map keys
+    pub const INT8: u8 = 20;
+    pub const INT16: u8 = 21;
+    pub const INT32: u8 = 22;
+    pub const UINT8: u8 = 23;
+    pub const UINT16: u8 = 24;
+    pub const UINT32: u8 = 25;
+    pub const UINT64: u8 = 26;
+}
+
+/// Builds an encoded frame.
+#[derive(Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn u8(&mut self, n: u8) {
+        self.buf.push(n);
+    }
+
+    pub fn u32(&mut self, n: u32) {
+        self.buf.extend_from_slice(&n.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, n: u64) {
+        self.buf.extend_from_slice(&n.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn len(&mut self, n: usize) {
+        self.u32(n as u32);
+    }
+
+    /// Append the encoding of `value`.
+    pub fn value(&mut self, value: &ThreadSafeValue, ctx: &dyn WireContext) -> Result<(), WireError> {
+        match value {
+            ThreadSafeValue::Unit => self.u8(tag::UNIT),
+            ThreadSafeValue::Bool(b) => {
+                self.u8(tag::BOOL);
+                self.u8(*b as u8);
+            }
+            ThreadSafeValue::Int64(n) => {
+                self.u8(tag::INT64);
+                self.u64(*n as u64);
+            }
+            ThreadSafeValue::Float64(f) => {
+                self.u8(tag::FLOAT64);
+                self.u64(f.to_bits());
+            }
+            ThreadSafeValue::Pid(pid) => {
+                self.u8(tag::PID);
+                self.pid(*pid, ctx);
+            }
+            ThreadSafeValue::String(s) => {
+                self.u8(tag::STRING);
+                self.str(s);
+            }
+            ThreadSafeValue::Char(c) => {
+                self.u8(tag::CHAR);
+                self.u32(*c as u32);
+            }
+            ThreadSafeValue::List(items) => {
+                self.u8(tag::LIST);
+                self.values(items, ctx)?;
+            }
+            ThreadSafeValue::Tuple(items) => {
+                self.u8(tag::TUPLE);
+                self.values(items, ctx)?;
+            }
+            ThreadSafeValue::Record { type_name, field_names, fields, mutable_fields } => {
+                self.u8(tag::RECORD);
+                self.str(type_name);
+                self.strs(field_names);
+                self.values(fields, ctx)?;
+                self.len(mutable_fields.len());
+                for mutable in mutable_fields {
+                    self.u8(*mutable as u8);
+                }
+            }
+            ThreadSafeValue::Closure { function, captures, capture_names } => {
+                self.u8(tag::CLOSURE);
+                self.function(function, ctx)?;
+                self.values(captures, ctx)?;
+                self.strs(capture_names);
+            }
+            ThreadSafeValue::Variant { type_name, constructor, fields } => {
+                self.u8(tag::VARIANT);
+                self.str(type_name);
+                self.str(constructor);
+                self.values(fields, ctx)?;
+            }
+            ThreadSafeValue::Function(function) => {
+                self.u8(tag::FUNCTION);
+                self.function(function, ctx)?;
+            }
+            ThreadSafeValue::NativeFunction(_) => return Err(WireError::Unsupported("a native function")),
+            ThreadSafeValue::Map(map) => {
+                self.u8(tag::MAP);
+                self.map(map, ctx);
+            }
+            ThreadSafeValue::Set(keys) => {
+                self.u8(tag::SET);
+                self.len(keys.len());
+                for key in keys {
+                    self.key(&shared_key(key));
+                }
+            }
+            ThreadSafeValue::Int64Array(items) => {
+                self.u8(tag::INT64_ARRAY);
+                self.len(items.len());
+                for n in items {
+                    self.u64(*n as u64);
+                }
+            }
+            ThreadSafeValue::Float64Array(items) => {
+                self.u8(tag::FLOAT64_ARRAY);
+                self.len(items.len());
+                for f in items {
+                    self.u64(f.to_bits());
+                }
+            }
+            ThreadSafeValue::Float32Array(items) => {
+                self.u8(tag::FLOAT32_ARRAY);
+                self.len(items.len());
+                for f in items {
+                    self.u32(f.to_bits());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn values(&mut self, items: &[ThreadSafeValue], ctx: &dyn WireContext) -> Result<(), WireError> {
+        self.len(items.len());
+        items.iter().try_for_each(|item| self.value(item, ctx))
+    }
+
+    fn strs(&mut self, items: &[String]) {
+        self.len(items.len());
+        for s in items {
+            self.str(s);
+        }
+    }
+
+    fn pid(&mut self, pid: u64, ctx: &dyn WireContext) {
+        let (node, id) = ctx.pid_to_wire(pid);
+        self.str(&node);
+        self.u64(id);
+    }
+
+    fn function(&mut self, function: &Arc<FunctionValue>, ctx: &dyn WireContext) -> Result<(), WireError> {
+        let function = ctx.function_to_wire(function)
+            .ok_or_else(|| WireError::UnknownFunction(function.name.clone()))?;
+        self.u32(function.index);
+        self.str(&function.name);
+        self.len(function.path.len());
+        for index in function.path {
+            self.u32(index);
+        }
+        Ok(())
+    }
+
+    fn map(&mut self, map: &SharedMap, ctx: &dyn WireContext) {
+        self.len(map.len());
+        for (key, value) in map.iter() {
+            self.key(key);
+            self.map_value(value, ctx);
+        }
+    }
+
+    fn map_value(&mut self, value: &SharedMapValue, ctx: &dyn WireContext) {
+        match value {
+            SharedMapValue::Unit => self.u8(tag::UNIT),
+            SharedMapValue::Bool(b) => {
+                self.u8(tag::BOOL);
+                self.u8(*b as u8);
+            }
+            SharedMapValue::Int64(n) => {
+                self.u8(tag::INT64);
+                self.u64(*n as u64);
+            }
+            SharedMapValue::Float64(f) => {
+                self.u8(tag::FLOAT64);
+                self.u64(f.to_bits());
+            }
+            SharedMapValue::Pid(pid) => {
+                self.u8(tag::PID);
+                self.pid(*pid, ctx);
+            }
+            SharedMapValue::String(s) => {
+                self.u8(tag::STRING);
+                self.str(s);
+            }
+            SharedMapValue::Char(c) => {
+                self.u8(tag::CHAR);
+                self.u32(*c as u32);
+            }
+            SharedMapValue::List(items) | SharedMapValue::Tuple(items) => {
+                self.u8(if matches!(value, SharedMapValue::List(_)) { tag::LIST } else { tag::TUPLE });
+                self.len(items.len());
+                for item in items {
+                    self.map_value(item, ctx);
+                }
+            }
+            SharedMapValue::Record { type_name, field_names, fields } => {
+                self.u8(tag::RECORD);
+                self.str(type_name);
+                self.strs(field_names);
+                self.len(fields.len());
+                for field in fields {
+                    self.map_value(field, ctx);
+                }
+            }
+            SharedMapValue::Variant { type_name, constructor, fields } => {
+                self.u8(tag::VARIANT);
+                self.str(type_name);
+                self.str(constructor);
+                self.len(fields.len());
+                for field in fields {
+                    self.map_value(field, ctx);
+                }
+            }
+            SharedMapValue::Map(map) => {
+                self.u8(tag::MAP);
+                self.map(map, ctx);
+            }
+            SharedMapValue::Set(keys) => {
+                self.u8(tag::SET);
+                self.len(keys.len());
+                for key in keys {
+                    self.key(key);
+                }
+            }
+            SharedMapValue::Int64Array(items) => {
+                self.u8(tag::INT64_ARRAY);
+                self.len(items.len());
+                for n in items {
+                    self.u64(*n as u64);
+                }
+            }
+            SharedMapValue::Float64Array(items) => {
+                self.u8(tag::FLOAT64_ARRAY);
+                self.len(items.len());
+                for f in items {
+                    self.u64(f.to_bits());
+                }
+            }
+            SharedMapValue::Float32Array(items) => {
+                self.u8(tag::FLOAT32_ARRAY);
+                self.len(items.len());
+                for f in items {
+                    self.u32(f.to_bits());
+                }
+            }
+        }
+    }
+
+    fn key(&mut self, key: &SharedMapKey) {
+        match key {
+            SharedMapKey::Unit => self.u8(tag::UNIT),
+            SharedMapKey::Bool(b) => {
+                self.u8(tag::BOOL);
+                self.u8(*b as u8);
+            }
+            SharedMapKey::Char(c) => {
+                self.u8(tag::CHAR);
+                self.u32(*c as u32);
+            }
+            SharedMapKey::Int8(n) => {
+                self.u8(tag::INT8);
+                self.u8(*n as u8);
+            }
+            SharedMapKey::Int16(n) => {
+                self.u8(tag::INT16);
+                self.u32(*n as u16 as u32);
+            }
+            SharedMapKey::Int32(n) => {
+                self.u8(tag::INT32);
+                self.u32(*n as u32);
+            }
+            SharedMapKey::Int64(n) => {
+                self.u8(tag::INT64);
+                self.u64(*n as u64);
+            }
+            SharedMapKey::UInt8(n) => {
+                self.u8(tag::UINT8);
+                self.u8(*n);
+            }
+            SharedMapKey::UInt16(n) => {
+                self.u8(tag::UINT16);
+                self.u32(*n as u32);
+            }
+            SharedMapKey::UInt32(n) => {
+                self.u8(tag::UINT32);
+                self.u32(*n);
+            }
+            SharedMapKey::UInt64(n) => {
+                self.u8(tag::UINT64);
+                self.u64(*n);
+            }
+            SharedMapKey::String(s) => {
+                self.u8(tag::STRING);
+                self.str(s);
+            }
+            SharedMapKey::Record { type_name, field_names, fields } => {
+                self.u8(tag::RECORD);
+                self.str(type_name);
+                self.strs(field_names);
+                self.len(fields.len());
+                for field in fields {
+                    self.key(field);
+                }
+            }
+            SharedMapKey::Variant { type_name, constructor, fields } => {
+                self.u8(tag::VARIANT);
+                self.str(type_name);
+                self.str(constructor);
+                self.len(fields.len());
+                for field in fields {
+                    self.key(field);
+                }
+            }
+            SharedMapKey::Tuple(items) => {
+                self.u8(tag::TUPLE);
+                self.len(items.len());
+                for item in items {
+                    self.key(item);
+                }
+            }
+        }
+    }
+}
+
+/// Reads an encoded frame.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    /// True once every byte was read.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], WireError> {
+        if self.buf.len() < n {
+            return Err(WireError::Malformed("unexpected end"));
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, WireError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, WireError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, WireError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8], WireError> {
+        self.take(n)
+    }
+
+    pub fn str(&mut self) -> Result<String, WireError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| WireError::Malformed("invalid UTF-8"))
+    }
+
+    /// A length prefix, checked against the remaining input so a corrupt
+    /// length can't make us allocate a huge vector.
+    fn len(&mut self) -> Result<usize, WireError> {
+        let len = self.u32()? as usize;
+        if len > self.buf.len() {
+            return Err(WireError::Malformed("length exceeds frame"));
+        }
+        Ok(len)
+    }
+
+    fn char(&mut self) -> Result<char, WireError> {
+        char::from_u32(self.u32()?).ok_or(WireError::Malformed("invalid char"))
+    }
+
+    fn bool(&mut self) -> Result<bool, WireError> {
+        Ok(self.u8()? != 0)
+    }
+
+    /// Read an encoded value.
+    pub fn value(&mut self, ctx: &dyn WireContext) -> Result<ThreadSafeValue, WireError> {
+        Ok(match self.u8()? {
+            tag::UNIT => ThreadSafeValue::Unit,
+            tag::BOOL => ThreadSafeValue::Bool(self.bool()?),
+            tag::INT64 => ThreadSafeValue::Int64(self.u64()? as i64),
+            tag::FLOAT64 => ThreadSafeValue::Float64(f64::from_bits(self.u64()?)),
+            tag::PID => ThreadSafeValue::Pid(self.pid(ctx)?),
+            tag::STRING => ThreadSafeValue::String(self.str()?),
+            tag::CHAR => ThreadSafeValue::Char(self.char()?),
+            tag::LIST => ThreadSafeValue::List(self.values(ctx)?),
+            tag::TUPLE => ThreadSafeValue::Tuple(self.values(ctx)?),
+            tag::RECORD => {
+                let type_name = self.str()?;
+                let field_names = self.strs()?;
+                let fields = self.values(ctx)?;
+                let count = self.len()?;
+                let mutable_fields = (0..count).map(|_| self.bool()).collect::<Result<_, _>>()?;
+                ThreadSafeValue::Record { type_name, field_names, fields, mutable_fields }
+            }
+            tag::CLOSURE => {
+                let function = self.function(ctx)?;
+                let captures = self.values(ctx)?;
+                let capture_names = self.strs()?;
+                ThreadSafeValue::Closure { function, captures, capture_names }
+            }
+            tag::VARIANT => {
+                let type_name = Arc::new(self.str()?);
+                let constructor = Arc::new(self.str()?);
+                let fields = self.values(ctx)?;
+                ThreadSafeValue::Variant { type_name, constructor, fields }
+            }
+            tag::FUNCTION => ThreadSafeValue::Function(self.function(ctx)?),
+            tag::MAP => ThreadSafeValue::Map(self.map(ctx)?),
+            tag::SET => {
+                let count = self.len()?;
+                let keys = (0..count)
+                    .map(|_| self.key().map(|key| set_key(&key)))
+                    .collect::<Result<_, _>>()?;
+                ThreadSafeValue::Set(keys)
+            }
+            tag::INT64_ARRAY => {
+                let count = self.len()?;
+                ThreadSafeValue::Int64Array((0..count).map(|_| self.u64().map(|n| n as i64)).collect::<Result<_, _>>()?)
+            }
+            tag::FLOAT64_ARRAY => {
+                let count = self.len()?;
+                ThreadSafeValue::Float64Array((0..count).map(|_| self.u64().map(f64::from_bits)).collect::<Result<_, _>>()?)
+            }
+            tag::FLOAT32_ARRAY => {
+                let count = self.len()?;
+                ThreadSafeValue::Float32Array((0..count).map(|_| self.u32().map(f32::from_bits)).collect::<Result<_, _>>()?)
+            }
+            _ => return Err(WireError::Malformed("unknown value tag")),
+        })
+    }
+
+    fn values(&mut self, ctx: &dyn WireContext) -> Result<Vec<ThreadSafeValue>, WireError> {
+        let count = self.len()?;
+        (0..count).map(|_| self.value(ctx)).collect()
+    }
+
+    fn strs(&mut self) -> Result<Vec<String>, WireError> {
+        let count = self.len()?;
+        (0..count).map(|_| self.str()).collect()
+    }
+
+    fn pid(&mut self, ctx: &dyn WireContext) -> Result<u64, WireError> {
+        let node = self.str()?;
+        let id = self.u64()?;
+        Ok(ctx.pid_from_wire(&node, id))
+    }
+
+    fn function(&mut self, ctx: &dyn WireContext) -> Result<Arc<FunctionValue>, WireError> {
+        let index = self.u32()?;
+        let name = self.str()?;
+        let count = self.len()?;
+        let path = (0..count).map(|_| self.u32()).collect::<Result<_, _>>()?;
+        let function = FunctionRef { index, name, path };
+        ctx.function_from_wire(&function).ok_or(WireError::UnknownFunction(function.name))
+    }
+
+    fn map(&mut self, ctx: &dyn WireContext) -> Result<SharedMap, WireError> {
+        let count = self.len()?;
+        let mut map = imbl::HashMap::new();
+        for _ in 0..count {
+            let key = self.key()?;
+            let value = self.map_value(ctx)?;
+            map.insert(key, value);
+        }
+        Ok(Arc::new(map))
+    }
+
+    fn map_values(&mut self, ctx: &dyn WireContext) -> Result<Vec<SharedMapValue>, WireError> {
+        let count = self.len()?;
+        (0..count).map(|_| self.map_value(ctx)).collect()
+    }
+
+    fn map_value(&mut self, ctx: &dyn WireContext) -> Result<SharedMapValue, WireError> {
+        Ok(match self.u8()? {
+            tag::UNIT => SharedMapValue::Unit,
+            tag::BOOL => SharedMapValue::Bool(self.bool()?),
+            tag::INT64 => SharedMapValue::Int64(self.u64()? as i64),
+            tag::FLOAT64 => SharedMapValue::Float64(f64::from_bits(self.u64()?)),
+            tag::PID => SharedMapValue::Pid(self.pid(ctx)?),
+            tag::STRING => SharedMapValue::String(self.str()?),
+            tag::CHAR => SharedMapValue::Char(self.char()?),
+            tag::LIST => SharedMapValue::List(self.map_values(ctx)?),
+            tag::TUPLE => SharedMapValue::Tuple(self.map_values(ctx)?),
+            tag::RECORD => {
+                let type_name = self.str()?;
+                let field_names = self.strs()?;
+                let fields = self.map_values(ctx)?;
+                SharedMapValue::Record { type_name, field_names, fields }
+            }
+            tag::VARIANT => {
+                let type_name = self.str()?;
+                let constructor = self.str()?;
+                let fields = self.map_values(ctx)?;
+                SharedMapValue::Variant { type_name, constructor, fields }
+            }
+            tag::MAP => SharedMapValue::Map(self.map(ctx)?),
+            tag::SET => {
+                let count = self.len()?;
+                SharedMapValue::Set((0..count).map(|_| self.key()).collect::<Result<_, _>>()?)
+            }
+            tag::INT64_ARRAY => {
+                let count = self.len()?;
+                SharedMapValue::Int64Array((0..count).map(|_| self.u64().map(|n| n as i64)).collect::<Result<_, _>>()?)
+            }
+            tag::FLOAT64_ARRAY => {
+                let count = self.len()?;
+                SharedMapValue::Float64Array((0..count).map(|_| self.u64().map(f64::from_bits)).collect::<Result<_, _>>()?)
+            }
+            tag::FLOAT32_ARRAY => {
+                let count = self.len()?;
+                SharedMapValue::Float32Array((0..count).map(|_| self.u32().map(f32::from_bits)).collect::<Result<_, _>>()?)
+            }
+            _ => return Err(WireError::Malformed("unknown map value tag")),
+        })
+    }
+
+    fn keys(&mut self) -> Result<Vec<SharedMapKey>, WireError> {
+        let count = self.len()?;
+        (0..count).map(|_| self.key()).collect()
+    }
+
+    fn key(&mut self) -> Result<SharedMapKey, WireError> {
+        Ok(match self.u8()? {
+            tag::UNIT => SharedMapKey::Unit,
+            tag::BOOL => SharedMapKey::Bool(self.bool()?),
+            tag::CHAR => SharedMapKey::Char(self.char()?),
+            tag::INT8 => SharedMapKey::Int8(self.u8()? as i8),
+            tag::INT16 => SharedMapKey::Int16(self.u32()? as u16 as i16),
+            tag::INT32 => SharedMapKey::Int32(self.u32()? as i32),
+            tag::INT64 => SharedMapKey::Int64(self.u64()? as i64),
+            tag::UINT8 => SharedMapKey::UInt8(self.u8()?),
+            tag::UINT16 => SharedMapKey::UInt16(self.u32()? as u16),
+            tag::UINT32 => SharedMapKey::UInt32(self.u32()?),
+            tag::UINT64 => SharedMapKey::UInt64(self.u64()?),
+            tag::STRING => SharedMapKey::String(self.str()?),
+            tag::RECORD => {
+                let type_name = self.str()?;
+                let field_names = self.strs()?;
+                let fields = self.keys()?;
+                SharedMapKey::Record { type_name, field_names, fields }
+            }
+            tag::VARIANT => {
+                let type_name = self.str()?;
+                let constructor = self.str()?;
+                let fields = self.keys()?;
+                SharedMapKey::Variant { type_name, constructor, fields }
+            }
+            tag::TUPLE => SharedMapKey::Tuple(self.keys()?),
+            _ => return Err(WireError::Malformed("unknown key tag")),
+        })
+    }
+}
+
+/// `ThreadSafeMapKey` (set elements) and `SharedMapKey` (map keys) have the
+/// same shape; sets are encoded like map keys.
+fn shared_key(key: &ThreadSafeMapKey) -> SharedMapKey {
+    match key {
+        ThreadSafeMapKey::Unit => SharedMapKey::Unit,
+        ThreadSafeMapKey::Bool(b) => SharedMapKey::Bool(*b),
+        ThreadSafeMapKey::Char(c) => SharedMapKey::Char(*c),
+        ThreadSafeMapKey::Int8(n) => SharedMapKey::Int8(*n),
+        ThreadSafeMapKey::Int16(n) => SharedMapKey::Int16(*n),
+        ThreadSafeMapKey::Int32(n) => SharedMapKey::Int32(*n),
+        ThreadSafeMapKey::Int64(n) => SharedMapKey::Int64(*n),
+        ThreadSafeMapKey::UInt8(n) => SharedMapKey::UInt8(*n),
+        ThreadSafeMapKey::UInt16(n) => SharedMapKey::UInt16(*n),
+        ThreadSafeMapKey::UInt32(n) => SharedMapKey::UInt32(*n),
+        ThreadSafeMapKey::UInt64(n) => SharedMapKey::UInt64(*n),
+        ThreadSafeMapKey::String(s) => SharedMapKey::String(s.clone()),
+        ThreadSafeMapKey::Record { type_name, field_names, fields } => SharedMapKey::Record {
+            type_name: type_name.clone(),
+            field_names: field_names.clone(),
+            fields: fields.iter().map(shared_key).collect(),
+        },
+        ThreadSafeMapKey::Variant { type_name, constructor, fields } => SharedMapKey::Variant {
+            type_name: type_name.clone(),
+            constructor: constructor.clone(),
+            fields: fields.iter().map(shared_key).collect(),
+        },
+        ThreadSafeMapKey::Tuple(items) => SharedMapKey::Tuple(items.iter().map(shared_key).collect()),
+    }
+}
+
+fn set_key(key: &SharedMapKey) -> ThreadSafeMapKey {
+    match key {
+        SharedMapKey::Unit => ThreadSafeMapKey::Unit,
+        SharedMapKey::Bool(b) => ThreadSafeMapKey::Bool(*b),
+        SharedMapKey::Char(c) => ThreadSafeMapKey::Char(*c),
+        SharedMapKey::Int8(n) => ThreadSafeMapKey::Int8(*n),
+        SharedMapKey::Int16(n) => ThreadSafeMapKey::Int16(*n),
+        SharedMapKey::Int32(n) => ThreadSafeMapKey::Int32(*n),
+        SharedMapKey::Int64(n) => ThreadSafeMapKey::Int64(*n),
+        SharedMapKey::UInt8(n) => ThreadSafeMapKey::UInt8(*n),
+        SharedMapKey::UInt16(n) => ThreadSafeMapKey::UInt16(*n),
+        SharedMapKey::UInt32(n) => ThreadSafeMapKey::UInt32(*n),
+        SharedMapKey::UInt64(n) => ThreadSafeMapKey::UInt64(*n),
+        SharedMapKey::String(s) => ThreadSafeMapKey::String(s.clone()),
+        SharedMapKey::Record { type_name, field_names, fields } => ThreadSafeMapKey::Record {
+            type_name: type_name.clone(),
+            field_names: field_names.clone(),
+            fields: fields.iter().map(set_key).collect(),
+        },
+        SharedMapKey::Variant { type_name, constructor, fields } => ThreadSafeMapKey::Variant {
+            type_name: type_name.clone(),
+            constructor: constructor.clone(),
+            fields: fields.iter().map(set_key).collect(),
+        },
+        SharedMapKey::Tuple(items) => ThreadSafeMapKey::Tuple(items.iter().map(set_key).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pids of node "a" are local, others get a node in the high bits.
+    struct TestContext;
+
+    impl WireContext for TestContext {
+        fn pid_to_wire(&self, pid: u64) -> (String, u64) {
+            match pid >> 48 {
+                0 => ("a".to_string(), pid),
+                node => (format!("n{}", node), pid & 0xffff_ffff_ffff),
+            }
+        }
+
+        fn pid_from_wire(&self, node: &str, id: u64) -> u64 {
+            match node {
+                "a" => id,
+                other => (other[1..].parse::<u64>().unwrap() << 48) | id,
+            }
+        }
+
+        fn function_to_wire(&self, _function: &Arc<FunctionValue>) -> Option<FunctionRef> {
+            None
+        }
+
+        fn function_from_wire(&self, _function: &FunctionRef) -> Option<Arc<FunctionValue>> {
+            None
+        }
+    }
+
+    fn round_trip(value: &ThreadSafeValue) -> ThreadSafeValue {
+        let mut writer = Writer::new();
+        writer.value(value, &TestContext).unwrap();
+        let bytes = writer.into_bytes();
+        let mut reader = Reader::new(&bytes);
+        let decoded = reader.value(&TestContext).unwrap();
+        assert!(reader.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        // One entry, as debug output follows the map's iteration order
+        let mut map = imbl::HashMap::new();
+        map.insert(
+            SharedMapKey::Tuple(vec![SharedMapKey::Int16(-2), SharedMapKey::UInt64(u64::MAX)]),
+            SharedMapValue::List(vec![SharedMapValue::Pid(5)]),
+        );
+        let value = ThreadSafeValue::Tuple(vec![
+            ThreadSafeValue::Unit,
+            ThreadSafeValue::Bool(true),
+            ThreadSafeValue::Int64(i64::MIN),
+            ThreadSafeValue::Float64(-1.5),
+            ThreadSafeValue::String("héllo".to_string()),
+            ThreadSafeValue::Char('λ'),
+            ThreadSafeValue::List(vec![ThreadSafeValue::Int64(1), ThreadSafeValue::Int64(2)]),
+            ThreadSafeValue::Record {
+                type_name: "Point".to_string(),
+                field_names: vec!["x".to_string(), "y".to_string()],
+                fields: vec![ThreadSafeValue::Int64(1), ThreadSafeValue::Float64(2.0)],
+                mutable_fields: vec![false, true],
+            },
+            ThreadSafeValue::Variant {
+                type_name: Arc::new("Option".to_string()),
+                constructor: Arc::new("Some".to_string()),
+                fields: vec![ThreadSafeValue::Int64(7)],
+            },
+            ThreadSafeValue::Map(Arc::new(map)),
+            ThreadSafeValue::Set(vec![ThreadSafeMapKey::Int64(1)]),
+            ThreadSafeValue::Int64Array(vec![1, -1]),
+            ThreadSafeValue::Float64Array(vec![0.5]),
+            ThreadSafeValue::Float32Array(vec![0.25]),
+        ]);
+        // ThreadSafeValue has no PartialEq; compare the debug output
+        assert_eq!(format!("{:?}", round_trip(&value)), format!("{:?}", value));
+    }
+
+    #[test]
+    fn test_pids_are_translated() {
+        let local = ThreadSafeValue::Pid(12);
+        let remote = ThreadSafeValue::Pid((3 << 48) | 9);
+        let mut writer = Writer::new();
+        writer.value(&ThreadSafeValue::Tuple(vec![local, remote]), &TestContext).unwrap();
+        let bytes = writer.into_bytes();
+        // "a" and "n3" are on the wire, not the VM's numbering
+        assert!(bytes.windows(2).any(|w| w == b"n3"));
+        match Reader::new(&bytes).value(&TestContext).unwrap() {
+            ThreadSafeValue::Tuple(items) => match items.as_slice() {
+                [ThreadSafeValue::Pid(a), ThreadSafeValue::Pid(b)] => {
+                    assert_eq!(*a, 12);
+                    assert_eq!(*b, (3 << 48) | 9);
+                }
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected() {
+        let mut writer = Writer::new();
+        writer.value(&ThreadSafeValue::String("abc".to_string()), &TestContext).unwrap();
+        let bytes = writer.into_bytes();
+        assert!(Reader::new(&bytes[..bytes.len() - 1]).value(&TestContext).is_err());
+        assert_eq!(Reader::new(&[99]).value(&TestContext).unwrap_err(), WireError::Malformed("unknown value tag"));
+        // A huge length prefix fails instead of allocating
+        let huge = [tag::LIST, 0xff, 0xff, 0xff, 0xff];
+        assert!(Reader::new(&huge).value(&TestContext).is_err());
+    }
+}
@@ -225,6 +225,10 @@ function activate(context) {
     decorationProvider = new NostosFileDecorationProvider();
     context.subscriptions.push(vscode_1.window.registerFileDecorationProvider(decorationProvider));
     extLog('Registered file decoration provider');
+    // Debug Nostos programs with the nostos-dap debug adapter
+    context.subscriptions.push(vscode_1.debug.registerDebugAdapterDescriptorFactory('nostos', {
+        createDebugAdapterDescriptor: () => new vscode_1.DebugAdapterExecutable(findBinaryPath(context, 'nostos-dap', 'debugAdapterPath'))
+    }));
     // Start the language server AFTER registering commands
     startLanguageServer(context);
 }
@@ -317,24 +321,28 @@ function startLanguageServer(context) {
     extLog('startLanguageServer() returning');
 }
 function findServerPath(context) {
+    return findBinaryPath(context, 'nostos-lsp', 'serverPath');
+}
+// Find a Nostos binary: the `nostos.<setting>` path, the extension's bin directory or a common install location
+function findBinaryPath(context, name, setting) {
     const config = vscode_1.workspace.getConfiguration('nostos');
     // 1. Check user-configured path
-    const configuredPath = config.get('serverPath');
+    const configuredPath = config.get(setting);
     if (configuredPath && fs.existsSync(configuredPath)) {
         return configuredPath;
     }
     // 2. Check bundled binary in extension
-    const bundledPath = path.join(context.extensionPath, 'bin', 'nostos-lsp');
+    const bundledPath = path.join(context.extensionPath, 'bin', name);
     if (fs.existsSync(bundledPath)) {
         return bundledPath;
     }
     // 3. Check common install locations
     const homeDir = process.env.HOME || process.env.USERPROFILE || '';
     const commonPaths = [
-        path.join(homeDir, '.cargo', 'bin', 'nostos-lsp'),
-        path.join(homeDir, '.local', 'bin', 'nostos-lsp'),
-        '/usr/local/bin/nostos-lsp',
-        '/usr/bin/nostos-lsp',
+        path.join(homeDir, '.cargo', 'bin', name),
+        path.join(homeDir, '.local', 'bin', name),
+        `/usr/local/bin/${name}`,
+        `/usr/bin/${name}`,
     ];
     for (const p of commonPaths) {
         if (fs.existsSync(p)) {
@@ -342,7 +350,7 @@ function findServerPath(context) {
         }
     }
     // 4. Try to find in PATH (will fail at runtime if not found)
-    return 'nostos-lsp';
+    return name;
 }
 function openReplPanel(context) {
     // If panel exists, reveal it
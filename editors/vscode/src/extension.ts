@@ -1,6 +1,6 @@
 import * as path from 'path';
 import * as fs from 'fs';
-import { workspace, ExtensionContext, window, commands, WebviewPanel, ViewColumn, Uri, FileDecoration, FileDecorationProvider, EventEmitter, Event, ThemeColor } from 'vscode';
+import { workspace, ExtensionContext, window, commands, WebviewPanel, ViewColumn, Uri, FileDecoration, FileDecorationProvider, EventEmitter, Event, ThemeColor, debug, DebugAdapterExecutable } from 'vscode';
 import {
     LanguageClient,
     LanguageClientOptions,
@@ -204,6 +204,11 @@ export function activate(context: ExtensionContext) {
     context.subscriptions.push(window.registerFileDecorationProvider(decorationProvider));
     extLog('Registered file decoration provider');
 
+    // Debug Nostos programs with the nostos-dap debug adapter
+    context.subscriptions.push(debug.registerDebugAdapterDescriptorFactory('nostos', {
+        createDebugAdapterDescriptor: () => new DebugAdapterExecutable(findBinaryPath(context, 'nostos-dap', 'debugAdapterPath'))
+    }));
+
     // Start the language server AFTER registering commands
     startLanguageServer(context);
 }
@@ -321,16 +326,21 @@ function startLanguageServer(context: ExtensionContext) {
 }
 
 function findServerPath(context: ExtensionContext): string | undefined {
+    return findBinaryPath(context, 'nostos-lsp', 'serverPath');
+}
+
+// Find a Nostos binary: the `nostos.<setting>` path, the extension's bin directory or a common install location
+function findBinaryPath(context: ExtensionContext, name: string, setting: string): string {
     const config = workspace.getConfiguration('nostos');
 
     // 1. Check user-configured path
-    const configuredPath = config.get<string>('serverPath');
+    const configuredPath = config.get<string>(setting);
     if (configuredPath && fs.existsSync(configuredPath)) {
         return configuredPath;
     }
 
     // 2. Check bundled binary in extension
-    const bundledPath = path.join(context.extensionPath, 'bin', 'nostos-lsp');
+    const bundledPath = path.join(context.extensionPath, 'bin', name);
     if (fs.existsSync(bundledPath)) {
         return bundledPath;
     }
@@ -338,10 +348,10 @@ function findServerPath(context: ExtensionContext): string | undefined {
     // 3. Check common install locations
     const homeDir = process.env.HOME || process.env.USERPROFILE || '';
     const commonPaths = [
-        path.join(homeDir, '.cargo', 'bin', 'nostos-lsp'),
-        path.join(homeDir, '.local', 'bin', 'nostos-lsp'),
-        '/usr/local/bin/nostos-lsp',
-        '/usr/bin/nostos-lsp',
+        path.join(homeDir, '.cargo', 'bin', name),
+        path.join(homeDir, '.local', 'bin', name),
+        `/usr/local/bin/${name}`,
+        `/usr/bin/${name}`,
     ];
 
     for (const p of commonPaths) {
@@ -351,7 +361,7 @@ function findServerPath(context: ExtensionContext): string | undefined {
     }
 
     // 4. Try to find in PATH (will fail at runtime if not found)
-    return 'nostos-lsp';
+    return name;
 }
 
 function openReplPanel(context: ExtensionContext) {